@@ -0,0 +1,248 @@
+use crate::scanner::metadata::{MetadataSource, Track};
+use anyhow::{Context, Result};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::Deserialize;
+use std::num::NonZeroU32;
+use std::path::Path;
+
+const ACOUSTID_LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+
+/// AcoustID allows 3 requests per second per application
+const ACOUSTID_RATE_LIMIT_PER_SEC: u32 = 3;
+
+/// Matches below this score are too uncertain to search with
+const MIN_SCORE: f64 = 0.5;
+
+/// Output of `fpcalc -json`
+#[derive(Debug, Deserialize)]
+struct Fingerprint {
+    duration: f64,
+    fingerprint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupResponse {
+    #[serde(default)]
+    results: Vec<LookupResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupResult {
+    score: f64,
+    #[serde(default)]
+    recordings: Vec<Recording>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Recording {
+    title: Option<String>,
+    #[serde(default)]
+    artists: Vec<Named>,
+    #[serde(default)]
+    releasegroups: Vec<Named>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    #[serde(alias = "title")]
+    name: String,
+}
+
+/// A recording identified from the audio itself
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identified {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub duration_secs: u64,
+    /// AcoustID match score (0-1)
+    pub score: f64,
+}
+
+/// Identifies untagged files by Chromaprint fingerprint through AcoustID
+pub struct AcoustIdClient {
+    client: reqwest::Client,
+    api_key: String,
+    rate_limiter: DefaultDirectRateLimiter,
+}
+
+impl AcoustIdClient {
+    /// Create a client, checking that Chromaprint's `fpcalc` is installed
+    pub fn new(api_key: &str) -> Result<Self> {
+        std::process::Command::new("fpcalc")
+            .arg("-version")
+            .output()
+            .context("fpcalc not found (install Chromaprint to identify untagged files)")?;
+
+        Ok(Self {
+            client: reqwest::Client::new(),
+            api_key: api_key.to_string(),
+            rate_limiter: RateLimiter::direct(Quota::per_second(
+                NonZeroU32::new(ACOUSTID_RATE_LIMIT_PER_SEC).unwrap(),
+            )),
+        })
+    }
+
+    /// Fingerprint a file and look up its best match, if any is confident enough
+    pub async fn identify(&self, path: &Path) -> Result<Option<Identified>> {
+        let output = tokio::process::Command::new("fpcalc")
+            .arg("-json")
+            .arg(path)
+            .output()
+            .await
+            .context("Failed to run fpcalc")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "fpcalc failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        let fingerprint: Fingerprint =
+            serde_json::from_slice(&output.stdout).context("Unexpected fpcalc output")?;
+
+        let url = format!(
+            "{}?client={}&meta=recordings+releasegroups&duration={}&fingerprint={}",
+            ACOUSTID_LOOKUP_URL,
+            urlencoding::encode(&self.api_key),
+            fingerprint.duration as u64,
+            urlencoding::encode(&fingerprint.fingerprint)
+        );
+
+        self.rate_limiter.until_ready().await;
+        let response = self.client.get(&url).send().await?.error_for_status()?;
+        let lookup = response.json::<LookupResponse>().await?;
+
+        Ok(best_match(lookup, fingerprint.duration as u64))
+    }
+
+    /// Replace missing or path-guessed metadata with the fingerprint match
+    /// `extracted` is the result of reading the file's tags; it's returned as-is when the
+    /// tags are complete or the audio can't be identified.
+    pub async fn identify_track(&self, path: &Path, extracted: Result<Track>) -> Result<Track> {
+        if extracted
+            .as_ref()
+            .is_ok_and(|track| !track.needs_identification())
+        {
+            return extracted;
+        }
+
+        let identified = match self.identify(path).await {
+            Ok(Some(identified)) => identified,
+            Ok(None) => {
+                tracing::debug!("No AcoustID match for {}", path.display());
+                return extracted;
+            }
+            Err(e) => {
+                tracing::warn!("AcoustID lookup failed for {}: {}", path.display(), e);
+                return extracted;
+            }
+        };
+
+        tracing::info!(
+            "Identified {} as {} - {} (score: {:.2})",
+            path.display(),
+            identified.artist,
+            identified.title,
+            identified.score
+        );
+        let tagged = extracted.ok();
+        let position = tagged.as_ref().map(|t| t.position).unwrap_or_default();
+        let compilation = tagged.as_ref().is_some_and(|t| t.compilation);
+        Ok(Track {
+            path: path.to_path_buf(),
+            artist: identified.artist,
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: identified.title,
+            album: if identified.album.is_empty() {
+                tagged.as_ref().map(|t| t.album.clone()).unwrap_or_default()
+            } else {
+                identified.album
+            },
+            genre: tagged.as_ref().map(|t| t.genre.clone()).unwrap_or_default(),
+            duration_secs: tagged
+                .as_ref()
+                .map_or(identified.duration_secs, |t| t.duration_secs),
+            has_embedded_lyrics: tagged.is_some_and(|t| t.has_embedded_lyrics),
+            source: MetadataSource::Fingerprint,
+            position,
+            compilation,
+        })
+    }
+}
+
+/// The highest-scoring result with a usable recording
+fn best_match(lookup: LookupResponse, duration_secs: u64) -> Option<Identified> {
+    lookup
+        .results
+        .into_iter()
+        .filter(|result| result.score >= MIN_SCORE)
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .and_then(|result| {
+            let score = result.score;
+            result.recordings.into_iter().find_map(|recording| {
+                let title = recording.title.filter(|t| !t.is_empty())?;
+                let artist = recording
+                    .artists
+                    .into_iter()
+                    .map(|artist| artist.name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if artist.is_empty() {
+                    return None;
+                }
+                let album = recording
+                    .releasegroups
+                    .into_iter()
+                    .next()
+                    .map(|group| group.name)
+                    .unwrap_or_default();
+
+                Some(Identified {
+                    artist,
+                    title,
+                    album,
+                    duration_secs,
+                    score,
+                })
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_match() {
+        let lookup: LookupResponse = serde_json::from_str(
+            r#"{
+                "status": "ok",
+                "results": [
+                    {"id": "a", "score": 0.3, "recordings": [{"title": "Wrong", "artists": [{"name": "Nobody"}]}]},
+                    {"id": "b", "score": 0.94, "recordings": [
+                        {"id": "r1"},
+                        {"title": "Song", "artists": [{"name": "Artist"}, {"name": "Guest"}],
+                         "releasegroups": [{"title": "Album"}]}
+                    ]}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            best_match(lookup, 215),
+            Some(Identified {
+                artist: "Artist, Guest".to_string(),
+                title: "Song".to_string(),
+                album: "Album".to_string(),
+                duration_secs: 215,
+                score: 0.94,
+            })
+        );
+
+        let lookup: LookupResponse =
+            serde_json::from_str(r#"{"status": "ok", "results": []}"#).unwrap();
+        assert_eq!(best_match(lookup, 215), None);
+    }
+}
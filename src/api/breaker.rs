@@ -0,0 +1,161 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Pause after the breaker first opens; doubled each time it opens again
+const BASE_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// What a failed request means for the caller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Failure {
+    /// Fewer failures in a row than the threshold: report the error as usual
+    Isolated,
+    /// This failure opened the breaker for the given backoff
+    Tripped(Duration),
+    /// The breaker is already open; wait and try again
+    Open,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// Connection failures in a row
+    failures: usize,
+    /// Times the breaker opened since the last success, for the backoff
+    trips: u32,
+    /// Requests wait until this time
+    open_until: Option<Instant>,
+}
+
+/// Stops requests after repeated connection failures
+/// Once `threshold` requests in a row fail with network or server errors, requests wait
+/// out an exponential backoff instead of failing file after file on a dead connection.
+pub struct CircuitBreaker {
+    threshold: usize,
+    state: Mutex<BreakerState>,
+    /// Wakes waiting requests when the backoff is cut short
+    closed: Notify,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            state: Mutex::new(BreakerState::default()),
+            closed: Notify::new(),
+        }
+    }
+
+    /// Wait until requests may be made again
+    pub async fn until_closed(&self) {
+        loop {
+            // Registered before checking, so a retry in between isn't missed
+            let closed = self.closed.notified();
+            let Some(wait) = self.remaining(Instant::now()) else {
+                return;
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = closed => {}
+            }
+        }
+    }
+
+    /// End the backoff early, letting the next request probe the connection
+    /// The backoff still doubles if it fails again.
+    pub fn retry_now(&self) {
+        self.state.lock().unwrap().open_until = None;
+        self.closed.notify_waiters();
+    }
+
+    /// Record a successful request
+    /// Returns true if the breaker had opened, i.e. the connection just came back.
+    pub fn record_success(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let recovered = state.trips > 0;
+        *state = BreakerState::default();
+        recovered
+    }
+
+    /// Record a request that failed with a network or server error
+    pub fn record_failure(&self) -> Failure {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) -> Failure {
+        let mut state = self.state.lock().unwrap();
+        if state.open_until.is_some_and(|until| until > now) {
+            // Requests already in flight when the breaker opened
+            return Failure::Open;
+        }
+        state.failures += 1;
+        if state.failures < self.threshold {
+            return Failure::Isolated;
+        }
+
+        let backoff = BASE_BACKOFF
+            .saturating_mul(2u32.saturating_pow(state.trips))
+            .min(MAX_BACKOFF);
+        state.trips += 1;
+        state.open_until = Some(now + backoff);
+        Failure::Tripped(backoff)
+    }
+
+    /// How long requests still have to wait, if the breaker is open
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        let until = self.state.lock().unwrap().open_until?;
+        (until > now).then(|| until - now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_threshold_with_backoff() {
+        let breaker = CircuitBreaker::new(3);
+        let now = Instant::now();
+
+        assert_eq!(breaker.record_failure_at(now), Failure::Isolated);
+        assert_eq!(breaker.record_failure_at(now), Failure::Isolated);
+        assert_eq!(
+            breaker.record_failure_at(now),
+            Failure::Tripped(BASE_BACKOFF)
+        );
+        assert_eq!(breaker.record_failure_at(now), Failure::Open);
+        assert_eq!(breaker.remaining(now), Some(BASE_BACKOFF));
+
+        // A failed probe after the backoff opens it again for twice as long
+        let later = now + BASE_BACKOFF;
+        assert_eq!(breaker.remaining(later), None);
+        assert_eq!(
+            breaker.record_failure_at(later),
+            Failure::Tripped(BASE_BACKOFF * 2)
+        );
+
+        assert!(breaker.record_success());
+        assert!(!breaker.record_success());
+        assert_eq!(breaker.record_failure_at(later), Failure::Isolated);
+    }
+
+    #[tokio::test]
+    async fn test_retry_now_ends_the_backoff() {
+        let breaker = std::sync::Arc::new(CircuitBreaker::new(1));
+        assert_eq!(breaker.record_failure(), Failure::Tripped(BASE_BACKOFF));
+
+        let waiting = tokio::spawn({
+            let breaker = breaker.clone();
+            async move { breaker.until_closed().await }
+        });
+        tokio::task::yield_now().await;
+        breaker.retry_now();
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("still waiting after retry_now")
+            .unwrap();
+
+        // The probe failing opens it again, for longer
+        assert_eq!(breaker.record_failure(), Failure::Tripped(BASE_BACKOFF * 2));
+    }
+}
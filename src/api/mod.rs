@@ -1,14 +1,52 @@
+pub mod acoustid;
+pub mod breaker;
+pub mod ratelimit;
+pub mod retry;
 pub mod types;
 
 use crate::scanner::clean::{self, NormalizedMetadata};
 use crate::scanner::metadata::Track;
 use anyhow::Result;
 use reqwest::StatusCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use types::LyricsResponse;
 
 const LRCLIB_BASE_URL: &str = "https://lrclib.net/api";
+
+/// Provider name recorded with negative cache entries
+pub const PROVIDER: &str = "lrclib";
 const SIMILARITY_THRESHOLD_AUTO: f64 = 0.85;
 const SIMILARITY_THRESHOLD_POTENTIAL: f64 = 0.6;
+/// Search results further than this from the track's length are different recordings
+const DURATION_TOLERANCE_SECS: f64 = 10.0;
+/// Search results offered to pick from, one per number key
+pub const MAX_CANDIDATES: usize = 9;
+
+/// Requests sent to LRCLIB by every client in the process
+static REQUESTS_SENT: AtomicUsize = AtomicUsize::new(0);
+
+/// Requests sent to LRCLIB so far
+pub fn requests_sent() -> usize {
+    REQUESTS_SENT.load(Ordering::Relaxed)
+}
+
+/// A status LRCLIB answered with other than a match or a miss
+#[derive(Debug)]
+pub struct UnexpectedStatus(pub StatusCode);
+
+impl std::fmt::Display for UnexpectedStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unexpected status code from LRCLIB: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnexpectedStatus {}
+
+/// Whether a lookup failed because LRCLIB couldn't be reached or had a server error
+/// Such failures say nothing about the track, unlike a malformed response.
+pub fn is_connection_failure(error: &anyhow::Error) -> bool {
+    retry::classify(error).is_some_and(retry::ErrorClass::is_connection_failure)
+}
 
 pub struct LrcLibClient {
     client: reqwest::Client,
@@ -24,8 +62,65 @@ pub enum SearchResult {
         lyrics: LyricsResponse,
         similarity: f64,
     },
-    /// No match found
-    NotFound,
+    /// No match found, with the HTTP status of the last request
+    /// A 200 means a result came back but was too dissimilar to use.
+    NotFound { status: u16 },
+}
+
+/// A query tried against LRCLIB while searching for a track
+#[derive(Debug, Clone)]
+pub struct SearchAttempt {
+    /// How the query was made ("normalized metadata", "stripped title", ...)
+    pub strategy: &'static str,
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    /// What came back, or None when LRCLIB had nothing
+    pub candidate: Option<Candidate>,
+}
+
+/// A result LRCLIB returned, and how closely it matches the query
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    pub duration_secs: Option<u64>,
+    pub artist_similarity: f64,
+    pub title_similarity: f64,
+    pub synced: bool,
+    pub instrumental: bool,
+}
+
+impl Candidate {
+    fn new(query: &NormalizedMetadata, lyrics: &LyricsResponse) -> Self {
+        Self {
+            artist: lyrics.artist_name.clone(),
+            title: lyrics.track_name.clone(),
+            album: lyrics.album_name.clone().unwrap_or_default(),
+            duration_secs: lyrics.duration.map(|secs| secs.round() as u64),
+            artist_similarity: clean::similarity_score(
+                &query.artist,
+                &lyrics.artist_name.to_lowercase(),
+            ),
+            title_similarity: clean::similarity_score(
+                &query.title,
+                &lyrics.track_name.to_lowercase(),
+            ),
+            synced: lyrics.synced_lyrics.is_some(),
+            instrumental: lyrics.instrumental,
+        }
+    }
+
+    /// Average of the artist and title similarity
+    pub fn similarity(&self) -> f64 {
+        (self.artist_similarity + self.title_similarity) / 2.0
+    }
+
+    /// Whether it is close enough to be used, as a match or a potential one
+    pub fn is_match(&self) -> bool {
+        self.similarity() >= SIMILARITY_THRESHOLD_POTENTIAL
+    }
 }
 
 impl LrcLibClient {
@@ -35,13 +130,43 @@ impl LrcLibClient {
         }
     }
 
+    /// Send a GET request, counting it
+    async fn send(&self, url: &str) -> reqwest::Result<reqwest::Response> {
+        REQUESTS_SENT.fetch_add(1, Ordering::Relaxed);
+        self.client.get(url).send().await
+    }
+
     /// Fetch lyrics with smart normalization and fuzzy matching
     /// Attempts multiple search strategies:
     /// 1. Normalized metadata with parentheticals
     /// 2. Stripped metadata (no parentheticals or featuring)
+    /// 3. The title's featured artist moved into the artist (`A feat. B` - `Title`)
+    /// 4. Every credited artist together (`A & B`), then each of the others alone
+    /// 5. The artist's sort name, in display order (`Beatles, The` becomes `The Beatles`)
     pub async fn get_lyrics_smart(&self, track: &Track) -> Result<SearchResult> {
+        self.get_lyrics_traced(track, &mut Vec::new()).await
+    }
+
+    /// Like `get_lyrics_smart`, recording each query tried and what came back
+    /// `attempts` is cleared first, and holds the queries made so far if the search fails.
+    pub async fn get_lyrics_traced(
+        &self,
+        track: &Track,
+        attempts: &mut Vec<SearchAttempt>,
+    ) -> Result<SearchResult> {
+        attempts.clear();
+
         // Normalize metadata
-        let normalized = clean::normalize_metadata(&track.artist, &track.title, &track.album);
+        let mut normalized = clean::normalize_metadata(&track.artist, &track.title, &track.album);
+
+        // Compilation album names rarely match the release lrclib knows the track from
+        if track.compilation {
+            tracing::debug!(
+                "Compilation track, searching without album: {}",
+                track.album
+            );
+            normalized.album.clear();
+        }
 
         tracing::debug!(
             "Searching for: {} - {} (normalized from: {} - {})",
@@ -51,52 +176,84 @@ impl LrcLibClient {
             track.title
         );
 
-        // Attempt 1: Search with normalized metadata (keeps parentheticals)
-        match self
-            .search_with_fuzzy(&normalized, track.duration_secs)
-            .await?
-        {
-            SearchResult::Found(lyrics) => {
-                tracing::info!("Found exact match for: {} - {}", track.artist, track.title);
-                return Ok(SearchResult::Found(lyrics));
-            }
-            SearchResult::PotentialMatch { lyrics, similarity } => {
-                tracing::info!(
-                    "Found potential match for: {} - {} (similarity: {:.2})",
-                    track.artist,
-                    track.title,
-                    similarity
-                );
-                return Ok(SearchResult::PotentialMatch { lyrics, similarity });
-            }
-            SearchResult::NotFound => {
-                tracing::debug!("No match with normalized metadata, trying stripped version");
-            }
-        }
+        let mut queries = vec![("normalized metadata", normalized.clone())];
 
-        // Attempt 2: Search with stripped metadata (removes parentheticals and featuring)
+        // Stripped metadata (removes parentheticals and featuring)
         let stripped_title = clean::get_stripped_title(&normalized);
         if stripped_title != normalized.title {
+            queries.push((
+                "stripped title",
+                NormalizedMetadata {
+                    title: stripped_title,
+                    ..normalized.clone()
+                },
+            ));
+        }
+
+        // Many uploads credit the featured artist in the artist field rather than the title
+        if let Some(artist) = clean::artist_with_featured(&track.artist, &track.title) {
+            queries.push((
+                "featured artist",
+                NormalizedMetadata {
+                    artist: clean::clean_string(&artist),
+                    ..normalized.clone()
+                },
+            ));
+        }
+
+        // Multi-artist tracks are uploaded under the joint credit or another of the artists
+        if track.artists.len() > 1 {
+            queries.push((
+                "all artists",
+                NormalizedMetadata {
+                    artist: clean::clean_string(&track.artists.join(" & ")),
+                    ..normalized.clone()
+                },
+            ));
+            for artist in &track.artists[1..] {
+                queries.push((
+                    "other artist",
+                    NormalizedMetadata {
+                        artist: clean::clean_string(artist),
+                        ..normalized.clone()
+                    },
+                ));
+            }
+        }
+
+        // Sort tags often hold the name lrclib uses (romanized, or in western order)
+        let sort_artist = clean::clean_string(&clean::display_order(&track.sort_artist));
+        if !sort_artist.is_empty() && sort_artist != normalized.artist {
+            queries.push((
+                "sort artist",
+                NormalizedMetadata {
+                    artist: sort_artist,
+                    ..normalized.clone()
+                },
+            ));
+        }
+
+        let mut last_status = StatusCode::NOT_FOUND.as_u16();
+        for (attempt, query) in queries {
             tracing::debug!(
-                "Attempting fallback search with stripped title: {}",
-                stripped_title
+                "Searching with {}: {} - {}",
+                attempt,
+                query.artist,
+                query.title
             );
-
-            let stripped_normalized = NormalizedMetadata {
-                artist: normalized.artist.clone(),
-                title: stripped_title,
-                album: normalized.album.clone(),
-                original_artist: normalized.original_artist.clone(),
-                original_title: normalized.original_title.clone(),
-            };
-
-            match self
-                .search_with_fuzzy(&stripped_normalized, track.duration_secs)
-                .await?
-            {
+            let (result, candidate) = self.search_with_fuzzy(&query, track.duration_secs).await?;
+            attempts.push(SearchAttempt {
+                strategy: attempt,
+                artist: query.artist,
+                title: query.title,
+                album: query.album,
+                candidate,
+            });
+            match result {
                 SearchResult::Found(lyrics) => {
                     tracing::info!(
-                        "Found match with stripped title for: {} - {}",
+                        "Found match with {} for: {} - {}",
+                        attempt,
                         track.artist,
                         track.title
                     );
@@ -104,75 +261,83 @@ impl LrcLibClient {
                 }
                 SearchResult::PotentialMatch { lyrics, similarity } => {
                     tracing::info!(
-                        "Found potential match with stripped title for: {} - {} (similarity: {:.2})",
+                        "Found potential match with {} for: {} - {} (similarity: {:.2})",
+                        attempt,
                         track.artist,
                         track.title,
                         similarity
                     );
                     return Ok(SearchResult::PotentialMatch { lyrics, similarity });
                 }
-                SearchResult::NotFound => {
-                    tracing::debug!("No match with stripped metadata either");
+                SearchResult::NotFound { status } => {
+                    tracing::debug!("No match with {}", attempt);
+                    last_status = status;
                 }
             }
         }
 
-        Ok(SearchResult::NotFound)
+        Ok(SearchResult::NotFound {
+            status: last_status,
+        })
     }
 
-    /// Search with fuzzy matching on the result
+    /// Search with fuzzy matching on the result, returning the result LRCLIB sent if any
     async fn search_with_fuzzy(
         &self,
         normalized: &NormalizedMetadata,
         duration_secs: u64,
-    ) -> Result<SearchResult> {
-        let url = format!(
-            "{}/get?artist_name={}&track_name={}&album_name={}&duration={}",
+    ) -> Result<(SearchResult, Option<Candidate>)> {
+        let mut url = format!(
+            "{}/get?artist_name={}&track_name={}&duration={}",
             LRCLIB_BASE_URL,
             urlencoding::encode(&normalized.artist),
             urlencoding::encode(&normalized.title),
-            urlencoding::encode(&normalized.album),
             duration_secs
         );
+        if !normalized.album.is_empty() {
+            url.push_str(&format!(
+                "&album_name={}",
+                urlencoding::encode(&normalized.album)
+            ));
+        }
 
         tracing::debug!("API request: {}", url);
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.send(&url).await?;
 
         match response.status() {
             StatusCode::OK => {
                 let lyrics = response.json::<LyricsResponse>().await?;
 
                 // Calculate similarity scores
-                let artist_similarity =
-                    clean::similarity_score(&normalized.artist, &lyrics.artist_name.to_lowercase());
-                let title_similarity =
-                    clean::similarity_score(&normalized.title, &lyrics.track_name.to_lowercase());
-
-                let avg_similarity = (artist_similarity + title_similarity) / 2.0;
+                let candidate = Candidate::new(normalized, &lyrics);
+                let avg_similarity = candidate.similarity();
 
                 tracing::debug!(
                     "Similarity scores - Artist: {:.2}, Title: {:.2}, Average: {:.2}",
-                    artist_similarity,
-                    title_similarity,
+                    candidate.artist_similarity,
+                    candidate.title_similarity,
                     avg_similarity
                 );
 
                 // Determine match quality
-                if avg_similarity >= SIMILARITY_THRESHOLD_AUTO {
-                    Ok(SearchResult::Found(lyrics))
+                let result = if avg_similarity >= SIMILARITY_THRESHOLD_AUTO {
+                    SearchResult::Found(lyrics)
                 } else if avg_similarity >= SIMILARITY_THRESHOLD_POTENTIAL {
-                    Ok(SearchResult::PotentialMatch {
+                    SearchResult::PotentialMatch {
                         lyrics,
                         similarity: avg_similarity,
-                    })
+                    }
                 } else {
                     tracing::debug!(
                         "Similarity too low ({:.2}), treating as not found",
                         avg_similarity
                     );
-                    Ok(SearchResult::NotFound)
-                }
+                    SearchResult::NotFound {
+                        status: StatusCode::OK.as_u16(),
+                    }
+                };
+                Ok((result, Some(candidate)))
             }
             StatusCode::NOT_FOUND => {
                 tracing::debug!(
@@ -180,14 +345,48 @@ impl LrcLibClient {
                     normalized.artist,
                     normalized.title
                 );
-                Ok(SearchResult::NotFound)
-            }
-            status => {
-                anyhow::bail!("Unexpected status code from LRCLIB: {}", status);
+                Ok((
+                    SearchResult::NotFound {
+                        status: StatusCode::NOT_FOUND.as_u16(),
+                    },
+                    None,
+                ))
             }
+            status => Err(UnexpectedStatus(status).into()),
         }
     }
 
+    /// Search LRCLIB for results close enough to the track to choose between, best first
+    /// Unlike the exact lookup this finds other uploads of the track (remasters, other
+    /// albums), so it is used when that lookup had no confident match.
+    pub async fn search_candidates(
+        &self,
+        track: &Track,
+    ) -> Result<Vec<(LyricsResponse, Candidate)>> {
+        let query = clean::normalize_metadata(&track.artist, &track.title, &track.album);
+        let url = format!(
+            "{}/search?artist_name={}&track_name={}",
+            LRCLIB_BASE_URL,
+            urlencoding::encode(&query.artist),
+            urlencoding::encode(&query.title)
+        );
+
+        tracing::debug!("API request: {}", url);
+
+        let response = self.send(&url).await?;
+        if response.status() != StatusCode::OK {
+            return Err(UnexpectedStatus(response.status()).into());
+        }
+        let results = response.json::<Vec<LyricsResponse>>().await?;
+        tracing::debug!(
+            "Search returned {} results for: {} - {}",
+            results.len(),
+            query.artist,
+            query.title
+        );
+        Ok(plausible_candidates(&query, track.duration_secs, results))
+    }
+
     /// Legacy method for backward compatibility
     /// Use get_lyrics_smart() for new code
     pub async fn get_lyrics(&self, track: &Track) -> Result<Option<LyricsResponse>> {
@@ -195,13 +394,84 @@ impl LrcLibClient {
             SearchResult::Found(lyrics) | SearchResult::PotentialMatch { lyrics, .. } => {
                 Ok(Some(lyrics))
             }
-            SearchResult::NotFound => Ok(None),
+            SearchResult::NotFound { .. } => Ok(None),
         }
     }
 }
 
+/// Search results with lyrics that match the query and the track's length, synced ones
+/// first and then by similarity
+fn plausible_candidates(
+    query: &NormalizedMetadata,
+    duration_secs: u64,
+    results: Vec<LyricsResponse>,
+) -> Vec<(LyricsResponse, Candidate)> {
+    let mut candidates: Vec<_> = results
+        .into_iter()
+        .filter(|lyrics| lyrics.synced_lyrics.is_some() || lyrics.plain_lyrics.is_some())
+        .filter(|lyrics| {
+            lyrics.duration.is_none_or(|secs| {
+                duration_secs == 0 || (secs - duration_secs as f64).abs() <= DURATION_TOLERANCE_SECS
+            })
+        })
+        .map(|lyrics| {
+            let candidate = Candidate::new(query, &lyrics);
+            (lyrics, candidate)
+        })
+        // Every song by the artist comes close on average, so the title has to on its own
+        .filter(|(_, candidate)| {
+            candidate.is_match() && candidate.title_similarity >= SIMILARITY_THRESHOLD_POTENTIAL
+        })
+        .collect();
+    candidates.sort_by(|(_, a), (_, b)| {
+        b.synced
+            .cmp(&a.synced)
+            .then(b.similarity().total_cmp(&a.similarity()))
+    });
+    candidates.truncate(MAX_CANDIDATES);
+    candidates
+}
+
 impl Default for LrcLibClient {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(artist: &str, title: &str, duration: f64, synced: bool) -> LyricsResponse {
+        LyricsResponse {
+            artist_name: artist.to_string(),
+            track_name: title.to_string(),
+            album_name: None,
+            duration: Some(duration),
+            synced_lyrics: synced.then(|| "[00:01.00] La".to_string()),
+            plain_lyrics: Some("La".to_string()),
+            instrumental: false,
+        }
+    }
+
+    #[test]
+    fn test_plausible_candidates() {
+        let query = clean::normalize_metadata("Queen", "Bicycle Race", "");
+        let candidates = plausible_candidates(
+            &query,
+            181,
+            vec![
+                result("Queen", "Bicycle Race", 182.0, false),
+                result("Queen", "Bicycle Race", 180.0, true),
+                // Another recording, and another song
+                result("Queen", "Bicycle Race", 240.0, true),
+                result("Queen", "Fat Bottomed Girls", 181.0, true),
+            ],
+        );
+        assert_eq!(candidates.len(), 2);
+        // Synced lyrics first
+        assert!(candidates[0].1.synced);
+        assert_eq!(candidates[0].1.duration_secs, Some(180));
+        assert!(!candidates[1].1.synced);
+    }
+}
@@ -0,0 +1,197 @@
+use anyhow::{Context, Result};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Token bucket state kept in the shared file
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct Bucket {
+    tokens: f64,
+    /// When `tokens` was last brought up to date (Unix milliseconds)
+    updated_ms: i64,
+}
+
+impl Bucket {
+    /// Add the tokens earned since the last update, up to one second's worth
+    fn refill(&mut self, per_second: f64, now_ms: i64) {
+        let elapsed = (now_ms - self.updated_ms).max(0) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * per_second).min(per_second);
+        self.updated_ms = now_ms;
+    }
+
+    /// Take a token, or return how long until one is available
+    fn take(&mut self, per_second: f64) -> Option<Duration> {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+}
+
+/// Rate limiter shared by every getlrc process on the machine
+/// The bucket lives in a small file guarded by an exclusive lock, so concurrent runs
+/// (e.g. a daemon plus a manual run) stay under the lrclib limit together.
+pub struct SharedRateLimiter {
+    path: PathBuf,
+    per_second: NonZeroU32,
+    /// Used when the shared file can't be opened or locked
+    local: DefaultDirectRateLimiter,
+    warned: AtomicBool,
+}
+
+impl SharedRateLimiter {
+    pub fn new(path: PathBuf, per_second: NonZeroU32) -> Self {
+        Self {
+            path,
+            per_second,
+            local: RateLimiter::direct(Quota::per_second(per_second)),
+            warned: AtomicBool::new(false),
+        }
+    }
+
+    /// Wait until a request may be made
+    pub async fn until_ready(&self) {
+        loop {
+            let path = self.path.clone();
+            let per_second = self.per_second.get();
+            let acquired = tokio::task::spawn_blocking(move || try_acquire(&path, per_second))
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result);
+
+            match acquired {
+                Ok(None) => return,
+                Ok(Some(wait)) => tokio::time::sleep(wait).await,
+                Err(e) => {
+                    if !self.warned.swap(true, Ordering::Relaxed) {
+                        tracing::warn!(
+                            "Shared rate limit unavailable ({}), limiting this process only: {}",
+                            self.path.display(),
+                            e
+                        );
+                    }
+                    self.local.until_ready().await;
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn per_second(&self) -> u32 {
+        self.per_second.get()
+    }
+
+    /// Requests that could be sent right away
+    /// None when the shared bucket can't be read, and once this process limits itself
+    /// alone, as its local limiter doesn't tell.
+    pub async fn available(&self) -> Option<f64> {
+        if self.warned.load(Ordering::Relaxed) {
+            return None;
+        }
+        let path = self.path.clone();
+        let per_second = self.per_second.get();
+        tokio::task::spawn_blocking(move || peek(&path, per_second))
+            .await
+            .ok()?
+            .ok()
+    }
+}
+
+/// Tokens in the shared bucket right now, without taking one
+fn peek(path: &Path, per_second: u32) -> Result<f64> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    file.lock_shared()
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let per_second = per_second as f64;
+    let mut bucket: Bucket = serde_json::from_str(&contents)?;
+    bucket.refill(per_second, now_millis()?);
+    Ok(bucket.tokens)
+}
+
+/// Take a token from the shared bucket, or return how long to wait before trying again
+fn try_acquire(path: &Path, per_second: u32) -> Result<Option<Duration>> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    // Released when the file is closed
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", path.display()))?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let now = now_millis()?;
+    let per_second = per_second as f64;
+    // A new or unreadable file starts with a full bucket
+    let mut bucket = serde_json::from_str(&contents).unwrap_or(Bucket {
+        tokens: per_second,
+        updated_ms: now,
+    });
+    bucket.refill(per_second, now);
+    let wait = bucket.take(per_second);
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(serde_json::to_string(&bucket)?.as_bytes())?;
+    Ok(wait)
+}
+
+fn now_millis() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_millis() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let mut bucket = Bucket {
+            tokens: 0.0,
+            updated_ms: 0,
+        };
+        assert_eq!(bucket.take(10.0), Some(Duration::from_millis(100)));
+
+        bucket.refill(10.0, 250);
+        assert_eq!(bucket.take(10.0), None);
+        assert_eq!(bucket.take(10.0), None);
+        assert!(bucket.take(10.0).is_some());
+
+        // Idle time never banks more than one second's worth
+        bucket.refill(10.0, 60_000);
+        assert_eq!(bucket.tokens, 10.0);
+    }
+
+    #[test]
+    fn test_processes_share_one_bucket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ratelimit");
+
+        // Alternate between two "processes" reading the same file
+        for _ in 0..5 {
+            assert_eq!(try_acquire(&path, 10).unwrap(), None);
+            assert_eq!(try_acquire(&path, 10).unwrap(), None);
+        }
+        assert!(try_acquire(&path, 10).unwrap().is_some());
+        // Looking doesn't take a token
+        assert!(peek(&path, 10).unwrap() < 1.0);
+        assert!(try_acquire(&path, 10).unwrap().is_some());
+    }
+}
@@ -0,0 +1,141 @@
+use super::UnexpectedStatus;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Longest pause between attempts, however many there are
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Kinds of failed lookups a retry policy can pick from
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The request took too long
+    Timeout,
+    /// LRCLIB couldn't be reached (DNS, refused or reset connections)
+    Connection,
+    /// LRCLIB answered with a 5xx status
+    Server,
+    /// LRCLIB answered 429 Too Many Requests
+    RateLimited,
+}
+
+impl ErrorClass {
+    /// Whether the failure means LRCLIB is unreachable, rather than something about the request
+    pub fn is_connection_failure(self) -> bool {
+        matches!(
+            self,
+            ErrorClass::Timeout | ErrorClass::Connection | ErrorClass::Server
+        )
+    }
+}
+
+/// Which class of error a failed lookup falls into, if any a retry could help with
+/// Malformed responses and unexpected statuses say something about the request, so they
+/// aren't classified.
+pub fn classify(error: &anyhow::Error) -> Option<ErrorClass> {
+    error.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() {
+                Some(ErrorClass::Timeout)
+            } else if e.is_connect() || e.is_request() {
+                Some(ErrorClass::Connection)
+            } else {
+                e.status().and_then(classify_status)
+            }
+        } else if let Some(UnexpectedStatus(status)) = cause.downcast_ref() {
+            classify_status(*status)
+        } else {
+            None
+        }
+    })
+}
+
+fn classify_status(status: StatusCode) -> Option<ErrorClass> {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        Some(ErrorClass::RateLimited)
+    } else if status.is_server_error() {
+        Some(ErrorClass::Server)
+    } else {
+        None
+    }
+}
+
+/// How often a file's lookup is attempted before it is recorded as an error
+/// Set in the `[retry]` table of the config file.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts per file, including the first
+    pub max_attempts: u32,
+    /// Pause before the second attempt in milliseconds, doubled for each one after
+    pub backoff_base_ms: u64,
+    /// Errors worth another attempt
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            backoff_base_ms: 1000,
+            retry_on: vec![
+                ErrorClass::Timeout,
+                ErrorClass::Connection,
+                ErrorClass::Server,
+                ErrorClass::RateLimited,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The pause before trying again after failed attempt number `attempt` (from 1), or None
+    /// if the error isn't retryable or the attempts are used up
+    pub fn retry_after(&self, error: &anyhow::Error, attempt: u32) -> Option<Duration> {
+        let class = classify(error)?;
+        if attempt >= self.max_attempts || !self.retry_on.contains(&class) {
+            return None;
+        }
+        let backoff = Duration::from_millis(self.backoff_base_ms)
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(MAX_BACKOFF);
+        Some(backoff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let server: anyhow::Error = UnexpectedStatus(StatusCode::BAD_GATEWAY).into();
+        let throttled: anyhow::Error = UnexpectedStatus(StatusCode::TOO_MANY_REQUESTS).into();
+        let other: anyhow::Error = UnexpectedStatus(StatusCode::BAD_REQUEST).into();
+        assert_eq!(classify(&server), Some(ErrorClass::Server));
+        assert_eq!(classify(&throttled), Some(ErrorClass::RateLimited));
+        assert_eq!(classify(&other), None);
+
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            backoff_base_ms: 500,
+            retry_on: vec![ErrorClass::Server],
+        };
+        assert_eq!(
+            policy.retry_after(&server, 1),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(policy.retry_after(&server, 2), Some(Duration::from_secs(1)));
+        assert_eq!(policy.retry_after(&server, 3), None);
+        assert_eq!(policy.retry_after(&throttled, 1), None);
+        assert_eq!(policy.retry_after(&other, 1), None);
+
+        // A single attempt is the old behavior
+        let once = RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(once.retry_after(&server, 1), None);
+    }
+}
@@ -1,13 +1,29 @@
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct LyricsResponse {
     #[serde(rename = "artistName")]
     pub artist_name: String,
     #[serde(rename = "trackName")]
     pub track_name: String,
+    #[serde(rename = "albumName", default)]
+    pub album_name: Option<String>,
+    /// Length of the recording in seconds
+    #[serde(default)]
+    pub duration: Option<f64>,
     #[serde(rename = "syncedLyrics")]
     pub synced_lyrics: Option<String>,
     #[serde(rename = "plainLyrics")]
     pub plain_lyrics: Option<String>,
+    #[serde(default)]
+    pub instrumental: bool,
+}
+
+impl LyricsResponse {
+    /// Synced lyrics, falling back to plain ones when `allow_plain` is set
+    pub fn into_lyrics(self, allow_plain: bool) -> Option<String> {
+        let instrumental = self.instrumental;
+        self.synced_lyrics
+            .or(self.plain_lyrics.filter(|_| allow_plain && !instrumental))
+    }
 }
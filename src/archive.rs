@@ -0,0 +1,429 @@
+use crate::cache::signature::TrackSignature;
+use crate::lrc::{self, WriteOptions};
+use crate::scanner::{clean, metadata, parallel};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const MANIFEST_NAME: &str = "manifest.json";
+const MANIFEST_VERSION: u32 = 1;
+const LYRICS_PREFIX: &str = "lyrics/";
+const DURATION_TOLERANCE_SECS: u32 = 2; // Max duration drift for metadata matches
+
+/// Index of every sidecar stored in a lyrics archive
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Manifest {
+    pub version: u32,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// One archived sidecar and the track it belongs to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ManifestEntry {
+    /// Track signature hash, if the audio file's tags could be read
+    pub signature: Option<String>,
+    /// Audio file path relative to the library root
+    pub audio_path: String,
+    /// Sidecar path inside the archive
+    pub lyrics_path: String,
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub album: String,
+    #[serde(default)]
+    pub duration_sec: u32,
+}
+
+/// Counts reported after an export
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub exported: usize,
+    pub untagged: usize,
+}
+
+/// Counts reported after an import
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub imported: usize,
+    pub existing: usize,
+    pub unmatched: usize,
+    pub errors: usize,
+    /// Source sidecars skipped for not being UTF-8 text
+    pub unreadable: usize,
+}
+
+/// Lyrics loaded from an archive or another library, ready for matching
+struct SourceLyrics {
+    entry: ManifestEntry,
+    lyrics: String,
+}
+
+/// Bundle every `.lrc` sidecar under `library` into a zip archive at `out`
+pub fn export(library: &Path, out: &Path) -> Result<ExportSummary> {
+    let audio_files = parallel::walk_directory_parallel(library);
+
+    let file = File::create(out)
+        .with_context(|| format!("Failed to create archive: {}", out.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = Manifest {
+        version: MANIFEST_VERSION,
+        entries: Vec::new(),
+    };
+    let mut summary = ExportSummary::default();
+
+    for audio_path in audio_files {
+        let lrc_path = lrc::sidecar_path(&audio_path);
+        if !lrc_path.exists() {
+            continue;
+        }
+
+        let lyrics = std::fs::read(&lrc_path)
+            .with_context(|| format!("Failed to read {}", lrc_path.display()))?;
+        let entry = manifest_entry(library, &audio_path, &lrc_path);
+        if entry.signature.is_none() {
+            summary.untagged += 1;
+        }
+
+        zip.start_file(entry.lyrics_path.as_str(), options)?;
+        zip.write_all(&lyrics)?;
+
+        manifest.entries.push(entry);
+        summary.exported += 1;
+    }
+
+    zip.start_file(MANIFEST_NAME, options)?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.finish()?;
+
+    tracing::info!(
+        "Exported {} sidecars from {} to {}",
+        summary.exported,
+        library.display(),
+        out.display()
+    );
+
+    Ok(summary)
+}
+
+/// Write missing sidecars under `library` using lyrics from `source`
+/// `source` is either an archive created by [`export`] or another library directory.
+/// Tracks are matched by signature first, then by normalized artist/title and duration.
+/// Sources without tags are matched by file name instead, when no other source shares it.
+pub fn import(
+    source: &Path,
+    library: &Path,
+    write_options: &WriteOptions,
+) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let sources = if source.is_dir() {
+        load_library(source, &mut summary)?
+    } else {
+        load_archive(source, &mut summary)?
+    };
+    tracing::info!(
+        "Loaded {} sidecars from {}",
+        sources.len(),
+        source.display()
+    );
+
+    let mut by_signature: HashMap<&str, usize> = HashMap::new();
+    let mut by_metadata: HashMap<(String, String), Vec<usize>> = HashMap::new();
+    let mut by_file_name: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, source) in sources.iter().enumerate() {
+        if let Some(signature) = &source.entry.signature {
+            by_signature.insert(signature.as_str(), index);
+            by_metadata
+                .entry(metadata_key(&source.entry.artist, &source.entry.title))
+                .or_default()
+                .push(index);
+        } else if let Some(name) = file_name_key(Path::new(&source.entry.audio_path)) {
+            by_file_name.entry(name).or_default().push(index);
+        }
+    }
+
+    for audio_path in parallel::walk_directory_parallel(library) {
+        if lrc::sidecar_path(&audio_path).exists() {
+            summary.existing += 1;
+            continue;
+        }
+
+        let matched = match metadata::extract(&audio_path) {
+            Ok(track) => {
+                let signature = TrackSignature::from_track(&track).generate_hash();
+                let duration = track.duration_secs as u32;
+                by_signature.get(signature.as_str()).copied().or_else(|| {
+                    by_metadata
+                        .get(&metadata_key(&track.artist, &track.title))?
+                        .iter()
+                        .copied()
+                        .find(|&i| {
+                            sources[i].entry.duration_sec.abs_diff(duration)
+                                <= DURATION_TOLERANCE_SECS
+                        })
+                })
+            }
+            Err(e) => {
+                tracing::debug!("No tags in {}: {}", audio_path.display(), e);
+                None
+            }
+        };
+        // A file name shared by several untagged sources ("01.flac") says too little
+        let matched = matched.or_else(|| match by_file_name.get(&file_name_key(&audio_path)?) {
+            Some(indices) if indices.len() == 1 => Some(indices[0]),
+            _ => None,
+        });
+
+        let Some(index) = matched else {
+            summary.unmatched += 1;
+            continue;
+        };
+
+        match lrc::write_sidecar(&audio_path, &sources[index].lyrics, write_options) {
+            Ok(_) => {
+                tracing::info!(
+                    "Imported lyrics for {} from {}",
+                    audio_path.display(),
+                    sources[index].entry.audio_path
+                );
+                summary.imported += 1;
+            }
+            Err(e) => {
+                tracing::error!("Failed to import {}: {}", audio_path.display(), e);
+                summary.errors += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Read the manifest and lyrics from an exported archive
+fn load_archive(path: &Path, summary: &mut ImportSummary) -> Result<Vec<SourceLyrics>> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open archive: {}", path.display()))?;
+    let mut zip = ZipArchive::new(file)
+        .with_context(|| format!("Not a valid lyrics archive: {}", path.display()))?;
+
+    let manifest: Manifest = serde_json::from_reader(
+        zip.by_name(MANIFEST_NAME)
+            .context("Archive has no manifest")?,
+    )
+    .context("Failed to parse archive manifest")?;
+
+    let mut sources = Vec::with_capacity(manifest.entries.len());
+    for entry in manifest.entries {
+        let mut bytes = Vec::new();
+        zip.by_name(&entry.lyrics_path)
+            .with_context(|| format!("Archive is missing {}", entry.lyrics_path))?
+            .read_to_end(&mut bytes)?;
+        match lyrics_text(bytes, &entry.lyrics_path) {
+            Some(lyrics) => sources.push(SourceLyrics { entry, lyrics }),
+            None => summary.unreadable += 1,
+        }
+    }
+
+    Ok(sources)
+}
+
+/// Collect sidecars and their tags from another library directory
+fn load_library(library: &Path, summary: &mut ImportSummary) -> Result<Vec<SourceLyrics>> {
+    let mut sources = Vec::new();
+
+    for audio_path in parallel::walk_directory_parallel(library) {
+        let lrc_path = lrc::sidecar_path(&audio_path);
+        if !lrc_path.exists() {
+            continue;
+        }
+
+        let bytes = std::fs::read(&lrc_path)
+            .with_context(|| format!("Failed to read {}", lrc_path.display()))?;
+        let Some(lyrics) = lyrics_text(bytes, &lrc_path.display().to_string()) else {
+            summary.unreadable += 1;
+            continue;
+        };
+        sources.push(SourceLyrics {
+            entry: manifest_entry(library, &audio_path, &lrc_path),
+            lyrics,
+        });
+    }
+
+    Ok(sources)
+}
+
+/// A sidecar's text, or None with a warning when it isn't UTF-8
+fn lyrics_text(bytes: Vec<u8>, name: &str) -> Option<String> {
+    String::from_utf8(bytes)
+        .map_err(|_| tracing::warn!("Skipping {}: not UTF-8 text", name))
+        .ok()
+}
+
+/// Key for matching untagged sources by file name: the lowercased stem
+fn file_name_key(audio_path: &Path) -> Option<String> {
+    Some(audio_path.file_stem()?.to_string_lossy().to_lowercase())
+}
+
+/// Key for loose metadata matching, using the same cleaning as API searches
+fn metadata_key(artist: &str, title: &str) -> (String, String) {
+    (clean::clean_string(artist), clean::clean_title(title))
+}
+
+/// Describe a sidecar and its audio file for the manifest
+fn manifest_entry(library: &Path, audio_path: &Path, lrc_path: &Path) -> ManifestEntry {
+    let audio_relative = relative_path(library, audio_path);
+    let lyrics_path = format!("{}{}", LYRICS_PREFIX, relative_path(library, lrc_path));
+
+    match metadata::extract(audio_path) {
+        Ok(track) => ManifestEntry {
+            signature: Some(TrackSignature::from_track(&track).generate_hash()),
+            audio_path: audio_relative,
+            lyrics_path,
+            artist: track.artist,
+            title: track.title,
+            album: track.album,
+            duration_sec: track.duration_secs as u32,
+        },
+        Err(e) => {
+            tracing::warn!(
+                "Archiving {} without signature: {}",
+                audio_path.display(),
+                e
+            );
+            ManifestEntry {
+                signature: None,
+                audio_path: audio_relative,
+                lyrics_path,
+                artist: String::new(),
+                title: String::new(),
+                album: String::new(),
+                duration_sec: 0,
+            }
+        }
+    }
+}
+
+/// Path of `path` relative to `root`, always `/`-separated
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_path() {
+        assert_eq!(
+            relative_path(
+                Path::new("/music"),
+                Path::new("/music/Artist/Album/01.flac")
+            ),
+            "Artist/Album/01.flac"
+        );
+    }
+
+    #[test]
+    fn test_export_writes_sidecars_and_manifest() {
+        let library = tempfile::tempdir().unwrap();
+        let album = library.path().join("Artist").join("Album");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(album.join("01.flac"), b"not really audio").unwrap();
+        std::fs::write(album.join("01.lrc"), "[00:01.00] hello").unwrap();
+        std::fs::write(album.join("02.flac"), b"no lyrics").unwrap();
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let out = out_dir.path().join("lyrics.zip");
+        let summary = export(library.path(), &out).unwrap();
+        assert_eq!(summary.exported, 1);
+        assert_eq!(summary.untagged, 1);
+
+        let mut archive = zip::ZipArchive::new(File::open(&out).unwrap()).unwrap();
+        let mut lyrics = String::new();
+        archive
+            .by_name("lyrics/Artist/Album/01.lrc")
+            .unwrap()
+            .read_to_string(&mut lyrics)
+            .unwrap();
+        assert_eq!(lyrics, "[00:01.00] hello");
+
+        let manifest: Manifest =
+            serde_json::from_reader(archive.by_name(MANIFEST_NAME).unwrap()).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert_eq!(manifest.entries[0].audio_path, "Artist/Album/01.flac");
+        assert!(manifest.entries[0].signature.is_none());
+    }
+
+    #[test]
+    fn test_metadata_key_ignores_featuring() {
+        assert_eq!(
+            metadata_key("The Artist", "Song (feat. Someone)"),
+            metadata_key("the artist", "Song")
+        );
+    }
+
+    #[test]
+    fn test_import_matches_untagged_entries_by_file_name() {
+        let library = tempfile::tempdir().unwrap();
+        std::fs::write(library.path().join("Intro.flac"), b"not really audio").unwrap();
+        std::fs::write(library.path().join("Intro.lrc"), "[00:01.00] hello").unwrap();
+        for album in ["a", "b"] {
+            let dir = library.path().join(album);
+            std::fs::create_dir(&dir).unwrap();
+            std::fs::write(dir.join("01.flac"), b"not really audio").unwrap();
+            std::fs::write(dir.join("01.lrc"), "[00:01.00] hello").unwrap();
+        }
+
+        let target = tempfile::tempdir().unwrap();
+        for name in ["intro.flac", "01.flac", "Other.flac"] {
+            std::fs::write(target.path().join(name), b"not really audio").unwrap();
+        }
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let out = out_dir.path().join("lyrics.zip");
+        export(library.path(), &out).unwrap();
+
+        // Two sources are named 01, so neither is picked for it
+        let summary = import(&out, target.path(), &WriteOptions::default()).unwrap();
+        assert_eq!(summary.imported, 1);
+        assert_eq!(summary.unmatched, 2);
+        assert_eq!(
+            std::fs::read_to_string(target.path().join("intro.lrc")).unwrap(),
+            "[00:01.00] hello"
+        );
+        assert!(!target.path().join("01.lrc").exists());
+    }
+
+    #[test]
+    fn test_import_skips_sidecars_that_are_not_text() {
+        let library = tempfile::tempdir().unwrap();
+        for name in ["good", "bad"] {
+            std::fs::write(library.path().join(format!("{}.flac", name)), b"audio").unwrap();
+        }
+        std::fs::write(library.path().join("good.lrc"), "[00:01.00] hello").unwrap();
+        std::fs::write(library.path().join("bad.lrc"), b"[00:01.00] \xff\xfe").unwrap();
+
+        let target = tempfile::tempdir().unwrap();
+        for name in ["good", "bad"] {
+            std::fs::write(target.path().join(format!("{}.flac", name)), b"audio").unwrap();
+        }
+
+        let summary = import(library.path(), target.path(), &WriteOptions::default()).unwrap();
+        assert_eq!(summary.unreadable, 1);
+        assert_eq!(summary.imported, 1);
+        assert!(target.path().join("good.lrc").exists());
+        assert!(!target.path().join("bad.lrc").exists());
+    }
+}
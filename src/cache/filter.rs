@@ -0,0 +1,109 @@
+use super::CacheEntry;
+use crate::scanner::clean::clean_string;
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// Selects entries for `cache invalidate`
+/// Entries recorded before tags and paths were stored have neither, so they never match.
+#[derive(Debug, Default)]
+pub struct EntryFilter {
+    /// Normalized artist name
+    artist: Option<String>,
+    path: Option<Regex>,
+}
+
+impl EntryFilter {
+    /// Build a filter from an artist name and/or a path glob (both must match when given)
+    pub fn new(artist: Option<&str>, path_glob: Option<&str>) -> Result<Self> {
+        let path = path_glob
+            .map(|pattern| {
+                glob_to_regex(pattern).with_context(|| format!("Invalid path glob: {}", pattern))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            artist: artist.map(clean_string),
+            path,
+        })
+    }
+
+    pub fn matches(&self, entry: &CacheEntry) -> bool {
+        let artist_matches = self.artist.as_ref().is_none_or(|artist| {
+            !entry.artist.is_empty() && clean_string(&entry.artist) == *artist
+        });
+        let path_matches = self
+            .path
+            .as_ref()
+            .is_none_or(|path| !entry.path.is_empty() && path.is_match(&entry.path));
+
+        artist_matches && path_matches
+    }
+}
+
+/// Translate a glob into an anchored regex
+/// `**` crosses directories, `*` and `?` stay within one path component.
+fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    // `**/` also matches no directories at all
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    Ok(Regex::new(&regex)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_to_regex() {
+        let glob = glob_to_regex("**/Artist/**").unwrap();
+        assert!(glob.is_match("/music/Artist/Album/01.flac"));
+        assert!(!glob.is_match("/music/Other Artist/Album/01.flac"));
+
+        let glob = glob_to_regex("/music/*.flac").unwrap();
+        assert!(glob.is_match("/music/01.flac"));
+        assert!(!glob.is_match("/music/Album/01.flac"));
+    }
+
+    #[test]
+    fn test_filter_matches_normalized_artist() {
+        let entry = CacheEntry {
+            signature: "sig".to_string(),
+            timestamp: 0,
+            attempts: 1,
+            last_attempt: None,
+            rules_version: 0,
+            reason: Default::default(),
+            artist: "The Artist".to_string(),
+            title: "Song".to_string(),
+            path: "/music/The Artist/01.flac".to_string(),
+            provider: "lrclib".to_string(),
+            status: Some(404),
+        };
+
+        assert!(EntryFilter::new(Some("the artist"), None)
+            .unwrap()
+            .matches(&entry));
+        assert!(!EntryFilter::new(Some("the artist"), Some("**/Other/**"))
+            .unwrap()
+            .matches(&entry));
+    }
+}
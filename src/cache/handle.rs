@@ -0,0 +1,254 @@
+use super::index::IndexEntry;
+use super::{now_secs, Expiry, FailedLookup, NegativeCache, NegativeReason};
+use crate::session::StatusType;
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+
+/// Requests served by the cache thread
+enum Command {
+    Add {
+        lookup: FailedLookup,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Remove {
+        signature: String,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Record {
+        entry: IndexEntry,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Lookup {
+        path: String,
+        reply: oneshot::Sender<Result<Option<IndexEntry>>>,
+    },
+    FilesWithStatus {
+        root: PathBuf,
+        status: StatusType,
+        reply: oneshot::Sender<Result<Vec<PathBuf>>>,
+    },
+    RecordDirs {
+        dirs: Vec<(String, i64)>,
+        settings: i64,
+        reply: oneshot::Sender<Result<()>>,
+    },
+    Flush {
+        reply: oneshot::Sender<Result<()>>,
+    },
+}
+
+/// Async handle to a [`NegativeCache`] owned by a dedicated thread
+/// Database calls block, so they run off the Tokio executor and never stall workers mid-await.
+/// Lookups are answered from an in-memory index loaded at startup; writes go through to the database.
+#[derive(Clone)]
+pub struct CacheHandle {
+    tx: mpsc::UnboundedSender<Command>,
+    /// signature -> (attempts, last_attempt, reason)
+    index: Arc<DashMap<String, (u32, i64, NegativeReason)>>,
+    expiry: Expiry,
+}
+
+impl CacheHandle {
+    /// Move the cache onto its own thread
+    /// The thread exits (flushing pending writes) once every handle is dropped.
+    pub fn spawn(cache: NegativeCache) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let expiry = cache.expiry();
+
+        let index: DashMap<String, (u32, i64, NegativeReason)> = cache
+            .entries()?
+            .into_iter()
+            .map(|e| {
+                let last_attempt = e.last_attempt.unwrap_or(e.timestamp);
+                (e.signature, (e.attempts, last_attempt, e.reason))
+            })
+            .collect();
+        tracing::info!("Loaded {} negative cache entries into memory", index.len());
+
+        std::thread::Builder::new()
+            .name("getlrc-cache".to_string())
+            .spawn(move || serve(cache, rx))?;
+
+        Ok(Self {
+            tx,
+            index: Arc::new(index),
+            expiry,
+        })
+    }
+
+    /// Check if a track signature is in the negative cache and not expired
+    pub fn is_cached(&self, signature: &str) -> Result<bool> {
+        Ok(self.cached_reason(signature)?.is_some())
+    }
+
+    /// Why a track signature is negative cached, if it is and has not expired
+    pub fn cached_reason(&self, signature: &str) -> Result<Option<NegativeReason>> {
+        let now = now_secs()?;
+        Ok(self.index.get(signature).and_then(|entry| {
+            let (attempts, last_attempt, reason) = *entry;
+            (!self.expiry.is_expired(attempts, last_attempt, now)).then_some(reason)
+        }))
+    }
+
+    /// Whether a track signature has a negative cache entry, expired or not
+    pub fn contains(&self, signature: &str) -> bool {
+        self.index.contains_key(signature)
+    }
+
+    /// Record a failed lookup
+    pub async fn add(&self, lookup: FailedLookup) -> Result<()> {
+        let now = now_secs()?;
+        let reason = lookup.reason;
+        self.index
+            .entry(lookup.signature.clone())
+            .and_modify(|entry| {
+                entry.0 += 1;
+                entry.1 = now;
+                entry.2 = reason;
+            })
+            .or_insert((1, now, reason));
+
+        self.request(|reply| Command::Add { lookup, reply }).await
+    }
+
+    /// Remove a track signature from the negative cache
+    pub async fn remove(&self, signature: &str) -> Result<()> {
+        self.index.remove(signature);
+
+        let signature = signature.to_string();
+        self.request(|reply| Command::Remove { signature, reply })
+            .await
+    }
+
+    /// Record a file's outcome in the library index
+    pub async fn record(&self, entry: IndexEntry) -> Result<()> {
+        self.request(|reply| Command::Record { entry, reply }).await
+    }
+
+    /// Look up a file in the library index
+    pub async fn indexed_file(&self, path: &Path) -> Result<Option<IndexEntry>> {
+        let path = crate::path_encoding::encode(path);
+        self.request(|reply| Command::Lookup { path, reply }).await
+    }
+
+    /// Indexed files under `root` whose last check ended with `status`
+    pub async fn files_with_status(&self, root: &Path, status: StatusType) -> Result<Vec<PathBuf>> {
+        let root = root.to_path_buf();
+        self.request(|reply| Command::FilesWithStatus {
+            root,
+            status,
+            reply,
+        })
+        .await
+    }
+
+    /// Remember directory mtimes from a completed scan, and the scan settings it used
+    pub async fn record_dirs(&self, dirs: Vec<(String, i64)>, settings: i64) -> Result<()> {
+        self.request(|reply| Command::RecordDirs {
+            dirs,
+            settings,
+            reply,
+        })
+        .await
+    }
+
+    /// Write buffered entries to disk
+    pub async fn flush(&self) -> Result<()> {
+        self.request(|reply| Command::Flush { reply }).await
+    }
+
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<Result<T>>) -> Command,
+    ) -> Result<T> {
+        let (reply, response) = oneshot::channel();
+        self.tx
+            .send(command(reply))
+            .map_err(|_| anyhow!("Cache thread has stopped"))?;
+        response
+            .await
+            .map_err(|_| anyhow!("Cache thread dropped the request"))?
+    }
+}
+
+fn serve(mut cache: NegativeCache, mut rx: mpsc::UnboundedReceiver<Command>) {
+    while let Some(command) = rx.blocking_recv() {
+        // A dropped receiver only means the caller stopped waiting
+        match command {
+            Command::Add { lookup, reply } => {
+                let _ = reply.send(cache.add(lookup));
+            }
+            Command::Remove { signature, reply } => {
+                let _ = reply.send(cache.remove(&signature));
+            }
+            Command::Record { entry, reply } => {
+                let _ = reply.send(cache.record_file(entry));
+            }
+            Command::Lookup { path, reply } => {
+                let _ = reply.send(cache.indexed_file(&path));
+            }
+            Command::FilesWithStatus {
+                root,
+                status,
+                reply,
+            } => {
+                let _ = reply.send(cache.files_with_status(&root, status));
+            }
+            Command::RecordDirs {
+                dirs,
+                settings,
+                reply,
+            } => {
+                let _ = reply.send(cache.record_dirs(&dirs, settings));
+            }
+            Command::Flush { reply } => {
+                let _ = reply.send(cache.flush());
+            }
+        }
+    }
+
+    tracing::debug!("Cache thread shutting down");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handle_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+        let handle = CacheHandle::spawn(cache).unwrap();
+
+        assert!(!handle.is_cached("sig").unwrap());
+        handle
+            .add(FailedLookup::new("sig", NegativeReason::PlainOnly))
+            .await
+            .unwrap();
+        assert_eq!(
+            handle.cached_reason("sig").unwrap(),
+            Some(NegativeReason::PlainOnly)
+        );
+        handle.remove("sig").await.unwrap();
+        assert!(!handle.is_cached("sig").unwrap());
+        handle.flush().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_loads_existing_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.db");
+        {
+            let mut cache = NegativeCache::open(&path).unwrap();
+            cache
+                .add(FailedLookup::new("sig", NegativeReason::NotFound))
+                .unwrap();
+        }
+
+        let handle = CacheHandle::spawn(NegativeCache::open(&path).unwrap()).unwrap();
+        assert!(handle.is_cached("sig").unwrap());
+    }
+}
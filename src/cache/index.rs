@@ -0,0 +1,175 @@
+use super::{now_secs, NegativeCache};
+use crate::api::PROVIDER;
+use crate::path_encoding;
+use crate::session::StatusType;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// One audio file in the library index
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IndexEntry {
+    /// Path in the form produced by [`path_encoding::encode`]
+    pub path: String,
+    /// Track signature, if the file's tags could be read
+    pub signature: Option<String>,
+    /// Outcome of the last check
+    pub status: StatusType,
+    /// Where the lyrics came from (`lrclib`, `sidecar`, `embedded`), if the file has any
+    pub source: Option<String>,
+    /// When the file was last checked
+    pub last_checked: i64,
+    /// File mtime (Unix seconds) and size when checked, used to spot retagged files
+    #[serde(default)]
+    pub mtime: Option<i64>,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl IndexEntry {
+    /// Describe the outcome of checking `path` just now
+    pub fn new(path: &Path, signature: Option<&str>, status: StatusType) -> Result<Self> {
+        let source = match status {
+            StatusType::Downloaded => Some(PROVIDER),
+            StatusType::Existing => Some("sidecar"),
+            StatusType::EmbeddedExists => Some("embedded"),
+            _ => None,
+        };
+
+        let stamp = file_stamp(path);
+        Ok(Self {
+            path: path_encoding::encode(path),
+            signature: signature.map(str::to_string),
+            status,
+            source: source.map(str::to_string),
+            last_checked: now_secs()?,
+            mtime: stamp.map(|(mtime, _)| mtime),
+            size: stamp.map(|(_, size)| size),
+        })
+    }
+
+    /// Whether the file's mtime and size still match what was indexed
+    /// Entries without a recorded stamp are never current.
+    pub fn is_current(&self, path: &Path) -> bool {
+        let (Some(mtime), Some(size)) = (self.mtime, self.size) else {
+            return false;
+        };
+        file_stamp(path) == Some((mtime, size))
+    }
+
+    /// Whether the file's mtime or size differs from when it was indexed
+    /// Entries without a recorded stamp never count as changed.
+    pub fn file_changed(&self, path: &Path) -> bool {
+        let (Some(mtime), Some(size)) = (self.mtime, self.size) else {
+            return false;
+        };
+        file_stamp(path).is_some_and(|current| current != (mtime, size))
+    }
+}
+
+/// Current (mtime, size) of a file, if it can be read
+fn file_stamp(path: &Path) -> Option<(i64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let mtime = filetime::FileTime::from_last_modification_time(&metadata).unix_seconds();
+    Some((mtime, metadata.len()))
+}
+
+/// Library coverage summary for `cache stats`
+#[derive(Debug, Default)]
+pub struct IndexStats {
+    pub total: usize,
+    /// Files with a sidecar or embedded lyrics
+    pub with_lyrics: usize,
+    pub by_status: BTreeMap<StatusType, usize>,
+}
+
+impl NegativeCache {
+    /// Summarize lyrics coverage across every indexed file
+    pub fn index_stats(&self) -> Result<IndexStats> {
+        let mut stats = IndexStats::default();
+
+        for entry in self.indexed_files()? {
+            stats.total += 1;
+            if entry.status.has_lyrics() {
+                stats.with_lyrics += 1;
+            }
+            *stats.by_status.entry(entry.status).or_default() += 1;
+        }
+
+        Ok(stats)
+    }
+
+    /// Indexed files under `root` whose last check ended with `status`
+    pub fn files_with_status(&self, root: &Path, status: StatusType) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .indexed_files()?
+            .into_iter()
+            .filter(|entry| entry.status == status)
+            .map(|entry| path_encoding::decode(&entry.path))
+            .filter(|path| path.starts_with(root))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_summarize_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+
+        let entry = IndexEntry::new(
+            Path::new("/music/01.flac"),
+            Some("sig"),
+            StatusType::Downloaded,
+        )
+        .unwrap();
+        cache.record_file(entry.clone()).unwrap();
+        assert_eq!(
+            cache.indexed_file("/music/01.flac").unwrap(),
+            Some(entry.clone())
+        );
+
+        cache
+            .record_file(
+                IndexEntry::new(Path::new("/music/02.flac"), None, StatusType::Error).unwrap(),
+            )
+            .unwrap();
+        cache.flush().unwrap();
+        assert_eq!(cache.indexed_file("/music/01.flac").unwrap(), Some(entry));
+
+        let stats = cache.index_stats().unwrap();
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.with_lyrics, 1);
+        assert_eq!(stats.by_status[&StatusType::Error], 1);
+
+        assert_eq!(
+            cache
+                .files_with_status(Path::new("/music"), StatusType::Error)
+                .unwrap(),
+            vec![PathBuf::from("/music/02.flac")]
+        );
+        assert!(cache
+            .files_with_status(Path::new("/other"), StatusType::Error)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_file_changed_after_retag() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("01.flac");
+        std::fs::write(&audio, b"old tags").unwrap();
+
+        let entry = IndexEntry::new(&audio, Some("sig"), StatusType::NotFound).unwrap();
+        assert!(!entry.file_changed(&audio));
+        assert!(entry.is_current(&audio));
+
+        std::fs::write(&audio, b"fixed tags, longer").unwrap();
+        assert!(entry.file_changed(&audio));
+        assert!(!entry.is_current(&audio));
+    }
+}
@@ -1,63 +1,364 @@
+pub mod filter;
+pub mod handle;
+pub mod index;
 pub mod signature;
+pub mod transfer;
 
+#[cfg(not(any(feature = "sqlite", feature = "redb")))]
+compile_error!("getlrc needs a cache backend: enable the `sqlite` or `redb` feature");
+
+#[cfg(all(feature = "redb", not(feature = "sqlite")))]
+mod redb;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(all(feature = "redb", not(feature = "sqlite")))]
+pub use self::redb::NegativeCache;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::NegativeCache;
+
+use crate::scanner::metadata::Track;
 use anyhow::Result;
-use rusqlite::Connection;
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+pub(crate) const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Days between automatic compactions of the cache database
+pub(crate) const VACUUM_INTERVAL_DAYS: i64 = 30;
+
+/// Number of buffered inserts written per transaction
+pub(crate) const WRITE_BATCH_SIZE: usize = 64;
+
+/// Retry delays for progressive backoff, indexed by failed attempts (last one repeats)
+const BACKOFF_DAYS: &[u64] = &[7, 30, 90];
 
-pub struct NegativeCache {
-    conn: Connection,
+/// Age buckets (upper bounds in days) reported by `cache stats`
+pub const AGE_BUCKETS_DAYS: &[i64] = &[7, 30, 90];
+
+/// When negative entries become due for a retry
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Expiry {
+    /// Never retry automatically
+    Never,
+    /// Retry once the last attempt is older than a fixed age
+    Fixed(Duration),
+    /// Wait longer after each failed attempt (7, 30, then every 90 days)
+    #[default]
+    Backoff,
+}
+
+impl Expiry {
+    /// How long an entry with `attempts` failures stays valid
+    pub fn retry_after(&self, attempts: u32) -> Option<Duration> {
+        match self {
+            Expiry::Never => None,
+            Expiry::Fixed(ttl) => Some(*ttl),
+            Expiry::Backoff => {
+                let index = (attempts.max(1) as usize - 1).min(BACKOFF_DAYS.len() - 1);
+                Some(Duration::from_secs(BACKOFF_DAYS[index] * DAY_SECS as u64))
+            }
+        }
+    }
+
+    pub(crate) fn is_expired(&self, attempts: u32, last_attempt: i64, now: i64) -> bool {
+        self.retry_after(attempts)
+            .is_some_and(|window| now - last_attempt >= window.as_secs() as i64)
+    }
+}
+
+/// Why a lookup was recorded as negative
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default,
+)]
+pub enum NegativeReason {
+    /// No matching track on the provider
+    #[default]
+    #[serde(rename = "404")]
+    NotFound,
+    /// Track found, but only unsynced lyrics are available
+    #[serde(rename = "plain_only")]
+    PlainOnly,
+    /// Track is marked as instrumental
+    #[serde(rename = "instrumental")]
+    Instrumental,
+}
+
+impl NegativeReason {
+    /// Name stored in the database and shown by `cache stats`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NegativeReason::NotFound => "404",
+            NegativeReason::PlainOnly => "plain_only",
+            NegativeReason::Instrumental => "instrumental",
+        }
+    }
+}
+
+impl std::str::FromStr for NegativeReason {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "404" => Ok(NegativeReason::NotFound),
+            "plain_only" => Ok(NegativeReason::PlainOnly),
+            "instrumental" => Ok(NegativeReason::Instrumental),
+            other => anyhow::bail!("Unknown negative cache reason: {}", other),
+        }
+    }
+}
+
+/// A single negative cache row
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CacheEntry {
+    pub signature: String,
+    /// When the track was first recorded as not found
+    pub timestamp: i64,
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    /// When the most recent lookup failed (defaults to `timestamp`)
+    #[serde(default)]
+    pub last_attempt: Option<i64>,
+    /// Normalization rules version the entry was recorded under
+    #[serde(default)]
+    pub rules_version: u32,
+    /// Why the lookup failed (entries from before reasons were tracked count as not found)
+    #[serde(default)]
+    pub reason: NegativeReason,
+    /// Tags and file path at the last failure; empty for entries recorded before they were stored
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub path: String,
+    /// Lyrics provider that answered the last lookup (empty if unknown)
+    #[serde(default)]
+    pub provider: String,
+    /// HTTP status of the last lookup
+    #[serde(default)]
+    pub status: Option<u16>,
+}
+
+/// A failed lookup to record in the negative cache
+#[derive(Debug, Clone, Default)]
+pub struct FailedLookup {
+    pub signature: String,
+    pub reason: NegativeReason,
+    pub artist: String,
+    pub title: String,
+    pub path: String,
+    pub provider: String,
+    pub status: Option<u16>,
+}
+
+impl FailedLookup {
+    pub fn new(signature: &str, reason: NegativeReason) -> Self {
+        Self {
+            signature: signature.to_string(),
+            reason,
+            ..Default::default()
+        }
+    }
+
+    /// Describe a failed lookup for a scanned track
+    pub fn for_track(track: &Track, signature: &str, reason: NegativeReason) -> Self {
+        Self {
+            signature: signature.to_string(),
+            reason,
+            artist: track.artist.clone(),
+            title: track.title.clone(),
+            path: track.path.display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Record which provider answered and with what HTTP status
+    pub fn with_response(mut self, provider: &str, status: u16) -> Self {
+        self.provider = provider.to_string();
+        self.status = Some(status);
+        self
+    }
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+/// Summary of cache contents for `cache stats`
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    pub total: usize,
+    /// Entries whose retry window has passed
+    pub due: usize,
+    /// Entry counts keyed by number of failed attempts
+    pub by_attempts: BTreeMap<u32, usize>,
+    /// Entry counts keyed by failure reason
+    pub by_reason: BTreeMap<NegativeReason, usize>,
+    /// Entry counts keyed by provider and HTTP status of the last lookup
+    pub by_response: BTreeMap<(String, Option<u16>), usize>,
+    /// Entry counts by last-attempt age, one slot per `AGE_BUCKETS_DAYS` bound plus an overflow slot
+    pub by_age: Vec<usize>,
 }
 
 impl NegativeCache {
-    /// Open or create the SQLite database
-    pub fn open(path: &Path) -> Result<Self> {
-        let conn = Connection::open(path)?;
+    /// Set when negative entries become due for a retry
+    pub fn with_expiry(mut self, expiry: Expiry) -> Self {
+        self.expiry = expiry;
+        self
+    }
+
+    /// The configured retry policy
+    pub fn expiry(&self) -> Expiry {
+        self.expiry
+    }
 
-        // Create table if it doesn't exist
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS negative_cache (
-                signature TEXT PRIMARY KEY,
-                timestamp INTEGER NOT NULL
-            )",
-            [],
-        )?;
+    /// Remove every entry matching `filter` and return how many were removed
+    pub fn invalidate(&mut self, filter: impl Fn(&CacheEntry) -> bool) -> Result<usize> {
+        self.flush()?;
 
-        Ok(Self { conn })
+        let matched: Vec<String> = self
+            .entries()?
+            .into_iter()
+            .filter(|entry| filter(entry))
+            .map(|entry| entry.signature)
+            .collect();
+        for signature in &matched {
+            self.remove(signature)?;
+        }
+
+        tracing::info!("Invalidated {} negative cache entries", matched.len());
+        Ok(matched.len())
     }
 
-    /// Check if a track signature is in the negative cache
-    pub fn is_cached(&self, signature: &str) -> Result<bool> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT 1 FROM negative_cache WHERE signature = ?1")?;
+    /// Summarize entry staleness and retry state
+    pub fn stats(&self) -> Result<CacheStats> {
+        let now = now_secs()?;
+        let mut stats = CacheStats {
+            by_age: vec![0; AGE_BUCKETS_DAYS.len() + 1],
+            ..Default::default()
+        };
+
+        for entry in self.entries()? {
+            let last_attempt = entry.last_attempt.unwrap_or(entry.timestamp);
+            let age_days = (now - last_attempt) / DAY_SECS;
 
-        let exists = stmt.exists([signature])?;
-        Ok(exists)
+            stats.total += 1;
+            *stats.by_attempts.entry(entry.attempts).or_default() += 1;
+            *stats.by_reason.entry(entry.reason).or_default() += 1;
+            *stats
+                .by_response
+                .entry((entry.provider, entry.status))
+                .or_default() += 1;
+            let bucket = AGE_BUCKETS_DAYS
+                .iter()
+                .position(|&bound| age_days < bound)
+                .unwrap_or(AGE_BUCKETS_DAYS.len());
+            stats.by_age[bucket] += 1;
+            if self.expiry.is_expired(entry.attempts, last_attempt, now) {
+                stats.due += 1;
+            }
+        }
+
+        Ok(stats)
     }
+}
+
+pub(crate) fn now_secs() -> Result<i64> {
+    Ok(std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::clean::NORMALIZATION_VERSION;
 
-    /// Add a track signature to the negative cache
-    pub fn add(&self, signature: &str) -> Result<()> {
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_secs() as i64;
+    fn insert(cache: &NegativeCache, signature: &str, attempts: u32, days_ago: i64) {
+        let last_attempt = now_secs().unwrap() - days_ago * DAY_SECS;
+        cache
+            .merge(&[CacheEntry {
+                signature: signature.to_string(),
+                timestamp: last_attempt,
+                attempts,
+                last_attempt: Some(last_attempt),
+                rules_version: NORMALIZATION_VERSION,
+                reason: NegativeReason::NotFound,
+                artist: String::new(),
+                title: String::new(),
+                path: String::new(),
+                provider: String::new(),
+                status: None,
+            }])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_backoff_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+        insert(&cache, "first-fresh", 1, 3);
+        insert(&cache, "first-due", 1, 8);
+        insert(&cache, "second-fresh", 2, 20);
+        insert(&cache, "third-fresh", 5, 60);
+        insert(&cache, "third-due", 5, 100);
+
+        assert!(cache.is_cached("first-fresh").unwrap());
+        assert!(!cache.is_cached("first-due").unwrap());
+        assert!(cache.is_cached("second-fresh").unwrap());
+        assert!(cache.is_cached("third-fresh").unwrap());
+        assert!(!cache.is_cached("third-due").unwrap());
+
+        let stats = cache.stats().unwrap();
+        assert_eq!(stats.total, 5);
+        assert_eq!(stats.due, 2);
+        assert_eq!(stats.by_age, vec![1, 2, 1, 1]);
+
+        let cache = cache.with_expiry(Expiry::Never);
+        assert!(cache.is_cached("third-due").unwrap());
+    }
 
-        self.conn.execute(
-            "INSERT OR REPLACE INTO negative_cache (signature, timestamp) VALUES (?1, ?2)",
-            [signature, &timestamp.to_string()],
-        )?;
+    #[test]
+    fn test_add_counts_attempts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+        cache
+            .add(FailedLookup::new("sig", NegativeReason::NotFound))
+            .unwrap();
+        cache
+            .add(FailedLookup::new("sig", NegativeReason::PlainOnly).with_response("lrclib", 200))
+            .unwrap();
+        assert!(cache.is_cached("sig").unwrap());
+        assert!(cache.entries().unwrap().is_empty());
+        cache.flush().unwrap();
 
-        Ok(())
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].attempts, 2);
+        assert_eq!(entries[0].reason, NegativeReason::PlainOnly);
+        assert_eq!(entries[0].provider, "lrclib");
+        assert_eq!(entries[0].status, Some(200));
     }
 
-    /// Remove a track signature from the negative cache
-    /// Used when a forced retry succeeds
-    pub fn remove(&self, signature: &str) -> Result<()> {
-        self.conn.execute(
-            "DELETE FROM negative_cache WHERE signature = ?1",
-            [signature],
-        )?;
+    #[test]
+    fn test_invalidate_by_artist() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+        for (signature, artist) in [("a", "Some Artist"), ("b", "Other")] {
+            cache
+                .add(FailedLookup {
+                    artist: artist.to_string(),
+                    ..FailedLookup::new(signature, NegativeReason::NotFound)
+                })
+                .unwrap();
+        }
 
-        tracing::debug!("Removed signature from negative cache: {}", signature);
-        Ok(())
+        let removed = cache.invalidate(|e| e.artist == "Some Artist").unwrap();
+        assert_eq!(removed, 1);
+        let entries = cache.entries().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].artist, "Other");
     }
 }
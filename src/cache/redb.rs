@@ -0,0 +1,491 @@
+use super::index::IndexEntry;
+use super::{
+    now_secs, CacheEntry, Expiry, FailedLookup, DAY_SECS, VACUUM_INTERVAL_DAYS, WRITE_BATCH_SIZE,
+};
+use crate::scanner::clean::NORMALIZATION_VERSION;
+use anyhow::{Context, Result};
+use redb::{Database, DatabaseError, ReadableTable, StorageError, TableDefinition};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// signature -> JSON-encoded [`CacheEntry`]
+/// JSON keeps older files readable as fields are added, like SQLite's column migrations.
+const NEGATIVE_CACHE: TableDefinition<&str, &[u8]> = TableDefinition::new("negative_cache");
+
+/// path -> JSON-encoded [`IndexEntry`]
+const LIBRARY_INDEX: TableDefinition<&str, &[u8]> = TableDefinition::new("library_index");
+
+/// directory path -> mtime (Unix seconds) at the end of the last completed scan
+const SCAN_DIRS: TableDefinition<&str, i64> = TableDefinition::new("scan_dirs");
+
+/// Bookkeeping values such as the last compaction time
+const CACHE_META: TableDefinition<&str, i64> = TableDefinition::new("cache_meta");
+
+/// Negative cache stored in a redb database
+pub struct NegativeCache {
+    db: Database,
+    pub(super) expiry: Expiry,
+    /// Failed lookups not yet written, with their timestamps
+    pending: Vec<(FailedLookup, i64)>,
+    /// Library index updates not yet written
+    pending_files: Vec<IndexEntry>,
+}
+
+impl NegativeCache {
+    /// Open or create the redb database
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut db = connect(path)?;
+
+        // Create tables if they don't exist
+        let tx = db.begin_write()?;
+        tx.open_table(NEGATIVE_CACHE)?;
+        tx.open_table(LIBRARY_INDEX)?;
+        tx.open_table(SCAN_DIRS)?;
+        tx.open_table(CACHE_META)?;
+        tx.commit()?;
+        purge_outdated(&db)?;
+        compact_if_due(&mut db)?;
+
+        Ok(Self {
+            db,
+            expiry: Expiry::default(),
+            pending: Vec::new(),
+            pending_files: Vec::new(),
+        })
+    }
+
+    /// Check if a track signature is in the negative cache and not expired
+    pub fn is_cached(&self, signature: &str) -> Result<bool> {
+        // A failure recorded moments ago is always fresh
+        if self.pending.iter().any(|(l, _)| l.signature == signature) {
+            return Ok(true);
+        }
+
+        let tx = self.db.begin_read()?;
+        let table = tx.open_table(NEGATIVE_CACHE)?;
+
+        match table.get(signature)? {
+            Some(row) => {
+                let entry = decode(row.value())?;
+                let last_attempt = entry.last_attempt.unwrap_or(entry.timestamp);
+                Ok(!self
+                    .expiry
+                    .is_expired(entry.attempts, last_attempt, now_secs()?))
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Add a track signature to the negative cache, or record another failed attempt
+    /// Writes are buffered and committed in batches; call [`flush`](Self::flush) to persist them
+    pub fn add(&mut self, lookup: FailedLookup) -> Result<()> {
+        self.pending.push((lookup, now_secs()?));
+
+        if self.pending.len() + self.pending_files.len() >= WRITE_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write all buffered entries in a single transaction
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() && self.pending_files.is_empty() {
+            return Ok(());
+        }
+
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(NEGATIVE_CACHE)?;
+            for (lookup, timestamp) in &self.pending {
+                let existing = match table.get(lookup.signature.as_str())? {
+                    Some(row) => Some(decode(row.value())?),
+                    None => None,
+                };
+                let (first, attempts, rules_version) = match existing {
+                    Some(entry) => (entry.timestamp, entry.attempts + 1, entry.rules_version),
+                    None => (*timestamp, 1, NORMALIZATION_VERSION),
+                };
+                let entry = CacheEntry {
+                    signature: lookup.signature.clone(),
+                    timestamp: first,
+                    attempts,
+                    last_attempt: Some(*timestamp),
+                    rules_version,
+                    reason: lookup.reason,
+                    artist: lookup.artist.clone(),
+                    title: lookup.title.clone(),
+                    path: lookup.path.clone(),
+                    provider: lookup.provider.clone(),
+                    status: lookup.status,
+                };
+                table.insert(lookup.signature.as_str(), encode(&entry)?.as_slice())?;
+            }
+
+            let mut table = tx.open_table(LIBRARY_INDEX)?;
+            for entry in &self.pending_files {
+                table.insert(entry.path.as_str(), encode(entry)?.as_slice())?;
+            }
+        }
+        tx.commit()?;
+
+        tracing::debug!(
+            "Flushed {} negative cache entries and {} index updates",
+            self.pending.len(),
+            self.pending_files.len()
+        );
+        self.pending.clear();
+        self.pending_files.clear();
+        Ok(())
+    }
+
+    /// Record the outcome of checking an audio file in the library index
+    /// Buffered together with negative cache writes
+    pub fn record_file(&mut self, entry: IndexEntry) -> Result<()> {
+        self.pending_files.push(entry);
+
+        if self.pending.len() + self.pending_files.len() >= WRITE_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Look up a file in the library index
+    pub fn indexed_file(&self, path: &str) -> Result<Option<IndexEntry>> {
+        if let Some(entry) = self.pending_files.iter().rev().find(|e| e.path == path) {
+            return Ok(Some(entry.clone()));
+        }
+
+        let tx = self.db.begin_read()?;
+        let table = tx.open_table(LIBRARY_INDEX)?;
+        let entry = match table.get(path)? {
+            Some(row) => Some(decode_index(row.value())?),
+            None => None,
+        };
+        Ok(entry)
+    }
+
+    /// List every file in the library index (buffered writes are not included)
+    pub fn indexed_files(&self) -> Result<Vec<IndexEntry>> {
+        let tx = self.db.begin_read()?;
+        let table = tx.open_table(LIBRARY_INDEX)?;
+
+        let mut entries = Vec::new();
+        for row in table.iter()? {
+            let (_, value) = row?;
+            entries.push(decode_index(value.value())?);
+        }
+        Ok(entries)
+    }
+
+    /// Remember directory mtimes from a completed scan, and the scan settings it used
+    pub fn record_dirs(&mut self, dirs: &[(String, i64)], settings: i64) -> Result<()> {
+        let tx = self.db.begin_write()?;
+        {
+            let mut table = tx.open_table(SCAN_DIRS)?;
+            for (path, mtime) in dirs {
+                table.insert(path.as_str(), *mtime)?;
+            }
+            tx.open_table(CACHE_META)?
+                .insert("scan_settings", settings)?;
+        }
+        tx.commit()?;
+
+        tracing::debug!("Recorded {} directory mtimes", dirs.len());
+        Ok(())
+    }
+
+    /// Directory mtimes recorded by earlier scans with the same settings
+    /// Directories scanned for other file types may hold files never indexed, so none are returned.
+    pub fn scanned_dirs(&self, settings: i64) -> Result<HashMap<String, i64>> {
+        let tx = self.db.begin_read()?;
+        let recorded = tx
+            .open_table(CACHE_META)?
+            .get("scan_settings")?
+            .map(|v| v.value());
+        if recorded != Some(settings) {
+            return Ok(HashMap::new());
+        }
+        let table = tx.open_table(SCAN_DIRS)?;
+
+        let mut dirs = HashMap::new();
+        for row in table.iter()? {
+            let (path, mtime) = row?;
+            dirs.insert(path.value().to_string(), mtime.value());
+        }
+        Ok(dirs)
+    }
+
+    /// Remove a track signature from the negative cache
+    /// Used when a forced retry succeeds
+    pub fn remove(&mut self, signature: &str) -> Result<()> {
+        self.pending.retain(|(l, _)| l.signature != signature);
+
+        let tx = self.db.begin_write()?;
+        tx.open_table(NEGATIVE_CACHE)?.remove(signature)?;
+        tx.commit()?;
+
+        tracing::debug!("Removed signature from negative cache: {}", signature);
+        Ok(())
+    }
+
+    /// List every entry, including expired ones (buffered writes are not included)
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let tx = self.db.begin_read()?;
+        let table = tx.open_table(NEGATIVE_CACHE)?;
+
+        let mut entries = Vec::new();
+        for row in table.iter()? {
+            let (_, value) = row?;
+            let mut entry = decode(value.value())?;
+            entry.last_attempt.get_or_insert(entry.timestamp);
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Merge entries into the cache, keeping the most recent attempt on conflict
+    /// Entries recorded under older normalization rules are skipped.
+    /// Returns the number of entries inserted or updated
+    pub fn merge(&self, entries: &[CacheEntry]) -> Result<usize> {
+        let tx = self.db.begin_write()?;
+        let mut changed = 0;
+        {
+            let mut table = tx.open_table(NEGATIVE_CACHE)?;
+            for entry in entries {
+                if entry.rules_version < NORMALIZATION_VERSION {
+                    continue;
+                }
+
+                let last_attempt = entry.last_attempt.unwrap_or(entry.timestamp);
+                let existing = match table.get(entry.signature.as_str())? {
+                    Some(row) => Some(decode(row.value())?),
+                    None => None,
+                };
+                let merged = match existing {
+                    Some(current) => {
+                        let current_last = current.last_attempt.unwrap_or(current.timestamp);
+                        if last_attempt <= current_last && entry.attempts <= current.attempts {
+                            continue;
+                        }
+                        let newer = last_attempt > current_last;
+                        CacheEntry {
+                            timestamp: current.timestamp.min(entry.timestamp),
+                            attempts: current.attempts.max(entry.attempts),
+                            last_attempt: Some(current_last.max(last_attempt)),
+                            reason: if newer { entry.reason } else { current.reason },
+                            provider: if newer {
+                                entry.provider.clone()
+                            } else {
+                                current.provider
+                            },
+                            status: if newer { entry.status } else { current.status },
+                            artist: non_empty_or(&entry.artist, current.artist),
+                            title: non_empty_or(&entry.title, current.title),
+                            path: non_empty_or(&entry.path, current.path),
+                            ..current
+                        }
+                    }
+                    None => CacheEntry {
+                        last_attempt: Some(last_attempt),
+                        ..entry.clone()
+                    },
+                };
+                table.insert(entry.signature.as_str(), encode(&merged)?.as_slice())?;
+                changed += 1;
+            }
+        }
+        tx.commit()?;
+
+        Ok(changed)
+    }
+}
+
+impl Drop for NegativeCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!("Failed to flush negative cache: {}", e);
+        }
+    }
+}
+
+/// Prefer a newly merged value unless it is empty
+fn non_empty_or(value: &str, current: String) -> String {
+    if value.is_empty() {
+        current
+    } else {
+        value.to_string()
+    }
+}
+
+fn encode(value: &impl serde::Serialize) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(value)?)
+}
+
+fn decode(bytes: &[u8]) -> Result<CacheEntry> {
+    serde_json::from_slice(bytes).context("Malformed negative cache entry")
+}
+
+fn decode_index(bytes: &[u8]) -> Result<IndexEntry> {
+    serde_json::from_slice(bytes).context("Malformed library index entry")
+}
+
+/// Open the database, recreating it if the integrity check fails
+/// Losing negative entries only costs some extra lookups, so this beats aborting the run.
+fn connect(path: &Path) -> Result<Database> {
+    let opened = Database::create(path).and_then(|mut db| {
+        if !db.check_integrity()? {
+            tracing::warn!("Repaired negative cache at {}", path.display());
+        }
+        Ok(db)
+    });
+
+    match opened {
+        Ok(db) => Ok(db),
+        Err(DatabaseError::Storage(e)) if is_corruption(&e) => {
+            tracing::warn!(
+                "Negative cache at {} is corrupted ({}), recreating it",
+                path.display(),
+                e
+            );
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove corrupted cache: {}", path.display()))?;
+            Ok(Database::create(path)?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether an open failure means the file itself is damaged
+fn is_corruption(error: &StorageError) -> bool {
+    match error {
+        StorageError::Corrupted(_) => true,
+        StorageError::Io(e) => e.kind() == std::io::ErrorKind::InvalidData,
+        _ => false,
+    }
+}
+
+/// Reclaim space from deleted entries once every `VACUUM_INTERVAL_DAYS`
+fn compact_if_due(db: &mut Database) -> Result<()> {
+    let now = now_secs()?;
+    let last_compaction = {
+        let tx = db.begin_read()?;
+        let table = tx.open_table(CACHE_META)?;
+        let last = table.get("last_vacuum")?.map(|v| v.value());
+        last
+    };
+
+    match last_compaction {
+        Some(last) if now - last < VACUUM_INTERVAL_DAYS * DAY_SECS => return Ok(()),
+        // First open: start the clock without compacting a fresh database
+        None => {}
+        Some(_) => {
+            db.compact()?;
+            tracing::info!("Compacted negative cache");
+        }
+    }
+
+    let tx = db.begin_write()?;
+    tx.open_table(CACHE_META)?.insert("last_vacuum", now)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Drop entries recorded under older normalization rules so those tracks get retried
+/// Only happens when the rules changed since the cache was last opened. Signatures include
+/// the rules version, so these entries could never match a lookup again.
+fn purge_outdated(db: &Database) -> Result<()> {
+    let tx = db.begin_write()?;
+    let mut removed = 0;
+    {
+        let mut meta = tx.open_table(CACHE_META)?;
+        let opened_under = meta.get("rules_version")?.map(|row| row.value());
+        if opened_under == Some(i64::from(NORMALIZATION_VERSION)) {
+            return Ok(());
+        }
+
+        tx.open_table(NEGATIVE_CACHE)?.retain(|_, value| {
+            // Unreadable entries are dropped too; the lookup just happens again
+            let keep = decode(value).is_ok_and(|e| e.rules_version >= NORMALIZATION_VERSION);
+            if !keep {
+                removed += 1;
+            }
+            keep
+        })?;
+        meta.insert("rules_version", i64::from(NORMALIZATION_VERSION))?;
+    }
+    tx.commit()?;
+
+    if removed > 0 {
+        tracing::info!(
+            "Removed {} negative cache entries from older normalization rules",
+            removed
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::NegativeReason;
+
+    #[test]
+    fn test_purges_outdated_entries_on_open() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.redb");
+        let write = |rules_version: u32, opened_under: Option<u32>| {
+            let db = Database::create(&path).unwrap();
+            let tx = db.begin_write().unwrap();
+            let entry = CacheEntry {
+                signature: "old".to_string(),
+                timestamp: 42,
+                attempts: 1,
+                last_attempt: Some(42),
+                rules_version,
+                reason: NegativeReason::NotFound,
+                artist: String::new(),
+                title: String::new(),
+                path: String::new(),
+                provider: String::new(),
+                status: None,
+            };
+            tx.open_table(NEGATIVE_CACHE)
+                .unwrap()
+                .insert("old", encode(&entry).unwrap().as_slice())
+                .unwrap();
+            if let Some(version) = opened_under {
+                tx.open_table(CACHE_META)
+                    .unwrap()
+                    .insert("rules_version", i64::from(version))
+                    .unwrap();
+            }
+            tx.commit().unwrap();
+        };
+
+        // Entries from before the current rules are purged, however the cache was last opened
+        write(0, None);
+        let cache = NegativeCache::open(&path).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+        drop(cache);
+
+        write(1, Some(1));
+        let cache = NegativeCache::open(&path).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recreates_corrupted_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.redb");
+        std::fs::write(&path, vec![0xAB; 4096]).unwrap();
+
+        let mut cache = NegativeCache::open(&path).unwrap();
+        cache
+            .add(FailedLookup::new("sig", NegativeReason::NotFound))
+            .unwrap();
+        cache.flush().unwrap();
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+}
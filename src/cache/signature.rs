@@ -1,3 +1,5 @@
+use crate::scanner::clean::NORMALIZATION_VERSION;
+use crate::scanner::metadata::{Track, TrackPosition};
 use serde::Serialize;
 use sha2::{Digest, Sha256};
 
@@ -7,9 +9,36 @@ pub struct TrackSignature {
     pub title: String,
     pub album: Option<String>,
     pub duration_sec: u32,
+    /// Disc and track number, only for multi-disc releases
+    /// Keeps repeated titles ("Intro" on each disc) apart without changing the
+    /// signatures of single-disc albums.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disc: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track: Option<u32>,
+    /// Normalization rules the signature was generated under
+    pub rules_version: u32,
 }
 
 impl TrackSignature {
+    /// Build the signature for an extracted track
+    pub fn from_track(track: &Track) -> Self {
+        let position = if track.position.is_multi_disc() {
+            track.position
+        } else {
+            TrackPosition::default()
+        };
+        Self {
+            artist: track.artist.clone(),
+            title: track.title.clone(),
+            album: Some(track.album.clone()),
+            duration_sec: track.duration_secs as u32,
+            disc: position.disc,
+            track: position.track,
+            rules_version: NORMALIZATION_VERSION,
+        }
+    }
+
     /// Generates a hex-encoded SHA-256 hash of the track metadata
     pub fn generate_hash(&self) -> String {
         let mut hasher = Sha256::new();
@@ -21,3 +50,49 @@ impl TrackSignature {
         format!("{:x}", result) // Convert bytes to a hex string
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::metadata::MetadataSource;
+    use std::path::PathBuf;
+
+    fn track(disc: Option<u32>, disc_total: Option<u32>, number: u32) -> Track {
+        Track {
+            path: PathBuf::from("/music/Intro.flac"),
+            artist: "Artist".to_string(),
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: "Intro".to_string(),
+            album: "Album".to_string(),
+            genre: String::new(),
+            duration_secs: 60,
+            has_embedded_lyrics: false,
+            source: MetadataSource::Tags,
+            position: TrackPosition {
+                disc,
+                disc_total,
+                track: Some(number),
+            },
+            compilation: false,
+        }
+    }
+
+    #[test]
+    fn test_multi_disc_positions_are_distinct() {
+        let hash = |track: &Track| TrackSignature::from_track(track).generate_hash();
+
+        // Single-disc albums keep their signatures
+        let single = serde_json::to_string(&TrackSignature::from_track(&track(None, None, 1)));
+        assert!(!single.unwrap().contains("disc"));
+        assert_eq!(
+            hash(&track(None, None, 1)),
+            hash(&track(Some(1), Some(1), 5))
+        );
+
+        assert_ne!(
+            hash(&track(Some(1), Some(2), 1)),
+            hash(&track(Some(2), Some(2), 1))
+        );
+    }
+}
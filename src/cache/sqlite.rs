@@ -0,0 +1,681 @@
+use super::index::IndexEntry;
+use super::{
+    now_secs, CacheEntry, Expiry, FailedLookup, DAY_SECS, VACUUM_INTERVAL_DAYS, WRITE_BATCH_SIZE,
+};
+use crate::scanner::clean::NORMALIZATION_VERSION;
+use anyhow::{Context, Result};
+use rusqlite::{Connection, ErrorCode, OptionalExtension};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Negative cache stored in SQLite
+pub struct NegativeCache {
+    conn: Connection,
+    pub(super) expiry: Expiry,
+    /// Failed lookups not yet written, with their timestamps
+    pending: Vec<(FailedLookup, i64)>,
+    /// Library index updates not yet written
+    pending_files: Vec<IndexEntry>,
+}
+
+impl NegativeCache {
+    /// Open or create the SQLite database
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = connect(path)?;
+
+        // WAL lets readers proceed while a batch is being written
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "NORMAL")?;
+
+        // Create table if it doesn't exist
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS negative_cache (
+                signature TEXT PRIMARY KEY,
+                timestamp INTEGER NOT NULL,
+                attempts INTEGER NOT NULL DEFAULT 1,
+                last_attempt INTEGER,
+                rules_version INTEGER NOT NULL DEFAULT 0,
+                reason TEXT NOT NULL DEFAULT '404',
+                artist TEXT NOT NULL DEFAULT '',
+                title TEXT NOT NULL DEFAULT '',
+                path TEXT NOT NULL DEFAULT '',
+                provider TEXT NOT NULL DEFAULT '',
+                status INTEGER
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS library_index (
+                path TEXT PRIMARY KEY,
+                signature TEXT,
+                status TEXT NOT NULL,
+                source TEXT,
+                last_checked INTEGER NOT NULL,
+                mtime INTEGER,
+                size INTEGER
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS scan_dirs (
+                path TEXT PRIMARY KEY,
+                mtime INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS cache_meta (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+            )",
+            [],
+        )?;
+        migrate(&conn)?;
+        purge_outdated(&conn)?;
+        vacuum_if_due(&conn)?;
+
+        Ok(Self {
+            conn,
+            expiry: Expiry::default(),
+            pending: Vec::new(),
+            pending_files: Vec::new(),
+        })
+    }
+
+    /// Check if a track signature is in the negative cache and not expired
+    pub fn is_cached(&self, signature: &str) -> Result<bool> {
+        // A failure recorded moments ago is always fresh
+        if self.pending.iter().any(|(l, _)| l.signature == signature) {
+            return Ok(true);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT attempts, last_attempt FROM negative_cache WHERE signature = ?1")?;
+
+        let row = stmt
+            .query_row([signature], |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, i64>(1)?))
+            })
+            .optional()?;
+
+        match row {
+            Some((attempts, last_attempt)) => {
+                Ok(!self.expiry.is_expired(attempts, last_attempt, now_secs()?))
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Add a track signature to the negative cache, or record another failed attempt
+    /// Writes are buffered and committed in batches; call [`flush`](Self::flush) to persist them
+    pub fn add(&mut self, lookup: FailedLookup) -> Result<()> {
+        self.pending.push((lookup, now_secs()?));
+
+        if self.pending.len() + self.pending_files.len() >= WRITE_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write all buffered entries in a single transaction
+    pub fn flush(&mut self) -> Result<()> {
+        if self.pending.is_empty() && self.pending_files.is_empty() {
+            return Ok(());
+        }
+
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO negative_cache
+                    (signature, timestamp, attempts, last_attempt, rules_version, reason, artist, title, path, provider, status)
+                 VALUES (?1, ?2, 1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 ON CONFLICT(signature) DO UPDATE SET
+                    attempts = attempts + 1,
+                    last_attempt = excluded.last_attempt,
+                    reason = excluded.reason,
+                    artist = excluded.artist,
+                    title = excluded.title,
+                    path = excluded.path,
+                    provider = excluded.provider,
+                    status = excluded.status",
+            )?;
+            for (lookup, timestamp) in &self.pending {
+                stmt.execute(rusqlite::params![
+                    lookup.signature,
+                    timestamp,
+                    NORMALIZATION_VERSION,
+                    lookup.reason.as_str(),
+                    lookup.artist,
+                    lookup.title,
+                    lookup.path,
+                    lookup.provider,
+                    lookup.status
+                ])?;
+            }
+
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO library_index
+                    (path, signature, status, source, last_checked, mtime, size)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for entry in &self.pending_files {
+                stmt.execute(rusqlite::params![
+                    entry.path,
+                    entry.signature,
+                    entry.status.as_str(),
+                    entry.source,
+                    entry.last_checked,
+                    entry.mtime,
+                    entry.size
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        tracing::debug!(
+            "Flushed {} negative cache entries and {} index updates",
+            self.pending.len(),
+            self.pending_files.len()
+        );
+        self.pending.clear();
+        self.pending_files.clear();
+        Ok(())
+    }
+
+    /// Record the outcome of checking an audio file in the library index
+    /// Buffered together with negative cache writes
+    pub fn record_file(&mut self, entry: IndexEntry) -> Result<()> {
+        self.pending_files.push(entry);
+
+        if self.pending.len() + self.pending_files.len() >= WRITE_BATCH_SIZE {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Look up a file in the library index
+    pub fn indexed_file(&self, path: &str) -> Result<Option<IndexEntry>> {
+        if let Some(entry) = self.pending_files.iter().rev().find(|e| e.path == path) {
+            return Ok(Some(entry.clone()));
+        }
+
+        let entry = self
+            .conn
+            .query_row(
+                "SELECT path, signature, status, source, last_checked, mtime, size
+                 FROM library_index WHERE path = ?1",
+                [path],
+                index_entry_from_row,
+            )
+            .optional()?;
+        Ok(entry)
+    }
+
+    /// List every file in the library index (buffered writes are not included)
+    pub fn indexed_files(&self) -> Result<Vec<IndexEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT path, signature, status, source, last_checked, mtime, size
+             FROM library_index ORDER BY path",
+        )?;
+
+        let entries = stmt
+            .query_map([], index_entry_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Remember directory mtimes from a completed scan, and the scan settings it used
+    pub fn record_dirs(&mut self, dirs: &[(String, i64)], settings: i64) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt =
+                tx.prepare("INSERT OR REPLACE INTO scan_dirs (path, mtime) VALUES (?1, ?2)")?;
+            for (path, mtime) in dirs {
+                stmt.execute(rusqlite::params![path, mtime])?;
+            }
+        }
+        tx.execute(
+            "INSERT INTO cache_meta (key, value) VALUES ('scan_settings', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            [settings],
+        )?;
+        tx.commit()?;
+
+        tracing::debug!("Recorded {} directory mtimes", dirs.len());
+        Ok(())
+    }
+
+    /// Directory mtimes recorded by earlier scans with the same settings
+    /// Directories scanned for other file types may hold files never indexed, so none are returned.
+    pub fn scanned_dirs(&self, settings: i64) -> Result<HashMap<String, i64>> {
+        let recorded: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT value FROM cache_meta WHERE key = 'scan_settings'",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if recorded != Some(settings) {
+            return Ok(HashMap::new());
+        }
+
+        let mut stmt = self.conn.prepare("SELECT path, mtime FROM scan_dirs")?;
+
+        let dirs = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(dirs)
+    }
+
+    /// Remove a track signature from the negative cache
+    /// Used when a forced retry succeeds
+    pub fn remove(&mut self, signature: &str) -> Result<()> {
+        self.pending.retain(|(l, _)| l.signature != signature);
+        self.conn.execute(
+            "DELETE FROM negative_cache WHERE signature = ?1",
+            [signature],
+        )?;
+
+        tracing::debug!("Removed signature from negative cache: {}", signature);
+        Ok(())
+    }
+
+    /// List every entry, including expired ones (buffered writes are not included)
+    pub fn entries(&self) -> Result<Vec<CacheEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT signature, timestamp, attempts, last_attempt, rules_version, reason, artist, title, path, provider, status
+             FROM negative_cache ORDER BY signature",
+        )?;
+
+        let entries = stmt
+            .query_map([], |row| {
+                Ok(CacheEntry {
+                    signature: row.get(0)?,
+                    timestamp: row.get(1)?,
+                    attempts: row.get(2)?,
+                    last_attempt: Some(row.get(3)?),
+                    rules_version: row.get(4)?,
+                    reason: row.get::<_, String>(5)?.parse().unwrap_or_default(),
+                    artist: row.get(6)?,
+                    title: row.get(7)?,
+                    path: row.get(8)?,
+                    provider: row.get(9)?,
+                    status: row.get(10)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// Merge entries into the cache, keeping the most recent attempt on conflict
+    /// Entries recorded under older normalization rules are skipped.
+    /// Returns the number of entries inserted or updated
+    pub fn merge(&self, entries: &[CacheEntry]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut changed = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO negative_cache
+                    (signature, timestamp, attempts, last_attempt, rules_version, reason, artist, title, path, provider, status)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                 ON CONFLICT(signature) DO UPDATE SET
+                    reason = CASE WHEN excluded.last_attempt > negative_cache.last_attempt
+                        THEN excluded.reason ELSE negative_cache.reason END,
+                    provider = CASE WHEN excluded.last_attempt > negative_cache.last_attempt
+                        THEN excluded.provider ELSE negative_cache.provider END,
+                    status = CASE WHEN excluded.last_attempt > negative_cache.last_attempt
+                        THEN excluded.status ELSE negative_cache.status END,
+                    artist = CASE WHEN excluded.artist != '' THEN excluded.artist ELSE negative_cache.artist END,
+                    title = CASE WHEN excluded.title != '' THEN excluded.title ELSE negative_cache.title END,
+                    path = CASE WHEN excluded.path != '' THEN excluded.path ELSE negative_cache.path END,
+                    timestamp = min(negative_cache.timestamp, excluded.timestamp),
+                    attempts = max(negative_cache.attempts, excluded.attempts),
+                    last_attempt = max(negative_cache.last_attempt, excluded.last_attempt)
+                 WHERE excluded.last_attempt > negative_cache.last_attempt
+                    OR excluded.attempts > negative_cache.attempts",
+            )?;
+            for entry in entries {
+                if entry.rules_version < NORMALIZATION_VERSION {
+                    continue;
+                }
+                changed += stmt.execute(rusqlite::params![
+                    entry.signature,
+                    entry.timestamp,
+                    entry.attempts,
+                    entry.last_attempt.unwrap_or(entry.timestamp),
+                    entry.rules_version,
+                    entry.reason.as_str(),
+                    entry.artist,
+                    entry.title,
+                    entry.path,
+                    entry.provider,
+                    entry.status
+                ])?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(changed)
+    }
+}
+
+impl Drop for NegativeCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::error!("Failed to flush negative cache: {}", e);
+        }
+    }
+}
+
+fn index_entry_from_row(row: &rusqlite::Row) -> rusqlite::Result<IndexEntry> {
+    let status: String = row.get(2)?;
+    Ok(IndexEntry {
+        path: row.get(0)?,
+        signature: row.get(1)?,
+        status: status.parse().map_err(|e: anyhow::Error| {
+            rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, e.into())
+        })?,
+        source: row.get(3)?,
+        last_checked: row.get(4)?,
+        mtime: row.get(5)?,
+        size: row.get(6)?,
+    })
+}
+
+/// Open the database, recreating it if the integrity check fails
+/// Losing negative entries only costs some extra lookups, so this beats aborting the run.
+fn connect(path: &Path) -> Result<Connection> {
+    let conn = Connection::open(path)?;
+    if is_intact(&conn)? {
+        return Ok(conn);
+    }
+
+    tracing::warn!(
+        "Negative cache at {} is corrupted, recreating it",
+        path.display()
+    );
+    drop(conn);
+    for suffix in ["", "-wal", "-shm"] {
+        let mut file = path.as_os_str().to_owned();
+        file.push(suffix);
+        match std::fs::remove_file(&file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| {
+                    format!("Failed to remove corrupted cache: {}", path.display())
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Connection::open(path)?)
+}
+
+/// Run `PRAGMA quick_check`, treating unreadable files as corrupted
+fn is_intact(conn: &Connection) -> Result<bool> {
+    match conn.query_row("PRAGMA quick_check", [], |row| row.get::<_, String>(0)) {
+        Ok(status) => Ok(status == "ok"),
+        Err(rusqlite::Error::SqliteFailure(e, _))
+            if matches!(e.code, ErrorCode::NotADatabase | ErrorCode::DatabaseCorrupt) =>
+        {
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Reclaim space from deleted entries once every `VACUUM_INTERVAL_DAYS`
+fn vacuum_if_due(conn: &Connection) -> Result<()> {
+    let now = now_secs()?;
+    let last_vacuum: Option<i64> = conn
+        .query_row(
+            "SELECT value FROM cache_meta WHERE key = 'last_vacuum'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    match last_vacuum {
+        Some(last) if now - last < VACUUM_INTERVAL_DAYS * DAY_SECS => return Ok(()),
+        // First open: start the clock without vacuuming a fresh database
+        None => {}
+        Some(_) => {
+            conn.execute_batch("VACUUM")?;
+            tracing::info!("Vacuumed negative cache");
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO cache_meta (key, value) VALUES ('last_vacuum', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [now],
+    )?;
+    Ok(())
+}
+
+/// Columns added after the original schema, in the order they were introduced
+const MIGRATED_COLUMNS: &[(&str, &str)] = &[
+    ("attempts", "INTEGER NOT NULL DEFAULT 1"),
+    ("last_attempt", "INTEGER"),
+    ("rules_version", "INTEGER NOT NULL DEFAULT 0"),
+    ("reason", "TEXT NOT NULL DEFAULT '404'"),
+    ("artist", "TEXT NOT NULL DEFAULT ''"),
+    ("title", "TEXT NOT NULL DEFAULT ''"),
+    ("path", "TEXT NOT NULL DEFAULT ''"),
+    ("provider", "TEXT NOT NULL DEFAULT ''"),
+    ("status", "INTEGER"),
+];
+
+/// Columns added to `library_index` after it was introduced
+const INDEX_MIGRATED_COLUMNS: &[(&str, &str)] = &[("mtime", "INTEGER"), ("size", "INTEGER")];
+
+/// Add columns introduced after the original schema
+fn migrate(conn: &Connection) -> Result<()> {
+    add_missing_columns(conn, "negative_cache", MIGRATED_COLUMNS)?;
+    add_missing_columns(conn, "library_index", INDEX_MIGRATED_COLUMNS)?;
+    conn.execute(
+        "UPDATE negative_cache SET last_attempt = timestamp WHERE last_attempt IS NULL",
+        [],
+    )?;
+
+    Ok(())
+}
+
+fn add_missing_columns(conn: &Connection, table: &str, migrated: &[(&str, &str)]) -> Result<()> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns = stmt
+        .query_map([table], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if columns.is_empty() {
+        // Table doesn't exist yet and will be created with the full schema
+        return Ok(());
+    }
+
+    for (column, definition) in migrated {
+        if !columns.iter().any(|c| c == column) {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+            tracing::info!("Migrated {}: added {} column", table, column);
+        }
+    }
+
+    Ok(())
+}
+
+/// Drop entries recorded under older normalization rules so those tracks get retried
+/// Only happens when the rules changed since the cache was last opened. Signatures include
+/// the rules version, so these entries could never match a lookup again.
+fn purge_outdated(conn: &Connection) -> Result<()> {
+    let opened_under: Option<u32> = conn
+        .query_row(
+            "SELECT value FROM cache_meta WHERE key = 'rules_version'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if opened_under == Some(NORMALIZATION_VERSION) {
+        return Ok(());
+    }
+
+    let removed = conn.execute(
+        "DELETE FROM negative_cache WHERE rules_version < ?1",
+        [NORMALIZATION_VERSION],
+    )?;
+    conn.execute(
+        "INSERT INTO cache_meta (key, value) VALUES ('rules_version', ?1)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        [NORMALIZATION_VERSION],
+    )?;
+
+    if removed > 0 {
+        tracing::info!(
+            "Removed {} negative cache entries from older normalization rules",
+            removed
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::signature::TrackSignature;
+    use crate::cache::NegativeReason;
+    use sha2::Digest;
+
+    #[test]
+    fn test_opens_in_wal_mode() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = NegativeCache::open(&dir.path().join("cache.db")).unwrap();
+        let mode: String = cache
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(mode, "wal");
+    }
+
+    #[test]
+    fn test_migrates_original_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.db");
+
+        // A signature as generated before the rules version was part of it
+        let signature = TrackSignature {
+            artist: "artist".to_string(),
+            title: "title".to_string(),
+            album: Some("album".to_string()),
+            duration_sec: 200,
+            disc: None,
+            track: None,
+            rules_version: NORMALIZATION_VERSION,
+        };
+        let mut unversioned = serde_json::to_value(&signature).unwrap();
+        unversioned.as_object_mut().unwrap().remove("rules_version");
+        let old = format!(
+            "{:x}",
+            sha2::Sha256::digest(serde_json::to_vec(&unversioned).unwrap())
+        );
+        assert_ne!(old, signature.generate_hash());
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute(
+            "CREATE TABLE negative_cache (signature TEXT PRIMARY KEY, timestamp INTEGER NOT NULL)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO negative_cache (signature, timestamp) VALUES (?1, 42)",
+            [&old],
+        )
+        .unwrap();
+
+        migrate(&conn).unwrap();
+        let (attempts, last_attempt, rules_version): (u32, i64, u32) = conn
+            .query_row(
+                "SELECT attempts, last_attempt, rules_version FROM negative_cache",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((attempts, last_attempt, rules_version), (1, 42, 0));
+        drop(conn);
+
+        // Entries from before rules versioning can't match any lookup, so they are purged
+        let cache = NegativeCache::open(&path).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_purges_entries_when_rules_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.db");
+        let mut cache = NegativeCache::open(&path).unwrap();
+        cache
+            .add(FailedLookup::new("sig", NegativeReason::NotFound))
+            .unwrap();
+        cache.flush().unwrap();
+        drop(cache);
+
+        // Reopening under the same rules keeps every entry
+        let cache = NegativeCache::open(&path).unwrap();
+        assert_eq!(cache.entries().unwrap().len(), 1);
+
+        cache
+            .conn
+            .execute_batch(
+                "UPDATE negative_cache SET rules_version = 1;
+                 UPDATE cache_meta SET value = 1 WHERE key = 'rules_version';",
+            )
+            .unwrap();
+        drop(cache);
+        let cache = NegativeCache::open(&path).unwrap();
+        assert!(cache.entries().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recreates_corrupted_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.db");
+        std::fs::write(&path, b"definitely not a sqlite database").unwrap();
+
+        let mut cache = NegativeCache::open(&path).unwrap();
+        cache
+            .add(FailedLookup::new("sig", NegativeReason::NotFound))
+            .unwrap();
+        cache.flush().unwrap();
+        assert_eq!(cache.entries().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_vacuums_when_due() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache.db");
+        let cache = NegativeCache::open(&path).unwrap();
+        let stale = now_secs().unwrap() - (VACUUM_INTERVAL_DAYS + 1) * DAY_SECS;
+        cache
+            .conn
+            .execute(
+                "UPDATE cache_meta SET value = ?1 WHERE key = 'last_vacuum'",
+                [stale],
+            )
+            .unwrap();
+        drop(cache);
+
+        let cache = NegativeCache::open(&path).unwrap();
+        let last_vacuum: i64 = cache
+            .conn
+            .query_row(
+                "SELECT value FROM cache_meta WHERE key = 'last_vacuum'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(last_vacuum > stale);
+    }
+}
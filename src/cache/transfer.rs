@@ -0,0 +1,124 @@
+use super::{CacheEntry, NegativeCache};
+use crate::scanner::clean::NORMALIZATION_VERSION;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const DUMP_VERSION: u32 = 1;
+
+/// Portable JSON representation of the cache
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CacheDump {
+    pub version: u32,
+    pub negative: Vec<CacheEntry>,
+}
+
+/// What an import did
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    /// Entries inserted or refreshed
+    pub changed: usize,
+    /// Entries skipped for being recorded under older normalization rules
+    pub outdated: usize,
+}
+
+/// Write every cache entry to a JSON file
+/// Returns the number of entries exported
+pub fn export(cache: &NegativeCache, path: &Path) -> Result<usize> {
+    let dump = CacheDump {
+        version: DUMP_VERSION,
+        negative: cache.entries()?,
+    };
+
+    let json = serde_json::to_string_pretty(&dump).context("Failed to serialize cache")?;
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write cache dump: {}", path.display()))?;
+
+    tracing::info!(
+        "Exported {} cache entries to {}",
+        dump.negative.len(),
+        path.display()
+    );
+    Ok(dump.negative.len())
+}
+
+/// Merge entries from a JSON file produced by [`export`]
+pub fn import(cache: &NegativeCache, path: &Path) -> Result<ImportSummary> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read cache dump: {}", path.display()))?;
+    let dump: CacheDump = serde_json::from_str(&json).context("Failed to parse cache dump")?;
+
+    if dump.version > DUMP_VERSION {
+        anyhow::bail!(
+            "Cache dump version {} is newer than supported ({})",
+            dump.version,
+            DUMP_VERSION
+        );
+    }
+
+    let changed = cache.merge(&dump.negative)?;
+    let outdated = dump
+        .negative
+        .iter()
+        .filter(|entry| entry.rules_version < NORMALIZATION_VERSION)
+        .count();
+    if outdated > 0 {
+        tracing::warn!(
+            "Skipped {} cache entries from older normalization rules in {}",
+            outdated,
+            path.display()
+        );
+    }
+    tracing::info!(
+        "Imported {} of {} cache entries from {}",
+        changed,
+        dump.negative.len(),
+        path.display()
+    );
+    Ok(ImportSummary { changed, outdated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{FailedLookup, NegativeReason};
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = NegativeCache::open(&dir.path().join("source.db")).unwrap();
+        source
+            .add(FailedLookup::new("abc", NegativeReason::NotFound))
+            .unwrap();
+        source
+            .add(FailedLookup::new("def", NegativeReason::Instrumental))
+            .unwrap();
+        source.flush().unwrap();
+
+        let dump = dir.path().join("cache.json");
+        assert_eq!(export(&source, &dump).unwrap(), 2);
+
+        let mut target = NegativeCache::open(&dir.path().join("target.db")).unwrap();
+        target
+            .add(FailedLookup::new("abc", NegativeReason::NotFound))
+            .unwrap();
+        target.flush().unwrap();
+        assert_eq!(import(&target, &dump).unwrap().outdated, 0);
+
+        assert_eq!(target.entries().unwrap(), source.entries().unwrap());
+
+        // Entries from older rules are skipped, and counted
+        let mut dump_json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&dump).unwrap()).unwrap();
+        dump_json["negative"][0]["rules_version"] = 0.into();
+        std::fs::write(&dump, dump_json.to_string()).unwrap();
+        let fresh = NegativeCache::open(&dir.path().join("fresh.db")).unwrap();
+        assert_eq!(
+            import(&fresh, &dump).unwrap(),
+            ImportSummary {
+                changed: 1,
+                outdated: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+/// A duration as "1h 05m", "6m 52s" or "41s"
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match (secs / 3600, secs / 60 % 60, secs % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, _) => format!("{}h {:02}m", h, m),
+    }
+}
+
+/// Civil date and time (year, month, day, hour, minute) of Unix seconds in local time,
+/// or in UTC where it isn't available
+#[cfg(unix)]
+pub fn local_time(secs: i64) -> (i64, u32, u32, u32, u32) {
+    let time = secs as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm it is given
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return utc_time(secs);
+    }
+    (
+        i64::from(tm.tm_year) + 1900,
+        tm.tm_mon as u32 + 1,
+        tm.tm_mday as u32,
+        tm.tm_hour as u32,
+        tm.tm_min as u32,
+    )
+}
+
+#[cfg(not(unix))]
+pub fn local_time(secs: i64) -> (i64, u32, u32, u32, u32) {
+    utc_time(secs)
+}
+
+/// Civil date and time from Unix seconds (Howard Hinnant's days-to-civil algorithm)
+fn utc_time(secs: i64) -> (i64, u32, u32, u32, u32) {
+    let days = secs.div_euclid(86_400);
+    let of_day = secs.rem_euclid(86_400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (
+        year,
+        month,
+        day,
+        (of_day / 3600) as u32,
+        (of_day % 3600 / 60) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(format_duration(Duration::from_secs(41)), "41s");
+        assert_eq!(format_duration(Duration::from_secs(412)), "6m 52s");
+        assert_eq!(format_duration(Duration::from_secs(3900)), "1h 05m");
+
+        assert_eq!(utc_time(1_714_599_000), (2024, 5, 1, 21, 30));
+        assert_eq!(utc_time(0), (1970, 1, 1, 0, 0));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::api::retry::RetryPolicy;
+use crate::scanner::{
+    filename::PathTemplate,
+    metadata::{self, TagMapping},
+};
+use crate::schedule::TimeWindow;
+use crate::tui::{
+    keys::{Keymap, KeysConfig},
+    notify::Notify,
+    theme::{Theme, ThemeConfig},
+};
+use anyhow::{Context, Result};
+use lofty::tag::TagType;
+use serde::Deserialize;
+use std::path::Path;
+
+/// User settings from `~/.config/getlrc/config.toml`
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Path templates for guessing artist and title of untagged files, tried in order
+    /// before the built-in patterns, e.g. `{artist}/{album}/{track} {title}`
+    pub filename_templates: Vec<String>,
+    /// AcoustID application key; when set, untagged files are identified by their audio
+    /// fingerprint (requires Chromaprint's `fpcalc`)
+    pub acoustid_api_key: Option<String>,
+    /// Extra file extensions to scan for, on top of the built-in audio formats
+    pub audio_extensions: Vec<String>,
+    /// Leave out files and directories whose names start with a dot
+    pub skip_hidden: bool,
+    /// Only look up tracks with one of these genres
+    pub genre_include: Vec<String>,
+    /// Never look up tracks with one of these genres (audiobooks, podcasts, ...)
+    pub genre_exclude: Vec<String>,
+    /// Leave out tracks shorter than this many seconds
+    pub min_duration: Option<u64>,
+    /// Leave out tracks longer than this many seconds
+    pub max_duration: Option<u64>,
+    /// Nonstandard tags (TXXX frames, custom Vorbis comments) to read artist, title and
+    /// album from when the standard fields are empty
+    pub tag_mapping: TagMapping,
+    /// Tag types to read, most trusted first (`id3v2`, `ape`, `vorbis`, ...), for files
+    /// carrying more than one; files without any of them use the format's primary tag
+    pub tag_priority: Vec<String>,
+    /// Words to strip from titles before searching ("Explicit", "Bonus Track", store names)
+    pub title_blacklist: Vec<String>,
+    /// How failed lookups are retried before a file is recorded as an error
+    pub retry: RetryPolicy,
+    /// Times of day to process files in, e.g. `["02:00-07:00"]`; outside them the run
+    /// pauses and saves its session. Always active when empty.
+    pub active_hours: Vec<String>,
+    /// Colors of the TUI: a preset (`dark` or `light`) and colors to replace in it
+    pub theme: ThemeConfig,
+    /// Keys of the TUI's actions, e.g. `quit = ["x"]`, replacing their defaults
+    pub keys: KeysConfig,
+    /// Ring the terminal bell (`bell`) or flash the screen (`flash`) when the run completes
+    /// or lyrics wait to be picked
+    pub notify: Notify,
+}
+
+impl Config {
+    /// Read the config file, or use defaults if it doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let config =
+            toml::from_str(&contents).with_context(|| format!("Invalid {}", path.display()))?;
+
+        tracing::info!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Compile the filename templates
+    pub fn filename_templates(&self) -> Result<Vec<PathTemplate>> {
+        self.filename_templates
+            .iter()
+            .map(|template| {
+                PathTemplate::parse(template)
+                    .with_context(|| format!("Invalid filename template: {}", template))
+            })
+            .collect()
+    }
+
+    /// Parse the active hours
+    pub fn active_hours(&self) -> Result<Vec<TimeWindow>> {
+        self.active_hours
+            .iter()
+            .map(|window| {
+                TimeWindow::parse(window)
+                    .with_context(|| format!("Invalid active hours: {}", window))
+            })
+            .collect()
+    }
+
+    /// Resolve the theme's preset and colors
+    pub fn theme(&self) -> Result<Theme> {
+        Theme::from_config(&self.theme).context("Invalid theme")
+    }
+
+    /// Resolve the TUI's keys
+    pub fn keys(&self) -> Result<Keymap> {
+        Keymap::from_config(&self.keys).context("Invalid keys")
+    }
+
+    /// Parse the tag priority
+    pub fn tag_priority(&self) -> Result<Vec<TagType>> {
+        self.tag_priority
+            .iter()
+            .map(|name| metadata::parse_tag_type(name))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert_eq!(Config::load(&path).unwrap(), Config::default());
+
+        std::fs::write(
+            &path,
+            "filename_templates = [\"{artist}/{album}/{track} {title}\"]\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.filename_templates.len(), 1);
+        assert_eq!(config.filename_templates().unwrap().len(), 1);
+
+        std::fs::write(&path, "[tag_mapping]\nartist = [\"TXXX:Band\"]\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().tag_mapping.artist,
+            ["TXXX:Band"]
+        );
+
+        std::fs::write(&path, "tag_priority = [\"ID3v2\", \"ape\"]\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().tag_priority().unwrap(),
+            [TagType::Id3v2, TagType::Ape]
+        );
+        std::fs::write(&path, "tag_priority = [\"id4\"]\n").unwrap();
+        assert!(Config::load(&path).unwrap().tag_priority().is_err());
+
+        std::fs::write(
+            &path,
+            "[retry]\nmax_attempts = 5\nretry_on = [\"timeout\"]\n",
+        )
+        .unwrap();
+        let retry = Config::load(&path).unwrap().retry;
+        assert_eq!(retry.max_attempts, 5);
+        assert_eq!(
+            retry.backoff_base_ms,
+            RetryPolicy::default().backoff_base_ms
+        );
+        assert_eq!(retry.retry_on, [crate::api::retry::ErrorClass::Timeout]);
+        std::fs::write(&path, "[retry]\nretry_on = [\"dns\"]\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        std::fs::write(&path, "active_hours = [\"02:00-07:00\"]\n").unwrap();
+        assert_eq!(
+            Config::load(&path).unwrap().active_hours().unwrap().len(),
+            1
+        );
+        std::fs::write(&path, "active_hours = [\"2am-7am\"]\n").unwrap();
+        assert!(Config::load(&path).unwrap().active_hours().is_err());
+
+        std::fs::write(&path, "[theme]\npreset = \"light\"\nheader = \"blue\"\n").unwrap();
+        let theme = Config::load(&path).unwrap().theme().unwrap();
+        assert_eq!(theme.header, ratatui::style::Color::Blue);
+        std::fs::write(&path, "[theme]\nbackground = \"white\"\n").unwrap();
+        assert!(Config::load(&path).is_err());
+
+        std::fs::write(&path, "[keys]\nquit = [\"x\"]\npage_down = [\"J\"]\n").unwrap();
+        let keys = Config::load(&path).unwrap().keys().unwrap();
+        assert_eq!(keys.quit, [crossterm::event::KeyCode::Char('x')]);
+        std::fs::write(&path, "[keys]\nquit = [\"d\"]\n").unwrap();
+        assert!(Config::load(&path).unwrap().keys().is_err());
+
+        std::fs::write(&path, "filename_templates = [\"{artist}/{name}\"]\n").unwrap();
+        assert!(Config::load(&path).unwrap().filename_templates().is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use crate::clock;
+use crate::messages::{self, ErrorKind};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Runs kept in the history, the oldest dropped first
+const MAX_RUNS: usize = 200;
+
+/// Summary of a finished or quit run, kept for `getlrc history` and the TUI's history screen
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct RunRecord {
+    /// Unix seconds
+    pub started_at: i64,
+    pub duration_secs: u64,
+    /// Music directory or playlist processed
+    pub target: PathBuf,
+    /// Whether every file was processed, rather than the run being quit
+    pub completed: bool,
+    pub downloaded: usize,
+    pub cached: usize,
+    pub existing: usize,
+    pub embedded: usize,
+    pub not_found: usize,
+    pub errors: usize,
+    pub untagged: usize,
+    pub excluded: usize,
+    pub spoken: usize,
+    /// Errors by kind, as in the completion summary ("3 network, 1 io")
+    pub error_kinds: String,
+    /// Time spent in each stage, with `--timing`
+    pub timings: Vec<String>,
+}
+
+impl RunRecord {
+    /// Files the run accounted for
+    pub fn total(&self) -> usize {
+        self.downloaded
+            + self.cached
+            + self.existing
+            + self.embedded
+            + self.not_found
+            + self.errors
+            + self.untagged
+            + self.excluded
+            + self.spoken
+    }
+
+    /// Fill in the error counts from what went wrong with each failed file
+    pub fn set_errors(&mut self, kinds: impl IntoIterator<Item = ErrorKind>) {
+        let counts = messages::error_counts(kinds);
+        self.errors = counts.iter().map(|(_, count)| count).sum();
+        self.error_kinds = messages::format_error_counts(&counts);
+    }
+
+    /// One line: when, how long, where and what came of it
+    pub fn summary(&self) -> String {
+        format!(
+            "{}  {:>7}  {} downloaded, {} not found, {} errors of {}{}  {}",
+            format_date(self.started_at),
+            clock::format_duration(Duration::from_secs(self.duration_secs)),
+            self.downloaded,
+            self.not_found,
+            self.errors,
+            self.total(),
+            if self.completed { "" } else { " (quit)" },
+            self.target.display()
+        )
+    }
+
+    /// The run's full report, a line per count
+    pub fn report(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("Target:      {}", self.target.display()),
+            format!("Started:     {}", format_date(self.started_at)),
+            format!(
+                "Duration:    {}{}",
+                clock::format_duration(Duration::from_secs(self.duration_secs)),
+                if self.completed { "" } else { " (quit early)" }
+            ),
+            String::new(),
+            format!("Downloaded:  {}", self.downloaded),
+            format!("Cached:      {}", self.cached),
+            format!("Existing:    {}", self.existing),
+            format!("Embedded:    {}", self.embedded),
+            format!("Not found:   {}", self.not_found),
+            format!("Errors:      {}", self.errors),
+        ];
+        if !self.error_kinds.is_empty() {
+            lines.push(format!("             {}", self.error_kinds));
+        }
+        lines.extend([
+            format!("Untagged:    {}", self.untagged),
+            format!("Excluded:    {}", self.excluded),
+            format!("Spoken word: {}", self.spoken),
+            format!("Total:       {}", self.total()),
+        ]);
+        if !self.timings.is_empty() {
+            lines.push(String::new());
+            lines.push("Time per stage:".to_string());
+            lines.extend(self.timings.iter().map(|line| format!("  {}", line)));
+        }
+        lines
+    }
+}
+
+/// Past runs, newest first
+/// A missing file is an empty history; lines that can't be read are skipped.
+pub fn load(path: &Path) -> Result<Vec<RunRecord>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let mut runs = Vec::new();
+    for line in BufReader::new(file).lines() {
+        match serde_json::from_str(&line?) {
+            Ok(run) => runs.push(run),
+            Err(e) => tracing::warn!("Skipping unreadable run in {}: {}", path.display(), e),
+        }
+    }
+    runs.reverse();
+    Ok(runs)
+}
+
+/// Add a run to the history, dropping the oldest beyond the limit
+/// The run is appended, and the history is only rewritten to drop old runs, through a
+/// temporary file so a failed write never loses the runs already recorded.
+pub fn append(path: &Path, run: &RunRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    file.write_all(format!("{}\n", serde_json::to_string(run)?).as_bytes())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    drop(file);
+
+    let mut runs = load(path)?;
+    if runs.len() <= MAX_RUNS {
+        return Ok(());
+    }
+    runs.truncate(MAX_RUNS);
+    runs.reverse();
+    let mut contents = String::new();
+    for run in &runs {
+        contents.push_str(&serde_json::to_string(run)?);
+        contents.push('\n');
+    }
+    let temp_path = path.with_extension("jsonl.tmp");
+    std::fs::write(&temp_path, contents)
+        .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Unix seconds as "2024-05-01 21:30" in local time (UTC where it isn't available)
+fn format_date(secs: i64) -> String {
+    let (year, month, day, hour, minute) = clock::local_time(secs);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year, month, day, hour, minute
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_keeps_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.jsonl");
+        assert!(load(&path).unwrap().is_empty());
+
+        for downloaded in 0..MAX_RUNS + 2 {
+            let run = RunRecord {
+                downloaded,
+                ..Default::default()
+            };
+            append(&path, &run).unwrap();
+        }
+        let runs = load(&path).unwrap();
+        assert_eq!(runs.len(), MAX_RUNS);
+        assert_eq!(runs[0].downloaded, MAX_RUNS + 1);
+        assert_eq!(runs[MAX_RUNS - 1].downloaded, 2);
+    }
+
+    #[test]
+    fn test_run_report() {
+        let mut run = RunRecord {
+            started_at: 1_714_599_000,
+            duration_secs: 412,
+            target: PathBuf::from("/music"),
+            downloaded: 40,
+            not_found: 5,
+            ..Default::default()
+        };
+        run.set_errors([ErrorKind::Network, ErrorKind::Network, ErrorKind::Io]);
+        assert_eq!(run.errors, 3);
+        assert_eq!(run.total(), 48);
+        assert!(run
+            .summary()
+            .ends_with("6m 52s  40 downloaded, 5 not found, 3 errors of 48 (quit)  /music"));
+        assert!(run
+            .report()
+            .contains(&"             2 network, 1 io".to_string()));
+    }
+}
@@ -49,6 +49,7 @@ pub fn install() -> Result<()> {
 
     println!("You can now run: getlrc <music_directory>");
     println!("\nData will be stored in: ~/.local/share/getlrc/");
+    println!("Cache will be stored in: ~/.cache/getlrc/");
 
     Ok(())
 }
@@ -71,8 +72,10 @@ pub fn uninstall() -> Result<()> {
     // Ask about data directory
     let data_dir = crate::paths::get_data_dir()?;
     println!("\nData directory: {}", data_dir.display());
+    let cache_dir = crate::paths::get_cache_dir()?;
+    println!("Cache directory: {}", cache_dir.display());
     println!("To remove cached data, run:");
-    println!("  rm -rf {}", data_dir.display());
+    println!("  rm -rf {} {}", data_dir.display(), cache_dir.display());
 
     Ok(())
 }
@@ -1,10 +1,20 @@
 pub mod api;
+pub mod archive;
 pub mod cache;
+pub mod clock;
+pub mod config;
 pub mod env;
+pub mod history;
 pub mod install;
+pub mod lrc;
 pub mod messages;
+pub mod nice;
+pub mod path_encoding;
 pub mod paths;
 pub mod scanner;
+pub mod schedule;
 pub mod session;
+pub mod throughput;
+pub mod timing;
 pub mod tui;
 pub mod worker;
@@ -0,0 +1,642 @@
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use filetime::FileTime;
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+static TIMESTAMP_REGEX: OnceLock<Regex> = OnceLock::new();
+
+fn get_timestamp_regex() -> &'static Regex {
+    TIMESTAMP_REGEX.get_or_init(|| Regex::new(r"^\[(\d+):(\d{1,2})(?:[.:](\d{1,3}))?\]").unwrap())
+}
+
+/// Formatting applied to lyrics before they are written
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatOptions {
+    /// Decimal places for timestamps (2 or 3); `None` keeps the source precision
+    pub precision: Option<u8>,
+    /// Split `[t1][t2]text` lines into one line per timestamp
+    pub expand_repeated: bool,
+    /// Zero-pad minutes to two digits (`[01:02.03]` vs `[1:02.03]`)
+    pub pad_minutes: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            precision: None,
+            expand_repeated: false,
+            pad_minutes: true,
+        }
+    }
+}
+
+/// A parsed `[mm:ss.xx]` tag
+#[derive(Debug, Clone, Copy)]
+struct Timestamp {
+    millis: u64,
+    /// Number of fraction digits in the source tag
+    digits: usize,
+}
+
+/// Options controlling how `.lrc` sidecar files are written
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    /// Restore the parent directory's mtime after writing the sidecar
+    pub preserve_dir_mtime: bool,
+    /// Each directory's mtime from before its first sidecar was written, shared by clones
+    /// Concurrent writes into one directory would otherwise restore each other's changes.
+    pub dir_mtimes: Arc<DashMap<PathBuf, FileTime>>,
+    /// Copy the audio file's owner, group and read/write permissions onto the sidecar (Unix only)
+    pub match_permissions: bool,
+    /// How timestamps and lines are formatted
+    pub format: FormatOptions,
+    /// Sidecar format for music videos
+    pub video_sidecar: VideoSidecar,
+    /// Hard-link a duplicate's sidecar to the original's instead of copying it
+    /// Falls back to a copy across filesystems, and when permissions must match each file.
+    pub link_duplicates: bool,
+}
+
+/// Sidecar format for music videos
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VideoSidecar {
+    #[default]
+    Lrc,
+    /// SRT subtitles, which video players pick up without lyrics support
+    Srt,
+}
+
+/// How long the last line of SRT subtitles stays on screen
+const SRT_LAST_LINE_MS: u64 = 5000;
+
+/// Get the sidecar path for an audio file
+pub fn sidecar_path(audio_path: &Path) -> PathBuf {
+    let mut lrc_path = audio_path.to_path_buf();
+    lrc_path.set_extension("lrc");
+    lrc_path
+}
+
+/// Write lyrics to the `.lrc` sidecar next to the audio file, returning the path written
+/// Music videos get `.srt` subtitles instead when configured and the lyrics are synced.
+pub fn write_sidecar(audio_path: &Path, lyrics: &str, options: &WriteOptions) -> Result<PathBuf> {
+    if writes_srt(audio_path, options) {
+        if let Some(srt) = to_srt(lyrics) {
+            let srt_path = audio_path.with_extension("srt");
+            write_contents(audio_path, &srt_path, &srt, options)?;
+            return Ok(srt_path);
+        }
+    }
+
+    let lrc_path = sidecar_path(audio_path);
+    write_lrc(audio_path, &lrc_path, lyrics, options)?;
+    Ok(lrc_path)
+}
+
+/// Write lyrics for `audio_path` to a differently named `.lrc` (e.g. one track of a cue image)
+pub fn write_lrc(
+    audio_path: &Path,
+    lrc_path: &Path,
+    lyrics: &str,
+    options: &WriteOptions,
+) -> Result<()> {
+    write_contents(
+        audio_path,
+        lrc_path,
+        &format_lyrics(lyrics, &options.format),
+        options,
+    )
+}
+
+/// Give `audio_path` a copy of the sidecar already written for `source` (e.g. a duplicate rip)
+pub fn copy_sidecar(source: &Path, audio_path: &Path, options: &WriteOptions) -> Result<()> {
+    let source_lrc = sidecar_path(source);
+    if !source_lrc.exists() && writes_srt(source, options) {
+        // The source is a video whose lyrics were written as subtitles
+        anyhow::ensure!(
+            writes_srt(audio_path, options),
+            "Can't convert the subtitles of {} to .lrc",
+            source.display()
+        );
+        let source_srt = source.with_extension("srt");
+        return link_or_copy(
+            &source_srt,
+            audio_path,
+            &audio_path.with_extension("srt"),
+            options,
+        );
+    }
+
+    if writes_srt(audio_path, options) {
+        let contents = std::fs::read_to_string(&source_lrc)
+            .with_context(|| format!("Failed to read {}", source_lrc.display()))?;
+        if let Some(srt) = to_srt(&contents) {
+            return write_contents(audio_path, &audio_path.with_extension("srt"), &srt, options);
+        }
+    }
+    link_or_copy(&source_lrc, audio_path, &sidecar_path(audio_path), options)
+}
+
+/// Give `audio_path` the sidecar at `source` as-is, hard-linked when configured
+fn link_or_copy(
+    source: &Path,
+    audio_path: &Path,
+    lrc_path: &Path,
+    options: &WriteOptions,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(source)
+        .with_context(|| format!("Failed to read {}", source.display()))?;
+    if !options.link_duplicates || options.match_permissions {
+        return write_contents(audio_path, lrc_path, &contents, options);
+    }
+
+    write_with(audio_path, options, || {
+        if let Err(e) = std::fs::hard_link(source, lrc_path) {
+            tracing::debug!("Copying instead of linking {}: {}", lrc_path.display(), e);
+            std::fs::write(lrc_path, &contents)
+                .with_context(|| format!("Failed to write {}", lrc_path.display()))?;
+        }
+        Ok(())
+    })
+}
+
+/// Whether lyrics for this file are written as `.srt` subtitles
+fn writes_srt(path: &Path, options: &WriteOptions) -> bool {
+    options.video_sidecar == VideoSidecar::Srt && crate::scanner::is_video_file(path)
+}
+
+/// Convert synced lyrics to SRT subtitles, each line shown until the next one starts
+/// Returns `None` for plain lyrics, which have no timing to convert.
+pub fn to_srt(lyrics: &str) -> Option<String> {
+    let lines = timed_lines(lyrics);
+    if lines.is_empty() {
+        return None;
+    }
+
+    let mut cues = Vec::new();
+    for (i, (start, text)) in lines.iter().enumerate() {
+        // Blank lines only end the line before them
+        if text.is_empty() {
+            continue;
+        }
+        let end = lines
+            .get(i + 1)
+            .map_or(start + SRT_LAST_LINE_MS, |(next, _)| *next);
+        cues.push(format!(
+            "{}\n{} --> {}\n{}\n",
+            cues.len() + 1,
+            srt_timestamp(*start),
+            srt_timestamp(end),
+            text
+        ));
+    }
+    Some(cues.join("\n"))
+}
+
+/// Lines of synced lyrics with their start in milliseconds, in playback order
+/// A line with several timestamps appears once for each; untimed lines are left out.
+pub fn timed_lines(lyrics: &str) -> Vec<(u64, &str)> {
+    let mut lines: Vec<(u64, &str)> = lyrics
+        .lines()
+        .flat_map(|line| {
+            let (timestamps, text) = split_timestamps(line);
+            timestamps
+                .into_iter()
+                .map(move |ts| (ts.millis, text.trim()))
+        })
+        .collect();
+    lines.sort_by_key(|(millis, _)| *millis);
+    lines
+}
+
+fn srt_timestamp(millis: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+/// Write already formatted lyrics, honouring the mtime and permission options
+fn write_contents(
+    audio_path: &Path,
+    lrc_path: &Path,
+    contents: &str,
+    options: &WriteOptions,
+) -> Result<()> {
+    write_with(audio_path, options, || {
+        std::fs::write(lrc_path, contents)
+            .with_context(|| format!("Failed to write {}", lrc_path.display()))?;
+        if options.match_permissions {
+            copy_permissions(audio_path, lrc_path)?;
+        }
+        Ok(())
+    })
+}
+
+/// Create a sidecar with `write`, restoring the directory mtime it would otherwise change
+fn write_with(
+    audio_path: &Path,
+    options: &WriteOptions,
+    write: impl FnOnce() -> Result<()>,
+) -> Result<()> {
+    let parent = audio_path.parent().filter(|p| !p.as_os_str().is_empty());
+
+    // Capture the timestamp before any write of this run touches it
+    let dir_mtime = match parent {
+        Some(dir) if options.preserve_dir_mtime => Some(
+            *options
+                .dir_mtimes
+                .entry(dir.to_path_buf())
+                .or_try_insert_with(|| read_mtime(dir))?,
+        ),
+        _ => None,
+    };
+
+    write()?;
+
+    if let (Some(dir), Some(mtime)) = (parent, dir_mtime) {
+        filetime::set_file_mtime(dir, mtime)
+            .with_context(|| format!("Failed to restore mtime of {}", dir.display()))?;
+        tracing::debug!("Restored directory mtime: {}", dir.display());
+    }
+
+    Ok(())
+}
+
+/// Reformat LRC lyrics according to the format options
+/// Lines without leading timestamps (e.g. `[ar:...]` headers) are kept as-is.
+pub fn format_lyrics(lyrics: &str, options: &FormatOptions) -> String {
+    if *options == FormatOptions::default() {
+        return lyrics.to_string();
+    }
+
+    let mut untimed = Vec::new();
+    let mut timed: Vec<(u64, String)> = Vec::new();
+    let mut output = Vec::new();
+
+    for line in lyrics.lines() {
+        let (timestamps, text) = split_timestamps(line);
+        if timestamps.is_empty() {
+            if options.expand_repeated {
+                untimed.push(line.to_string());
+            } else {
+                output.push(line.to_string());
+            }
+            continue;
+        }
+
+        if options.expand_repeated {
+            for ts in &timestamps {
+                timed.push((
+                    ts.millis,
+                    format!("{}{}", format_timestamp(ts, options), text),
+                ));
+            }
+        } else {
+            let tags: String = timestamps
+                .iter()
+                .map(|ts| format_timestamp(ts, options))
+                .collect();
+            output.push(format!("{}{}", tags, text));
+        }
+    }
+
+    if options.expand_repeated {
+        // Stable sort keeps lines sharing a timestamp in source order
+        timed.sort_by_key(|(millis, _)| *millis);
+        output = untimed;
+        output.extend(timed.into_iter().map(|(_, line)| line));
+    }
+
+    let mut result = output.join("\n");
+    if lyrics.ends_with('\n') {
+        result.push('\n');
+    }
+    result
+}
+
+/// Shift every timestamp by `offset_ms`, e.g. to place a track's lyrics within a cue image
+/// Lines without timestamps (plain lyrics) are pinned to the offset itself.
+pub fn offset_lyrics(lyrics: &str, offset_ms: u64) -> String {
+    let options = FormatOptions::default();
+    let start = Timestamp {
+        millis: offset_ms,
+        digits: 2,
+    };
+
+    lyrics
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (timestamps, text) = split_timestamps(line);
+            if timestamps.is_empty() {
+                return format!("{}{}", format_timestamp(&start, &options), line);
+            }
+            let tags: String = timestamps
+                .iter()
+                .map(|ts| {
+                    let shifted = Timestamp {
+                        millis: ts.millis + offset_ms,
+                        digits: ts.digits,
+                    };
+                    format_timestamp(&shifted, &options)
+                })
+                .collect();
+            format!("{}{}", tags, text)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split the leading timestamp tags off a lyrics line
+fn split_timestamps(line: &str) -> (Vec<Timestamp>, &str) {
+    let mut timestamps = Vec::new();
+    let mut rest = line;
+
+    while let Some(caps) = get_timestamp_regex().captures(rest) {
+        let minutes: u64 = caps[1].parse().unwrap_or(0);
+        let seconds: u64 = caps[2].parse().unwrap_or(0);
+        let (fraction, digits) = match caps.get(3) {
+            Some(m) => (m.as_str().parse::<u64>().unwrap_or(0), m.as_str().len()),
+            None => (0, 0),
+        };
+        let fraction_millis = match digits {
+            1 => fraction * 100,
+            2 => fraction * 10,
+            _ => fraction,
+        };
+
+        timestamps.push(Timestamp {
+            millis: (minutes * 60 + seconds) * 1000 + fraction_millis,
+            digits,
+        });
+        rest = &rest[caps[0].len()..];
+    }
+
+    (timestamps, rest)
+}
+
+fn format_timestamp(ts: &Timestamp, options: &FormatOptions) -> String {
+    let digits = options.precision.map(usize::from).unwrap_or(ts.digits);
+    let minutes = ts.millis / 60_000;
+    let seconds = (ts.millis / 1000) % 60;
+    let millis = ts.millis % 1000;
+
+    let minutes = if options.pad_minutes {
+        format!("{:02}", minutes)
+    } else {
+        minutes.to_string()
+    };
+
+    match digits {
+        0 => format!("[{}:{:02}]", minutes, seconds),
+        1 => format!("[{}:{:02}.{:01}]", minutes, seconds, millis / 100),
+        2 => format!("[{}:{:02}.{:02}]", minutes, seconds, millis / 10),
+        _ => format!("[{}:{:02}.{:03}]", minutes, seconds, millis),
+    }
+}
+
+/// Copy owner, group and the read/write bits of the mode from the audio file onto the sidecar
+/// Execute, setuid, setgid and sticky bits make no sense on a text file, so they're left off.
+#[cfg(unix)]
+fn copy_permissions(audio_path: &Path, lrc_path: &Path) -> Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = std::fs::metadata(audio_path)
+        .with_context(|| format!("Failed to read metadata: {}", audio_path.display()))?;
+
+    std::fs::set_permissions(
+        lrc_path,
+        std::fs::Permissions::from_mode(metadata.mode() & 0o666),
+    )
+    .with_context(|| format!("Failed to set permissions on {}", lrc_path.display()))?;
+
+    // Changing ownership needs privileges we may not have, so only warn
+    if let Err(e) = std::os::unix::fs::chown(lrc_path, Some(metadata.uid()), Some(metadata.gid())) {
+        tracing::warn!(
+            "Failed to set owner {}:{} on {}: {}",
+            metadata.uid(),
+            metadata.gid(),
+            lrc_path.display(),
+            e
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn copy_permissions(_audio_path: &Path, _lrc_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn read_mtime(path: &Path) -> Result<FileTime> {
+    let metadata = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
+    Ok(FileTime::from_last_modification_time(&metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_path() {
+        assert_eq!(
+            sidecar_path(Path::new("/music/song.flac")),
+            PathBuf::from("/music/song.lrc")
+        );
+    }
+
+    #[test]
+    fn test_format_lyrics_default_is_untouched() {
+        let lyrics = "[ar:Someone]\n[00:01.5]a\n";
+        assert_eq!(format_lyrics(lyrics, &FormatOptions::default()), lyrics);
+    }
+
+    #[test]
+    fn test_format_lyrics_precision_and_padding() {
+        let options = FormatOptions {
+            precision: Some(3),
+            pad_minutes: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_lyrics("[ar:Someone]\n[01:02.34]line", &options),
+            "[ar:Someone]\n[1:02.340]line"
+        );
+
+        let options = FormatOptions {
+            precision: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(format_lyrics("[00:05.678]x", &options), "[00:05.67]x");
+    }
+
+    #[test]
+    fn test_format_lyrics_expand_repeated() {
+        let options = FormatOptions {
+            expand_repeated: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_lyrics(
+                "[ti:Song]\n[00:01.00][00:20.00]chorus\n[00:10.00]verse",
+                &options
+            ),
+            "[ti:Song]\n[00:01.00]chorus\n[00:10.00]verse\n[00:20.00]chorus"
+        );
+    }
+
+    #[test]
+    fn test_to_srt() {
+        assert_eq!(
+            to_srt("[ti:Song]\n[00:01.50]first\n[00:04.00]\n[00:05.00][01:02.00]second").unwrap(),
+            "1\n00:00:01,500 --> 00:00:04,000\nfirst\n\n\
+             2\n00:00:05,000 --> 00:01:02,000\nsecond\n\n\
+             3\n00:01:02,000 --> 00:01:07,000\nsecond\n"
+        );
+        assert_eq!(to_srt("plain lyrics\nno timing"), None);
+    }
+
+    #[test]
+    fn test_write_video_subtitles() {
+        let dir = tempfile::tempdir().unwrap();
+        let video = dir.path().join("clip.mkv");
+        let options = WriteOptions {
+            video_sidecar: VideoSidecar::Srt,
+            ..Default::default()
+        };
+
+        let written = write_sidecar(&video, "[00:01.00] hello", &options).unwrap();
+        assert_eq!(written, dir.path().join("clip.srt"));
+
+        // Duplicates of the video get its subtitles; audio files can't use them
+        copy_sidecar(&video, &dir.path().join("copy.mp4"), &options).unwrap();
+        assert!(dir.path().join("copy.srt").exists());
+        assert!(copy_sidecar(&video, &dir.path().join("song.flac"), &options).is_err());
+    }
+
+    #[test]
+    fn test_offset_lyrics() {
+        assert_eq!(
+            offset_lyrics("[00:01.50]a\n\n[00:59.9][01:00]b\nplain", 240_490),
+            "[04:01.99]a\n[05:00.3][05:00]b\n[04:00.49]plain"
+        );
+    }
+
+    #[test]
+    fn test_write_sidecar_preserves_dir_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("song.flac");
+        std::fs::write(&audio, b"audio").unwrap();
+
+        let old = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(dir.path(), old).unwrap();
+
+        let options = WriteOptions {
+            preserve_dir_mtime: true,
+            ..Default::default()
+        };
+        write_sidecar(&audio, "[00:01.00] hello", &options).unwrap();
+
+        assert!(dir.path().join("song.lrc").exists());
+        assert_eq!(read_mtime(dir.path()).unwrap(), old);
+    }
+
+    #[test]
+    fn test_concurrent_writes_preserve_dir_mtime() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = FileTime::from_unix_time(1_000_000_000, 0);
+        filetime::set_file_mtime(dir.path(), old).unwrap();
+
+        let options = WriteOptions {
+            preserve_dir_mtime: true,
+            ..Default::default()
+        };
+        std::thread::scope(|scope| {
+            for worker in 0..8 {
+                let (dir, options) = (dir.path(), options.clone());
+                scope.spawn(move || {
+                    for track in 0..20 {
+                        let audio = dir.join(format!("{worker:02}-{track:02}.flac"));
+                        write_sidecar(&audio, "[00:01.00] hello", &options).unwrap();
+                    }
+                });
+            }
+        });
+
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 160);
+        assert_eq!(read_mtime(dir.path()).unwrap(), old);
+    }
+
+    #[test]
+    fn test_copy_sidecar_keeps_formatting() {
+        let dir = tempfile::tempdir().unwrap();
+        let flac = dir.path().join("song.flac");
+        let mp3 = dir.path().join("song (copy).mp3");
+
+        let options = WriteOptions {
+            format: FormatOptions {
+                precision: Some(3),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        write_sidecar(&flac, "[00:01.00] hello", &options).unwrap();
+        copy_sidecar(&flac, &mp3, &options).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("song (copy).lrc")).unwrap(),
+            "[00:01.000] hello"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_copy_sidecar_links_duplicates() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let flac = dir.path().join("song.flac");
+        let mp3 = dir.path().join("song (copy).mp3");
+
+        let options = WriteOptions {
+            link_duplicates: true,
+            ..Default::default()
+        };
+        write_sidecar(&flac, "[00:01.00] hello", &options).unwrap();
+        copy_sidecar(&flac, &mp3, &options).unwrap();
+
+        let original = std::fs::metadata(dir.path().join("song.lrc")).unwrap();
+        let linked = std::fs::metadata(dir.path().join("song (copy).lrc")).unwrap();
+        assert_eq!(original.ino(), linked.ino());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_sidecar_matches_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("song.flac");
+        std::fs::write(&audio, b"audio").unwrap();
+        std::fs::set_permissions(&audio, std::fs::Permissions::from_mode(0o6750)).unwrap();
+
+        let options = WriteOptions {
+            match_permissions: true,
+            ..Default::default()
+        };
+        write_sidecar(&audio, "[00:01.00] hello", &options).unwrap();
+
+        let mode = std::fs::metadata(dir.path().join("song.lrc"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o640);
+    }
+}
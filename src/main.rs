@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
+use tracing_subscriber::{filter::LevelFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[derive(Parser)]
 #[command(name = "getlrc")]
@@ -11,13 +11,186 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Music directory to scan for audio files
+    /// Music directory to scan for audio files, or an .m3u/.m3u8/.pls playlist
     #[arg(value_name = "DIRECTORY")]
     directory: Option<PathBuf>,
 
     /// Force retry: ignore negative cache and retry all files
     #[arg(short = 'f', long = "force-retry")]
     force_retry: bool,
+
+    /// Only re-process files that failed with errors last time, keeping not-found entries cached
+    #[arg(long = "retry", value_name = "WHICH", conflicts_with = "force_retry")]
+    retry: Option<RetryScope>,
+
+    /// Use this SQLite file as the negative cache (e.g. next to the library on a NAS)
+    #[arg(long = "cache-path", value_name = "FILE", global = true)]
+    cache_path: Option<PathBuf>,
+
+    /// Store session, logs and other data in this directory
+    #[arg(long = "data-dir", value_name = "DIR", global = true)]
+    data_dir: Option<PathBuf>,
+
+    /// Retry negative cache entries after a fixed number of days (0 = never expire)
+    /// Without this, retries back off progressively: 7, 30, then every 90 days
+    #[arg(long = "cache-ttl", value_name = "DAYS", value_parser = clap::value_parser!(u64).range(..=36_500))]
+    cache_ttl: Option<u64>,
+
+    /// Re-check every file instead of skipping ones unchanged since the last scan
+    #[arg(long = "full-scan")]
+    full_scan: bool,
+
+    /// Fetch lyrics even for files that already have embedded lyrics tags
+    #[arg(long = "ignore-embedded")]
+    ignore_embedded: bool,
+
+    /// Also scan for files with these extensions (comma-separated, e.g. dff,mpc)
+    #[arg(
+        long = "audio-extensions",
+        value_name = "EXT",
+        value_delimiter = ',',
+        global = true
+    )]
+    audio_extensions: Vec<String>,
+
+    /// Don't scan files and directories whose names start with a dot (.stfolder, .Trash, ...)
+    #[arg(long = "skip-hidden", global = true)]
+    skip_hidden: bool,
+
+    /// Also scan music videos (.mp4, .m4v, .mkv, .webm)
+    #[arg(long = "include-videos", global = true)]
+    include_videos: bool,
+
+    /// Only look up tracks whose genre contains one of these (comma-separated)
+    #[arg(long = "genre-include", value_name = "GENRES", value_delimiter = ',')]
+    genre_include: Vec<String>,
+
+    /// Skip tracks whose genre contains one of these (comma-separated, e.g. Audiobook,Podcast)
+    #[arg(long = "genre-exclude", value_name = "GENRES", value_delimiter = ',')]
+    genre_exclude: Vec<String>,
+
+    /// Also treat files with identical audio as duplicates, whatever their tags (FLAC, MP3)
+    #[arg(long = "dedupe-audio")]
+    dedupe_audio: bool,
+
+    /// Look up tracks that look like podcast episodes or audiobooks instead of skipping them
+    #[arg(long = "keep-spoken")]
+    keep_spoken: bool,
+
+    /// Process each album (directory) with one worker, retrying its failed tracks together
+    #[arg(long = "group-albums")]
+    group_albums: bool,
+
+    /// Order in which files are processed
+    #[arg(long = "order", value_name = "ORDER", default_value = "discovery")]
+    order: ProcessOrder,
+
+    /// One worker, files in path order and a full scan, so repeated runs give identical results
+    /// Excludes `--timing` and `--pick`, whose output differs from run to run.
+    #[arg(long = "deterministic", conflicts_with_all = ["order", "timing", "pick"])]
+    deterministic: bool,
+
+    /// Measure where time goes (tags, caches, rate limit, requests, writes) and summarize it,
+    /// adding each file's time to its log entry
+    #[arg(long = "timing")]
+    timing: bool,
+
+    /// Draw the TUI with ASCII symbols only, for terminals and fonts that lack the Unicode ones
+    #[arg(long = "ascii")]
+    ascii: bool,
+
+    /// Run at low CPU and disk priority with fewer workers and a throttled scan, for media servers
+    #[arg(long = "nice", global = true)]
+    nice: bool,
+
+    /// Skip tracks shorter than this many seconds (e.g. 30 for skits and interludes)
+    #[arg(long = "min-duration", value_name = "SECONDS")]
+    min_duration: Option<u64>,
+
+    /// Skip tracks longer than this many seconds (e.g. 3600 for DJ mixes)
+    #[arg(long = "max-duration", value_name = "SECONDS")]
+    max_duration: Option<u64>,
+
+    /// Sidecar format for music videos
+    #[arg(
+        long = "video-subtitles",
+        value_name = "FORMAT",
+        default_value = "lrc",
+        global = true
+    )]
+    video_subtitles: VideoSubtitles,
+
+    /// How to write lyrics for single-file albums split by a cue sheet
+    #[arg(long = "cue-lyrics", value_name = "LAYOUT", default_value = "tracks")]
+    cue_lyrics: CueLyrics,
+
+    /// Save plain (unsynced) lyrics when no synced version exists
+    /// Also retries tracks cached as having only plain lyrics
+    #[arg(long = "allow-plain", global = true)]
+    allow_plain: bool,
+
+    /// Search for other uploads of tracks without a confident match and pick which to use
+    /// With `fetch`, the best search result is used.
+    #[arg(long = "pick", global = true)]
+    pick: bool,
+
+    /// Restore each album directory's mtime after writing a sidecar
+    #[arg(long = "preserve-dir-mtime", global = true)]
+    preserve_dir_mtime: bool,
+
+    /// Copy owner, group and read/write permissions of each audio file onto its sidecar (Unix)
+    #[arg(long = "match-permissions", global = true)]
+    match_permissions: bool,
+
+    /// Hard-link the sidecars of duplicate files to the first copy's instead of copying them
+    #[arg(long = "link-duplicates", global = true)]
+    link_duplicates: bool,
+
+    /// Timestamp decimal places in written lyrics (default: keep as downloaded)
+    #[arg(long = "timestamp-precision", value_name = "DIGITS", global = true, value_parser = clap::value_parser!(u8).range(2..=3))]
+    timestamp_precision: Option<u8>,
+
+    /// Split lines with repeated timestamps into one line per timestamp
+    #[arg(long = "expand-repeated", global = true)]
+    expand_repeated: bool,
+
+    /// Don't zero-pad minutes in timestamps ([1:02.34] instead of [01:02.34])
+    #[arg(long = "no-pad-minutes", global = true)]
+    no_pad_minutes: bool,
+}
+
+/// Sidecar layout for cue sheet images
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CueLyrics {
+    /// One `NN - Title.lrc` per track
+    Tracks,
+    /// A single sidecar for the whole image, timed to match it
+    Combined,
+}
+
+/// Order in which queued files are processed
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ProcessOrder {
+    /// As the scan finds them
+    Discovery,
+    /// Most recently modified first (e.g. new additions to the library)
+    Newest,
+}
+
+/// Sidecar format for music videos
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum VideoSubtitles {
+    /// `.lrc` lyrics, like audio files
+    Lrc,
+    /// `.srt` subtitles, shown by video players
+    Srt,
+}
+
+/// Which previously processed files `--retry` re-processes
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum RetryScope {
+    /// Files whose last check failed with a network or I/O error
+    Errors,
 }
 
 #[derive(Subcommand)]
@@ -26,12 +199,144 @@ enum Commands {
     Install,
     /// Uninstall getlrc from ~/.local/bin
     Uninstall,
+    /// Export every .lrc sidecar in a library to a zip archive
+    Export {
+        /// Music directory to export lyrics from
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Archive file to write
+        #[arg(short = 'o', long = "out", default_value = "lyrics.zip")]
+        out: PathBuf,
+    },
+    /// Write missing sidecars from an exported archive or another library (no network)
+    Import {
+        /// Archive created by `getlrc export`, or another music directory
+        #[arg(value_name = "SOURCE")]
+        source: PathBuf,
+
+        /// Music directory to write sidecars into
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+    },
+    /// Fetch lyrics for a single audio file
+    Fetch {
+        /// Audio file to fetch lyrics for
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Print the LRC to stdout instead of writing a sidecar
+        #[arg(long = "stdout")]
+        stdout: bool,
+    },
+    /// List past runs with their summaries, or show one run's report
+    History {
+        /// Run to show the report of, 1 being the latest
+        #[arg(value_name = "RUN")]
+        run: Option<usize>,
+    },
+    /// Manage the negative cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Export cache entries to a JSON file
+    Export {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Merge cache entries from a JSON file
+    Import {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+    /// Show how many entries are cached, how often they failed and how stale they are
+    Stats,
+    /// Delete entries for an artist or for paths matching a glob, so they are retried next run
+    #[command(group(clap::ArgGroup::new("filter").required(true).multiple(true)))]
+    Invalidate {
+        /// Artist name (matched case-insensitively after normalization)
+        #[arg(long = "artist", group = "filter")]
+        artist: Option<String>,
+
+        /// Glob matched against the audio file path, e.g. "**/Artist/**"
+        #[arg(long = "path-glob", value_name = "GLOB", group = "filter")]
+        path_glob: Option<String>,
+    },
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    // Before the runtime and the scan's thread pool start: Linux sets the priorities of
+    // the calling thread only, and threads inherit them from the thread that spawns them
+    if cli.nice {
+        getlrc::nice::lower_priority();
+    }
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(cli))
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    getlrc::paths::set_overrides(getlrc::paths::PathOverrides {
+        data_dir: cli.data_dir.clone(),
+        cache_path: cli.cache_path.clone(),
+    });
+
+    let config = getlrc::config::Config::load(&getlrc::paths::get_config_path()?)?;
+    getlrc::scanner::filename::set_templates(config.filename_templates()?);
+    getlrc::scanner::metadata::set_tag_mapping(config.tag_mapping.clone());
+    getlrc::scanner::metadata::set_tag_priority(config.tag_priority()?);
+    let active_hours = config.active_hours()?;
+    let mut theme = config.theme()?;
+    let keys = config.keys()?;
+    if getlrc::tui::theme::Theme::no_color() {
+        theme = getlrc::tui::theme::Theme::monochrome();
+    }
+    getlrc::scanner::clean::set_title_blacklist(&config.title_blacklist);
+    let video_extensions = getlrc::scanner::video::VIDEO_EXTENSIONS
+        .iter()
+        .filter(|_| cli.include_videos)
+        .map(|ext| ext.to_string());
+    getlrc::scanner::set_extra_extensions(
+        config
+            .audio_extensions
+            .iter()
+            .chain(&cli.audio_extensions)
+            .cloned()
+            .chain(video_extensions)
+            .collect(),
+    );
+
+    getlrc::scanner::set_skip_hidden(cli.skip_hidden || config.skip_hidden);
+
+    if cli.nice {
+        getlrc::scanner::set_scan_pause(getlrc::nice::SCAN_PAUSE);
+    }
+
+    let write_options = getlrc::lrc::WriteOptions {
+        preserve_dir_mtime: cli.preserve_dir_mtime,
+        match_permissions: cli.match_permissions,
+        link_duplicates: cli.link_duplicates,
+        dir_mtimes: Default::default(),
+        format: getlrc::lrc::FormatOptions {
+            precision: cli.timestamp_precision,
+            expand_repeated: cli.expand_repeated,
+            pad_minutes: !cli.no_pad_minutes,
+        },
+        video_sidecar: match cli.video_subtitles {
+            VideoSubtitles::Lrc => getlrc::lrc::VideoSidecar::Lrc,
+            VideoSubtitles::Srt => getlrc::lrc::VideoSidecar::Srt,
+        },
+    };
+
     // Handle subcommands that don't need logging
     match cli.command {
         Some(Commands::Install) => {
@@ -40,6 +345,29 @@ async fn main() -> Result<()> {
         Some(Commands::Uninstall) => {
             return getlrc::install::uninstall();
         }
+        Some(Commands::Export { directory, out }) => {
+            return run_export(&directory, &out);
+        }
+        Some(Commands::Import { source, directory }) => {
+            return run_import(&source, &directory, &write_options);
+        }
+        Some(Commands::Fetch { file, stdout }) => {
+            return run_fetch(
+                &file,
+                stdout,
+                cli.allow_plain,
+                cli.pick,
+                &write_options,
+                config.acoustid_api_key.as_deref(),
+            )
+            .await;
+        }
+        Some(Commands::History { run }) => {
+            return run_history(run);
+        }
+        Some(Commands::Cache { action }) => {
+            return run_cache(action);
+        }
         None => {
             // Continue to scanner mode
         }
@@ -52,20 +380,38 @@ async fn main() -> Result<()> {
         )
     })?;
 
-    if !target_dir.is_dir() {
-        anyhow::bail!("Path is not a directory: {}", target_dir.display());
-    }
+    // A playlist limits the run to the files it references
+    let playlist = if target_dir.is_file() && getlrc::scanner::playlist::is_playlist(&target_dir) {
+        let files = getlrc::scanner::playlist::read(&target_dir)?;
+        if files.is_empty() {
+            anyhow::bail!(
+                "Playlist has no local audio files: {}",
+                target_dir.display()
+            );
+        }
+        Some(files)
+    } else if target_dir.is_dir() {
+        None
+    } else {
+        anyhow::bail!(
+            "Path is not a directory or playlist: {}",
+            target_dir.display()
+        );
+    };
+    // Long paths, UNC shares and device-like file names all work in extended form
+    let target_dir = getlrc::scanner::winpath::extended(&target_dir);
 
     // Initialize file-based logging for TUI mode
     let log_dir = getlrc::paths::get_log_dir()?;
     let file_appender = tracing_appender::rolling::never(&log_dir, "getlrc.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
+    // The filter can be switched between debug and info from the TUI
+    let (filter, filter_handle) = tracing_subscriber::reload::Layer::new(
+        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| log_filter(true)),
+    );
     tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "getlrc=debug,reqwest=warn".into()),
-        )
+        .with(filter)
         .with(
             tracing_subscriber::fmt::layer()
                 .with_writer(non_blocking)
@@ -73,10 +419,355 @@ async fn main() -> Result<()> {
         )
         .init();
 
-    run_scanner(target_dir, cli.force_retry).await
+    let options = getlrc::worker::RunOptions {
+        force_retry: cli.force_retry,
+        retry_errors: matches!(cli.retry, Some(RetryScope::Errors)),
+        ignore_embedded: cli.ignore_embedded,
+        allow_plain: cli.allow_plain,
+        full_scan: cli.full_scan,
+        cache_expiry: match cli.cache_ttl {
+            None => getlrc::cache::Expiry::Backoff,
+            Some(0) => getlrc::cache::Expiry::Never,
+            Some(days) => {
+                getlrc::cache::Expiry::Fixed(std::time::Duration::from_secs(days * 24 * 60 * 60))
+            }
+        },
+        write: write_options,
+        acoustid_key: config.acoustid_api_key,
+        cue_layout: match cli.cue_lyrics {
+            CueLyrics::Tracks => getlrc::scanner::cue::CueLayout::Tracks,
+            CueLyrics::Combined => getlrc::scanner::cue::CueLayout::Combined,
+        },
+        playlist,
+        filter: getlrc::scanner::track_filter::TrackFilter::new(
+            config
+                .genre_include
+                .iter()
+                .chain(&cli.genre_include)
+                .cloned()
+                .collect(),
+            config
+                .genre_exclude
+                .iter()
+                .chain(&cli.genre_exclude)
+                .cloned()
+                .collect(),
+        )
+        .with_duration(
+            cli.min_duration.or(config.min_duration),
+            cli.max_duration.or(config.max_duration),
+        ),
+        dedupe_audio: cli.dedupe_audio,
+        group_albums: cli.group_albums,
+        queue_order: match cli.order {
+            ProcessOrder::Discovery => getlrc::worker::QueueOrder::Discovery,
+            ProcessOrder::Newest => getlrc::worker::QueueOrder::Newest,
+        },
+        detect_spoken: !cli.keep_spoken,
+        deterministic: cli.deterministic,
+        timing: cli.timing,
+        retry: config.retry,
+        nice: cli.nice,
+        active_hours,
+        pick: cli.pick,
+    };
+
+    let glyphs = if cli.ascii {
+        getlrc::tui::glyphs::Glyphs::ascii()
+    } else {
+        getlrc::tui::glyphs::Glyphs::unicode()
+    };
+    let debug_log = filter_handle
+        .with_current(|filter| filter.max_level_hint() >= Some(LevelFilter::DEBUG))
+        .unwrap_or_default();
+    let set_log_level: getlrc::tui::SetLogLevel =
+        Box::new(move |debug| Ok(filter_handle.reload(log_filter(debug))?));
+    let notify = config.notify;
+    let tui = move |app: getlrc::tui::App| {
+        app.with_theme(theme)
+            .with_glyphs(glyphs)
+            .with_keys(keys)
+            .with_log_level(debug_log, set_log_level)
+            .with_notify(notify)
+    };
+    run_scanner(target_dir, options, tui).await
 }
 
-async fn run_scanner(target_dir: PathBuf, force_retry: bool) -> Result<()> {
+fn run_export(directory: &Path, out: &Path) -> Result<()> {
+    if !directory.is_dir() {
+        anyhow::bail!("Path is not a directory: {}", directory.display());
+    }
+
+    println!("📦 Exporting lyrics from {}...", directory.display());
+    let summary = getlrc::archive::export(directory, out)?;
+
+    println!(
+        "✓ Exported {} sidecars to {}",
+        summary.exported,
+        out.display()
+    );
+    if summary.untagged > 0 {
+        println!(
+            "⚠️  {} sidecars belong to files without readable tags (stored without signature)",
+            summary.untagged
+        );
+    }
+
+    Ok(())
+}
+
+/// Filter of the log file: getlrc's debug or info messages, and warnings from the HTTP client
+fn log_filter(debug: bool) -> tracing_subscriber::EnvFilter {
+    match debug {
+        true => tracing_subscriber::EnvFilter::new("getlrc=debug,reqwest=warn"),
+        false => tracing_subscriber::EnvFilter::new("getlrc=info,reqwest=warn"),
+    }
+}
+
+fn run_history(run: Option<usize>) -> Result<()> {
+    let runs = getlrc::history::load(&getlrc::paths::get_history_path()?)?;
+    if runs.is_empty() {
+        println!("No runs recorded yet");
+        return Ok(());
+    }
+
+    match run {
+        Some(number) => {
+            let Some(run) = number.checked_sub(1).and_then(|index| runs.get(index)) else {
+                anyhow::bail!("No run {} (1 to {})", number, runs.len());
+            };
+            for line in run.report() {
+                println!("{}", line);
+            }
+        }
+        None => {
+            for (index, run) in runs.iter().enumerate() {
+                println!("{:>3}  {}", index + 1, run.summary());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_import(
+    source: &Path,
+    directory: &Path,
+    write_options: &getlrc::lrc::WriteOptions,
+) -> Result<()> {
+    if !directory.is_dir() {
+        anyhow::bail!("Path is not a directory: {}", directory.display());
+    }
+
+    println!(
+        "📥 Importing lyrics from {} into {}...",
+        source.display(),
+        directory.display()
+    );
+    let summary = getlrc::archive::import(source, directory, write_options)?;
+
+    println!("✓ Imported {} sidecars", summary.imported);
+    println!(
+        "  {} already had lyrics, {} had no match, {} failed to write",
+        summary.existing, summary.unmatched, summary.errors
+    );
+    if summary.unreadable > 0 {
+        println!(
+            "⚠️  Skipped {} source sidecars that aren't UTF-8 text",
+            summary.unreadable
+        );
+    }
+
+    Ok(())
+}
+
+async fn run_fetch(
+    file: &Path,
+    stdout: bool,
+    allow_plain: bool,
+    pick: bool,
+    write_options: &getlrc::lrc::WriteOptions,
+    acoustid_key: Option<&str>,
+) -> Result<()> {
+    use getlrc::api::SearchResult;
+    use getlrc::scanner::metadata::MetadataSource;
+
+    if !file.is_file() {
+        anyhow::bail!("Path is not a file: {}", file.display());
+    }
+
+    let mut track = getlrc::scanner::metadata::extract_or_guess(file);
+    if let Some(key) = acoustid_key {
+        let acoustid = getlrc::api::acoustid::AcoustIdClient::new(key)?;
+        track = acoustid.identify_track(file, track).await;
+    }
+    let mut track =
+        track.with_context(|| format!("Failed to read tags from {}", file.display()))?;
+    let sanitized = track.sanitize();
+    if !sanitized.is_empty() {
+        eprintln!(
+            "⚠️  Invisible characters removed from {} (worth fixing in the tags)",
+            sanitized.join(", ")
+        );
+    }
+    match track.source {
+        MetadataSource::Tags | MetadataSource::Manual => {}
+        MetadataSource::Path => eprintln!(
+            "⚠️  No usable tags, searching for {} - {} (guessed from the path)",
+            track.artist, track.title
+        ),
+        MetadataSource::Fingerprint => eprintln!(
+            "🔎 No usable tags, searching for {} - {} (identified by AcoustID)",
+            track.artist, track.title
+        ),
+    }
+
+    let client = getlrc::api::LrcLibClient::new();
+    let mut result = client.get_lyrics_smart(&track).await?;
+    if pick && !matches!(result, SearchResult::Found(_)) {
+        // Nobody to ask here, so the best search result is used
+        match client.search_candidates(&track).await {
+            Ok(candidates) => {
+                let count = candidates.len();
+                if let Some((lyrics, candidate)) = candidates.into_iter().next() {
+                    eprintln!(
+                        "🔎 Using the best of {} search results ({:.0}% similar): {} - {}",
+                        count,
+                        candidate.similarity() * 100.0,
+                        candidate.artist,
+                        candidate.title
+                    );
+                    result = SearchResult::Found(lyrics);
+                }
+            }
+            Err(e) => eprintln!("⚠️  Search failed: {:#}", e),
+        }
+    }
+    let lyrics = match result {
+        SearchResult::Found(lyrics) => lyrics,
+        SearchResult::PotentialMatch { lyrics, similarity } => {
+            eprintln!(
+                "⚠️  Potential match ({:.0}% similar): {} - {}",
+                similarity * 100.0,
+                lyrics.artist_name,
+                lyrics.track_name
+            );
+            lyrics
+        }
+        SearchResult::NotFound { .. } => {
+            anyhow::bail!("No lyrics found for {} - {}", track.artist, track.title)
+        }
+    };
+
+    let synced = lyrics.into_lyrics(allow_plain).ok_or_else(|| {
+        anyhow::anyhow!(
+            "No synced lyrics available for {} - {}",
+            track.artist,
+            track.title
+        )
+    })?;
+
+    if stdout {
+        let formatted = getlrc::lrc::format_lyrics(&synced, &write_options.format);
+        println!("{}", formatted.trim_end_matches('\n'));
+    } else {
+        let saved = getlrc::lrc::write_sidecar(file, &synced, write_options)?;
+        println!("✓ Saved {}", saved.display());
+    }
+
+    Ok(())
+}
+
+fn run_cache(action: CacheCommands) -> Result<()> {
+    let cache_path = getlrc::paths::get_cache_db_path()?;
+    let mut cache = getlrc::cache::NegativeCache::open(&cache_path)?;
+
+    match action {
+        CacheCommands::Export { file } => {
+            let count = getlrc::cache::transfer::export(&cache, &file)?;
+            println!("✓ Exported {} cache entries to {}", count, file.display());
+        }
+        CacheCommands::Import { file } => {
+            let summary = getlrc::cache::transfer::import(&cache, &file)?;
+            println!(
+                "✓ Imported {} new or updated cache entries from {}",
+                summary.changed,
+                file.display()
+            );
+            if summary.outdated > 0 {
+                println!(
+                    "⚠️  Skipped {} entries from older normalization rules (those tracks are looked up again)",
+                    summary.outdated
+                );
+            }
+        }
+        CacheCommands::Invalidate { artist, path_glob } => {
+            let filter =
+                getlrc::cache::filter::EntryFilter::new(artist.as_deref(), path_glob.as_deref())?;
+            let removed = cache.invalidate(|entry| filter.matches(entry))?;
+            println!("✓ Invalidated {} cache entries", removed);
+        }
+        CacheCommands::Stats => {
+            let stats = cache.stats()?;
+            println!("Negative cache: {}", cache_path.display());
+            println!("  Entries:         {}", stats.total);
+            println!("  Due for retry:   {}", stats.due);
+
+            println!("\nBy failed attempts:");
+            for (attempts, count) in &stats.by_attempts {
+                println!("  {:>3} attempt(s): {}", attempts, count);
+            }
+
+            println!("\nBy reason:");
+            for (reason, count) in &stats.by_reason {
+                println!("  {:<14} {}", reason.as_str(), count);
+            }
+
+            println!("\nBy last response:");
+            for ((provider, status), count) in &stats.by_response {
+                let label = match (provider.as_str(), status) {
+                    ("", _) => "unknown".to_string(),
+                    (provider, Some(status)) => format!("{} {}", provider, status),
+                    (provider, None) => provider.to_string(),
+                };
+                println!("  {:<14} {}", label, count);
+            }
+
+            println!("\nBy last attempt:");
+            let bounds = getlrc::cache::AGE_BUCKETS_DAYS;
+            for (i, count) in stats.by_age.iter().enumerate() {
+                let label = match (i.checked_sub(1).map(|j| bounds[j]), bounds.get(i)) {
+                    (None, Some(upper)) => format!("< {} days", upper),
+                    (Some(lower), Some(upper)) => format!("{}-{} days", lower, upper),
+                    (Some(lower), None) => format!(">= {} days", lower),
+                    (None, None) => "any age".to_string(),
+                };
+                println!("  {:<14} {}", label, count);
+            }
+
+            let index = cache.index_stats()?;
+            if index.total > 0 {
+                println!("\nLibrary index: {} files", index.total);
+                println!(
+                    "  With lyrics:     {} ({:.1}%)",
+                    index.with_lyrics,
+                    index.with_lyrics as f64 * 100.0 / index.total as f64
+                );
+                for (status, count) in &index.by_status {
+                    println!("  {:<14} {}", status.as_str(), count);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_scanner(
+    target_dir: PathBuf,
+    options: getlrc::worker::RunOptions,
+    tui: impl FnOnce(getlrc::tui::App) -> getlrc::tui::App,
+) -> Result<()> {
+    let force_retry = options.force_retry;
     tracing::info!(
         "Starting getlrc for directory: {} (force_retry: {})",
         target_dir.display(),
@@ -87,6 +778,13 @@ async fn run_scanner(target_dir: PathBuf, force_retry: bool) -> Result<()> {
         tracing::info!("Force retry enabled: negative cache will be bypassed");
         println!("🔄 Force retry mode: bypassing negative cache");
     }
+    if let Some(files) = &options.playlist {
+        println!("🎶 Playlist: {} files", files.len());
+    }
+    if options.retry_errors {
+        tracing::info!("Retry errors enabled: re-processing files that failed with errors");
+        println!("🔁 Retrying files that failed with errors");
+    }
 
     // Verify environment before initializing TUI
     getlrc::env::verify_environment()
@@ -95,7 +793,7 @@ async fn run_scanner(target_dir: PathBuf, force_retry: bool) -> Result<()> {
     // Initialize negative cache using XDG-compliant paths
     let cache_path = getlrc::paths::get_cache_db_path()?;
     tracing::info!("Cache database: {}", cache_path.display());
-    let cache = getlrc::cache::NegativeCache::open(&cache_path)?;
+    let cache = getlrc::cache::NegativeCache::open(&cache_path)?.with_expiry(options.cache_expiry);
 
     // Check for existing session
     let session_path = getlrc::paths::get_session_path()?;
@@ -156,7 +854,7 @@ async fn run_scanner(target_dir: PathBuf, force_retry: bool) -> Result<()> {
             ui_rx,
             session_path_clone,
             session,
-            force_retry,
+            options,
         )
         .await
         {
@@ -165,11 +863,36 @@ async fn run_scanner(target_dir: PathBuf, force_retry: bool) -> Result<()> {
     });
 
     // Run TUI
-    let mut app = getlrc::tui::App::new(worker_rx, ui_tx);
+    let mut app = tui(getlrc::tui::App::new(worker_rx, ui_tx));
     app.run().await?;
 
     // Wait for worker to complete
     worker_handle.await?;
 
+    if let Some(timings) = &app.state().timings {
+        println!("⏱️  Time per stage (summed over all workers):");
+        for line in timings.lines() {
+            println!("   {}", line);
+        }
+    }
+
+    let errors = app.state().error_counts();
+    if !errors.is_empty() {
+        let total: usize = errors.iter().map(|(_, count)| count).sum();
+        println!(
+            "❗ {} files failed with errors: {}",
+            total,
+            getlrc::messages::format_error_counts(&errors)
+        );
+    }
+
+    let untagged_list = getlrc::paths::get_untagged_list_path()?;
+    if untagged_list.exists() {
+        println!(
+            "🏷️  Files without readable tags are listed in {}",
+            untagged_list.display()
+        );
+    }
+
     Ok(())
 }
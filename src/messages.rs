@@ -1,5 +1,11 @@
-use crate::scanner::metadata::Track;
+use crate::api::{self, retry::ErrorClass};
+use crate::scanner::metadata::{TagOverride, Track};
+use crate::schedule::TimeWindow;
 use crate::session::StatusType;
+use crate::timing::{FileTiming, TimingSummary};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// Messages sent from Worker to TUI
 #[derive(Debug, Clone)]
@@ -9,6 +15,7 @@ pub enum WorkerMessage {
         downloaded: usize,
         cached: usize,
         skipped: usize,
+        untagged: usize,
         processed: usize,
     },
     ScanProgress {
@@ -20,22 +27,125 @@ pub enum WorkerMessage {
     TrackProcessing {
         track: Track,
     },
+    /// Queries tried for a track and what LRCLIB returned, for the details pane
+    SearchAttempts {
+        path: PathBuf,
+        attempts: Vec<api::SearchAttempt>,
+    },
+    /// The search found several plausible results for a track; one is picked with
+    /// `UiMessage::PickLyrics`
+    PickLyrics {
+        path: PathBuf,
+        candidates: Vec<api::Candidate>,
+    },
     LyricsFound {
-        path: String,
+        path: PathBuf,
     },
     LyricsNotFound {
-        path: String,
+        path: PathBuf,
     },
     CacheHit {
-        path: String,
+        path: PathBuf,
     },
     AlreadyHasLrc {
-        path: String,
+        path: PathBuf,
+    },
+    EmbeddedLyrics {
+        path: PathBuf,
     },
     Error {
-        path: String,
+        path: PathBuf,
+        kind: ErrorKind,
+        error: String,
+    },
+    /// Left out by a genre or duration filter
+    Excluded {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Looks like a podcast episode or an audiobook, so no lyrics are searched for
+    SpokenWord {
+        path: PathBuf,
+        reason: String,
+    },
+    /// Tags couldn't be read and the track couldn't be identified otherwise
+    NoMetadata {
+        path: PathBuf,
         error: String,
     },
+    /// Something about a file worth fixing, without affecting its outcome
+    Warning {
+        path: PathBuf,
+        message: String,
+    },
+    /// A worker started on an album (a directory's files), when grouping by album
+    AlbumStarted {
+        dir: PathBuf,
+    },
+    /// An album's tracks that failed with errors are being retried
+    AlbumRetry {
+        dir: PathBuf,
+        tracks: usize,
+    },
+    /// Every track of an album has been processed
+    AlbumComplete {
+        dir: PathBuf,
+        with_lyrics: usize,
+        total: usize,
+    },
+    /// Lookups failed repeatedly, so they are paused until `retry_in` has passed
+    ConnectionLost {
+        error: String,
+        retry_in: Duration,
+    },
+    /// A lookup succeeded again after the connection was lost
+    ConnectionRestored,
+    /// The pool paused outside the configured active hours, or resumed in them
+    ActiveHours {
+        active: bool,
+        /// The window that started, or the next one to start
+        window: TimeWindow,
+    },
+    /// A worker moved on to another stage of processing a file
+    StageChanged {
+        worker: usize,
+        path: Option<PathBuf>,
+        stage: WorkerStage,
+    },
+    /// Periodic report of what a worker is doing and how long it has been at it
+    WorkerHeartbeat {
+        worker: usize,
+        path: Option<PathBuf>,
+        stage: WorkerStage,
+        in_stage: Duration,
+    },
+    /// Current processing speed, for estimating the time left
+    Throughput {
+        files_per_min: f64,
+        /// Expected time per remaining file with every worker busy
+        pace: Duration,
+        /// Requests sent to LRCLIB so far
+        requests: usize,
+    },
+    /// Queue depth and the shared rate limit's state, reported with the heartbeats
+    PoolStatus {
+        /// Files waiting to be processed
+        queued: usize,
+        /// Requests sent per second since the last report
+        requests_per_sec: f64,
+        /// Requests that can be sent right away, unknown when the limit is local to this process
+        tokens: Option<f64>,
+        /// Most requests per second allowed
+        rate_limit: u32,
+    },
+    /// How long a finished file took, with `--timing`
+    FileTimed {
+        timing: FileTiming,
+    },
+    /// Time spent in each stage over the run, with `--timing`
+    Timings {
+        summary: TimingSummary,
+    },
     LogRestore {
         filename: String,
         status: StatusType,
@@ -46,10 +156,212 @@ pub enum WorkerMessage {
     },
 }
 
+/// What a worker is busy with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkerStage {
+    /// Waiting for work (or finished)
+    Idle,
+    /// Reading tags, or fingerprinting an untagged file
+    Reading,
+    /// Looking the file's signature up in the negative cache
+    CheckingCache,
+    /// Waiting for the shared request rate limit
+    RateLimited,
+    /// Searching for lyrics
+    Searching,
+    /// Pausing before retrying an album's failed tracks
+    AlbumRetry,
+    /// Pausing before another attempt at a failed lookup
+    Backoff,
+    /// Waiting for lyrics to be picked from several search results
+    Picking,
+    /// Writing the sidecar
+    Writing,
+}
+
+impl WorkerStage {
+    /// Steps of processing a file, for the current track's stage indicator
+    /// Lyrics come with the search's answer, so there is no separate download.
+    pub const STEPS: [&'static str; 4] = ["reading tags", "checking cache", "searching", "writing"];
+
+    /// Which of `STEPS` the stage is part of, if any
+    pub fn step(self) -> Option<usize> {
+        match self {
+            WorkerStage::Reading => Some(0),
+            WorkerStage::CheckingCache => Some(1),
+            WorkerStage::RateLimited
+            | WorkerStage::Searching
+            | WorkerStage::Backoff
+            | WorkerStage::Picking => Some(2),
+            WorkerStage::Writing => Some(3),
+            WorkerStage::Idle | WorkerStage::AlbumRetry => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WorkerStage::Idle => "idle",
+            WorkerStage::Reading => "reading",
+            WorkerStage::CheckingCache => "checking cache",
+            WorkerStage::RateLimited => "rate limited",
+            WorkerStage::Searching => "searching",
+            WorkerStage::AlbumRetry => "album retry",
+            WorkerStage::Backoff => "retrying",
+            WorkerStage::Picking => "picking",
+            WorkerStage::Writing => "writing",
+        }
+    }
+}
+
+/// What went wrong with a file that failed, to count failures by cause
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// LRCLIB couldn't be reached or didn't answer in time
+    Network,
+    /// LRCLIB answered with an error status
+    HttpStatus,
+    /// LRCLIB answered 429 Too Many Requests
+    RateLimited,
+    /// The audio file couldn't be parsed
+    TagParse,
+    /// Reading or writing a file failed
+    Io,
+    Other,
+}
+
+impl ErrorKind {
+    pub const ALL: [ErrorKind; 6] = [
+        ErrorKind::Network,
+        ErrorKind::HttpStatus,
+        ErrorKind::RateLimited,
+        ErrorKind::TagParse,
+        ErrorKind::Io,
+        ErrorKind::Other,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Network => "network",
+            ErrorKind::HttpStatus => "http status",
+            ErrorKind::RateLimited => "rate limited",
+            ErrorKind::TagParse => "tag parse",
+            ErrorKind::Io => "io",
+            ErrorKind::Other => "other",
+        }
+    }
+
+    /// Which kind an error falls into, from the first cause in its chain that says
+    pub fn of(error: &anyhow::Error) -> Self {
+        if let Some(class) = api::retry::classify(error) {
+            return match class {
+                ErrorClass::Timeout | ErrorClass::Connection => ErrorKind::Network,
+                ErrorClass::Server => ErrorKind::HttpStatus,
+                ErrorClass::RateLimited => ErrorKind::RateLimited,
+            };
+        }
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<lofty::error::LoftyError>() {
+                    Some(match e.kind() {
+                        lofty::error::ErrorKind::Io(_) => ErrorKind::Io,
+                        _ => ErrorKind::TagParse,
+                    })
+                } else if cause.downcast_ref::<api::UnexpectedStatus>().is_some() {
+                    Some(ErrorKind::HttpStatus)
+                } else if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                    e.status().map(|_| ErrorKind::HttpStatus)
+                } else if cause.downcast_ref::<std::io::Error>().is_some() {
+                    Some(ErrorKind::Io)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorKind::Other)
+    }
+}
+
+/// Failed files counted by kind, in `ErrorKind::ALL` order, leaving out kinds with none
+pub fn error_counts(kinds: impl IntoIterator<Item = ErrorKind>) -> Vec<(ErrorKind, usize)> {
+    let mut counts = [0; ErrorKind::ALL.len()];
+    for kind in kinds {
+        counts[kind as usize] += 1;
+    }
+    ErrorKind::ALL
+        .into_iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// Counts by kind as one line, e.g. "300 network, 12 io"
+pub fn format_error_counts(counts: &[(ErrorKind, usize)]) -> String {
+    counts
+        .iter()
+        .map(|(kind, count)| format!("{} {}", count, kind.label()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Messages sent from TUI to Worker
 #[derive(Debug, Clone)]
 pub enum UiMessage {
-    Quit,
+    /// Stop the run, saving the remaining files as a session first when `save` is set
+    Quit {
+        save: bool,
+    },
     Pause,
     Resume,
+    /// Process a failed file again straight away, bypassing the negative cache
+    Retry {
+        path: PathBuf,
+    },
+    /// Take the queued files of this album (directory) before any others
+    Prioritize {
+        dir: PathBuf,
+    },
+    /// Search for a file again under fields entered by hand, writing what is found
+    ManualSearch {
+        path: PathBuf,
+        tags: TagOverride,
+    },
+    /// Stop waiting out the backoff after `WorkerMessage::ConnectionLost` and try again
+    RetryConnection,
+    /// The search result to write for a `WorkerMessage::PickLyrics`, or None to skip it
+    PickLyrics {
+        path: PathBuf,
+        choice: Option<usize>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use reqwest::StatusCode;
+
+    #[test]
+    fn test_error_kind_of() {
+        let status = |code| anyhow::Error::from(api::UnexpectedStatus(code));
+        assert_eq!(
+            ErrorKind::of(&status(StatusCode::TOO_MANY_REQUESTS)),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            ErrorKind::of(&status(StatusCode::BAD_GATEWAY)),
+            ErrorKind::HttpStatus
+        );
+        assert_eq!(
+            ErrorKind::of(&status(StatusCode::BAD_REQUEST)),
+            ErrorKind::HttpStatus
+        );
+
+        let missing = std::fs::read("/nonexistent/a.lrc").context("Failed to write sidecar");
+        assert_eq!(ErrorKind::of(&missing.unwrap_err()), ErrorKind::Io);
+        assert_eq!(
+            ErrorKind::of(&anyhow::anyhow!("something else")),
+            ErrorKind::Other
+        );
+    }
 }
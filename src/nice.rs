@@ -0,0 +1,68 @@
+use std::time::Duration;
+
+/// Workers with `--nice`, down from the usual five
+pub const NICE_WORKERS: usize = 2;
+/// Pause after each directory read with `--nice`, so a scan doesn't saturate the disk
+pub const SCAN_PAUSE: Duration = Duration::from_millis(20);
+
+/// Scheduling priority (niceness) for `--nice`, the lowest there is
+#[cfg(unix)]
+const NICENESS: libc::c_int = 19;
+
+/// Run the rest of the process at the lowest CPU and disk priority
+/// On Linux this only covers the calling thread and the threads it spawns afterwards, so it
+/// must be called before the async runtime and the scan's thread pool start.
+/// Failures are logged and otherwise ignored: the run still works, just at normal priority.
+pub fn lower_priority() {
+    lower_cpu_priority();
+    lower_io_priority();
+}
+
+#[cfg(unix)]
+fn lower_cpu_priority() {
+    // SAFETY: setpriority only reads its arguments; 0 is the calling process
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, NICENESS) } != 0 {
+        tracing::warn!(
+            "Failed to lower process priority: {}",
+            std::io::Error::last_os_error()
+        );
+    } else {
+        tracing::info!("Process priority lowered to nice {}", NICENESS);
+    }
+}
+
+#[cfg(not(unix))]
+fn lower_cpu_priority() {
+    tracing::warn!("Lowering process priority isn't supported on this platform");
+}
+
+/// Idle I/O class: disk access only when no other process wants it
+#[cfg(target_os = "linux")]
+fn lower_io_priority() {
+    const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_long = 13;
+
+    // SAFETY: ioprio_set only reads its arguments; 0 is the calling process
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        )
+    };
+    if result != 0 {
+        tracing::warn!(
+            "Failed to lower I/O priority: {}",
+            std::io::Error::last_os_error()
+        );
+    } else {
+        tracing::info!("I/O priority set to idle");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn lower_io_priority() {
+    tracing::debug!("Lowering I/O priority is only supported on Linux");
+}
@@ -0,0 +1,150 @@
+use crate::scanner::winpath;
+use std::path::{Path, PathBuf};
+
+/// Prefix marking an encoded non-UTF-8 path; NUL can't occur in real paths
+const RAW_PREFIX: char = '\0';
+
+/// Lossless text form of a path, for the session file and cache database
+/// UTF-8 paths are stored as-is, minus the `\\?\` prefix of extended Windows paths. Others
+/// (e.g. Latin-1 file names on Linux) are stored as hex-encoded raw bytes after a NUL,
+/// instead of being mangled by a lossy conversion.
+pub fn encode(path: &Path) -> String {
+    let path = &winpath::simplified(path);
+    if let Some(text) = path.to_str() {
+        return text.to_string();
+    }
+
+    let mut encoded = String::from(RAW_PREFIX);
+    for unit in raw_units(path) {
+        encoded.push_str(&format!("{:0width$x}", unit, width = UNIT_WIDTH));
+    }
+    encoded
+}
+
+/// Inverse of [`encode`], giving extended-length paths on Windows
+pub fn decode(text: &str) -> PathBuf {
+    match text.strip_prefix(RAW_PREFIX) {
+        Some(hex) => winpath::extended(&from_raw_units(
+            hex.as_bytes()
+                .chunks(UNIT_WIDTH)
+                .filter_map(|chunk| u32::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok())
+                .collect(),
+        )),
+        None => winpath::extended(Path::new(text)),
+    }
+}
+
+#[cfg(unix)]
+const UNIT_WIDTH: usize = 2;
+
+#[cfg(unix)]
+fn raw_units(path: &Path) -> Vec<u32> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&b| b.into())
+        .collect()
+}
+
+#[cfg(unix)]
+fn from_raw_units(units: Vec<u32>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    let bytes = units.into_iter().map(|unit| unit as u8).collect();
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(windows)]
+const UNIT_WIDTH: usize = 4;
+
+/// Windows paths are UTF-16 that may contain unpaired surrogates
+#[cfg(windows)]
+fn raw_units(path: &Path) -> Vec<u32> {
+    use std::os::windows::ffi::OsStrExt;
+    path.as_os_str().encode_wide().map(u32::from).collect()
+}
+
+#[cfg(windows)]
+fn from_raw_units(units: Vec<u32>) -> PathBuf {
+    use std::os::windows::ffi::OsStringExt;
+    let wide: Vec<u16> = units.into_iter().map(|unit| unit as u16).collect();
+    PathBuf::from(std::ffi::OsString::from_wide(&wide))
+}
+
+#[cfg(not(any(unix, windows)))]
+const UNIT_WIDTH: usize = 2;
+
+#[cfg(not(any(unix, windows)))]
+fn raw_units(path: &Path) -> Vec<u32> {
+    path.to_string_lossy().bytes().map(u32::from).collect()
+}
+
+#[cfg(not(any(unix, windows)))]
+fn from_raw_units(units: Vec<u32>) -> PathBuf {
+    let bytes: Vec<u8> = units.into_iter().map(|unit| unit as u8).collect();
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// `#[serde(with = "...")]` adapter for a `PathBuf` field
+pub mod serde_path {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::{Path, PathBuf};
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::encode(path))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        Ok(super::decode(&String::deserialize(deserializer)?))
+    }
+}
+
+/// `#[serde(with = "...")]` adapter for a `Vec<PathBuf>` field
+pub mod serde_paths {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::path::PathBuf;
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(paths.iter().map(|path| super::encode(path)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<PathBuf>, D::Error> {
+        let encoded = Vec::<String>::deserialize(deserializer)?;
+        Ok(encoded.iter().map(|text| super::decode(text)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_paths_are_unchanged() {
+        let path = Path::new("/music/Björk/01 - Jóga.flac");
+        assert_eq!(encode(path), "/music/Björk/01 - Jóga.flac");
+        assert_eq!(decode(&encode(path)), path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_paths_round_trip() {
+        use std::os::unix::ffi::OsStrExt;
+
+        // "Café.mp3" with a Latin-1 é
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/music/Caf\xe9.mp3"));
+        let encoded = encode(path);
+        assert!(encoded.starts_with('\0'));
+        assert_eq!(decode(&encoded), path);
+
+        // Sessions with such files can be saved and resumed
+        let dir = tempfile::tempdir().unwrap();
+        let session_path = dir.path().join("session.json");
+        crate::session::PersistentSession::new(PathBuf::from("/music"), vec![path.into()], false)
+            .save(&session_path)
+            .unwrap();
+        let session = crate::session::PersistentSession::load(&session_path).unwrap();
+        assert_eq!(session.pending_files, vec![path.to_path_buf()]);
+    }
+}
@@ -1,12 +1,41 @@
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const CACHE_DB_NAME: &str = "negative_cache.db";
+
+/// User-supplied locations that replace the XDG defaults
+#[derive(Debug, Clone, Default)]
+pub struct PathOverrides {
+    /// Replaces ~/.local/share/getlrc (session, logs)
+    pub data_dir: Option<PathBuf>,
+    /// Replaces ~/.cache/getlrc/negative_cache.db
+    pub cache_path: Option<PathBuf>,
+}
+
+static OVERRIDES: OnceLock<PathOverrides> = OnceLock::new();
+
+/// Install path overrides for the rest of the process
+/// Must be called before any other path lookup; later calls are ignored.
+pub fn set_overrides(overrides: PathOverrides) {
+    if OVERRIDES.set(overrides).is_err() {
+        tracing::warn!("Path overrides already set, ignoring");
+    }
+}
+
+fn overrides() -> &'static PathOverrides {
+    OVERRIDES.get_or_init(PathOverrides::default)
+}
 
 /// Get the application's data directory following XDG standards
 /// On Linux: ~/.local/share/getlrc
 pub fn get_data_dir() -> Result<PathBuf> {
-    let data_dir = dirs::data_local_dir()
-        .context("Failed to determine user data directory")?
-        .join("getlrc");
+    let data_dir = match &overrides().data_dir {
+        Some(dir) => dir.clone(),
+        None => dirs::data_local_dir()
+            .context("Failed to determine user data directory")?
+            .join("getlrc"),
+    };
 
     // Ensure directory exists
     std::fs::create_dir_all(&data_dir)
@@ -16,9 +45,82 @@ pub fn get_data_dir() -> Result<PathBuf> {
     Ok(data_dir)
 }
 
+/// Get the application's cache directory following XDG standards
+/// On Linux: ~/.cache/getlrc
+pub fn get_cache_dir() -> Result<PathBuf> {
+    let cache_dir = dirs::cache_dir()
+        .context("Failed to determine user cache directory")?
+        .join("getlrc");
+
+    std::fs::create_dir_all(&cache_dir)
+        .with_context(|| format!("Failed to create cache directory: {}", cache_dir.display()))?;
+
+    tracing::debug!("Cache directory: {}", cache_dir.display());
+    Ok(cache_dir)
+}
+
 /// Get the path to the negative cache database
+/// Moves a database left in the data directory by older versions into the cache directory.
 pub fn get_cache_db_path() -> Result<PathBuf> {
-    Ok(get_data_dir()?.join("negative_cache.db"))
+    if let Some(path) = &overrides().cache_path {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| {
+                format!("Failed to create cache directory: {}", parent.display())
+            })?;
+        }
+        return Ok(path.clone());
+    }
+
+    let cache_path = get_cache_dir()?.join(CACHE_DB_NAME);
+    migrate_legacy_cache_db(&get_data_dir()?.join(CACHE_DB_NAME), &cache_path)?;
+    Ok(cache_path)
+}
+
+/// Move the cache database (and its WAL/SHM files) to its new location
+fn migrate_legacy_cache_db(legacy: &Path, target: &Path) -> Result<()> {
+    if !legacy.exists() || target.exists() {
+        return Ok(());
+    }
+
+    for suffix in ["", "-wal", "-shm"] {
+        let from = PathBuf::from(format!("{}{}", legacy.display(), suffix));
+        let to = PathBuf::from(format!("{}{}", target.display(), suffix));
+        if !from.exists() {
+            continue;
+        }
+
+        // rename fails across filesystems, so fall back to copy + delete
+        if std::fs::rename(&from, &to).is_err() {
+            std::fs::copy(&from, &to).with_context(|| {
+                format!("Failed to move {} to {}", from.display(), to.display())
+            })?;
+            std::fs::remove_file(&from)
+                .with_context(|| format!("Failed to remove {}", from.display()))?;
+        }
+    }
+
+    tracing::info!(
+        "Migrated negative cache from {} to {}",
+        legacy.display(),
+        target.display()
+    );
+    Ok(())
+}
+
+/// Get the path to the user config file
+/// On Linux: ~/.config/getlrc/config.toml
+pub fn get_config_path() -> Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("Failed to determine user config directory")?
+        .join("getlrc")
+        .join("config.toml"))
+}
+
+/// Get the path to the rate limit state shared by all getlrc processes
+/// Always in the local cache directory: the lrclib limit applies per machine, even when the
+/// negative cache lives on a shared drive.
+pub fn get_rate_limit_path() -> Result<PathBuf> {
+    Ok(get_cache_dir()?.join("ratelimit.json"))
 }
 
 /// Get the path to the session state file
@@ -26,6 +128,26 @@ pub fn get_session_path() -> Result<PathBuf> {
     Ok(get_data_dir()?.join("session.json"))
 }
 
+/// Get the path to the live status file polled by dashboards and scripts
+pub fn get_status_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("status.json"))
+}
+
+/// Get the path to the playlist of files without readable tags
+pub fn get_untagged_list_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("untagged.m3u8"))
+}
+
+/// Get the path to the playlist of failed files exported from the TUI
+pub fn get_failures_list_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("failures.m3u8"))
+}
+
+/// Get the path to the history of past runs
+pub fn get_history_path() -> Result<PathBuf> {
+    Ok(get_data_dir()?.join("history.jsonl"))
+}
+
 /// Get the application's log directory
 pub fn get_log_dir() -> Result<PathBuf> {
     let log_dir = get_data_dir()?.join("logs");
@@ -71,4 +193,20 @@ mod tests {
         let cache_path = get_cache_db_path().unwrap();
         assert!(cache_path.ends_with("negative_cache.db"));
     }
+
+    #[test]
+    fn test_migrate_legacy_cache_db() {
+        let old_dir = tempfile::tempdir().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+        let legacy = old_dir.path().join(CACHE_DB_NAME);
+        let target = new_dir.path().join(CACHE_DB_NAME);
+        std::fs::write(&legacy, b"db").unwrap();
+        std::fs::write(old_dir.path().join("negative_cache.db-wal"), b"wal").unwrap();
+
+        migrate_legacy_cache_db(&legacy, &target).unwrap();
+
+        assert!(!legacy.exists());
+        assert_eq!(std::fs::read(&target).unwrap(), b"db");
+        assert!(new_dir.path().join("negative_cache.db-wal").exists());
+    }
 }
@@ -0,0 +1,113 @@
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+/// Identity of a file's audio, unaffected by its tags
+/// FLAC files carry an MD5 of their decoded audio in STREAMINFO, which also survives
+/// re-encoding at another compression level. For MP3 the frames between the ID3v2 header
+/// and the ID3v1/APE trailer are hashed. Other formats have no key.
+pub fn content_key(path: &Path) -> Option<String> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    match ext.as_str() {
+        "flac" => flac_md5(path).map(|md5| format!("flac:{}", hex(&md5))),
+        "mp3" => {
+            let bytes = std::fs::read(path).ok()?;
+            let frames = mp3_frames(&bytes)?;
+            Some(format!("mp3:{}", hex(&Sha256::digest(frames))))
+        }
+        _ => None,
+    }
+}
+
+/// The audio MD5 from a FLAC file's STREAMINFO block, if the encoder filled it in
+fn flac_md5(path: &Path) -> Option<[u8; 16]> {
+    // "fLaC", block header (4 bytes), then STREAMINFO with the MD5 in its last 16 bytes
+    let mut header = [0u8; 42];
+    std::fs::File::open(path)
+        .ok()?
+        .read_exact(&mut header)
+        .ok()?;
+    if &header[..4] != b"fLaC" || header[4] & 0x7f != 0 {
+        return None;
+    }
+
+    let md5: [u8; 16] = header[26..42].try_into().ok()?;
+    (md5 != [0; 16]).then_some(md5)
+}
+
+/// MPEG frames of an MP3, without its ID3v2, ID3v1 and APE tags
+fn mp3_frames(bytes: &[u8]) -> Option<&[u8]> {
+    let mut start = 0;
+    if bytes.len() >= 10 && &bytes[..3] == b"ID3" {
+        // Syncsafe size, excluding the 10-byte header and the optional footer
+        let size = bytes[6..10]
+            .iter()
+            .fold(0usize, |size, &b| (size << 7) | usize::from(b & 0x7f));
+        let footer = if bytes[5] & 0x10 != 0 { 10 } else { 0 };
+        start = 10 + size + footer;
+    }
+
+    let mut end = bytes.len();
+    if end >= start + 128 && &bytes[end - 128..end - 125] == b"TAG" {
+        end -= 128;
+    }
+    if end >= start + 32 && &bytes[end - 32..end - 24] == b"APETAGEX" {
+        let footer = &bytes[end - 32..end];
+        // Size covers the items and footer; a header is flagged separately
+        let size = u32::from_le_bytes(footer[12..16].try_into().ok()?) as usize;
+        let flags = u32::from_le_bytes(footer[20..24].try_into().ok()?);
+        let header = if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+        end = end.checked_sub(size + header)?;
+    }
+
+    (start < end).then(|| &bytes[start..end])
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mp3_key_ignores_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames = [0xffu8, 0xfb, 0x90, 0x64, 1, 2, 3, 4];
+
+        let plain = dir.path().join("plain.mp3");
+        std::fs::write(&plain, frames).unwrap();
+
+        let mut tagged_bytes = b"ID3\x04\x00\x00\x00\x00\x00\x05TIT2x".to_vec();
+        tagged_bytes.extend_from_slice(&frames);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        tagged_bytes.extend_from_slice(&id3v1);
+        let tagged = dir.path().join("tagged.mp3");
+        std::fs::write(&tagged, tagged_bytes).unwrap();
+
+        assert!(content_key(&plain).is_some());
+        assert_eq!(content_key(&plain), content_key(&tagged));
+        assert_eq!(content_key(&dir.path().join("missing.mp3")), None);
+    }
+
+    #[test]
+    fn test_flac_key_from_streaminfo() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut bytes = b"fLaC\x80\x00\x00\x22".to_vec();
+        bytes.extend_from_slice(&[0; 18]);
+        bytes.extend_from_slice(&[0xab; 16]);
+        let path = dir.path().join("a.flac");
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(
+            content_key(&path),
+            Some(format!("flac:{}", "ab".repeat(16)))
+        );
+
+        // Encoders may leave the MD5 unset
+        bytes[26..42].fill(0);
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(content_key(&path), None);
+    }
+}
@@ -1,9 +1,15 @@
 use regex::Regex;
 use std::sync::OnceLock;
 
+/// Version of the cleaning rules below
+/// Bump this whenever normalization changes so cached misses made under older rules are retried.
+pub const NORMALIZATION_VERSION: u32 = 2;
+
 /// Lazy-initialized regex patterns for metadata cleaning
 static TRACK_NUMBER_REGEX: OnceLock<Regex> = OnceLock::new();
 static FEAT_REGEX: OnceLock<Regex> = OnceLock::new();
+static FEATURED_ARTIST_REGEX: OnceLock<Regex> = OnceLock::new();
+static TITLE_BLACKLIST_REGEX: OnceLock<Option<Regex>> = OnceLock::new();
 static WHITESPACE_REGEX: OnceLock<Regex> = OnceLock::new();
 static PUNCTUATION_REGEX: OnceLock<Regex> = OnceLock::new();
 
@@ -18,6 +24,47 @@ fn get_feat_regex() -> &'static Regex {
     })
 }
 
+/// A featured artist credit (`feat. X`, `ft. X`, `featuring X`), capturing the artist
+fn get_featured_artist_regex() -> &'static Regex {
+    FEATURED_ARTIST_REGEX.get_or_init(|| {
+        Regex::new(r"(?i)\b(?:feat\.?|ft\.?|featuring)\s+([^\)\]]+?)\s*(?:[\)\]]|$)").unwrap()
+    })
+}
+
+/// Install the user's title blacklist for the rest of the process
+/// Each token is removed from titles as a whole word, along with brackets around it.
+/// Must be called before any title is cleaned; later calls are ignored.
+pub fn set_title_blacklist(tokens: &[String]) {
+    if TITLE_BLACKLIST_REGEX.set(blacklist_regex(tokens)).is_err() {
+        tracing::warn!("Title blacklist already set, ignoring");
+    }
+}
+
+/// Pattern matching any of the blacklisted tokens, if there are any
+fn blacklist_regex(tokens: &[String]) -> Option<Regex> {
+    let tokens: Vec<String> = tokens
+        .iter()
+        .map(|token| token.trim_matches(|c: char| c.is_whitespace() || "()[]".contains(c)))
+        .filter(|token| !token.is_empty())
+        .map(regex::escape)
+        .collect();
+    (!tokens.is_empty()).then(|| {
+        Regex::new(&format!(
+            r"(?i)\s*[\(\[]?\s*\b(?:{})\b\s*[\)\]]?",
+            tokens.join("|")
+        ))
+        .unwrap()
+    })
+}
+
+/// Remove blacklisted tokens from a title
+fn strip_blacklisted(title: &str) -> String {
+    match TITLE_BLACKLIST_REGEX.get().and_then(Option::as_ref) {
+        Some(regex) => regex.replace_all(title, "").into_owned(),
+        None => title.to_string(),
+    }
+}
+
 fn get_whitespace_regex() -> &'static Regex {
     WHITESPACE_REGEX.get_or_init(|| Regex::new(r"\s+").unwrap())
 }
@@ -59,7 +106,7 @@ pub fn clean_string(input: &str) -> String {
 /// Keeps: Remix, Live, Acoustic, Unplugged, Radio Edit, etc.
 /// Removes: Featuring artists only
 pub fn clean_title(title: &str) -> String {
-    let mut result = title.to_string();
+    let mut result = strip_blacklisted(title);
 
     // Remove featuring artists (but not other parentheticals)
     result = get_feat_regex().replace_all(&result, "").to_string();
@@ -75,7 +122,7 @@ pub fn clean_title(title: &str) -> String {
 /// Clean a title but keep parenthetical content (for first attempt)
 /// Removes the parentheses themselves but keeps the text inside
 pub fn clean_title_keep_parens(title: &str) -> String {
-    let mut result = title.to_string();
+    let mut result = strip_blacklisted(title);
 
     // Remove featuring artists
     result = get_feat_regex().replace_all(&result, "").to_string();
@@ -103,6 +150,53 @@ pub fn get_stripped_title(normalized: &NormalizedMetadata) -> String {
     clean_title(&normalized.original_title)
 }
 
+/// Remove characters that don't show but break exact matches: control characters,
+/// zero-width spaces and joiners, and byte order marks
+/// Tabs and line breaks become spaces.
+pub fn strip_invisible(input: &str) -> String {
+    input
+        .chars()
+        .filter_map(|c| match c {
+            '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' => None,
+            c if c.is_control() && c.is_whitespace() => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Artist credit with the title's featured artist moved into it: `A feat. B`
+/// Returns None when the title doesn't credit a featured artist.
+pub fn artist_with_featured(artist: &str, title: &str) -> Option<String> {
+    let featured = get_featured_artist_regex()
+        .captures(title)?
+        .get(1)?
+        .as_str();
+    Some(format!("{} feat. {}", artist, featured))
+}
+
+/// Turn a sort name into display order: `Beatles, The` and `Cash, Johnny` become
+/// `The Beatles` and `Johnny Cash`
+/// Names whose part after the comma has several words (`Earth, Wind & Fire`) are kept.
+pub fn display_order(sort_name: &str) -> String {
+    match sort_name.split_once(", ") {
+        Some((last, first))
+            if !first.is_empty() && !first.contains([' ', ',']) && !last.contains(',') =>
+        {
+            format!("{} {}", first, last)
+        }
+        _ => sort_name.to_string(),
+    }
+}
+
+/// Whether an artist name is a compilation placeholder rather than a real artist
+pub fn is_various_artists(artist: &str) -> bool {
+    matches!(
+        clean_string(artist).as_str(),
+        "various artists" | "various" | "va" | "v a" | "v/a"
+    )
+}
+
 /// Calculate similarity score between two strings (0.0 to 1.0)
 pub fn similarity_score(a: &str, b: &str) -> f64 {
     strsim::jaro_winkler(a, b)
@@ -117,6 +211,36 @@ pub fn is_similar(a: &str, b: &str, threshold: f64) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_invisible() {
+        assert_eq!(strip_invisible("\u{FEFF}Artist"), "Artist");
+        assert_eq!(strip_invisible("Ti\u{200B}tle\0"), "Title");
+        assert_eq!(strip_invisible("Line\tBreak"), "Line Break");
+        assert_eq!(strip_invisible("Plain"), "Plain");
+    }
+
+    #[test]
+    fn test_artist_with_featured() {
+        assert_eq!(
+            artist_with_featured("Artist", "Song (feat. Guest) [Remix]").as_deref(),
+            Some("Artist feat. Guest")
+        );
+        assert_eq!(
+            artist_with_featured("Artist", "Song ft. Guest & Other").as_deref(),
+            Some("Artist feat. Guest & Other")
+        );
+        assert_eq!(artist_with_featured("Artist", "Song (Live)"), None);
+        assert_eq!(artist_with_featured("Artist", "Defeat the Night"), None);
+    }
+
+    #[test]
+    fn test_display_order() {
+        assert_eq!(display_order("Beatles, The"), "The Beatles");
+        assert_eq!(display_order("Yamashita, Tatsuro"), "Tatsuro Yamashita");
+        assert_eq!(display_order("Earth, Wind & Fire"), "Earth, Wind & Fire");
+        assert_eq!(display_order("Radiohead"), "Radiohead");
+    }
+
     #[test]
     fn test_clean_string() {
         assert_eq!(clean_string("20. Song Name"), "song name");
@@ -133,6 +257,19 @@ mod tests {
         assert_eq!(clean_title("Song (Live)"), "song live"); // Live preserved!
     }
 
+    #[test]
+    fn test_blacklist_regex() {
+        let regex =
+            blacklist_regex(&["Explicit".to_string(), " [Bonus Track] ".to_string()]).unwrap();
+        assert_eq!(regex.replace_all("Song [Explicit]", ""), "Song");
+        assert_eq!(
+            regex.replace_all("Song (bonus track) (Live)", ""),
+            "Song (Live)"
+        );
+        assert_eq!(regex.replace_all("Inexplicit", ""), "Inexplicit");
+        assert!(blacklist_regex(&[" ".to_string()]).is_none());
+    }
+
     #[test]
     fn test_clean_title_keep_parens() {
         // Same behavior - both preserve version info
@@ -144,6 +281,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_various_artists() {
+        assert!(is_various_artists("Various Artists"));
+        assert!(is_various_artists("V.A."));
+        assert!(is_various_artists("VA"));
+        assert!(!is_various_artists("Vanessa Carlton"));
+    }
+
     #[test]
     fn test_similarity_score() {
         assert!(similarity_score("hello", "hello") > 0.99);
@@ -0,0 +1,347 @@
+use super::clean;
+use super::metadata::{MetadataSource, Track, TrackPosition};
+use super::winpath;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Cue sheet positions are in frames of 1/75 second
+const FRAMES_PER_SECOND: u64 = 75;
+
+/// How lyrics for the tracks of a single-file album are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CueLayout {
+    /// One `NN - Title.lrc` per track, next to the image
+    #[default]
+    Tracks,
+    /// A single sidecar for the image, with each track's timestamps offset by its start
+    Combined,
+}
+
+/// A track listed in a cue sheet
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub performer: String,
+    pub title: String,
+    /// Position of `INDEX 01` in the image (milliseconds)
+    pub start_ms: u64,
+}
+
+/// A cue sheet describing several tracks in one audio file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueSheet {
+    pub performer: String,
+    /// Album title
+    pub title: String,
+    /// The `FILE` the tracks are stored in
+    pub file: String,
+    /// From `REM GENRE`
+    pub genre: String,
+    /// From `REM DISCNUMBER` and `REM TOTALDISCS`
+    pub disc: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub tracks: Vec<CueTrack>,
+}
+
+impl CueSheet {
+    /// Parse a cue sheet; only sheets with a single `FILE` are supported
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut sheet = CueSheet {
+            performer: String::new(),
+            title: String::new(),
+            file: String::new(),
+            genre: String::new(),
+            disc: None,
+            disc_total: None,
+            tracks: Vec::new(),
+        };
+
+        for line in contents.lines() {
+            let line = line.trim();
+            let (command, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let args = args.trim();
+
+            match command.to_ascii_uppercase().as_str() {
+                "FILE" => {
+                    anyhow::ensure!(sheet.file.is_empty(), "Cue sheet lists several files");
+                    sheet.file = file_name(args);
+                }
+                "REM" => {
+                    let (key, value) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                    let value = unquote(value);
+                    match key.to_ascii_uppercase().as_str() {
+                        "GENRE" => sheet.genre = value,
+                        "DISCNUMBER" => sheet.disc = value.parse().ok(),
+                        "TOTALDISCS" => sheet.disc_total = value.parse().ok(),
+                        _ => {}
+                    }
+                }
+                "TRACK" => {
+                    let number = args
+                        .split_whitespace()
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .with_context(|| format!("Invalid TRACK line: {}", line))?;
+                    sheet.tracks.push(CueTrack {
+                        number,
+                        performer: String::new(),
+                        title: String::new(),
+                        start_ms: 0,
+                    });
+                }
+                "TITLE" => match sheet.tracks.last_mut() {
+                    Some(track) => track.title = unquote(args),
+                    None => sheet.title = unquote(args),
+                },
+                "PERFORMER" => match sheet.tracks.last_mut() {
+                    Some(track) => track.performer = unquote(args),
+                    None => sheet.performer = unquote(args),
+                },
+                "INDEX" => {
+                    let mut parts = args.split_whitespace();
+                    if parts.next() == Some("01") {
+                        let start_ms = parts
+                            .next()
+                            .and_then(parse_position)
+                            .with_context(|| format!("Invalid INDEX line: {}", line))?;
+                        if let Some(track) = sheet.tracks.last_mut() {
+                            track.start_ms = start_ms;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        anyhow::ensure!(!sheet.file.is_empty(), "Cue sheet has no FILE");
+        Ok(sheet)
+    }
+
+    /// Read a cue sheet file (tolerating a BOM and non-UTF-8 text)
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let contents = String::from_utf8_lossy(&bytes);
+        Self::parse(contents.trim_start_matches('\u{feff}'))
+            .with_context(|| format!("Invalid cue sheet {}", path.display()))
+    }
+
+    /// Tracks to search for, with durations derived from the next track's start
+    /// `image_secs` is the length of the whole image, which bounds the last track.
+    pub fn to_tracks(&self, image: &Path, image_secs: u64) -> Vec<(CueTrack, Track)> {
+        let compilation = clean::is_various_artists(&self.performer);
+        self.tracks
+            .iter()
+            .enumerate()
+            .map(|(i, cue_track)| {
+                let end_ms = self
+                    .tracks
+                    .get(i + 1)
+                    .map_or(image_secs * 1000, |next| next.start_ms);
+                let artist = if !cue_track.performer.is_empty() {
+                    &cue_track.performer
+                } else if !compilation {
+                    &self.performer
+                } else {
+                    ""
+                };
+
+                let track = Track {
+                    path: image.to_path_buf(),
+                    artist: artist.to_string(),
+                    artists: Vec::new(),
+                    sort_artist: String::new(),
+                    title: cue_track.title.clone(),
+                    album: self.title.clone(),
+                    genre: self.genre.clone(),
+                    duration_secs: (end_ms.saturating_sub(cue_track.start_ms) + 500) / 1000,
+                    has_embedded_lyrics: false,
+                    source: MetadataSource::Tags,
+                    position: TrackPosition {
+                        disc: self.disc,
+                        disc_total: self.disc_total,
+                        track: Some(cue_track.number),
+                    },
+                    compilation,
+                };
+                (cue_track.clone(), track)
+            })
+            .collect()
+    }
+}
+
+/// The cue sheets in one directory, read once for all of its audio files
+#[derive(Debug, Default)]
+pub struct DirCueSheets {
+    sheets: Vec<(PathBuf, CueSheet)>,
+}
+
+impl DirCueSheets {
+    /// Read every cue sheet in `dir`, skipping ones that can't be parsed
+    pub fn read(dir: &Path) -> Self {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Self::default();
+        };
+        let sheets = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("cue"))
+            })
+            .filter_map(|path| Some((path.clone(), load(&path)?)))
+            .collect();
+        Self { sheets }
+    }
+
+    /// Find the cue sheet that splits `audio`, a file in this directory, into several tracks
+    /// Looks for `Album.cue` or `Album.flac.cue` first, then any cue sheet whose `FILE`
+    /// names the audio file.
+    pub fn find_for(&self, audio: &Path) -> Option<CueSheet> {
+        let file_name = audio.file_name()?.to_string_lossy().to_string();
+        let candidates = [
+            audio.with_extension("cue"),
+            audio.with_file_name(format!("{}.cue", file_name)),
+        ];
+
+        // A sheet named after the audio file belongs to it whatever its FILE says
+        let sheet = candidates
+            .iter()
+            .find_map(|candidate| self.sheets.iter().find(|(path, _)| path == candidate))
+            .or_else(|| {
+                self.sheets
+                    .iter()
+                    .find(|(_, sheet)| describes(sheet, &file_name))
+            })
+            .map(|(_, sheet)| sheet)?;
+
+        // A single-track sheet adds nothing over the file's own tags
+        (sheet.tracks.len() > 1).then(|| sheet.clone())
+    }
+}
+
+/// Whether a cue sheet describes the named audio file
+/// Rippers often reference the original `.wav` after the image was compressed,
+/// so only the file stems are compared.
+fn describes(sheet: &CueSheet, file_name: &str) -> bool {
+    let stem = |name: &str| {
+        Path::new(name)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+    };
+    stem(&sheet.file) == stem(file_name)
+}
+
+fn load(path: &Path) -> Option<CueSheet> {
+    CueSheet::load(path)
+        .map_err(|e| tracing::debug!("Skipping cue sheet: {:#}", e))
+        .ok()
+}
+
+/// Path of the `.lrc` written for one track of an image, e.g. `03 - Title.lrc`
+pub fn track_lrc_path(image: &Path, track: &CueTrack) -> PathBuf {
+    image.with_file_name(format!(
+        "{:02} - {}.lrc",
+        track.number,
+        winpath::safe_file_name(&track.title)
+    ))
+}
+
+/// Strip surrounding quotes from a cue sheet value
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .unwrap_or(value)
+        .to_string()
+}
+
+/// The file name from a `FILE "name" TYPE` line
+fn file_name(args: &str) -> String {
+    match args.strip_prefix('"') {
+        Some(rest) => rest.split('"').next().unwrap_or_default().to_string(),
+        // Unquoted names can't contain spaces; the type follows the name
+        None => args
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// Parse an `mm:ss:ff` position into milliseconds
+fn parse_position(position: &str) -> Option<u64> {
+    let mut parts = position.split(':').map(|part| part.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (parts.next()??, parts.next()??, parts.next()??);
+    Some((minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "\u{feff}REM GENRE Rock
+REM DISCNUMBER 2
+REM TOTALDISCS 2
+PERFORMER \"The Band\"
+TITLE \"Live Album\"
+FILE \"The Band - Live Album.wav\" WAVE
+  TRACK 01 AUDIO
+    TITLE \"Intro\"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE \"Song: Part 2\"
+    PERFORMER \"The Band feat. Guest\"
+    INDEX 00 03:58:00
+    INDEX 01 04:00:37
+";
+
+    #[test]
+    fn test_parse_cue_sheet() {
+        let sheet = CueSheet::parse(SHEET.trim_start_matches('\u{feff}')).unwrap();
+        assert_eq!(sheet.performer, "The Band");
+        assert_eq!(sheet.title, "Live Album");
+        assert_eq!(sheet.file, "The Band - Live Album.wav");
+        assert_eq!(sheet.tracks.len(), 2);
+        assert_eq!(sheet.tracks[1].start_ms, 240_493);
+        assert_eq!((sheet.disc, sheet.disc_total), (Some(2), Some(2)));
+        assert_eq!(sheet.genre, "Rock");
+
+        let tracks = sheet.to_tracks(Path::new("/music/image.flac"), 480);
+        assert_eq!(tracks[0].1.artist, "The Band");
+        assert_eq!(tracks[0].1.duration_secs, 240);
+        assert_eq!(tracks[1].1.artist, "The Band feat. Guest");
+        assert_eq!(tracks[1].1.album, "Live Album");
+        assert_eq!(tracks[1].1.duration_secs, 240);
+
+        assert_eq!(
+            track_lrc_path(Path::new("/music/image.flac"), &tracks[1].0),
+            PathBuf::from("/music/02 - Song_ Part 2.lrc")
+        );
+
+        assert!(CueSheet::parse("FILE \"a.wav\" WAVE\nFILE \"b.wav\" WAVE\n").is_err());
+    }
+
+    #[test]
+    fn test_find_cue_sheet() {
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("The Band - Live Album.flac");
+        std::fs::write(&image, b"").unwrap();
+        assert!(DirCueSheets::read(dir.path()).find_for(&image).is_none());
+
+        // Named differently, but its FILE points at the image (as the original .wav)
+        std::fs::write(dir.path().join("disc.cue"), SHEET).unwrap();
+        let sheets = DirCueSheets::read(dir.path());
+        assert_eq!(sheets.find_for(&image).unwrap().tracks.len(), 2);
+
+        let other = dir.path().join("other.flac");
+        std::fs::write(&other, b"").unwrap();
+        assert!(sheets.find_for(&other).is_none());
+
+        // A sheet named after the file wins, whatever its FILE says
+        std::fs::write(dir.path().join("other.cue"), SHEET).unwrap();
+        let sheets = DirCueSheets::read(dir.path());
+        assert_eq!(sheets.find_for(&other).unwrap().tracks.len(), 2);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+static TRACK_PREFIX_REGEX: OnceLock<Regex> = OnceLock::new();
+static TEMPLATES: OnceLock<Vec<PathTemplate>> = OnceLock::new();
+
+/// Leading track numbers like `01 - `, `01. ` or `1-01 ` (disc-track)
+fn get_track_prefix_regex() -> &'static Regex {
+    TRACK_PREFIX_REGEX
+        .get_or_init(|| Regex::new(r"^(?:\d{1,2}[-.])?\d{1,3}(?:\s*[-.]\s*|\s+)").unwrap())
+}
+
+/// Artist and title guessed from a file's path
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathGuess {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+}
+
+/// A user-defined library layout such as `{artist}/{album}/{track} {title}`
+/// Matched against the end of the path, without the file extension.
+#[derive(Debug, Clone)]
+pub struct PathTemplate {
+    regex: Regex,
+}
+
+impl PathTemplate {
+    /// Compile a template; `{artist}` and `{title}` are required, `{album}`, `{track}`,
+    /// `{disc}`, `{year}` and `{ignore}` are optional
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut pattern = String::from("(?:^|/)");
+        let mut rest = template;
+        let mut fields = Vec::new();
+
+        while let Some(start) = rest.find('{') {
+            pattern.push_str(&regex::escape(&rest[..start]));
+            let end = start + rest[start..].find('}').context("Unclosed '{'")?;
+            let name = &rest[start + 1..end];
+            match name {
+                "artist" | "album" | "title" => {
+                    anyhow::ensure!(!fields.contains(&name), "{{{}}} appears twice", name);
+                    fields.push(name);
+                    pattern.push_str(&format!("(?P<{}>[^/]+?)", name));
+                }
+                "track" | "disc" | "year" => pattern.push_str(r"\d+"),
+                "ignore" => pattern.push_str("[^/]*?"),
+                other => anyhow::bail!("Unknown placeholder {{{}}}", other),
+            }
+            rest = &rest[end + 1..];
+        }
+        pattern.push_str(&regex::escape(rest));
+        pattern.push('$');
+
+        anyhow::ensure!(
+            fields.contains(&"artist") && fields.contains(&"title"),
+            "Template needs both {{artist}} and {{title}}"
+        );
+        Ok(Self {
+            regex: Regex::new(&pattern)?,
+        })
+    }
+
+    /// Read artist, title and album from a path that fits the template
+    pub fn apply(&self, path: &Path) -> Option<PathGuess> {
+        let path = path.with_extension("");
+        let path = path.to_string_lossy();
+        let captures = self.regex.captures(&path)?;
+        let field = |name: &str| {
+            captures
+                .name(name)
+                .map(|m| m.as_str().replace('_', " ").trim().to_string())
+                .unwrap_or_default()
+        };
+
+        let guess = PathGuess {
+            artist: field("artist"),
+            title: field("title"),
+            album: field("album"),
+        };
+        (!guess.artist.is_empty() && !guess.title.is_empty()).then_some(guess)
+    }
+}
+
+/// Install user templates for the rest of the process
+/// Must be called before any guess is made; later calls are ignored.
+pub fn set_templates(templates: Vec<PathTemplate>) {
+    if TEMPLATES.set(templates).is_err() {
+        tracing::warn!("Filename templates already set, ignoring");
+    }
+}
+
+/// Guess artist and title for an untagged file
+/// User templates are tried first, then the built-in layouts: `Artist - Title`,
+/// `01 - Artist - Title`, `Artist/Album/01 - Title` and `Artist - Album/01 Title`.
+pub fn guess(path: &Path) -> Option<PathGuess> {
+    let templates = TEMPLATES.get().map(Vec::as_slice).unwrap_or_default();
+    if let Some(guess) = templates.iter().find_map(|template| template.apply(path)) {
+        return Some(guess);
+    }
+
+    let stem = component(path.file_stem()?);
+    let stem = get_track_prefix_regex().replace(&stem, "");
+
+    let parent = path.parent().and_then(|p| p.file_name()).map(component);
+    let grandparent = path
+        .parent()
+        .and_then(|p| p.parent())
+        .and_then(|p| p.file_name())
+        .map(component);
+
+    let (artist, title, album) = match stem.split_once(" - ") {
+        Some((artist, title)) => (artist.to_string(), title.to_string(), parent),
+        None => {
+            // Album directories are often named `Artist - Album`
+            let parent = parent?;
+            match parent
+                .split_once(" - ")
+                .filter(|(artist, _)| !is_number(artist))
+            {
+                Some((artist, album)) => (
+                    artist.to_string(),
+                    stem.to_string(),
+                    Some(album.to_string()),
+                ),
+                None => (grandparent?, stem.to_string(), Some(parent)),
+            }
+        }
+    };
+
+    let artist = artist.trim().to_string();
+    let title = title.trim().to_string();
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+
+    Some(PathGuess {
+        artist,
+        title,
+        album: album.unwrap_or_default().trim().to_string(),
+    })
+}
+
+/// A path component with underscores read as spaces
+fn component(name: &std::ffi::OsStr) -> String {
+    name.to_string_lossy().replace('_', " ")
+}
+
+/// Year prefixes such as `2001 - Album` aren't artists
+fn is_number(s: &str) -> bool {
+    s.trim().chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guessed(path: &str) -> Option<(String, String, String)> {
+        guess(Path::new(path)).map(|g| (g.artist, g.title, g.album))
+    }
+
+    #[test]
+    fn test_guess_from_filename() {
+        assert_eq!(
+            guessed("/music/misc/Artist - Title.mp3"),
+            Some(("Artist".into(), "Title".into(), "misc".into()))
+        );
+        assert_eq!(
+            guessed("/music/Album/01 - Artist - Title - Live.flac"),
+            Some(("Artist".into(), "Title - Live".into(), "Album".into()))
+        );
+    }
+
+    #[test]
+    fn test_guess_from_directories() {
+        assert_eq!(
+            guessed("/music/Artist/Album/01 - Title.flac"),
+            Some(("Artist".into(), "Title".into(), "Album".into()))
+        );
+        assert_eq!(
+            guessed("/music/Some_Artist - Album/1-02 Title.flac"),
+            Some(("Some Artist".into(), "Title".into(), "Album".into()))
+        );
+        assert_eq!(
+            guessed("/music/Artist/2001 - Album/03. Title.flac"),
+            Some(("Artist".into(), "Title".into(), "2001 - Album".into()))
+        );
+        assert_eq!(guessed("Title.flac"), None);
+    }
+
+    #[test]
+    fn test_path_template() {
+        let template =
+            PathTemplate::parse("{artist}/{year} {album}/{disc}-{track}. {title}").unwrap();
+        assert_eq!(
+            template.apply(Path::new(
+                "/music/Some_Artist/1999 Album/1-03. Mr. Title.flac"
+            )),
+            Some(PathGuess {
+                artist: "Some Artist".into(),
+                title: "Mr. Title".into(),
+                album: "Album".into(),
+            })
+        );
+        assert_eq!(
+            template.apply(Path::new("/music/Artist/Album/03 Title.flac")),
+            None
+        );
+
+        assert!(PathTemplate::parse("{artist}/{album}").is_err());
+        assert!(PathTemplate::parse("{artist}/{title").is_err());
+        assert!(PathTemplate::parse("{artist}/{bogus} {title}").is_err());
+    }
+}
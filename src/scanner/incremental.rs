@@ -0,0 +1,209 @@
+use super::{has_skip_marker, is_audio_file, is_skipped_hidden, throttle_scan};
+use crate::path_encoding;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Directories and files remembered from the last completed scan
+#[derive(Debug, Default)]
+pub struct DirJournal {
+    /// directory -> mtime (Unix seconds)
+    dirs: HashMap<PathBuf, i64>,
+    /// directory -> known subdirectories
+    subdirs: HashMap<PathBuf, Vec<PathBuf>>,
+    /// directory -> indexed audio files
+    files: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl DirJournal {
+    /// Build from recorded directory mtimes and the paths in the library index
+    pub fn new(dirs: HashMap<String, i64>, files: impl IntoIterator<Item = PathBuf>) -> Self {
+        let dirs: HashMap<PathBuf, i64> = dirs
+            .into_iter()
+            .map(|(path, mtime)| (path_encoding::decode(&path), mtime))
+            .collect();
+
+        let mut subdirs: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        for dir in dirs.keys() {
+            if let Some(parent) = dir.parent() {
+                subdirs
+                    .entry(parent.to_path_buf())
+                    .or_default()
+                    .push(dir.clone());
+            }
+        }
+
+        let mut by_dir: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+        for file in files {
+            if let Some(parent) = file.parent() {
+                by_dir.entry(parent.to_path_buf()).or_default().push(file);
+            }
+        }
+
+        Self {
+            dirs,
+            subdirs,
+            files: by_dir,
+        }
+    }
+
+    /// Whether the directory's mtime still matches the last completed scan
+    fn unchanged(&self, dir: &Path, mtime: Option<i64>) -> bool {
+        mtime.is_some() && self.dirs.get(dir).copied() == mtime
+    }
+}
+
+/// An audio file found by the walk
+#[derive(Debug, Clone, PartialEq)]
+pub enum Discovered {
+    /// In a directory that changed (or was never scanned)
+    Changed(PathBuf),
+    /// Indexed file in a directory that hasn't changed, taken from the journal
+    Unchanged(PathBuf),
+}
+
+/// Walk a directory tree, reading only directories whose mtime changed since the last scan
+/// Files are handed to `found` as they are discovered. Returns every directory visited, to
+/// record once the run completes. A directory's mtime doesn't reflect changes deeper down,
+/// so known subdirectories of an unchanged directory are still visited.
+pub fn walk_incremental(
+    root: &Path,
+    journal: &DirJournal,
+    mut found: impl FnMut(Discovered),
+) -> Vec<PathBuf> {
+    tracing::info!("Starting incremental directory scan: {}", root.display());
+
+    let mut dirs = Vec::new();
+    let mut stack = vec![root.to_path_buf()];
+    let (mut changed, mut unchanged, mut skipped) = (0, 0, 0);
+
+    while let Some(dir) = stack.pop() {
+        if journal.unchanged(&dir, dir_stamp(&dir)) {
+            skipped += 1;
+            // Entries recorded before hidden ones were skipped may still be journaled
+            for file in journal.files.get(&dir).into_iter().flatten() {
+                if !is_skipped_hidden(file) {
+                    unchanged += 1;
+                    found(Discovered::Unchanged(file.clone()));
+                }
+            }
+            if let Some(subdirs) = journal.subdirs.get(&dir) {
+                stack.extend(
+                    subdirs
+                        .iter()
+                        .filter(|subdir| !is_skipped_hidden(subdir))
+                        .cloned(),
+                );
+            }
+            dirs.push(dir);
+            continue;
+        }
+
+        // Recorded like any directory, so removing the marker (a change) brings it back
+        if has_skip_marker(&dir) {
+            tracing::debug!("Skipping marked directory: {}", dir.display());
+            dirs.push(dir);
+            continue;
+        }
+
+        throttle_scan();
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                tracing::warn!("Error walking directory {}: {}", dir.display(), e);
+                // Leave the parent unrecorded so the next scan finds and retries this directory
+                if let Some(parent) = dir.parent() {
+                    dirs.retain(|visited| visited != parent);
+                }
+                continue;
+            }
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if is_skipped_hidden(&path) {
+                continue;
+            }
+            if file_type.is_dir() {
+                stack.push(path);
+            } else if file_type.is_file() && is_audio_file(&path) {
+                changed += 1;
+                found(Discovered::Changed(path));
+            }
+        }
+        dirs.push(dir);
+    }
+
+    tracing::info!(
+        "Incremental scan complete: {} audio files in changed directories, {} in {} unchanged directories",
+        changed,
+        unchanged,
+        skipped
+    );
+    dirs
+}
+
+/// Current mtime (Unix seconds) of a directory, if it can be read
+pub fn dir_stamp(path: &Path) -> Option<i64> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some(filetime::FileTime::from_last_modification_time(&metadata).unix_seconds())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walk(root: &Path, journal: &DirJournal) -> (Vec<Discovered>, Vec<PathBuf>) {
+        let mut found = Vec::new();
+        let dirs = walk_incremental(root, journal, |file| found.push(file));
+        (found, dirs)
+    }
+
+    #[test]
+    fn test_skips_marked_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let samples = root.path().join("Samples");
+        std::fs::create_dir_all(samples.join("Kicks")).unwrap();
+        std::fs::write(samples.join(".getlrc-skip"), b"").unwrap();
+        std::fs::write(samples.join("Kicks").join("kick.wav"), b"").unwrap();
+        std::fs::write(root.path().join("song.flac"), b"").unwrap();
+
+        let (found, _) = walk(root.path(), &DirJournal::default());
+        assert_eq!(
+            found,
+            vec![Discovered::Changed(root.path().join("song.flac"))]
+        );
+    }
+
+    #[test]
+    fn test_skips_unchanged_directories() {
+        let root = tempfile::tempdir().unwrap();
+        let album = root.path().join("Artist").join("Album");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(album.join("01.flac"), b"").unwrap();
+        std::fs::write(album.join("cover.jpg"), b"").unwrap();
+
+        let (found, dirs) = walk(root.path(), &DirJournal::default());
+        assert_eq!(found, vec![Discovered::Changed(album.join("01.flac"))]);
+        assert_eq!(dirs.len(), 3);
+
+        let stamps = dirs
+            .iter()
+            .map(|dir| (path_encoding::encode(dir), dir_stamp(dir).unwrap()))
+            .collect();
+        let journal = DirJournal::new(stamps, [album.join("01.flac")]);
+
+        let (found, _) = walk(root.path(), &journal);
+        assert_eq!(found, vec![Discovered::Unchanged(album.join("01.flac"))]);
+
+        // Only the album directory changes, but it's still reached through its unchanged parents
+        std::fs::write(album.join("02.flac"), b"").unwrap();
+        filetime::set_file_mtime(&album, filetime::FileTime::from_unix_time(0, 0)).unwrap();
+        let (found, _) = walk(root.path(), &journal);
+        assert_eq!(found.len(), 2);
+        assert!(found
+            .iter()
+            .all(|file| matches!(file, Discovered::Changed(_))));
+    }
+}
@@ -1,41 +1,452 @@
+use super::{clean, filename, video};
 use anyhow::{Context, Result};
-use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, FileType, TaggedFile, TaggedFileExt};
+use lofty::id3::v2::FrameId;
+use lofty::mpeg::MpegFile;
 use lofty::probe::Probe;
-use lofty::tag::Accessor;
+use lofty::tag::{Accessor, ItemKey, Tag, TagType};
+use serde::Deserialize;
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static TAG_MAPPING: OnceLock<TagMapping> = OnceLock::new();
+static TAG_PRIORITY: OnceLock<Vec<TagType>> = OnceLock::new();
+
+/// Nonstandard tags to read a field from when the standard one is empty
+/// Keys are TXXX descriptions (optionally written `TXXX:Description`), Vorbis comment or
+/// APE item names, tried in order, e.g. `artist = ["PERFORMER_NAME", "TXXX:Band"]`.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TagMapping {
+    pub artist: Vec<String>,
+    pub title: Vec<String>,
+    pub album: Vec<String>,
+}
+
+/// Install the tag mapping for the rest of the process
+/// Must be called before any file is read; later calls are ignored.
+pub fn set_tag_mapping(mapping: TagMapping) {
+    if TAG_MAPPING.set(mapping).is_err() {
+        tracing::warn!("Tag mapping already set, ignoring");
+    }
+}
+
+/// Parse a tag type name from the config
+/// Accepts `id3v2`, `id3v1`, `ape`, `vorbis`, `mp4`, `riff` and `aiff`, in any case.
+pub fn parse_tag_type(name: &str) -> Result<TagType> {
+    match name.trim().to_lowercase().as_str() {
+        "id3v2" => Ok(TagType::Id3v2),
+        "id3v1" => Ok(TagType::Id3v1),
+        "ape" | "apev2" => Ok(TagType::Ape),
+        "vorbis" | "vorbis_comments" => Ok(TagType::VorbisComments),
+        "mp4" | "ilst" => Ok(TagType::Mp4Ilst),
+        "riff" | "riff_info" => Ok(TagType::RiffInfo),
+        "aiff" | "aiff_text" => Ok(TagType::AiffText),
+        other => anyhow::bail!("Unknown tag type: {}", other),
+    }
+}
+
+/// Install the order in which tag types are preferred for the rest of the process
+/// Must be called before any file is read; later calls are ignored.
+pub fn set_tag_priority(priority: Vec<TagType>) {
+    if TAG_PRIORITY.set(priority).is_err() {
+        tracing::warn!("Tag priority already set, ignoring");
+    }
+}
+
+/// The tag to read a file's fields from
+/// The first tag type in the configured priority that the file has, otherwise lofty's
+/// choice: the format's primary tag, or whichever tag comes first.
+fn preferred_tag(tagged_file: &TaggedFile) -> Option<&Tag> {
+    TAG_PRIORITY
+        .get()
+        .into_iter()
+        .flatten()
+        .find_map(|tag_type| tagged_file.tag(*tag_type))
+        .or_else(|| tagged_file.primary_tag())
+        .or_else(|| tagged_file.first_tag())
+}
+
+/// First non-empty value among `keys`, in any of the file's tags
+fn mapped_value(tags: &[Tag], keys: &[String]) -> String {
+    keys.iter()
+        .find_map(|key| {
+            let key = key.strip_prefix("TXXX:").unwrap_or(key);
+            tags.iter().find_map(|tag| {
+                // Known names map to lofty's keys; others are kept as written, in any case
+                let value = tag
+                    .get_string(&ItemKey::from_key(tag.tag_type(), key))
+                    .or_else(|| {
+                        tag.items()
+                            .find(|item| {
+                                matches!(item.key(), ItemKey::Unknown(k) if k.eq_ignore_ascii_case(key))
+                            })
+                            .and_then(|item| item.value().text())
+                    })?;
+                Some(value.trim().to_string()).filter(|value| !value.is_empty())
+            })
+        })
+        .unwrap_or_default()
+}
+
+/// Where a track's artist and title came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataSource {
+    Tags,
+    /// Guessed from the file and directory names because the tags lack them
+    Path,
+    /// Identified from the audio fingerprint through AcoustID
+    Fingerprint,
+    /// Entered by hand in the TUI's manual search
+    Manual,
+}
+
+/// Where a track sits on its release, from the disc and track number tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrackPosition {
+    pub disc: Option<u32>,
+    pub disc_total: Option<u32>,
+    pub track: Option<u32>,
+}
+
+impl TrackPosition {
+    /// Whether the release has more than one disc
+    pub fn is_multi_disc(&self) -> bool {
+        self.disc.is_some_and(|disc| disc > 1) || self.disc_total.is_some_and(|total| total > 1)
+    }
+}
+
+/// Parse a `3` or `3/12` number tag into the number and the total
+pub fn parse_number_pair(value: &str) -> (Option<u32>, Option<u32>) {
+    let (number, total) = value.split_once('/').unwrap_or((value, ""));
+    let parse = |part: &str| part.trim().parse().ok().filter(|&n| n > 0);
+    (parse(number), parse(total))
+}
 
 #[derive(Debug, Clone)]
 pub struct Track {
     pub path: PathBuf,
     pub artist: String,
+    /// Every artist the tags credit, the first being `artist`
+    pub artists: Vec<String>,
+    /// Artist sort name (ARTISTSORT/TSOP), often the canonical or romanized form
+    pub sort_artist: String,
     pub title: String,
     pub album: String,
+    pub genre: String,
     pub duration_secs: u64,
+    /// Whether the file already carries lyrics in its tags (USLT/SYLT/LYRICS)
+    pub has_embedded_lyrics: bool,
+    pub source: MetadataSource,
+    pub position: TrackPosition,
+    /// Part of a compilation (`Various Artists` album artist or compilation flag)
+    pub compilation: bool,
+}
+
+impl Track {
+    /// Strip invisible characters (control characters, zero-width spaces, BOMs) from the
+    /// fields used for searching
+    /// Returns the names of the fields that changed.
+    pub fn sanitize(&mut self) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        for (name, field) in [
+            ("artist", &mut self.artist),
+            ("title", &mut self.title),
+            ("album", &mut self.album),
+            ("sort artist", &mut self.sort_artist),
+        ] {
+            let clean = clean::strip_invisible(field);
+            if clean != *field {
+                *field = clean;
+                changed.push(name);
+            }
+        }
+        for artist in &mut self.artists {
+            *artist = clean::strip_invisible(artist);
+        }
+        changed
+    }
+
+    /// Artist or title are missing, or only guessed from the path
+    pub fn needs_identification(&self) -> bool {
+        self.source == MetadataSource::Path || self.artist.is_empty() || self.title.is_empty()
+    }
+}
+
+/// Fields entered by hand to search for a track the tags (or the cleaner) get wrong
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagOverride {
+    pub artist: String,
+    pub title: String,
+    pub album: String,
+    /// Kept from the file when None
+    pub duration_secs: Option<u64>,
+}
+
+impl TagOverride {
+    /// Search for the track under these fields instead of its own
+    pub fn apply(&self, track: &mut Track) {
+        track.artist = self.artist.clone();
+        track.artists = vec![self.artist.clone()];
+        // The sort name belongs to the tagged artist
+        track.sort_artist = String::new();
+        track.title = self.title.clone();
+        track.album = self.album.clone();
+        if let Some(duration_secs) = self.duration_secs {
+            track.duration_secs = duration_secs;
+        }
+        track.source = MetadataSource::Manual;
+    }
 }
 
 /// Extract metadata from an audio file using lofty
 pub fn extract(path: &Path) -> Result<Track> {
+    read(path, false)
+}
+
+/// Extract metadata, guessing a missing artist or title from the file and directory names
+pub fn extract_or_guess(path: &Path) -> Result<Track> {
+    read(path, true)
+}
+
+/// Length of an audio file, whether or not it has tags
+pub fn duration_secs(path: &Path) -> Result<u64> {
     let tagged_file = Probe::open(path)
         .context("Failed to open audio file")?
         .read()
         .context("Failed to read audio file")?;
+    Ok(tagged_file.properties().duration().as_secs())
+}
 
-    let tag = tagged_file
-        .primary_tag()
-        .or_else(|| tagged_file.first_tag())
-        .context("No tags found in audio file")?;
-
-    let artist = tag.artist().map(|s| s.to_string()).unwrap_or_default();
-    let title = tag.title().map(|s| s.to_string()).unwrap_or_default();
-    let album = tag.album().map(|s| s.to_string()).unwrap_or_default();
+fn read(path: &Path, guess: bool) -> Result<Track> {
+    let (tagged_file, has_sylt) = match read_tagged(path) {
+        Ok(read) => read,
+        // lofty can't parse Matroska/WebM, so music videos fall back to ffprobe
+        Err(e) if super::is_video_file(path) => {
+            let mut track = video::extract(path).with_context(|| format!("{:#}", e))?;
+            if guess {
+                fill_from_path(&mut track);
+            }
+            anyhow::ensure!(
+                !track.artist.is_empty() || !track.title.is_empty(),
+                "No tags found in video file"
+            );
+            return Ok(track);
+        }
+        Err(e) => return Err(e),
+    };
 
     let duration_secs = tagged_file.properties().duration().as_secs();
 
-    Ok(Track {
+    let Some(tag) = preferred_tag(&tagged_file) else {
+        let guessed = guess
+            .then(|| filename::guess(path))
+            .flatten()
+            .context("No tags found in audio file")?;
+        return Ok(Track {
+            path: path.to_path_buf(),
+            artist: guessed.artist,
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: guessed.title,
+            album: guessed.album,
+            genre: String::new(),
+            duration_secs,
+            has_embedded_lyrics: false,
+            source: MetadataSource::Path,
+            position: TrackPosition::default(),
+            compilation: false,
+        });
+    };
+
+    let has_embedded_lyrics = tagged_file.tags().iter().any(|t| {
+        t.get_string(&ItemKey::Lyrics)
+            .is_some_and(|l| !l.trim().is_empty())
+    }) || has_sylt;
+
+    // A "Various Artists" track artist says nothing about who performs the track
+    let artists: Vec<String> = tag_values(tag, &ItemKey::TrackArtist)
+        .into_iter()
+        .filter(|artist| !clean::is_various_artists(artist))
+        .collect();
+    let compilation = tag.get_string(&ItemKey::FlagCompilation) == Some("1")
+        || tag
+            .get_string(&ItemKey::AlbumArtist)
+            .is_some_and(clean::is_various_artists)
+        || tag_values(tag, &ItemKey::TrackArtist)
+            .first()
+            .is_some_and(|artist| clean::is_various_artists(artist));
+
+    let first = |key: &ItemKey| tag_values(tag, key).into_iter().next().unwrap_or_default();
+    let mut track = Track {
         path: path.to_path_buf(),
-        artist,
-        title,
-        album,
+        artist: artists.first().cloned().unwrap_or_default(),
+        artists,
+        sort_artist: first(&ItemKey::TrackArtistSortOrder),
+        title: first(&ItemKey::TrackTitle),
+        album: first(&ItemKey::AlbumTitle),
+        genre: first(&ItemKey::Genre),
         duration_secs,
-    })
+        has_embedded_lyrics,
+        source: MetadataSource::Tags,
+        position: TrackPosition {
+            disc: tag.disk(),
+            disc_total: tag.disk_total(),
+            track: tag.track(),
+        },
+        compilation,
+    };
+    fill_from_mapping(&mut track, tagged_file.tags());
+    if guess {
+        fill_from_path(&mut track);
+    }
+    Ok(track)
+}
+
+/// Every value of a field, whether repeated (Vorbis, ID3v2.4) or null-separated in one frame
+fn tag_values(tag: &Tag, key: &ItemKey) -> Vec<String> {
+    tag.get_strings(key)
+        .flat_map(|value| value.split('\0'))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Fill in fields the standard tags leave empty from the configured nonstandard ones
+fn fill_from_mapping(track: &mut Track, tags: &[Tag]) {
+    let Some(mapping) = TAG_MAPPING.get() else {
+        return;
+    };
+    for (field, keys) in [
+        (&mut track.artist, &mapping.artist),
+        (&mut track.title, &mapping.title),
+        (&mut track.album, &mapping.album),
+    ] {
+        if field.is_empty() {
+            *field = mapped_value(tags, keys);
+        }
+    }
+}
+
+/// Fill in whatever the tags are missing from the file and directory names
+fn fill_from_path(track: &mut Track) {
+    if !track.artist.is_empty() && !track.title.is_empty() {
+        return;
+    }
+    let Some(from_path) = filename::guess(&track.path) else {
+        return;
+    };
+
+    track.source = MetadataSource::Path;
+    if track.artist.is_empty() {
+        track.artist = from_path.artist;
+    }
+    if track.title.is_empty() {
+        track.title = from_path.title;
+    }
+    if track.album.is_empty() {
+        track.album = from_path.album;
+    }
+}
+
+/// Read an audio file's tags and properties, and whether it has a SYLT frame
+/// The generic tags drop SYLT (synchronised lyrics), so MP3s are read as such to check their
+/// ID3v2 tag before it's converted, rather than parsing the file a second time.
+fn read_tagged(path: &Path) -> Result<(TaggedFile, bool)> {
+    let probe = Probe::open(path).context("Failed to open audio file")?;
+    if probe.file_type() != Some(FileType::Mpeg) {
+        let tagged_file = probe.read().context("Failed to read audio file")?;
+        return Ok((tagged_file, false));
+    }
+
+    let mpeg = MpegFile::read_from(&mut probe.into_inner(), ParseOptions::new())
+        .context("Failed to read audio file")?;
+    let has_sylt = mpeg
+        .id3v2()
+        .is_some_and(|tag| tag.get(&FrameId::Valid("SYLT".into())).is_some());
+    Ok((mpeg.into(), has_sylt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lofty::tag::{ItemValue, TagItem, TagType};
+
+    #[test]
+    fn test_mapped_value() {
+        let mut tag = Tag::new(TagType::VorbisComments);
+        tag.push_unchecked(TagItem::new(
+            ItemKey::Unknown("PERFORMER_NAME".to_string()),
+            ItemValue::Text(" Some Band ".to_string()),
+        ));
+        let tags = [tag];
+
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            mapped_value(&tags, &keys(&["MISSING", "performer_name"])),
+            "Some Band"
+        );
+        assert_eq!(mapped_value(&tags, &keys(&["TXXX:MISSING"])), "");
+    }
+
+    #[test]
+    fn test_tag_values_splits_multi_value_artists() {
+        let mut tag = Tag::new(TagType::Id3v2);
+        tag.push(TagItem::new(
+            ItemKey::TrackArtist,
+            ItemValue::Text("Artist A\0Artist B".to_string()),
+        ));
+        tag.push(TagItem::new(
+            ItemKey::TrackArtist,
+            ItemValue::Text("Artist C".to_string()),
+        ));
+
+        assert_eq!(
+            tag_values(&tag, &ItemKey::TrackArtist),
+            ["Artist A", "Artist B", "Artist C"]
+        );
+    }
+
+    #[test]
+    fn test_sylt_frame_counts_as_embedded_lyrics() {
+        use lofty::config::WriteOptions;
+        use lofty::id3::v2::{
+            BinaryFrame, Frame, Id3v2Tag, SyncTextContentType, SynchronizedTextFrame,
+            TimestampFormat,
+        };
+        use lofty::tag::TagExt;
+        use lofty::TextEncoding;
+
+        // A few silent MPEG-1 Layer III frames (128 kbps, 44.1 kHz)
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        std::fs::write(&path, frame.repeat(8)).unwrap();
+
+        let mut tag = Id3v2Tag::new();
+        tag.set_artist("Artist".to_string());
+        tag.set_title("Title".to_string());
+        tag.save_to_path(&path, WriteOptions::default()).unwrap();
+        assert!(!extract(&path).unwrap().has_embedded_lyrics);
+
+        let sylt = SynchronizedTextFrame::new(
+            TextEncoding::UTF8,
+            *b"eng",
+            TimestampFormat::MS,
+            SyncTextContentType::Lyrics,
+            None,
+            vec![(1000, "la".to_string())],
+        );
+        tag.insert(Frame::Binary(BinaryFrame::new(
+            FrameId::Valid("SYLT".into()),
+            sylt.as_bytes().unwrap(),
+        )));
+        tag.save_to_path(&path, WriteOptions::default()).unwrap();
+        let track = extract(&path).unwrap();
+        assert!(track.has_embedded_lyrics);
+        assert_eq!(track.title, "Title");
+    }
 }
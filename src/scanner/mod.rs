@@ -1,40 +1,160 @@
+pub mod audio_hash;
 pub mod clean;
+pub mod cue;
+pub mod filename;
+pub mod incremental;
 pub mod metadata;
 pub mod parallel;
+pub mod playlist;
+pub mod spoken;
+pub mod track_filter;
+pub mod video;
+pub mod winpath;
 
 use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::Duration;
 use walkdir::WalkDir;
 
-const AUDIO_EXTENSIONS: &[&str] = &["flac", "mp3", "m4a", "aac", "opus", "ogg", "ape", "wav"];
+/// File extensions scanned for by default (lowercase)
+pub const AUDIO_EXTENSIONS: &[&str] = &[
+    "flac", "mp3", "m4a", "aac", "opus", "ogg", "ape", "wav", "wv", "aiff", "aif", "dsf", "mka",
+    "tak",
+];
+
+static EXTRA_EXTENSIONS: OnceLock<Vec<String>> = OnceLock::new();
+static SKIP_HIDDEN: OnceLock<bool> = OnceLock::new();
+static SCAN_PAUSE: OnceLock<Duration> = OnceLock::new();
+
+/// Scan for these extensions too, for the rest of the process
+/// Must be called before any scan; later calls are ignored.
+pub fn set_extra_extensions(extensions: Vec<String>) {
+    let extensions = extensions
+        .into_iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    if EXTRA_EXTENSIONS.set(extensions).is_err() {
+        tracing::warn!("Audio extensions already set, ignoring");
+    }
+}
+
+/// Identifies which files a scan looks at (extensions, hidden entries), so a change can
+/// trigger a full walk. FNV-1a over the settings, which stays stable across builds unlike
+/// `DefaultHasher`.
+pub fn scan_fingerprint() -> i64 {
+    let mut extensions: Vec<&str> = AUDIO_EXTENSIONS.to_vec();
+    extensions.extend(
+        EXTRA_EXTENSIONS
+            .get()
+            .into_iter()
+            .flatten()
+            .map(String::as_str),
+    );
+    extensions.sort_unstable();
+    extensions.dedup();
+    let settings = format!("{};skip_hidden={}", extensions.join(","), skips_hidden());
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in settings.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash as i64
+}
+
+/// Skip files and directories whose names start with a dot, for the rest of the process
+/// Must be called before any scan; later calls are ignored.
+pub fn set_skip_hidden(skip: bool) {
+    if SKIP_HIDDEN.set(skip).is_err() {
+        tracing::warn!("Hidden entry skipping already set, ignoring");
+    }
+}
+
+fn skips_hidden() -> bool {
+    SKIP_HIDDEN.get().copied().unwrap_or(false)
+}
+
+/// Pause after each directory read for the rest of the process, to go easy on the disk
+/// Must be called before any scan; later calls are ignored.
+pub fn set_scan_pause(pause: Duration) {
+    if SCAN_PAUSE.set(pause).is_err() {
+        tracing::warn!("Scan pause already set, ignoring");
+    }
+}
+
+/// Sleep between directory reads, if a pause was set
+fn throttle_scan() {
+    if let Some(pause) = SCAN_PAUSE.get() {
+        std::thread::sleep(*pause);
+    }
+}
+
+/// Marker files that leave a directory and everything below it out of scans
+pub const SKIP_MARKERS: &[&str] = &[".nomedia", ".getlrc-skip"];
+
+/// Whether a directory holds a skip marker (sample packs, ringtones, sound effects)
+pub fn has_skip_marker(dir: &Path) -> bool {
+    SKIP_MARKERS.iter().any(|marker| dir.join(marker).is_file())
+}
+
+/// Whether a walk should leave out this entry (e.g. `.stfolder`, `.Trash`, `._song.flac`)
+pub fn is_skipped_hidden(path: &Path) -> bool {
+    skips_hidden()
+        && path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+}
+
+/// Whether a file has one of the default or user-added audio extensions
+pub fn is_audio_file(path: &Path) -> bool {
+    let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+        return false;
+    };
+    let ext = ext.to_lowercase();
+    AUDIO_EXTENSIONS.contains(&ext.as_str())
+        || EXTRA_EXTENSIONS
+            .get()
+            .is_some_and(|extra| extra.contains(&ext))
+}
 
 /// Walk a directory and yield all audio file paths
 pub fn walk_directory(path: &Path) -> impl Iterator<Item = PathBuf> {
     WalkDir::new(path)
         .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !is_skipped_hidden(e.path()))
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
-        .filter_map(|e| {
-            let path = e.path();
-            if let Some(ext) = path.extension() {
-                if AUDIO_EXTENSIONS.contains(&ext.to_str().unwrap_or("").to_lowercase().as_str()) {
-                    return Some(path.to_path_buf());
-                }
-            }
-            None
-        })
-}
-
-/// Check if a .lrc sidecar file exists for the given audio file
+        .filter(|e| is_audio_file(e.path()))
+        .map(|e| e.path().to_path_buf())
+}
+
+/// Whether a file is a music video container
+pub fn is_video_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| video::VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Check if a .lrc sidecar file (or a video's .srt subtitles) exists for the given file
 pub fn has_lrc_sidecar(path: &Path) -> bool {
     let mut lrc_path = path.to_path_buf();
     lrc_path.set_extension("lrc");
-    lrc_path.exists()
+    lrc_path.exists() || (is_video_file(path) && path.with_extension("srt").exists())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_is_audio_file() {
+        assert!(is_audio_file(Path::new("/music/song.FLAC")));
+        assert!(is_audio_file(Path::new("/music/song.wv")));
+        assert!(!is_audio_file(Path::new("/music/cover.jpg")));
+        assert!(!is_audio_file(Path::new("/music/flac")));
+    }
+
     #[test]
     fn test_has_lrc_sidecar() {
         let path = Path::new("/tmp/test.flac");
@@ -1,7 +1,46 @@
+use super::{has_skip_marker, is_audio_file, skips_hidden, throttle_scan};
 use jwalk::WalkDir;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-const AUDIO_EXTENSIONS: &[&str] = &["flac", "mp3", "m4a", "aac", "opus", "ogg", "ape", "wav"];
+/// Files found between progress reports
+const PROGRESS_EVERY_FILES: usize = 500;
+/// Longest time between progress reports while files are being found
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Decides when a running count is worth reporting: every so many files, or after a while
+#[derive(Debug)]
+pub struct ProgressThrottle {
+    found: usize,
+    last_report: Instant,
+}
+
+impl ProgressThrottle {
+    pub fn new() -> Self {
+        Self {
+            found: 0,
+            last_report: Instant::now(),
+        }
+    }
+
+    /// Count a found file, returning the total when it should be reported
+    pub fn tick(&mut self) -> Option<usize> {
+        self.found += 1;
+        if self.found.is_multiple_of(PROGRESS_EVERY_FILES)
+            || self.last_report.elapsed() >= PROGRESS_INTERVAL
+        {
+            self.last_report = Instant::now();
+            return Some(self.found);
+        }
+        None
+    }
+}
+
+impl Default for ProgressThrottle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Parallel directory walker using jwalk
 /// Returns a vector of all audio files found in the directory tree
@@ -9,7 +48,13 @@ pub fn walk_directory_parallel(path: &Path) -> Vec<PathBuf> {
     tracing::info!("Starting parallel directory scan: {}", path.display());
 
     let audio_files: Vec<PathBuf> = WalkDir::new(path)
-        .skip_hidden(false)
+        .skip_hidden(skips_hidden())
+        .process_read_dir(|_, dir, _, children| {
+            throttle_scan();
+            if has_skip_marker(dir) {
+                children.clear();
+            }
+        })
         .into_iter()
         .filter_map(|entry| match entry {
             Ok(e) => {
@@ -18,13 +63,7 @@ pub fn walk_directory_parallel(path: &Path) -> Vec<PathBuf> {
                 }
 
                 let path = e.path();
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_str().unwrap_or("").to_lowercase();
-                    if AUDIO_EXTENSIONS.contains(&ext_str.as_str()) {
-                        return Some(path);
-                    }
-                }
-                None
+                is_audio_file(&path).then_some(path)
             }
             Err(e) => {
                 tracing::warn!("Error walking directory: {}", e);
@@ -51,6 +90,19 @@ pub fn has_lrc_sidecar(path: &Path) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_progress_throttle() {
+        let mut throttle = ProgressThrottle::new();
+        let reported: Vec<usize> = (0..PROGRESS_EVERY_FILES)
+            .filter_map(|_| throttle.tick())
+            .collect();
+        assert_eq!(reported.last(), Some(&PROGRESS_EVERY_FILES));
+
+        throttle.last_report -= PROGRESS_INTERVAL;
+        assert_eq!(throttle.tick(), Some(PROGRESS_EVERY_FILES + 1));
+        assert_eq!(throttle.tick(), None);
+    }
+
     #[test]
     fn test_has_lrc_sidecar() {
         let path = Path::new("/tmp/test.flac");
@@ -0,0 +1,143 @@
+use super::winpath;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls"];
+
+/// Whether a path looks like a playlist file
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PLAYLIST_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Read the audio files referenced by an `.m3u`/`.m3u8`/`.pls` playlist
+/// Relative entries are resolved against the playlist's directory. Streams and
+/// entries that don't exist are skipped with a warning.
+pub fn read(path: &Path) -> Result<Vec<PathBuf>> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let contents = String::from_utf8_lossy(&bytes);
+    let contents = contents.trim_start_matches('\u{feff}');
+
+    let is_pls = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pls"));
+    let entries = if is_pls {
+        pls_entries(contents)
+    } else {
+        m3u_entries(contents)
+    };
+
+    let plain = winpath::simplified(path);
+    let base = plain.parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    for entry in entries {
+        let Some(file) = resolve(base, entry) else {
+            tracing::warn!("Skipping playlist entry that isn't a local file: {}", entry);
+            continue;
+        };
+        if file.is_file() {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        } else {
+            tracing::warn!("Playlist entry not found: {}", file.display());
+        }
+    }
+
+    tracing::info!("Playlist {} lists {} files", path.display(), files.len());
+    Ok(files)
+}
+
+/// Write an `.m3u8` playlist of absolute paths
+pub fn write(path: &Path, files: &[PathBuf]) -> Result<()> {
+    let mut contents = String::from("#EXTM3U\n");
+    for file in files {
+        contents.push_str(&winpath::simplified(file).to_string_lossy());
+        contents.push('\n');
+    }
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Non-comment lines of an M3U playlist (`#EXTINF` and other directives are comments)
+fn m3u_entries(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// `FileN=` values of a PLS playlist
+fn pls_entries(contents: &str) -> Vec<&str> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let key = key.trim().to_ascii_lowercase();
+            (key.starts_with("file") && key[4..].chars().all(|c| c.is_ascii_digit()))
+                .then(|| value.trim())
+        })
+        .collect()
+}
+
+/// Turn a playlist entry into a local path
+fn resolve(base: &Path, entry: &str) -> Option<PathBuf> {
+    let path = if let Some(url) = entry.strip_prefix("file://") {
+        PathBuf::from(urlencoding::decode(url).ok()?.into_owned())
+    } else if entry.contains("://") {
+        return None;
+    } else {
+        PathBuf::from(entry)
+    };
+
+    // Joined before extending: extended-length paths keep `..` literally
+    if path.is_absolute() {
+        Some(winpath::extended(&path))
+    } else {
+        Some(winpath::extended(&base.join(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_m3u() {
+        let dir = tempfile::tempdir().unwrap();
+        let album = dir.path().join("Artist").join("Album");
+        std::fs::create_dir_all(&album).unwrap();
+        std::fs::write(album.join("01 Song.flac"), b"").unwrap();
+        std::fs::write(album.join("02 Other Song.mp3"), b"").unwrap();
+
+        let playlist = dir.path().join("favorites.m3u8");
+        std::fs::write(
+            &playlist,
+            format!(
+                "#EXTM3U\n#EXTINF:215,Artist - Song\nArtist/Album/01 Song.flac\n\nhttp://radio.example/stream\nmissing.flac\nfile://{}\n",
+                urlencoding::encode(&album.join("02 Other Song.mp3").display().to_string())
+                    .replace("%2F", "/")
+            ),
+        )
+        .unwrap();
+
+        assert!(is_playlist(&playlist));
+        let files = read(&playlist).unwrap();
+        assert_eq!(
+            files,
+            vec![album.join("01 Song.flac"), album.join("02 Other Song.mp3")]
+        );
+
+        let copy = dir.path().join("copy.m3u8");
+        write(&copy, &files).unwrap();
+        assert_eq!(read(&copy).unwrap(), files);
+    }
+
+    #[test]
+    fn test_pls_entries() {
+        let pls = "[playlist]\nNumberOfEntries=2\nFile1=/music/a.flac\nTitle1=A\nFile2=b.mp3\n";
+        assert_eq!(pls_entries(pls), vec!["/music/a.flac", "b.mp3"]);
+    }
+}
@@ -0,0 +1,168 @@
+use super::metadata::Track;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Shortest track without an album that is taken for an episode or a book
+const LONG_TRACK_SECS: u64 = 20 * 60;
+
+/// Genre terms used for podcasts and audiobooks
+const SPOKEN_GENRES: &[&str] = &[
+    "podcast",
+    "audiobook",
+    "audio book",
+    "spoken word",
+    "hörbuch",
+    "hörspiel",
+    "livre audio",
+];
+
+/// Why a track looks like a podcast episode or an audiobook rather than music, if it does
+/// Checks the genre, the `.m4b` audiobook extension, chapter markers, and long tracks that
+/// aren't part of an album.
+pub fn detect(track: &Track) -> Option<String> {
+    let genre = track.genre.to_lowercase();
+    if let Some(term) = SPOKEN_GENRES.iter().find(|term| genre.contains(*term)) {
+        return Some(format!("{} genre", term));
+    }
+
+    let ext = track
+        .path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    if ext.as_deref() == Some("m4b") {
+        return Some("audiobook file".to_string());
+    }
+
+    if has_chapters(&track.path) {
+        return Some("chapter markers".to_string());
+    }
+
+    if track.album.is_empty() && track.duration_secs > LONG_TRACK_SECS {
+        return Some(format!(
+            "{} minutes long without an album",
+            track.duration_secs / 60
+        ));
+    }
+    None
+}
+
+/// Whether a file carries chapter markers: ID3v2 `CHAP` frames, or an MP4 chapter list
+fn has_chapters(path: &Path) -> bool {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase);
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    match ext.as_deref() {
+        Some("mp3") => id3_has_chapters(&mut file).unwrap_or(false),
+        Some("m4a" | "mp4" | "m4b" | "aac") => mp4_has_chapters(&mut file).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Look for a `CHAP` frame in the ID3v2 tag at the start of an MP3
+fn id3_has_chapters(file: &mut File) -> std::io::Result<bool> {
+    let mut header = [0u8; 10];
+    file.read_exact(&mut header)?;
+    if &header[..3] != b"ID3" {
+        return Ok(false);
+    }
+
+    let size = header[6..10]
+        .iter()
+        .fold(0usize, |size, &b| (size << 7) | usize::from(b & 0x7f));
+    let mut tag = vec![0u8; size];
+    file.read_exact(&mut tag)?;
+    Ok(tag.windows(4).any(|id| id == b"CHAP"))
+}
+
+/// Look for a Nero `chpl` list or a QuickTime `chap` track reference in an MP4's `moov` box
+fn mp4_has_chapters(file: &mut File) -> std::io::Result<bool> {
+    let len = file.metadata()?.len();
+    let mut offset = 0;
+
+    // Walk the top-level boxes; `moov` may come before or after the audio data
+    while offset + 8 <= len {
+        file.seek(SeekFrom::Start(offset))?;
+        let mut header = [0u8; 8];
+        file.read_exact(&mut header)?;
+        let mut size = u64::from(u32::from_be_bytes(header[..4].try_into().unwrap()));
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            file.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = len - offset;
+        }
+        if size < header_len {
+            return Ok(false);
+        }
+
+        if &header[4..8] == b"moov" {
+            let mut moov = vec![0u8; (size - header_len) as usize];
+            file.read_exact(&mut moov)?;
+            return Ok(moov
+                .windows(4)
+                .any(|kind| kind == b"chpl" || kind == b"chap"));
+        }
+        offset += size;
+    }
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::metadata::{MetadataSource, TrackPosition};
+    use std::path::PathBuf;
+
+    fn track(path: PathBuf, genre: &str, album: &str, duration_secs: u64) -> Track {
+        Track {
+            path,
+            artist: "Host".to_string(),
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: "Episode 1".to_string(),
+            album: album.to_string(),
+            genre: genre.to_string(),
+            duration_secs,
+            has_embedded_lyrics: false,
+            source: MetadataSource::Tags,
+            position: TrackPosition::default(),
+            compilation: false,
+        }
+    }
+
+    #[test]
+    fn test_detect_spoken_word() {
+        let dir = tempfile::tempdir().unwrap();
+        let song = dir.path().join("song.flac");
+
+        assert!(detect(&track(song.clone(), "Podcast", "", 1800)).is_some());
+        assert!(detect(&track(song.clone(), "", "", 1800)).is_some());
+        assert!(detect(&track(dir.path().join("book.m4b"), "", "Book", 600)).is_some());
+        assert!(detect(&track(song.clone(), "Progressive Rock", "Album", 1800)).is_none());
+        assert!(detect(&track(song, "Rock", "", 240)).is_none());
+    }
+
+    #[test]
+    fn test_id3_chapters() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episode.mp3");
+        let mut bytes = b"ID3\x04\x00\x00\x00\x00\x00\x0a".to_vec();
+        bytes.extend_from_slice(b"CHAP\x00\x00\x00\x00\x00\x00");
+        bytes.extend_from_slice(&[0xff, 0xfb, 0x90, 0x64]);
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(has_chapters(&path));
+
+        bytes[10..14].copy_from_slice(b"TIT2");
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(!has_chapters(&path));
+    }
+}
@@ -0,0 +1,127 @@
+use super::metadata::Track;
+
+/// Which tracks are worth looking up, judged from their tags
+/// Applied after metadata extraction, so non-music audio (audiobooks, podcasts) in a
+/// mixed library never reaches the API.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrackFilter {
+    /// Only look up tracks with one of these genres (tracks without a genre are excluded)
+    pub genre_include: Vec<String>,
+    /// Never look up tracks with one of these genres
+    pub genre_exclude: Vec<String>,
+    /// Shortest track to look up (seconds), e.g. to leave out skits
+    pub min_duration_secs: Option<u64>,
+    /// Longest track to look up (seconds), e.g. to leave out DJ mixes
+    pub max_duration_secs: Option<u64>,
+}
+
+impl TrackFilter {
+    /// Build a filter, ignoring blank entries
+    pub fn new(genre_include: Vec<String>, genre_exclude: Vec<String>) -> Self {
+        let clean = |genres: Vec<String>| {
+            genres
+                .into_iter()
+                .map(|genre| genre.trim().to_lowercase())
+                .filter(|genre| !genre.is_empty())
+                .collect()
+        };
+        Self {
+            genre_include: clean(genre_include),
+            genre_exclude: clean(genre_exclude),
+            ..Default::default()
+        }
+    }
+
+    /// Also leave out tracks shorter or longer than these durations (seconds)
+    pub fn with_duration(mut self, min_secs: Option<u64>, max_secs: Option<u64>) -> Self {
+        self.min_duration_secs = min_secs;
+        self.max_duration_secs = max_secs;
+        self
+    }
+
+    /// Why a track is left out, if it is
+    pub fn exclusion(&self, track: &Track) -> Option<String> {
+        // A length of 0 means it couldn't be read, which says nothing about the track
+        let duration = track.duration_secs;
+        if duration > 0 {
+            if let Some(min) = self.min_duration_secs.filter(|&min| duration < min) {
+                return Some(format!("shorter than {}s ({}s)", min, duration));
+            }
+            if let Some(max) = self.max_duration_secs.filter(|&max| duration > max) {
+                return Some(format!("longer than {}s ({}s)", max, duration));
+            }
+        }
+
+        let genre = track.genre.to_lowercase();
+        // A filter term matches within a genre ("spoken" matches "Spoken Word")
+        let matches = |terms: &[String]| {
+            terms
+                .iter()
+                .find(|term| genre.contains(term.as_str()))
+                .cloned()
+        };
+
+        if let Some(term) = matches(&self.genre_exclude) {
+            return Some(format!("genre {} is excluded ({})", track.genre, term));
+        }
+        if !self.genre_include.is_empty() && matches(&self.genre_include).is_none() {
+            return Some(if track.genre.is_empty() {
+                "no genre tag".to_string()
+            } else {
+                format!("genre {} is not included", track.genre)
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::metadata::{MetadataSource, TrackPosition};
+    use std::path::PathBuf;
+
+    fn track(genre: &str) -> Track {
+        track_of_length(genre, 200)
+    }
+
+    fn track_of_length(genre: &str, duration_secs: u64) -> Track {
+        Track {
+            path: PathBuf::from("/music/track.mp3"),
+            artist: "Artist".to_string(),
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: "Title".to_string(),
+            album: String::new(),
+            genre: genre.to_string(),
+            duration_secs,
+            has_embedded_lyrics: false,
+            source: MetadataSource::Tags,
+            position: TrackPosition::default(),
+            compilation: false,
+        }
+    }
+
+    #[test]
+    fn test_genre_filters() {
+        let filter = TrackFilter::new(vec![], vec!["Audiobook".into(), " spoken ".into()]);
+        assert!(filter.exclusion(&track("Spoken Word")).is_some());
+        assert!(filter.exclusion(&track("audiobook")).is_some());
+        assert!(filter.exclusion(&track("Rock")).is_none());
+        assert!(filter.exclusion(&track("")).is_none());
+
+        let filter = TrackFilter::new(vec!["rock".into()], vec![]);
+        assert!(filter.exclusion(&track("Indie Rock")).is_none());
+        assert!(filter.exclusion(&track("Jazz")).is_some());
+        assert!(filter.exclusion(&track("")).is_some());
+    }
+
+    #[test]
+    fn test_duration_filters() {
+        let filter = TrackFilter::default().with_duration(Some(30), Some(3600));
+        assert!(filter.exclusion(&track_of_length("", 12)).is_some());
+        assert!(filter.exclusion(&track_of_length("", 7200)).is_some());
+        assert!(filter.exclusion(&track_of_length("", 200)).is_none());
+        assert!(filter.exclusion(&track_of_length("", 0)).is_none());
+    }
+}
@@ -0,0 +1,106 @@
+use super::clean;
+use super::metadata::{self, MetadataSource, Track, TrackPosition};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Music video containers scanned with `--include-videos`
+pub const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "mkv", "webm"];
+
+/// Output of `ffprobe -show_format`
+#[derive(Debug, Deserialize)]
+struct Probe {
+    format: Format,
+}
+
+#[derive(Debug, Deserialize)]
+struct Format {
+    /// Seconds, as a decimal string
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// Read container tags with ffprobe, for formats lofty can't parse (Matroska, WebM)
+pub fn extract(path: &Path) -> Result<Track> {
+    let output = std::process::Command::new("ffprobe")
+        .args(["-v", "quiet", "-print_format", "json", "-show_format"])
+        .arg(path)
+        .output()
+        .context("ffprobe not found (install FFmpeg to read video tags)")?;
+    anyhow::ensure!(output.status.success(), "ffprobe failed to read the file");
+
+    let probe: Probe =
+        serde_json::from_slice(&output.stdout).context("Unexpected ffprobe output")?;
+    Ok(to_track(path, probe.format))
+}
+
+fn to_track(path: &Path, format: Format) -> Track {
+    // Matroska tag names are upper case, MP4 ones lower case
+    let tags: HashMap<String, String> = format
+        .tags
+        .into_iter()
+        .map(|(key, value)| (key.to_lowercase(), value.trim().to_string()))
+        .collect();
+    let tag = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| tags.get(*key).filter(|value| !value.is_empty()))
+            .cloned()
+            .unwrap_or_default()
+    };
+
+    let (disc, disc_total) = metadata::parse_number_pair(&tag(&["disc"]));
+    let album_artist = tag(&["album_artist"]);
+    let compilation = tag(&["compilation"]) == "1" || clean::is_various_artists(&album_artist);
+    // The album artist only stands in for a missing artist on regular albums
+    let artist = [tag(&["artist"]), album_artist]
+        .into_iter()
+        .find(|artist| !artist.is_empty() && !clean::is_various_artists(artist))
+        .unwrap_or_default();
+    let (track, _) = metadata::parse_number_pair(&tag(&["track", "part_number"]));
+
+    Track {
+        path: path.to_path_buf(),
+        artist,
+        artists: Vec::new(),
+        sort_artist: tag(&["sort_artist"]),
+        title: tag(&["title"]),
+        album: tag(&["album"]),
+        genre: tag(&["genre"]),
+        duration_secs: format
+            .duration
+            .and_then(|d| d.parse::<f64>().ok())
+            .map_or(0, |d| d.round() as u64),
+        has_embedded_lyrics: !tag(&["lyrics"]).is_empty(),
+        source: MetadataSource::Tags,
+        position: TrackPosition {
+            disc,
+            disc_total,
+            track,
+        },
+        compilation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matroska_tags() {
+        let probe: Probe = serde_json::from_str(
+            r#"{"format": {"filename": "clip.mkv", "duration": "215.480000",
+                "tags": {"TITLE": "Song", "ALBUM_ARTIST": "Artist", "ENCODER": "x", "disc": "2/2"}}}"#,
+        )
+        .unwrap();
+
+        let track = to_track(Path::new("/videos/clip.mkv"), probe.format);
+        assert_eq!(track.artist, "Artist");
+        assert_eq!(track.title, "Song");
+        assert_eq!(track.album, "");
+        assert_eq!(track.duration_secs, 215);
+        assert!(!track.has_embedded_lyrics);
+        assert!(track.position.is_multi_disc());
+    }
+}
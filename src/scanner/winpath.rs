@@ -0,0 +1,136 @@
+use std::path::{Path, PathBuf};
+
+/// Device names Windows reserves in every directory, with or without an extension
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Extended-length form of a path on Windows (`\\?\C:\...`, `\\?\UNC\server\share\...`)
+/// Lifts the 260 character limit and lets files named like devices (`aux.flac` on a
+/// Samba share) be opened. Relative paths are made absolute first, since extended
+/// paths aren't normalized. Unchanged on other platforms.
+#[cfg(windows)]
+pub fn extended(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    let Ok(absolute) = std::path::absolute(path) else {
+        return path.to_path_buf();
+    };
+    let mut components = absolute.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return absolute;
+    };
+
+    let mut extended = OsString::from(r"\\?\");
+    match prefix.kind() {
+        Prefix::Disk(letter) => extended.push(format!("{}:", letter as char)),
+        Prefix::UNC(server, share) => {
+            extended.push(r"UNC\");
+            extended.push(server);
+            extended.push(r"\");
+            extended.push(share);
+        }
+        // Already extended, or a device path
+        _ => return absolute,
+    }
+    let mut extended = PathBuf::from(extended);
+    extended.push(components.as_path());
+    extended
+}
+
+#[cfg(not(windows))]
+pub fn extended(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Inverse of [`extended`], for paths shown to users or stored in the index
+#[cfg(windows)]
+pub fn simplified(path: &Path) -> PathBuf {
+    use std::ffi::OsString;
+    use std::path::{Component, Prefix};
+
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_path_buf();
+    };
+
+    let mut simplified = OsString::new();
+    match prefix.kind() {
+        Prefix::VerbatimDisk(letter) => simplified.push(format!("{}:", letter as char)),
+        Prefix::VerbatimUNC(server, share) => {
+            simplified.push(r"\\");
+            simplified.push(server);
+            simplified.push(r"\");
+            simplified.push(share);
+        }
+        _ => return path.to_path_buf(),
+    }
+    let mut simplified = PathBuf::from(simplified);
+    simplified.push(components.as_path());
+    simplified
+}
+
+#[cfg(not(windows))]
+pub fn simplified(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Turn text (e.g. a track title) into a file name that's valid on every platform
+/// Replaces characters Windows forbids, drops trailing dots and spaces, and prefixes
+/// names Windows reserves for devices.
+pub fn safe_file_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let name = name.trim().trim_end_matches(['.', ' ']);
+
+    if is_reserved_name(name) {
+        format!("_{}", name)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Whether Windows treats a file name as a device (`CON`, `nul.lrc`, `COM1.txt`, ...)
+pub fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_file_name() {
+        assert_eq!(safe_file_name("Song: Part 2"), "Song_ Part 2");
+        assert_eq!(safe_file_name("Why?..."), "Why_");
+        assert_eq!(safe_file_name("aux"), "_aux");
+        assert_eq!(safe_file_name("Con.lrc"), "_Con.lrc");
+        assert_eq!(safe_file_name("Console"), "Console");
+        assert!(!is_reserved_name("COM10"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_extended_paths() {
+        let cases = [
+            (r"C:\Music\Album", r"\\?\C:\Music\Album"),
+            (r"\\nas\music\Album", r"\\?\UNC\nas\music\Album"),
+        ];
+        for (plain, long) in cases {
+            assert_eq!(extended(Path::new(plain)), PathBuf::from(long));
+            assert_eq!(simplified(Path::new(long)), PathBuf::from(plain));
+            assert_eq!(extended(Path::new(long)), PathBuf::from(long));
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::clock;
+use anyhow::{bail, Context, Result};
+use std::fmt;
+
+/// Minutes in a day
+const DAY: u32 = 24 * 60;
+
+/// Time of day during which the worker pool processes files, e.g. `02:00-07:00`
+/// A window that ends before it starts runs past midnight (`22:00-06:00`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// Minutes after midnight
+    start: u32,
+    end: u32,
+}
+
+impl TimeWindow {
+    pub fn parse(text: &str) -> Result<Self> {
+        let (start, end) = text
+            .split_once('-')
+            .context("Expected a window like 02:00-07:00")?;
+        let window = Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+        };
+        if window.start % DAY == window.end % DAY {
+            bail!("Window starts and ends at the same time");
+        }
+        Ok(window)
+    }
+
+    /// Whether a time of day (minutes after midnight) falls in the window
+    pub fn contains(&self, minute: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+
+    /// Start of the window as `HH:MM`
+    pub fn start_time(&self) -> String {
+        format_time(self.start)
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", format_time(self.start), format_time(self.end))
+    }
+}
+
+fn parse_time(text: &str) -> Result<u32> {
+    let text = text.trim();
+    let (hours, minutes) = text
+        .split_once(':')
+        .with_context(|| format!("Expected HH:MM, got {:?}", text))?;
+    let hours: u32 = hours
+        .parse()
+        .with_context(|| format!("Invalid hour in {}", text))?;
+    let minutes: u32 = minutes
+        .parse()
+        .with_context(|| format!("Invalid minutes in {}", text))?;
+    if minutes >= 60 || hours * 60 + minutes > DAY {
+        bail!("{} is not a time of day", text);
+    }
+    Ok(hours * 60 + minutes)
+}
+
+fn format_time(minute: u32) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Whether files may be processed at a time of day: in any window, or always without any
+pub fn is_active(windows: &[TimeWindow], minute: u32) -> bool {
+    windows.is_empty() || windows.iter().any(|window| window.contains(minute))
+}
+
+/// The window that opens next after a time of day
+pub fn next_window(windows: &[TimeWindow], minute: u32) -> Option<TimeWindow> {
+    windows
+        .iter()
+        .min_by_key(|window| (window.start + DAY - minute) % DAY)
+        .copied()
+}
+
+/// Current local time of day in minutes after midnight
+pub fn local_minute() -> u32 {
+    let (_, _, _, hour, minute) = clock::local_time(crate::cache::now_secs().unwrap_or_default());
+    hour * 60 + minute
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_windows() {
+        let night = TimeWindow::parse("02:00-07:00").unwrap();
+        assert!(night.contains(2 * 60));
+        assert!(night.contains(6 * 60 + 59));
+        assert!(!night.contains(7 * 60));
+        assert_eq!(night.to_string(), "02:00-07:00");
+
+        // Past midnight
+        let late = TimeWindow::parse("22:30 - 01:00").unwrap();
+        assert!(late.contains(23 * 60));
+        assert!(late.contains(30));
+        assert!(!late.contains(12 * 60));
+
+        assert!(TimeWindow::parse("02:00").is_err());
+        assert!(TimeWindow::parse("25:00-07:00").is_err());
+        assert!(TimeWindow::parse("02:00-02:00").is_err());
+
+        let windows = [night, late];
+        assert!(is_active(&windows, 3 * 60));
+        assert!(!is_active(&windows, 12 * 60));
+        assert!(is_active(&[], 12 * 60));
+        assert_eq!(next_window(&windows, 12 * 60), Some(late));
+        assert_eq!(next_window(&windows, 90), Some(night));
+    }
+}
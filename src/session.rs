@@ -1,5 +1,7 @@
+use crate::path_encoding;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
 const MAX_LOG_HISTORY: usize = 500;
@@ -9,9 +11,11 @@ const INTEGRITY_CHECK_THRESHOLD: usize = 5;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PersistentSession {
     /// The original root directory being scanned
+    #[serde(with = "path_encoding::serde_path")]
     pub root_path: PathBuf,
 
     /// List of file paths that still need processing
+    #[serde(with = "path_encoding::serde_paths")]
     pub pending_files: Vec<PathBuf>,
 
     /// Current counts for the UI Progress widget
@@ -19,6 +23,14 @@ pub struct PersistentSession {
     pub cached_count: usize,
     pub existing_count: usize,
     pub failed_count: usize,
+    #[serde(default)]
+    pub embedded_count: usize,
+    #[serde(default)]
+    pub untagged_count: usize,
+    #[serde(default)]
+    pub excluded_count: usize,
+    #[serde(default)]
+    pub spoken_count: usize,
 
     /// Buffer of recent TUI log entries to restore visual history
     pub log_history: Vec<LogEntry>,
@@ -34,13 +46,61 @@ pub struct LogEntry {
     pub status: StatusType,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum StatusType {
-    Downloaded, // [✓]
-    Cached,     // [~]
-    Existing,   // [○]
-    NotFound,   // [✗]
-    Error,      // [!]
+    Downloaded,     // [✓]
+    Cached,         // [~]
+    Existing,       // [○]
+    EmbeddedExists, // [♪]
+    NotFound,       // [✗]
+    Error,          // [!]
+    NoMetadata,     // [?]
+    Excluded,       // [-]
+    SpokenWord,     // [≡]
+}
+
+impl StatusType {
+    /// Name stored in the library index
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatusType::Downloaded => "downloaded",
+            StatusType::Cached => "cached",
+            StatusType::Existing => "existing",
+            StatusType::EmbeddedExists => "embedded",
+            StatusType::NotFound => "not_found",
+            StatusType::Error => "error",
+            StatusType::NoMetadata => "no_metadata",
+            StatusType::Excluded => "excluded",
+            StatusType::SpokenWord => "spoken_word",
+        }
+    }
+
+    /// Whether the file ends up with lyrics (sidecar or embedded)
+    pub fn has_lyrics(&self) -> bool {
+        matches!(
+            self,
+            StatusType::Downloaded | StatusType::Existing | StatusType::EmbeddedExists
+        )
+    }
+}
+
+impl std::str::FromStr for StatusType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "downloaded" => Ok(StatusType::Downloaded),
+            "cached" => Ok(StatusType::Cached),
+            "existing" => Ok(StatusType::Existing),
+            "embedded" => Ok(StatusType::EmbeddedExists),
+            "not_found" => Ok(StatusType::NotFound),
+            "error" => Ok(StatusType::Error),
+            "no_metadata" => Ok(StatusType::NoMetadata),
+            "excluded" => Ok(StatusType::Excluded),
+            "spoken_word" => Ok(StatusType::SpokenWord),
+            other => anyhow::bail!("Unknown file status: {}", other),
+        }
+    }
 }
 
 impl PersistentSession {
@@ -53,23 +113,30 @@ impl PersistentSession {
             cached_count: 0,
             existing_count: 0,
             failed_count: 0,
+            embedded_count: 0,
+            untagged_count: 0,
+            excluded_count: 0,
+            spoken_count: 0,
             log_history: Vec::new(),
             force_retry,
         }
     }
 
     /// Save session to disk using atomic write pattern
-    pub fn save(&self, path: &Path) -> Result<()> {
-        // Create a copy with capped log history
-        let mut session_to_save = self.clone();
-        session_to_save.cap_log_history();
+    /// Streamed straight to the file, so a million pending paths aren't copied into one string.
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        self.cap_log_history();
 
         // Write to temporary file first
         let temp_path = path.with_extension("json.tmp");
-        let json = serde_json::to_string_pretty(&session_to_save)
-            .context("Failed to serialize session")?;
-
-        std::fs::write(&temp_path, json).with_context(|| {
+        let write = || -> Result<()> {
+            let mut writer = std::io::BufWriter::new(std::fs::File::create(&temp_path)?);
+            serde_json::to_writer_pretty(&mut writer, self)
+                .context("Failed to serialize session")?;
+            writer.flush()?;
+            Ok(())
+        };
+        write().with_context(|| {
             format!(
                 "Failed to write temporary session file: {}",
                 temp_path.display()
@@ -88,8 +155,8 @@ impl PersistentSession {
         tracing::info!(
             "Session saved atomically to {} ({} pending files, {} log entries)",
             path.display(),
-            session_to_save.pending_files.len(),
-            session_to_save.log_history.len()
+            self.pending_files.len(),
+            self.log_history.len()
         );
         Ok(())
     }
@@ -204,13 +271,23 @@ impl PersistentSession {
             StatusType::Downloaded => self.downloaded_count += 1,
             StatusType::Cached => self.cached_count += 1,
             StatusType::Existing => self.existing_count += 1,
+            StatusType::EmbeddedExists => self.embedded_count += 1,
             StatusType::NotFound | StatusType::Error => self.failed_count += 1,
+            StatusType::NoMetadata => self.untagged_count += 1,
+            StatusType::Excluded => self.excluded_count += 1,
+            StatusType::SpokenWord => self.spoken_count += 1,
         }
     }
 
     /// Get total files processed
     pub fn total_processed(&self) -> usize {
-        self.downloaded_count + self.cached_count + self.failed_count
+        self.downloaded_count
+            + self.cached_count
+            + self.failed_count
+            + self.embedded_count
+            + self.untagged_count
+            + self.excluded_count
+            + self.spoken_count
     }
 
     /// Get total files in session
@@ -226,8 +303,12 @@ impl StatusType {
             StatusType::Downloaded => "[✓]",
             StatusType::Cached => "[~]",
             StatusType::Existing => "[○]",
+            StatusType::EmbeddedExists => "[♪]",
             StatusType::NotFound => "[✗]",
             StatusType::Error => "[!]",
+            StatusType::NoMetadata => "[?]",
+            StatusType::Excluded => "[-]",
+            StatusType::SpokenWord => "[≡]",
         }
     }
 
@@ -0,0 +1,160 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How far back finished files count towards the current pace
+const WINDOW: Duration = Duration::from_secs(120);
+
+/// A finished file: when, how long a worker spent on it, and whether it needed a lookup
+#[derive(Debug, Clone, Copy)]
+struct Completion {
+    at: Instant,
+    busy: Duration,
+    fetched: bool,
+}
+
+/// Files finished this run and the time workers spent on them, for one kind of file
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    files: u32,
+    busy: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, busy: Duration) {
+        self.files += 1;
+        self.busy += busy;
+    }
+
+    fn mean(&self) -> Option<Duration> {
+        (self.files > 0).then(|| self.busy / self.files)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ThroughputState {
+    recent: VecDeque<Completion>,
+    fetched: Totals,
+    local: Totals,
+}
+
+/// Current processing speed, as reported to the UI
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    /// Files finished per minute over the last couple of minutes
+    pub files_per_min: f64,
+    /// Expected time per remaining file with every worker busy
+    pub pace: Duration,
+}
+
+/// Rolling estimate of how fast files are being processed
+/// Files answered from disk or the negative cache take milliseconds while lookups wait on the
+/// rate limit, so the two are timed separately and weighted by this run's mix of them.
+pub struct Throughput {
+    workers: u32,
+    state: Mutex<ThroughputState>,
+}
+
+impl Throughput {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1) as u32,
+            state: Mutex::new(ThroughputState::default()),
+        }
+    }
+
+    /// Record a finished file and how long its worker spent on it
+    pub fn record(&self, busy: Duration, fetched: bool) {
+        self.record_at(Instant::now(), busy, fetched);
+    }
+
+    fn record_at(&self, now: Instant, busy: Duration, fetched: bool) {
+        let mut state = self.state.lock().unwrap();
+        if fetched {
+            state.fetched.add(busy);
+        } else {
+            state.local.add(busy);
+        }
+        state.recent.push_back(Completion {
+            at: now,
+            busy,
+            fetched,
+        });
+        while state
+            .recent
+            .front()
+            .is_some_and(|oldest| now.duration_since(oldest.at) > WINDOW)
+        {
+            state.recent.pop_front();
+        }
+    }
+
+    /// The current pace, once a file has finished in the last couple of minutes
+    pub fn estimate(&self) -> Option<Estimate> {
+        self.estimate_at(Instant::now())
+    }
+
+    fn estimate_at(&self, now: Instant) -> Option<Estimate> {
+        let state = self.state.lock().unwrap();
+        let recent: Vec<&Completion> = state
+            .recent
+            .iter()
+            .filter(|completion| now.duration_since(completion.at) <= WINDOW)
+            .collect();
+        let oldest = recent.first()?;
+
+        // Rate over the window, or since the first file finished early in a run
+        let span = now.duration_since(oldest.at).max(Duration::from_secs(1));
+        let files_per_min = recent.len() as f64 * 60.0 / span.as_secs_f64();
+
+        // Recent times per kind follow the network and rate limit as they change;
+        // the mix comes from the whole run so a burst of cached files doesn't skew it
+        let (mut fetched, mut local) = (Totals::default(), Totals::default());
+        for completion in &recent {
+            if completion.fetched {
+                fetched.add(completion.busy);
+            } else {
+                local.add(completion.busy);
+            }
+        }
+        let fetched_busy = fetched.mean().or(state.fetched.mean()).unwrap_or_default();
+        let local_busy = local.mean().or(state.local.mean()).unwrap_or_default();
+        let share = state.fetched.files as f64 / (state.fetched.files + state.local.files) as f64;
+        let per_file = fetched_busy.mul_f64(share) + local_busy.mul_f64(1.0 - share);
+
+        Some(Estimate {
+            files_per_min,
+            pace: per_file / self.workers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pace_weights_lookups_by_run_mix() {
+        let throughput = Throughput::new(2);
+        let start = Instant::now();
+        assert_eq!(throughput.estimate_at(start), None);
+
+        // One lookup at 4s and three cache hits at 0s each: 1s per file, halved by two workers
+        throughput.record_at(start, Duration::from_secs(4), true);
+        for _ in 0..3 {
+            throughput.record_at(start, Duration::ZERO, false);
+        }
+        let estimate = throughput
+            .estimate_at(start + Duration::from_secs(30))
+            .unwrap();
+        assert_eq!(estimate.files_per_min, 8.0);
+        assert_eq!(estimate.pace, Duration::from_millis(500));
+
+        // Once the lookup leaves the window its run average still counts for the mix
+        let later = start + WINDOW + Duration::from_secs(10);
+        throughput.record_at(later, Duration::ZERO, false);
+        let estimate = throughput.estimate_at(later).unwrap();
+        assert_eq!(estimate.files_per_min, 60.0);
+        assert_eq!(estimate.pace, Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,256 @@
+use dashmap::DashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Slowest files listed in the summary
+const SLOWEST: usize = 5;
+
+/// Steps of processing a file that `--timing` measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading tags, or identifying the file by fingerprint
+    Metadata,
+    /// Library index and negative cache lookups
+    Cache,
+    /// Waiting for the shared rate limit
+    RateLimit,
+    /// Requests to LRCLIB
+    Api,
+    /// Writing or copying sidecars
+    Write,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::Metadata,
+        Stage::Cache,
+        Stage::RateLimit,
+        Stage::Api,
+        Stage::Write,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage::Metadata => "metadata",
+            Stage::Cache => "cache",
+            Stage::RateLimit => "rate limit",
+            Stage::Api => "api",
+            Stage::Write => "write",
+        }
+    }
+}
+
+/// Time spent in one stage over a run, counted per file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StageSummary {
+    /// Files that went through the stage
+    pub files: usize,
+    pub total: Duration,
+    /// Longest any one file spent in the stage
+    pub max: Duration,
+}
+
+impl StageSummary {
+    fn add(&mut self, elapsed: Duration) {
+        self.files += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.files {
+            0 => Duration::ZERO,
+            files => self.total / files as u32,
+        }
+    }
+}
+
+/// How long one file took from start to finish
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTiming {
+    pub path: PathBuf,
+    pub total: Duration,
+    /// Part of the total spent waiting for the shared rate limit
+    pub rate_limited: Duration,
+}
+
+impl fmt::Display for FileTiming {
+    /// "1.2s", or "1.2s, 0.9s rate limited" when the wait took part of it
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Short(self.total))?;
+        if !self.rate_limited.is_zero() {
+            write!(f, ", {} rate limited", Short(self.rate_limited))?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-stage times for a whole run, in `Stage::ALL` order
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingSummary {
+    pub stages: Vec<(Stage, StageSummary)>,
+    /// The files that took longest, slowest first
+    pub slowest: Vec<FileTiming>,
+}
+
+impl TimingSummary {
+    /// One line per stage, with its share of the time spent in all of them, then the
+    /// slowest files
+    pub fn lines(&self) -> Vec<String> {
+        let all: Duration = self.stages.iter().map(|(_, summary)| summary.total).sum();
+        let slowest = self
+            .slowest
+            .iter()
+            .map(|file| format!("slowest    {}  {}", file, file.path.display()));
+        self.stages
+            .iter()
+            .map(|(stage, summary)| {
+                let share = if all.is_zero() {
+                    0.0
+                } else {
+                    summary.total.as_secs_f64() * 100.0 / all.as_secs_f64()
+                };
+                format!(
+                    "{:<10} {:>7} files  avg {:>8}  max {:>8}  total {:>8} ({:.0}%)",
+                    stage.label(),
+                    summary.files,
+                    Short(summary.mean()),
+                    Short(summary.max),
+                    Short(summary.total),
+                    share
+                )
+            })
+            .chain(slowest)
+            .collect()
+    }
+}
+
+/// Durations as milliseconds below a second, seconds above
+struct Short(Duration);
+
+impl fmt::Display for Short {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self.0.as_secs_f64() {
+            secs if secs < 1.0 => format!("{:.1}ms", secs * 1000.0),
+            secs => format!("{:.1}s", secs),
+        };
+        f.pad(&text)
+    }
+}
+
+/// Stage times collected while files are processed
+/// Each file's time per stage is added up until it finishes, so a file that needs several
+/// requests counts once in the summary.
+#[derive(Default)]
+pub struct StageTimings {
+    /// Files being processed, with their time so far in each stage
+    files: DashMap<PathBuf, [Duration; 5]>,
+    totals: Mutex<[StageSummary; 5]>,
+    /// The files that took longest so far, slowest first
+    slowest: Mutex<Vec<FileTiming>>,
+}
+
+impl StageTimings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add time a file spent in a stage
+    pub fn add(&self, path: &Path, stage: Stage, elapsed: Duration) {
+        let index = stage as usize;
+        match self.files.get_mut(path) {
+            Some(mut times) => times[index] += elapsed,
+            None => {
+                let mut times = [Duration::ZERO; 5];
+                times[index] = elapsed;
+                self.files.insert(path.to_path_buf(), times);
+            }
+        }
+    }
+
+    /// Count a finished file's stage times towards the totals, returning how long it took
+    /// in all (`total`, measured by the caller) and waiting for the rate limit
+    pub fn finish(&self, path: &Path, total: Duration) -> FileTiming {
+        let times = self
+            .files
+            .remove(path)
+            .map(|(_, times)| times)
+            .unwrap_or_default();
+        let mut totals = self.totals.lock().unwrap();
+        for (total, elapsed) in totals.iter_mut().zip(times) {
+            if !elapsed.is_zero() {
+                total.add(elapsed);
+            }
+        }
+        drop(totals);
+
+        let timing = FileTiming {
+            path: path.to_path_buf(),
+            total,
+            rate_limited: times[Stage::RateLimit as usize],
+        };
+        let mut slowest = self.slowest.lock().unwrap();
+        let position = slowest.partition_point(|file| file.total >= total);
+        if position < SLOWEST {
+            slowest.insert(position, timing.clone());
+            slowest.truncate(SLOWEST);
+        }
+        timing
+    }
+
+    pub fn summary(&self) -> TimingSummary {
+        let totals = self.totals.lock().unwrap();
+        TimingSummary {
+            stages: Stage::ALL.into_iter().zip(totals.iter().copied()).collect(),
+            slowest: self.slowest.lock().unwrap().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_times_add_up_per_file() {
+        let timings = StageTimings::new();
+        let (a, b) = (Path::new("/music/a.flac"), Path::new("/music/b.flac"));
+        let ms = Duration::from_millis;
+
+        // Two requests for one file count as one file
+        timings.add(a, Stage::Api, ms(300));
+        timings.add(a, Stage::Api, ms(500));
+        timings.add(a, Stage::Cache, ms(2));
+        timings.add(b, Stage::Api, ms(200));
+        timings.add(b, Stage::RateLimit, ms(700));
+        let finished = timings.finish(a, ms(810));
+        assert_eq!(finished.to_string(), "810.0ms");
+        let finished = timings.finish(b, ms(1200));
+        assert_eq!(finished.to_string(), "1.2s, 700.0ms rate limited");
+        // Files still in progress aren't counted yet
+        timings.add(a, Stage::Write, ms(1));
+
+        let summary = timings.summary();
+        let api = summary.stages[Stage::Api as usize].1;
+        assert_eq!(api.files, 2);
+        assert_eq!(api.total, ms(1000));
+        assert_eq!(api.max, ms(800));
+        assert_eq!(api.mean(), ms(500));
+        assert_eq!(summary.stages[Stage::Write as usize].1.files, 0);
+
+        let lines = summary.lines();
+        assert_eq!(lines.len(), Stage::ALL.len() + 2);
+        assert!(lines[Stage::Api as usize].starts_with("api"));
+        assert!(lines[Stage::Api as usize].ends_with("(59%)"));
+        // The slowest files follow the stages, slowest first
+        assert_eq!(
+            lines[Stage::ALL.len()..],
+            [
+                "slowest    1.2s, 700.0ms rate limited  /music/b.flac",
+                "slowest    810.0ms  /music/a.flac"
+            ]
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io::{self, Write};
+
+/// Put text on the system clipboard with an OSC 52 escape sequence
+/// The terminal sets the clipboard, so it works over SSH too, in terminals that allow it
+/// (inside tmux, `set-clipboard` has to be on).
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", osc52(text))?;
+    stdout.flush()
+}
+
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(osc52("/music/a.flac"), "\x1b]52;c;L211c2ljL2EuZmxhYw==\x07");
+    }
+}
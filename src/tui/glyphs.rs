@@ -0,0 +1,142 @@
+use crate::session::StatusType;
+
+/// Symbols the TUI draws with: Unicode by default, plain ASCII with `--ascii` for
+/// terminals, fonts and screen readers that handle the Unicode ones poorly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyphs {
+    ascii: bool,
+    /// Filled part of the progress bar
+    pub bar: &'static str,
+    /// Empty part of the progress bar
+    pub bar_empty: &'static str,
+    /// Color swatch in the progress legend
+    pub swatch: &'static str,
+    /// Marks a finished run in the header
+    pub done: &'static str,
+    /// Cursor at the end of the search prompt
+    pub cursor: &'static str,
+    pub up_down: &'static str,
+    pub left_right: &'static str,
+    /// Newer log entries below the scrolled view
+    pub down: &'static str,
+    /// Leads the result of a search attempt in the details pane
+    pub arrow: &'static str,
+    pub playing: &'static str,
+    pub paused: &'static str,
+    /// Closed and open albums in the album view
+    pub collapsed: &'static str,
+    pub expanded: &'static str,
+    /// Log entry of a file queued for retry
+    pub retry: &'static str,
+    /// Log entry of an album moved to the front of the queue
+    pub moved: &'static str,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl Glyphs {
+    pub fn unicode() -> Self {
+        Self {
+            ascii: false,
+            bar: "█",
+            bar_empty: "░",
+            swatch: "● ",
+            done: "✓",
+            cursor: "█",
+            up_down: "↑↓",
+            left_right: "←→",
+            down: "↓",
+            arrow: "→",
+            playing: "▶",
+            paused: "⏸",
+            collapsed: "▸",
+            expanded: "▾",
+            retry: "[↻]",
+            moved: "[↑]",
+        }
+    }
+
+    pub fn ascii() -> Self {
+        Self {
+            ascii: true,
+            bar: "#",
+            bar_empty: "-",
+            swatch: "* ",
+            done: "(done)",
+            cursor: "_",
+            up_down: "Up/Down",
+            left_right: "Left/Right",
+            down: "v",
+            arrow: "->",
+            playing: ">",
+            paused: "||",
+            collapsed: "+",
+            expanded: "-",
+            retry: "[r]",
+            moved: "[^]",
+        }
+    }
+
+    /// Log symbol of an outcome
+    pub fn status(&self, status: &StatusType) -> &'static str {
+        if !self.ascii {
+            return status.to_symbol();
+        }
+        match status {
+            StatusType::Downloaded => "[+]",
+            StatusType::Existing => "[o]",
+            StatusType::EmbeddedExists => "[e]",
+            StatusType::NotFound => "[x]",
+            StatusType::SpokenWord => "[=]",
+            // The rest are ASCII already
+            other => other.to_symbol(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_glyphs() {
+        let glyphs = Glyphs::ascii();
+        for glyph in [
+            glyphs.bar,
+            glyphs.bar_empty,
+            glyphs.swatch,
+            glyphs.done,
+            glyphs.cursor,
+            glyphs.up_down,
+            glyphs.left_right,
+            glyphs.down,
+            glyphs.arrow,
+            glyphs.playing,
+            glyphs.paused,
+            glyphs.collapsed,
+            glyphs.expanded,
+            glyphs.retry,
+            glyphs.moved,
+        ] {
+            assert!(glyph.is_ascii(), "{:?}", glyph);
+        }
+        for status in [
+            StatusType::Downloaded,
+            StatusType::Cached,
+            StatusType::Existing,
+            StatusType::EmbeddedExists,
+            StatusType::NotFound,
+            StatusType::Error,
+            StatusType::NoMetadata,
+            StatusType::Excluded,
+            StatusType::SpokenWord,
+        ] {
+            assert!(glyphs.status(&status).is_ascii());
+        }
+        assert_eq!(Glyphs::default().status(&StatusType::Downloaded), "[✓]");
+    }
+}
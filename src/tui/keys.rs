@@ -0,0 +1,425 @@
+use crate::tui::state::LogFilter;
+use anyhow::{bail, Context, Result};
+use crossterm::event::KeyCode;
+use serde::Deserialize;
+
+/// The `[keys]` section of the config: keys that replace the defaults of each action
+/// Keys are single characters (`q`, `N`) or names (`esc`, `enter`, `space`, `up`,
+/// `pgdn`, `home`, `f1`, ...).
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub quit: Option<Vec<String>>,
+    pub pause: Option<Vec<String>>,
+    pub resume: Option<Vec<String>>,
+    /// Select the entry above
+    pub up: Option<Vec<String>>,
+    /// Select the entry below
+    pub down: Option<Vec<String>>,
+    pub page_up: Option<Vec<String>>,
+    pub page_down: Option<Vec<String>>,
+    pub top: Option<Vec<String>>,
+    pub bottom: Option<Vec<String>>,
+    pub details: Option<Vec<String>>,
+    pub preview: Option<Vec<String>>,
+    pub search: Option<Vec<String>>,
+    pub next_match: Option<Vec<String>>,
+    pub previous_match: Option<Vec<String>>,
+    pub retry: Option<Vec<String>>,
+    pub album_first: Option<Vec<String>>,
+    /// Switch between the log and the failures screen
+    pub failures: Option<Vec<String>>,
+    /// Switch between the log and the album view
+    pub albums: Option<Vec<String>>,
+    /// Switch between the log and the history of past runs
+    pub history: Option<Vec<String>>,
+    /// Open an album in the album view
+    pub expand: Option<Vec<String>>,
+    /// Close an album in the album view
+    pub collapse: Option<Vec<String>>,
+    /// Mark a failure for a retry or export
+    pub mark: Option<Vec<String>>,
+    /// Export the marked failures to a playlist
+    pub export: Option<Vec<String>>,
+    /// Copy the selected file's path, or the failures list, to the clipboard
+    pub copy: Option<Vec<String>>,
+    /// Edit the selected not found file's tags and search again
+    pub manual_search: Option<Vec<String>>,
+    /// Switch the log file between debug and info
+    pub log_level: Option<Vec<String>>,
+    /// One key per log filter: downloaded, not found, errors, cached, skipped
+    pub filters: Option<Vec<String>>,
+    /// Show every log entry again
+    pub filter_all: Option<Vec<String>>,
+}
+
+/// What a key does outside the search prompt, preview and picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Pause,
+    Resume,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Details,
+    Preview,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Retry,
+    AlbumFirst,
+    Failures,
+    Albums,
+    History,
+    Expand,
+    Collapse,
+    Mark,
+    Export,
+    Copy,
+    ManualSearch,
+    LogLevel,
+    Filter(Option<LogFilter>),
+}
+
+/// Keys of the TUI's actions
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keymap {
+    pub quit: Vec<KeyCode>,
+    pub pause: Vec<KeyCode>,
+    pub resume: Vec<KeyCode>,
+    pub up: Vec<KeyCode>,
+    pub down: Vec<KeyCode>,
+    pub page_up: Vec<KeyCode>,
+    pub page_down: Vec<KeyCode>,
+    pub top: Vec<KeyCode>,
+    pub bottom: Vec<KeyCode>,
+    pub details: Vec<KeyCode>,
+    pub preview: Vec<KeyCode>,
+    pub search: Vec<KeyCode>,
+    pub next_match: Vec<KeyCode>,
+    pub previous_match: Vec<KeyCode>,
+    pub retry: Vec<KeyCode>,
+    pub album_first: Vec<KeyCode>,
+    pub failures: Vec<KeyCode>,
+    pub albums: Vec<KeyCode>,
+    pub history: Vec<KeyCode>,
+    pub expand: Vec<KeyCode>,
+    pub collapse: Vec<KeyCode>,
+    pub mark: Vec<KeyCode>,
+    pub export: Vec<KeyCode>,
+    pub copy: Vec<KeyCode>,
+    pub manual_search: Vec<KeyCode>,
+    pub log_level: Vec<KeyCode>,
+    /// In `LogFilter::ALL` order
+    pub filters: Vec<KeyCode>,
+    pub filter_all: Vec<KeyCode>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            quit: vec![KeyCode::Char('q'), KeyCode::Esc],
+            pause: vec![KeyCode::Char('p')],
+            resume: vec![KeyCode::Char('r')],
+            up: vec![KeyCode::Up],
+            down: vec![KeyCode::Down],
+            page_up: vec![KeyCode::PageUp],
+            page_down: vec![KeyCode::PageDown],
+            top: vec![KeyCode::Home],
+            bottom: vec![KeyCode::End],
+            details: vec![KeyCode::Char('d')],
+            preview: vec![KeyCode::Char('l')],
+            search: vec![KeyCode::Char('/')],
+            next_match: vec![KeyCode::Char('n')],
+            previous_match: vec![KeyCode::Char('N')],
+            retry: vec![KeyCode::Enter],
+            album_first: vec![KeyCode::Char('f')],
+            failures: vec![KeyCode::Tab],
+            albums: vec![KeyCode::Char('g')],
+            history: vec![KeyCode::Char('h')],
+            expand: vec![KeyCode::Right],
+            collapse: vec![KeyCode::Left],
+            mark: vec![KeyCode::Char(' ')],
+            export: vec![KeyCode::Char('e')],
+            copy: vec![KeyCode::Char('y')],
+            manual_search: vec![KeyCode::Char('s')],
+            log_level: vec![KeyCode::Char('v')],
+            filters: ('1'..='5').map(KeyCode::Char).collect(),
+            filter_all: vec![KeyCode::Char('0')],
+        }
+    }
+}
+
+impl Keymap {
+    /// Start from the default keys and replace those of the actions set in the config
+    pub fn from_config(config: &KeysConfig) -> Result<Self> {
+        let mut keymap = Self::default();
+        for (name, value, keys) in [
+            ("quit", &config.quit, &mut keymap.quit),
+            ("pause", &config.pause, &mut keymap.pause),
+            ("resume", &config.resume, &mut keymap.resume),
+            ("up", &config.up, &mut keymap.up),
+            ("down", &config.down, &mut keymap.down),
+            ("page_up", &config.page_up, &mut keymap.page_up),
+            ("page_down", &config.page_down, &mut keymap.page_down),
+            ("top", &config.top, &mut keymap.top),
+            ("bottom", &config.bottom, &mut keymap.bottom),
+            ("details", &config.details, &mut keymap.details),
+            ("preview", &config.preview, &mut keymap.preview),
+            ("search", &config.search, &mut keymap.search),
+            ("next_match", &config.next_match, &mut keymap.next_match),
+            (
+                "previous_match",
+                &config.previous_match,
+                &mut keymap.previous_match,
+            ),
+            ("retry", &config.retry, &mut keymap.retry),
+            ("album_first", &config.album_first, &mut keymap.album_first),
+            ("failures", &config.failures, &mut keymap.failures),
+            ("albums", &config.albums, &mut keymap.albums),
+            ("history", &config.history, &mut keymap.history),
+            ("expand", &config.expand, &mut keymap.expand),
+            ("collapse", &config.collapse, &mut keymap.collapse),
+            ("mark", &config.mark, &mut keymap.mark),
+            ("export", &config.export, &mut keymap.export),
+            ("copy", &config.copy, &mut keymap.copy),
+            (
+                "manual_search",
+                &config.manual_search,
+                &mut keymap.manual_search,
+            ),
+            ("log_level", &config.log_level, &mut keymap.log_level),
+            ("filters", &config.filters, &mut keymap.filters),
+            ("filter_all", &config.filter_all, &mut keymap.filter_all),
+        ] {
+            if let Some(value) = value {
+                if value.is_empty() {
+                    bail!("No keys for {}", name);
+                }
+                *keys = value
+                    .iter()
+                    .map(|key| parse_key(key))
+                    .collect::<Result<_>>()
+                    .with_context(|| format!("Invalid key for {}", name))?;
+            }
+        }
+        if keymap.filters.len() != LogFilter::ALL.len() {
+            bail!(
+                "Expected {} filter keys (downloaded, not found, errors, cached, skipped)",
+                LogFilter::ALL.len()
+            );
+        }
+
+        // A key can only do one thing
+        let named: Vec<(&str, KeyCode)> = keymap
+            .named()
+            .into_iter()
+            .flat_map(|(name, keys)| keys.iter().map(move |key| (name, *key)))
+            .collect();
+        for (index, (name, key)) in named.iter().enumerate() {
+            if let Some((other, _)) = named[index + 1..].iter().find(|(_, k)| k == key) {
+                bail!("{} is bound to both {} and {}", key_name(*key), name, other);
+            }
+        }
+        Ok(keymap)
+    }
+
+    /// The action of a key, if it has one
+    pub fn action(&self, code: KeyCode) -> Option<Action> {
+        self.bindings()
+            .into_iter()
+            .find(|(key, _)| *key == code)
+            .map(|(_, action)| action)
+    }
+
+    /// Each action's keys under its name in the config
+    fn named(&self) -> [(&'static str, &Vec<KeyCode>); 28] {
+        [
+            ("quit", &self.quit),
+            ("pause", &self.pause),
+            ("resume", &self.resume),
+            ("up", &self.up),
+            ("down", &self.down),
+            ("page_up", &self.page_up),
+            ("page_down", &self.page_down),
+            ("top", &self.top),
+            ("bottom", &self.bottom),
+            ("details", &self.details),
+            ("preview", &self.preview),
+            ("search", &self.search),
+            ("next_match", &self.next_match),
+            ("previous_match", &self.previous_match),
+            ("retry", &self.retry),
+            ("album_first", &self.album_first),
+            ("failures", &self.failures),
+            ("albums", &self.albums),
+            ("history", &self.history),
+            ("expand", &self.expand),
+            ("collapse", &self.collapse),
+            ("mark", &self.mark),
+            ("export", &self.export),
+            ("copy", &self.copy),
+            ("manual_search", &self.manual_search),
+            ("log_level", &self.log_level),
+            ("filters", &self.filters),
+            ("filter_all", &self.filter_all),
+        ]
+    }
+
+    fn bindings(&self) -> Vec<(KeyCode, Action)> {
+        let filters = self
+            .filters
+            .iter()
+            .zip(LogFilter::ALL)
+            .map(|(key, filter)| (*key, Action::Filter(Some(filter))));
+        [
+            (&self.quit, Action::Quit),
+            (&self.pause, Action::Pause),
+            (&self.resume, Action::Resume),
+            (&self.up, Action::Up),
+            (&self.down, Action::Down),
+            (&self.page_up, Action::PageUp),
+            (&self.page_down, Action::PageDown),
+            (&self.top, Action::Top),
+            (&self.bottom, Action::Bottom),
+            (&self.details, Action::Details),
+            (&self.preview, Action::Preview),
+            (&self.search, Action::Search),
+            (&self.next_match, Action::NextMatch),
+            (&self.previous_match, Action::PreviousMatch),
+            (&self.retry, Action::Retry),
+            (&self.album_first, Action::AlbumFirst),
+            (&self.failures, Action::Failures),
+            (&self.albums, Action::Albums),
+            (&self.history, Action::History),
+            (&self.expand, Action::Expand),
+            (&self.collapse, Action::Collapse),
+            (&self.mark, Action::Mark),
+            (&self.export, Action::Export),
+            (&self.copy, Action::Copy),
+            (&self.manual_search, Action::ManualSearch),
+            (&self.log_level, Action::LogLevel),
+            (&self.filter_all, Action::Filter(None)),
+        ]
+        .into_iter()
+        .flat_map(|(keys, action)| keys.iter().map(move |key| (*key, action)))
+        .chain(filters)
+        .collect()
+    }
+}
+
+/// Hint for an action's keys in the footer, its first key
+pub fn hint(keys: &[KeyCode]) -> String {
+    keys.first().map(|key| key_name(*key)).unwrap_or_default()
+}
+
+/// A key as the footer shows it
+pub fn key_name(code: KeyCode) -> String {
+    match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Delete => "Del".to_string(),
+        KeyCode::Insert => "Ins".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::PageUp => "PgUp".to_string(),
+        KeyCode::PageDown => "PgDn".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// A key from the config: a single character, or a key's name in any case
+fn parse_key(name: &str) -> Result<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(KeyCode::Char(c));
+    }
+    let lower = name.to_ascii_lowercase();
+    let code = match lower.as_str() {
+        "space" => KeyCode::Char(' '),
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" | "ins" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => bail!("Unknown key {:?}", name),
+        },
+    };
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_from_config() {
+        let keymap = Keymap::from_config(&KeysConfig::default()).unwrap();
+        assert_eq!(keymap, Keymap::default());
+        assert_eq!(keymap.action(KeyCode::Char('q')), Some(Action::Quit));
+        assert_eq!(
+            keymap.action(KeyCode::Char('2')),
+            Some(Action::Filter(Some(LogFilter::NotFound)))
+        );
+        assert_eq!(keymap.action(KeyCode::Char('6')), None);
+
+        let config = KeysConfig {
+            quit: Some(vec!["x".to_string()]),
+            up: Some(vec!["k".to_string(), "up".to_string()]),
+            filters: Some(["F1", "F2", "F3", "F4", "F5"].map(String::from).to_vec()),
+            ..Default::default()
+        };
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.action(KeyCode::Char('x')), Some(Action::Quit));
+        assert_eq!(keymap.action(KeyCode::Char('q')), None);
+        assert_eq!(keymap.action(KeyCode::Esc), None);
+        assert_eq!(keymap.action(KeyCode::Char('k')), Some(Action::Up));
+        assert_eq!(keymap.action(KeyCode::Up), Some(Action::Up));
+        assert_eq!(
+            keymap.action(KeyCode::F(3)),
+            Some(Action::Filter(Some(LogFilter::Error)))
+        );
+        assert_eq!(hint(&keymap.filters), "F1");
+
+        // The default `p` is still pause
+        let config = KeysConfig {
+            quit: Some(vec!["p".to_string()]),
+            ..Default::default()
+        };
+        assert!(Keymap::from_config(&config).is_err());
+        let config = KeysConfig {
+            search: Some(vec!["ctrl-f".to_string()]),
+            ..Default::default()
+        };
+        assert!(Keymap::from_config(&config).is_err());
+        let config = KeysConfig {
+            filters: Some(vec!["a".to_string()]),
+            ..Default::default()
+        };
+        assert!(Keymap::from_config(&config).is_err());
+    }
+}
@@ -1,23 +1,55 @@
+pub mod clipboard;
+pub mod glyphs;
+pub mod keys;
+pub mod notify;
 pub mod state;
+pub mod status;
+pub mod theme;
 pub mod ui;
 pub mod widgets;
 
 use crate::messages::{UiMessage, WorkerMessage};
 use anyhow::Result;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
-use state::AppState;
+use keys::Action;
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
+use state::{AppState, OutageChoice, QuitChoice, Screen, SearchInput};
+use status::LiveStatus;
 use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
+/// How often status.json is rewritten
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+/// Redraw this often without any input or worker messages, for the clocks and countdowns
+const TICK: Duration = Duration::from_secs(1);
+/// Redraw this often while the lyrics preview plays, to move its highlight in time
+const PREVIEW_TICK: Duration = Duration::from_millis(100);
+/// Worker messages arriving within this long of the last redraw wait to be drawn together
+const FRAME: Duration = Duration::from_millis(16);
+/// How long the input thread waits for an event before checking whether to stop
+const INPUT_POLL: Duration = Duration::from_millis(100);
+/// Log lines scrolled per mouse wheel step
+const WHEEL_LINES: usize = 3;
+
+/// Sets the log file's level, debug when given true
+pub type SetLogLevel = Box<dyn Fn(bool) -> Result<()> + Send>;
+
 pub struct App {
     state: AppState,
     worker_rx: mpsc::UnboundedReceiver<WorkerMessage>,
     ui_tx: mpsc::UnboundedSender<UiMessage>,
+    set_log_level: Option<SetLogLevel>,
 }
 
 impl App {
@@ -29,9 +61,46 @@ impl App {
             state: AppState::new(),
             worker_rx,
             ui_tx,
+            set_log_level: None,
         }
     }
 
+    /// Draw the TUI in these colors
+    pub fn with_theme(mut self, theme: theme::Theme) -> Self {
+        self.state.theme = theme;
+        self
+    }
+
+    /// Draw the TUI with these symbols
+    pub fn with_glyphs(mut self, glyphs: glyphs::Glyphs) -> Self {
+        self.state.glyphs = glyphs;
+        self
+    }
+
+    /// Answer keys with these actions
+    pub fn with_keys(mut self, keys: keys::Keymap) -> Self {
+        self.state.keys = keys;
+        self
+    }
+
+    /// Get attention this way when the run completes or lyrics wait to be picked
+    pub fn with_notify(mut self, notify: notify::Notify) -> Self {
+        self.state.notify = notify;
+        self
+    }
+
+    /// Let the log file's level be switched at runtime, starting from debug or info
+    pub fn with_log_level(mut self, debug: bool, set: SetLogLevel) -> Self {
+        self.state.debug_log = debug;
+        self.set_log_level = Some(set);
+        self
+    }
+
+    /// The state as the TUI left it, for a summary once it has closed
+    pub fn state(&self) -> &AppState {
+        &self.state
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         // Setup terminal
         enable_raw_mode()?;
@@ -58,33 +127,326 @@ impl App {
         &mut self,
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> Result<()> {
+        let status_path = crate::paths::get_status_path().ok();
+        let mut status_failed = false;
+
+        let (event_tx, mut events) = mpsc::unbounded_channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let input = read_events(event_tx, stop.clone());
+        let result = self
+            .handle_events(
+                terminal,
+                &mut events,
+                status_path.as_deref(),
+                &mut status_failed,
+            )
+            .await;
+        stop.store(true, Ordering::Relaxed);
+        let _ = input.join();
+        result?;
+
+        let stopped = self.state.status != state::Status::Complete;
+        self.write_status(status_path.as_deref(), stopped, &mut status_failed);
+
+        Ok(())
+    }
+
+    /// Redraw, then wait for a key, a worker message or the next tick, until the TUI closes
+    async fn handle_events(
+        &mut self,
+        terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+        events: &mut mpsc::UnboundedReceiver<io::Result<Event>>,
+        status_path: Option<&Path>,
+        status_failed: &mut bool,
+    ) -> Result<()> {
+        let mut status_written: Option<Instant> = None;
+
         loop {
             // Render UI
-            terminal.draw(|f| ui::render(f, &self.state))?;
-
-            // Handle events (non-blocking)
-            if event::poll(std::time::Duration::from_millis(16))? {
-                if let Event::Key(key) = event::read()? {
-                    match key.code {
-                        KeyCode::Char('q') | KeyCode::Esc => {
-                            self.state.should_quit = true;
-                            let _ = self.ui_tx.send(UiMessage::Quit);
+            let size = terminal.size()?;
+            self.state.log_height =
+                ui::log_height(Rect::new(0, 0, size.width, size.height), &self.state);
+            let flashing = self.state.flashing();
+            terminal.draw(|f| {
+                ui::render(f, &self.state);
+                if flashing {
+                    ui::flash(f);
+                }
+            })?;
+            let drawn = Instant::now();
+            if std::mem::take(&mut self.state.bell) {
+                notify::bell(terminal.backend_mut())?;
+            }
+
+            let tick = match (&self.state.preview, self.state.flash_until) {
+                // Wake to end a flash on time
+                (_, Some(until)) => until.saturating_duration_since(drawn),
+                (Some(preview), _) if !preview.is_paused() => PREVIEW_TICK,
+                _ => TICK,
+            };
+            let event = tokio::select! {
+                Some(event) = events.recv() => Some(event?),
+                Some(msg) = self.worker_rx.recv() => {
+                    self.state.update(msg);
+                    // Let a burst of messages build up into one redraw
+                    tokio::time::sleep_until((drawn + FRAME).into()).await;
+                    None
+                }
+                _ = tokio::time::sleep(tick) => None,
+            };
+
+            if let Some(event) = event {
+                match event {
+                    // The search prompt takes every key until it is closed
+                    Event::Key(key) if self.state.search.as_ref().is_some_and(|s| s.editing) => {
+                        match key.code {
+                            KeyCode::Char(c) => self.state.search_input(SearchInput::Char(c)),
+                            KeyCode::Backspace => self.state.search_input(SearchInput::Backspace),
+                            KeyCode::Enter => self.state.finish_search(),
+                            KeyCode::Esc => self.state.clear_search(),
+                            _ => {}
+                        }
+                    }
+                    // So does the manual search form
+                    Event::Key(key) if self.state.manual_search.is_some() => match key.code {
+                        KeyCode::Char(c) => self.state.manual_search_input(SearchInput::Char(c)),
+                        KeyCode::Backspace => {
+                            self.state.manual_search_input(SearchInput::Backspace)
+                        }
+                        KeyCode::Tab | KeyCode::Down => self.state.focus_manual_field(false),
+                        KeyCode::BackTab | KeyCode::Up => self.state.focus_manual_field(true),
+                        KeyCode::Enter => {
+                            if let Some((path, tags)) = self.state.take_manual_search() {
+                                let _ = self.ui_tx.send(UiMessage::ManualSearch { path, tags });
+                            }
+                        }
+                        KeyCode::Esc => self.state.close_manual_search(),
+                        _ => {}
+                    },
+                    // The quit prompt takes every key until it is answered
+                    Event::Key(key) if self.state.quit_prompt.is_some() => {
+                        let choice = match key.code {
+                            KeyCode::Up => {
+                                self.state.select_quit_choice(true);
+                                None
+                            }
+                            KeyCode::Down => {
+                                self.state.select_quit_choice(false);
+                                None
+                            }
+                            KeyCode::Enter => self.state.take_quit_choice(None),
+                            KeyCode::Esc => self.state.take_quit_choice(Some(QuitChoice::Cancel)),
+                            KeyCode::Char(c) => QuitChoice::ALL
+                                .into_iter()
+                                .find(|choice| choice.key() == c)
+                                .and_then(|choice| self.state.take_quit_choice(Some(choice))),
+                            _ => None,
+                        };
+                        match choice {
+                            Some(QuitChoice::Save) => {
+                                self.quit(true);
+                                break;
+                            }
+                            Some(QuitChoice::Discard) => {
+                                self.quit(false);
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                    // So does the connection lost modal
+                    Event::Key(key) if self.state.outage_open() => {
+                        let choice = match key.code {
+                            KeyCode::Up => {
+                                self.state.select_outage_choice(true);
+                                None
+                            }
+                            KeyCode::Down => {
+                                self.state.select_outage_choice(false);
+                                None
+                            }
+                            KeyCode::Enter => self.state.take_outage_choice(None),
+                            KeyCode::Esc => self.state.take_outage_choice(Some(OutageChoice::Wait)),
+                            KeyCode::Char(c) => OutageChoice::ALL
+                                .into_iter()
+                                .find(|choice| choice.key() == c)
+                                .and_then(|choice| self.state.take_outage_choice(Some(choice))),
+                            _ => None,
+                        };
+                        match choice {
+                            Some(OutageChoice::RetryNow) => {
+                                let _ = self.ui_tx.send(UiMessage::RetryConnection);
+                            }
+                            Some(OutageChoice::Quit) => {
+                                self.quit(self.state.scan_complete);
+                                break;
+                            }
+                            _ => {}
+                        }
+                    }
+                    // The preview takes the keys that control playback, and its own to close it
+                    Event::Key(key)
+                        if self.state.preview.is_some()
+                            && (matches!(
+                                key.code,
+                                KeyCode::Char(' ') | KeyCode::Left | KeyCode::Right | KeyCode::Esc
+                            ) || self.state.keys.preview.contains(&key.code)) =>
+                    {
+                        let Some(preview) = &mut self.state.preview else {
+                            continue;
+                        };
+                        match key.code {
+                            KeyCode::Char(' ') => preview.toggle_pause(),
+                            KeyCode::Left => preview.seek(false),
+                            KeyCode::Right => preview.seek(true),
+                            _ => self.state.close_preview(),
+                        }
+                    }
+                    // A pick takes the keys that answer it, leaving quitting and pausing free
+                    Event::Key(key)
+                        if !self.state.picks.is_empty()
+                            && matches!(
+                                key.code,
+                                KeyCode::Up
+                                    | KeyCode::Down
+                                    | KeyCode::Enter
+                                    | KeyCode::Esc
+                                    | KeyCode::Char('1'..='9')
+                            ) =>
+                    {
+                        let answer = match key.code {
+                            KeyCode::Up => {
+                                self.state.select_pick(true);
+                                None
+                            }
+                            KeyCode::Down => {
+                                self.state.select_pick(false);
+                                None
+                            }
+                            KeyCode::Enter => {
+                                let selected = self.state.picks.front().map(|pick| pick.selected);
+                                self.state.take_pick(selected)
+                            }
+                            KeyCode::Char(c) => self
+                                .state
+                                .take_pick(c.to_digit(10).map(|digit| digit as usize - 1)),
+                            _ => self.state.take_pick(None),
+                        };
+                        if let Some((path, choice)) = answer {
+                            let _ = self.ui_tx.send(UiMessage::PickLyrics { path, choice });
+                        }
+                    }
+                    Event::Key(key) if key.code == KeyCode::Esc && self.state.search.is_some() => {
+                        self.state.clear_search()
+                    }
+                    Event::Key(key) => match self.state.keys.action(key.code) {
+                        Some(Action::Quit) if self.state.request_quit() => {
+                            self.quit(false);
                             break;
                         }
-                        KeyCode::Char('p') => {
-                            if !self.state.paused {
-                                self.state.paused = true;
-                                let _ = self.ui_tx.send(UiMessage::Pause);
+                        Some(Action::Pause) if !self.state.paused => {
+                            self.state.paused = true;
+                            let _ = self.ui_tx.send(UiMessage::Pause);
+                        }
+                        Some(Action::Resume) if self.state.paused => {
+                            self.state.paused = false;
+                            let _ = self.ui_tx.send(UiMessage::Resume);
+                        }
+                        Some(Action::Details) => self.state.toggle_details(),
+                        Some(Action::Preview) => self.state.open_preview(),
+                        Some(Action::Search) => self.state.start_search(),
+                        Some(Action::NextMatch) => self.state.next_match(true),
+                        Some(Action::PreviousMatch) => self.state.next_match(false),
+                        Some(Action::Failures) => self.state.toggle_screen(),
+                        Some(Action::Albums) => self.state.toggle_albums(),
+                        Some(Action::History) => self.state.toggle_history(|| {
+                            crate::history::load(&crate::paths::get_history_path()?)
+                        }),
+                        Some(Action::Up) if self.state.screen == Screen::History => {
+                            self.state.select_run(true)
+                        }
+                        Some(Action::Down) if self.state.screen == Screen::History => {
+                            self.state.select_run(false)
+                        }
+                        Some(Action::Expand | Action::Retry)
+                            if self.state.screen == Screen::History =>
+                        {
+                            self.state.open_run(true)
+                        }
+                        Some(Action::Collapse) if self.state.screen == Screen::History => {
+                            self.state.open_run(false)
+                        }
+                        Some(Action::Up) if self.state.screen == Screen::Albums => {
+                            self.state.select_album_row(true)
+                        }
+                        Some(Action::Down) if self.state.screen == Screen::Albums => {
+                            self.state.select_album_row(false)
+                        }
+                        Some(Action::Expand) if self.state.screen == Screen::Albums => {
+                            self.state.expand_album(true)
+                        }
+                        Some(Action::Collapse) if self.state.screen == Screen::Albums => {
+                            self.state.expand_album(false)
+                        }
+                        // The failures screen has its own selection, marks and retries
+                        Some(Action::Up) if self.state.screen == Screen::Failures => {
+                            self.state.select_failure(true)
+                        }
+                        Some(Action::Down) if self.state.screen == Screen::Failures => {
+                            self.state.select_failure(false)
+                        }
+                        Some(Action::Mark) if self.state.screen == Screen::Failures => {
+                            self.state.toggle_mark()
+                        }
+                        Some(Action::Export) if self.state.screen == Screen::Failures => {
+                            if let Ok(path) = crate::paths::get_failures_list_path() {
+                                self.state.export_failures(&path);
                             }
                         }
-                        KeyCode::Char('r') => {
-                            if self.state.paused {
-                                self.state.paused = false;
-                                let _ = self.ui_tx.send(UiMessage::Resume);
+                        Some(Action::Retry)
+                            if self.state.screen == Screen::Failures
+                                && self.state.status != state::Status::Complete =>
+                        {
+                            for path in self.state.take_failure_retries() {
+                                let _ = self.ui_tx.send(UiMessage::Retry { path });
+                            }
+                        }
+                        Some(Action::Copy) => self.state.copy_paths(clipboard::copy),
+                        Some(Action::ManualSearch) => self.state.open_manual_search(),
+                        Some(Action::LogLevel) => {
+                            if let Some(set) = &self.set_log_level {
+                                self.state.toggle_log_level(set);
+                            }
+                        }
+                        Some(Action::Up) => self.state.select(true),
+                        Some(Action::Down) => self.state.select(false),
+                        Some(Action::PageUp) => self.state.scroll_up(self.state.log_height.max(1)),
+                        Some(Action::PageDown) => {
+                            self.state.scroll_down(self.state.log_height.max(1))
+                        }
+                        Some(Action::Top) => self.state.scroll_to_top(),
+                        Some(Action::Bottom) => self.state.scroll_to_bottom(),
+                        Some(Action::Filter(filter)) => self.state.set_log_filter(filter),
+                        Some(Action::Retry) if self.state.status != state::Status::Complete => {
+                            if let Some(path) = self.state.take_retry() {
+                                let _ = self.ui_tx.send(UiMessage::Retry { path });
+                            }
+                        }
+                        Some(Action::AlbumFirst)
+                            if self.state.status != state::Status::Complete =>
+                        {
+                            if let Some(dir) = self.state.take_album() {
+                                let _ = self.ui_tx.send(UiMessage::Prioritize { dir });
                             }
                         }
                         _ => {}
-                    }
+                    },
+                    Event::Mouse(MouseEvent { kind, .. }) => match kind {
+                        MouseEventKind::ScrollUp => self.state.scroll_up(WHEEL_LINES),
+                        MouseEventKind::ScrollDown => self.state.scroll_down(WHEEL_LINES),
+                        _ => {}
+                    },
+                    _ => {}
                 }
             }
 
@@ -93,15 +455,55 @@ impl App {
                 self.state.update(msg);
             }
 
+            // Refresh status.json for external dashboards once a second
+            if status_written.is_none_or(|at| at.elapsed() >= STATUS_INTERVAL) {
+                self.write_status(status_path, false, status_failed);
+                status_written = Some(Instant::now());
+            }
+
             // Exit if worker is done and user hasn't quit
             if self.state.status == state::Status::Complete && self.state.should_quit {
                 break;
             }
-
-            // 60fps target
-            tokio::time::sleep(std::time::Duration::from_millis(16)).await;
         }
 
         Ok(())
     }
+
+    /// Tell the worker to stop, saving its session first if asked to
+    fn quit(&mut self, save: bool) {
+        self.state.should_quit = true;
+        let _ = self.ui_tx.send(UiMessage::Quit { save });
+    }
+
+    /// Write the live status file, warning only on the first failure
+    fn write_status(&self, path: Option<&Path>, stopped: bool, failed: &mut bool) {
+        let Some(path) = path else { return };
+        if let Err(e) = LiveStatus::from_state(&self.state, stopped).write(path) {
+            if !*failed {
+                tracing::warn!("Failed to write live status: {:#}", e);
+                *failed = true;
+            }
+        }
+    }
+}
+
+/// Read terminal events on a thread of their own, as reading blocks, until told to stop
+fn read_events(
+    tx: mpsc::UnboundedSender<io::Result<Event>>,
+    stop: Arc<AtomicBool>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            let event = match event::poll(INPUT_POLL) {
+                Ok(true) => event::read(),
+                Ok(false) => continue,
+                Err(e) => Err(e),
+            };
+            let failed = event.is_err();
+            if tx.send(event).is_err() || failed {
+                break;
+            }
+        }
+    })
 }
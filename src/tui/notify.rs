@@ -0,0 +1,25 @@
+use serde::Deserialize;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/// How long the screen stays inverted for a flash
+pub const FLASH: Duration = Duration::from_millis(150);
+
+/// How the TUI gets attention when the run completes or lyrics wait to be picked, for
+/// runs left in a background tmux pane or terminal tab
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Notify {
+    #[default]
+    Off,
+    /// Ring the terminal bell, which tmux and most terminals can show on the pane or tab
+    Bell,
+    /// Invert the screen for a moment
+    Flash,
+}
+
+/// Ring the terminal bell
+pub fn bell(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(b"\x07")?;
+    out.flush()
+}
@@ -1,7 +1,28 @@
-use crate::messages::WorkerMessage;
-use std::collections::VecDeque;
+use crate::api::{Candidate, SearchAttempt};
+use crate::history::RunRecord;
+use crate::messages::{self, ErrorKind, WorkerMessage, WorkerStage};
+use crate::scanner::metadata::{TagOverride, Track};
+use crate::session::StatusType;
+use crate::timing::TimingSummary;
+use crate::tui::{
+    glyphs::Glyphs,
+    keys::Keymap,
+    notify::{self, Notify},
+    theme::Theme,
+};
+use regex::{Regex, RegexBuilder};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
-const MAX_LOG_LINES: usize = 100;
+/// Log entries kept, enough to review a run's failures with a filter
+const MAX_LOG_LINES: usize = 2000;
+/// Time in one stage (other than idle) after which a worker is considered stuck
+const STUCK_AFTER: Duration = Duration::from_secs(120);
+/// How far the lyrics preview jumps with the arrow keys
+const PREVIEW_SEEK: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Clone)]
 pub struct AppState {
@@ -11,12 +32,508 @@ pub struct AppState {
     pub downloaded: usize,
     pub cached: usize,
     pub skipped: usize,
+    /// Files without readable tags, to be tagged by hand
+    pub untagged: usize,
+    /// Files that already had lyrics (also counted in `skipped`)
+    pub existing: usize,
+    /// Files searched without finding lyrics
+    pub not_found: usize,
+    /// What went wrong with each file that failed with an error
+    pub errors: HashMap<PathBuf, ErrorKind>,
+    /// Whether the scan has finished, so `total_files` is final
+    pub scan_complete: bool,
+    /// Files finished per minute, as last reported by the workers
+    pub files_per_min: Option<f64>,
+    /// Expected time per remaining file, as last reported by the workers
+    pub pace: Option<Duration>,
+    /// Requests sent to LRCLIB, as last reported by the workers
+    pub requests: usize,
+    /// When the TUI started, for the elapsed time
+    pub started: Instant,
+    /// When the run completed, stopping the elapsed time
+    pub finished: Option<Instant>,
+    /// Time spent in each stage, reported at the end of a run with `--timing`
+    pub timings: Option<TimingSummary>,
     pub current_track: Option<String>,
-    pub logs: VecDeque<String>,
+    /// File of the track being processed most recently
+    pub current_path: Option<PathBuf>,
+    /// Tags and search attempts of the tracks in the log, for the details pane
+    pub details: HashMap<PathBuf, TrackDetails>,
+    /// Whether the details pane is open, toggled with `d`
+    pub show_details: bool,
+    /// Synced lyrics of the selected entry playing, opened with `l`
+    pub preview: Option<LyricsPreview>,
+    /// Tracks with several plausible search results, waiting for one to be picked
+    /// The oldest is shown.
+    pub picks: VecDeque<LyricsPick>,
+    /// Choices offered on quitting while work is pending
+    pub quit_prompt: Option<QuitPrompt>,
+    /// The connection to LRCLIB while it is lost
+    pub outage: Option<Outage>,
+    /// Form to search for a not found file under other tags
+    pub manual_search: Option<ManualSearch>,
+    /// When lookups resume, while they are paused after repeated connection failures
+    pub connection_lost: Option<Instant>,
+    /// Latest heartbeat from each worker
+    pub workers: BTreeMap<usize, WorkerStatus>,
+    /// Queue depth and rate limit, as last reported by the workers
+    pub pool: Option<PoolStatus>,
+    pub logs: VecDeque<LogEntry>,
+    /// Files that weren't found or failed with an error, kept for the failures screen
+    pub failures: BTreeMap<PathBuf, Failure>,
+    /// Failure highlighted on the failures screen
+    pub failure_cursor: usize,
+    /// Failures marked for a retry or export
+    pub marked: BTreeSet<PathBuf>,
+    /// Screen shown below the progress, switched with Tab
+    pub screen: Screen,
+    /// Row highlighted in the album view
+    pub album_cursor: usize,
+    /// Albums opened in the album view to show their entries
+    pub expanded: HashSet<PathBuf>,
+    /// Past runs, newest first, read when the history screen is opened
+    pub history: Vec<RunRecord>,
+    /// Run highlighted on the history screen
+    pub history_cursor: usize,
+    /// Whether the highlighted run's report is shown instead of the list
+    pub history_report: bool,
+    /// Log entry selected for a retry or to move its album to the front
+    pub selected: Option<usize>,
     pub status: Status,
     pub should_quit: bool,
     pub paused: bool,
+    /// Log lines scrolled back from the newest; 0 follows new entries
     pub scroll_offset: usize,
+    /// Outcome the log is limited to, picked with the number keys
+    pub log_filter: Option<LogFilter>,
+    /// Text searched for in the log, started with `/`
+    pub search: Option<LogSearch>,
+    /// Lines the log pane shows, updated from the terminal size
+    pub log_height: usize,
+    /// Colors to draw in
+    pub theme: Theme,
+    /// Symbols to draw with
+    pub glyphs: Glyphs,
+    /// Keys of the actions, for the footer's hints
+    pub keys: Keymap,
+    /// Whether the log file takes debug messages, rather than info and above
+    pub debug_log: bool,
+    /// How to get attention when the run completes or lyrics wait to be picked
+    pub notify: Notify,
+    /// Whether to ring the bell after the next redraw
+    pub bell: bool,
+    /// When a flash of the screen ends, while it's inverted
+    pub flash_until: Option<Instant>,
+}
+
+/// Screens of the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Screen {
+    /// The log of every file
+    #[default]
+    Log,
+    /// Files that weren't found or failed, with their full paths
+    Failures,
+    /// The log grouped by album (directory)
+    Albums,
+    /// Past runs, and the report of one
+    History,
+}
+
+/// The log entries about one album (directory) and how many of its files have lyrics
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlbumGroup {
+    pub dir: PathBuf,
+    /// Indexes of the entries, oldest first
+    pub entries: Vec<usize>,
+    /// Files whose latest outcome left them with lyrics
+    pub with_lyrics: usize,
+    /// Files with an outcome
+    pub total: usize,
+}
+
+/// A line of the album view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlbumRow {
+    /// Index into `AppState::albums`
+    Album(usize),
+    /// Index of a log entry
+    Entry(usize),
+}
+
+/// Why a file has no lyrics yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct Failure {
+    /// `NotFound` or `Error`
+    pub status: StatusType,
+    pub reason: String,
+}
+
+/// Outcomes the log can be limited to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFilter {
+    Downloaded,
+    NotFound,
+    Error,
+    Cached,
+    /// Existing, embedded, excluded, spoken word and untagged files
+    Skipped,
+}
+
+impl LogFilter {
+    /// In number key order, from `1`
+    pub const ALL: [LogFilter; 5] = [
+        LogFilter::Downloaded,
+        LogFilter::NotFound,
+        LogFilter::Error,
+        LogFilter::Cached,
+        LogFilter::Skipped,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LogFilter::Downloaded => "downloaded",
+            LogFilter::NotFound => "not found",
+            LogFilter::Error => "errors",
+            LogFilter::Cached => "cached",
+            LogFilter::Skipped => "skipped",
+        }
+    }
+
+    fn matches(self, status: &StatusType) -> bool {
+        match self {
+            LogFilter::Downloaded => *status == StatusType::Downloaded,
+            LogFilter::NotFound => *status == StatusType::NotFound,
+            LogFilter::Error => *status == StatusType::Error,
+            LogFilter::Cached => *status == StatusType::Cached,
+            LogFilter::Skipped => matches!(
+                status,
+                StatusType::Existing
+                    | StatusType::EmbeddedExists
+                    | StatusType::Excluded
+                    | StatusType::SpokenWord
+                    | StatusType::NoMetadata
+            ),
+        }
+    }
+}
+
+/// Text searched for in the log
+#[derive(Debug, Clone)]
+pub struct LogSearch {
+    pub query: String,
+    /// Whether the query is still being typed
+    pub editing: bool,
+    /// The query as a case-insensitive regex, or as plain text when it isn't a valid one
+    pattern: Option<Regex>,
+    /// Log entry of the match last jumped to
+    pub current: Option<usize>,
+}
+
+impl Default for LogSearch {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            pattern: None,
+            current: None,
+        }
+    }
+}
+
+impl LogSearch {
+    fn compile(&mut self) {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .ok()
+        };
+        self.pattern = if self.query.is_empty() {
+            None
+        } else {
+            build(&self.query).or_else(|| build(&regex::escape(&self.query)))
+        };
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(text))
+    }
+}
+
+/// Edits to the search prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchInput {
+    Char(char),
+    Backspace,
+}
+
+/// A not found file's tags being edited to search for it again
+#[derive(Debug, Clone)]
+pub struct ManualSearch {
+    pub path: PathBuf,
+    /// As typed, in `ManualSearch::LABELS` order
+    pub fields: [String; 4],
+    /// Field being edited
+    pub focus: usize,
+    /// Why the search can't be sent yet
+    pub error: Option<String>,
+}
+
+impl ManualSearch {
+    pub const LABELS: [&'static str; 4] = ["Artist", "Title", "Album", "Duration"];
+
+    /// The fields to search for, or what is wrong with them
+    fn tags(&self) -> Result<TagOverride, &'static str> {
+        let [artist, title, album, duration] =
+            self.fields.clone().map(|field| field.trim().to_string());
+        if artist.is_empty() || title.is_empty() {
+            return Err("Artist and title are needed");
+        }
+        let duration_secs = match duration.split_once(':') {
+            _ if duration.is_empty() => None,
+            Some((minutes, seconds)) => minutes
+                .parse::<u64>()
+                .ok()
+                .zip(seconds.parse::<u64>().ok().filter(|seconds| *seconds < 60))
+                .map(|(minutes, seconds)| minutes * 60 + seconds),
+            None => duration.parse().ok(),
+        };
+        if !duration.is_empty() && duration_secs.is_none() {
+            return Err("Duration is m:ss or seconds");
+        }
+        Ok(TagOverride {
+            artist,
+            title,
+            album,
+            duration_secs,
+        })
+    }
+}
+
+/// A line of the activity log
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub text: String,
+    /// File the entry is about
+    pub path: Option<PathBuf>,
+    /// Outcome the entry reports, for filtering the log
+    pub status: Option<StatusType>,
+    /// Whether the file failed and can be queued again from this entry
+    pub retryable: bool,
+}
+
+/// What is known about a track, for explaining why it was or wasn't matched
+#[derive(Debug, Clone)]
+pub struct TrackDetails {
+    /// Tags as read from the file
+    pub track: Track,
+    /// Queries tried for it in order, once it has been searched for
+    pub attempts: Vec<SearchAttempt>,
+}
+
+/// A sidecar's lyrics played back line by line, to judge how well they are synced
+#[derive(Debug, Clone)]
+pub struct LyricsPreview {
+    /// The `.lrc` file
+    pub path: PathBuf,
+    /// Start of each line, in playback order
+    pub lines: Vec<(Duration, String)>,
+    /// Position when playback last started, paused or jumped
+    position: Duration,
+    /// When playback last started, or None while paused
+    playing_since: Option<Instant>,
+}
+
+impl LyricsPreview {
+    /// Start playing synced lyrics from the beginning; None for plain lyrics
+    pub fn new(path: PathBuf, lyrics: &str) -> Option<Self> {
+        let lines: Vec<(Duration, String)> = crate::lrc::timed_lines(lyrics)
+            .into_iter()
+            .map(|(millis, text)| (Duration::from_millis(millis), text.to_string()))
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        Some(Self {
+            path,
+            lines,
+            position: Duration::ZERO,
+            playing_since: Some(Instant::now()),
+        })
+    }
+
+    /// How far playback has got
+    pub fn position(&self) -> Duration {
+        self.position
+            + self
+                .playing_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+
+    /// Start of the last line, as the length of the lyrics
+    pub fn length(&self) -> Duration {
+        self.lines
+            .last()
+            .map_or(Duration::ZERO, |(start, _)| *start)
+    }
+
+    /// The line being sung, None before the first one starts
+    pub fn current_line(&self) -> Option<usize> {
+        let position = self.position();
+        self.lines.iter().rposition(|(start, _)| *start <= position)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.playing_since.is_none()
+    }
+
+    pub fn toggle_pause(&mut self) {
+        match self.playing_since.take() {
+            Some(since) => self.position += since.elapsed(),
+            None => self.playing_since = Some(Instant::now()),
+        }
+    }
+
+    /// Jump a few seconds forwards or back
+    pub fn seek(&mut self, forward: bool) {
+        let position = self.position();
+        self.position = match forward {
+            true => (position + PREVIEW_SEEK).min(self.length()),
+            false => position.saturating_sub(PREVIEW_SEEK),
+        };
+        if self.playing_since.is_some() {
+            self.playing_since = Some(Instant::now());
+        }
+    }
+}
+
+/// Search results offered for a track, to pick the lyrics to write
+#[derive(Debug, Clone)]
+pub struct LyricsPick {
+    pub path: PathBuf,
+    /// Best first
+    pub candidates: Vec<Candidate>,
+    /// Candidate highlighted in the picker
+    pub selected: usize,
+}
+
+/// Answers to the prompt shown on quitting while work is pending
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuitChoice {
+    /// Save the remaining files as a session to resume, then quit
+    Save,
+    /// Quit, leaving any session from an earlier pause as it is
+    Discard,
+    Cancel,
+}
+
+impl QuitChoice {
+    pub const ALL: [QuitChoice; 3] = [QuitChoice::Save, QuitChoice::Discard, QuitChoice::Cancel];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            QuitChoice::Save => "Save session and quit",
+            QuitChoice::Discard => "Quit without saving",
+            QuitChoice::Cancel => "Cancel",
+        }
+    }
+
+    /// The key that answers with this choice
+    pub fn key(self) -> char {
+        match self {
+            QuitChoice::Save => 's',
+            QuitChoice::Discard => 'd',
+            QuitChoice::Cancel => 'c',
+        }
+    }
+}
+
+/// The quit prompt's choices and the one highlighted
+#[derive(Debug, Clone)]
+pub struct QuitPrompt {
+    pub choices: Vec<QuitChoice>,
+    pub selected: usize,
+}
+
+/// Answers to the modal shown while lookups are paused after connection failures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutageChoice {
+    /// Try a lookup now instead of waiting out the backoff
+    RetryNow,
+    /// Close the modal until the connection is back
+    Wait,
+    /// Quit, saving the session when the scan is done
+    Quit,
+}
+
+impl OutageChoice {
+    pub const ALL: [OutageChoice; 3] = [
+        OutageChoice::RetryNow,
+        OutageChoice::Wait,
+        OutageChoice::Quit,
+    ];
+
+    pub fn label(self, scan_complete: bool) -> &'static str {
+        match self {
+            OutageChoice::RetryNow => "Retry now",
+            OutageChoice::Wait => "Keep waiting",
+            OutageChoice::Quit if scan_complete => "Save session and quit",
+            OutageChoice::Quit => "Quit",
+        }
+    }
+
+    /// The key that answers with this choice
+    pub fn key(self) -> char {
+        match self {
+            OutageChoice::RetryNow => 'r',
+            OutageChoice::Wait => 'w',
+            OutageChoice::Quit => 'q',
+        }
+    }
+}
+
+/// Lookups paused after connection failures, until one succeeds again
+#[derive(Debug, Clone)]
+pub struct Outage {
+    /// The failure that paused lookups most recently
+    pub error: String,
+    /// Times lookups were paused since the connection was lost
+    pub trips: usize,
+    /// Whether the modal asking what to do is shown
+    pub open: bool,
+    /// Keep the modal closed when lookups are paused again
+    pub waiting: bool,
+    /// Choice highlighted in the modal
+    pub selected: usize,
+}
+
+/// What a worker reported in its last heartbeat
+#[derive(Debug, Clone)]
+pub struct WorkerStatus {
+    pub path: Option<PathBuf>,
+    pub stage: WorkerStage,
+    pub in_stage: Duration,
+}
+
+/// Queue depth and the shared rate limit's state, from the last report
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoolStatus {
+    pub queued: usize,
+    pub requests_per_sec: f64,
+    /// Requests that can be sent right away, if known
+    pub tokens: Option<f64>,
+    pub rate_limit: u32,
+}
+
+impl WorkerStatus {
+    /// Whether the worker has been in one stage for too long and should be restarted
+    pub fn is_stuck(&self) -> bool {
+        !matches!(self.stage, WorkerStage::Idle | WorkerStage::Picking)
+            && self.in_stage >= STUCK_AFTER
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -38,12 +555,54 @@ impl AppState {
             downloaded: 0,
             cached: 0,
             skipped: 0,
+            untagged: 0,
+            existing: 0,
+            not_found: 0,
+            errors: HashMap::new(),
+            scan_complete: false,
+            files_per_min: None,
+            pace: None,
+            requests: 0,
+            started: Instant::now(),
+            finished: None,
+            timings: None,
             current_track: None,
+            current_path: None,
+            details: HashMap::new(),
+            show_details: false,
+            preview: None,
+            failures: BTreeMap::new(),
+            failure_cursor: 0,
+            marked: BTreeSet::new(),
+            album_cursor: 0,
+            expanded: HashSet::new(),
+            history: Vec::new(),
+            history_cursor: 0,
+            history_report: false,
+            screen: Screen::Log,
+            quit_prompt: None,
+            outage: None,
+            manual_search: None,
+            picks: VecDeque::new(),
+            connection_lost: None,
+            workers: BTreeMap::new(),
+            pool: None,
             logs: VecDeque::new(),
+            selected: None,
             status: Status::Idle,
             should_quit: false,
             paused: false,
             scroll_offset: 0,
+            log_filter: None,
+            search: None,
+            log_height: 0,
+            theme: Theme::default(),
+            glyphs: Glyphs::default(),
+            keys: Keymap::default(),
+            debug_log: true,
+            notify: Notify::Off,
+            bell: false,
+            flash_until: None,
         }
     }
 
@@ -58,104 +617,1277 @@ impl AppState {
                 downloaded,
                 cached,
                 skipped,
+                untagged,
                 processed,
             } => {
                 // Restore counts from session
                 self.downloaded = downloaded;
                 self.cached = cached;
                 self.skipped = skipped;
+                self.untagged = untagged;
                 self.processed = processed;
                 self.found = downloaded; // found = downloaded
             }
             WorkerMessage::ScanProgress { files_found } => {
-                self.status = Status::Scanning;
-                self.add_log(format!("Scanning... {} files found", files_found));
+                // Streamed while the walk runs, possibly alongside processing
+                self.total_files = files_found;
+                if matches!(self.status, Status::Idle | Status::Scanning) {
+                    self.status = Status::Scanning;
+                }
+                let line = format!("Scanning... {} files found", files_found);
+                match self.logs.back_mut() {
+                    Some(last) if last.text.starts_with("Scanning... ") => last.text = line,
+                    _ => self.add_log(line),
+                }
             }
             WorkerMessage::ScanStarted { total_files } => {
                 self.total_files = total_files;
+                self.scan_complete = true;
                 self.status = Status::Processing;
                 self.add_log(format!("Scan complete: {} files to process", total_files));
             }
             WorkerMessage::TrackProcessing { track } => {
                 self.current_track = Some(format!("{} - {}", track.artist, track.title));
+                self.current_path = Some(track.path.clone());
+                self.details.insert(
+                    track.path.clone(),
+                    TrackDetails {
+                        track,
+                        attempts: Vec::new(),
+                    },
+                );
                 self.status = Status::Processing;
             }
+            WorkerMessage::SearchAttempts { path, attempts } => {
+                if let Some(details) = self.details.get_mut(&path) {
+                    details.attempts = attempts;
+                }
+            }
+            WorkerMessage::PickLyrics { path, candidates } => {
+                // Once for a batch of picks, rather than for each one
+                if self.picks.is_empty() {
+                    self.alert();
+                }
+                self.picks.push_back(LyricsPick {
+                    path,
+                    candidates,
+                    selected: 0,
+                });
+            }
             WorkerMessage::LyricsFound { path } => {
                 self.found += 1;
                 self.processed += 1;
                 self.downloaded += 1;
-                let filename = std::path::Path::new(&path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or(&path);
-                self.add_log(format!("[✓] {}", filename));
+                self.add_file_log(
+                    format!(
+                        "{} {}",
+                        self.glyphs.status(&StatusType::Downloaded),
+                        display_name(&path)
+                    ),
+                    path,
+                    Some(StatusType::Downloaded),
+                );
             }
             WorkerMessage::LyricsNotFound { path } => {
                 self.processed += 1;
-                let filename = std::path::Path::new(&path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or(&path);
-                self.add_log(format!("[✗] {}", filename));
+                self.not_found += 1;
+                let text = format!(
+                    "{} {}",
+                    self.glyphs.status(&StatusType::NotFound),
+                    display_name(&path)
+                );
+                self.add_retryable_log(text, path.clone(), StatusType::NotFound);
+                self.failures.insert(
+                    path,
+                    Failure {
+                        status: StatusType::NotFound,
+                        reason: "no lyrics found".to_string(),
+                    },
+                );
             }
             WorkerMessage::CacheHit { path } => {
                 self.processed += 1;
                 self.cached += 1;
-                let filename = std::path::Path::new(&path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or(&path);
-                self.add_log(format!("[~] {}", filename));
+                self.add_file_log(
+                    format!(
+                        "{} {}",
+                        self.glyphs.status(&StatusType::Cached),
+                        display_name(&path)
+                    ),
+                    path,
+                    Some(StatusType::Cached),
+                );
             }
             WorkerMessage::AlreadyHasLrc { path } => {
                 self.skipped += 1;
-                let filename = std::path::Path::new(&path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or(&path);
-                self.add_log(format!("[○] {}", filename));
+                self.existing += 1;
+                self.add_file_log(
+                    format!(
+                        "{} {}",
+                        self.glyphs.status(&StatusType::Existing),
+                        display_name(&path)
+                    ),
+                    path,
+                    Some(StatusType::Existing),
+                );
+            }
+            WorkerMessage::EmbeddedLyrics { path } => {
+                self.processed += 1;
+                self.skipped += 1;
+                self.add_file_log(
+                    format!(
+                        "{} {}",
+                        self.glyphs.status(&StatusType::EmbeddedExists),
+                        display_name(&path)
+                    ),
+                    path,
+                    Some(StatusType::EmbeddedExists),
+                );
+            }
+            WorkerMessage::Error { path, kind, error } => {
+                self.processed += 1;
+                self.errors.insert(path.clone(), kind);
+                let text = format!(
+                    "{} {}: {}",
+                    self.glyphs.status(&StatusType::Error),
+                    display_name(&path),
+                    error
+                );
+                self.add_retryable_log(text, path.clone(), StatusType::Error);
+                self.failures.insert(
+                    path,
+                    Failure {
+                        status: StatusType::Error,
+                        reason: error,
+                    },
+                );
+            }
+            WorkerMessage::Excluded { path, reason } => {
+                self.processed += 1;
+                self.skipped += 1;
+                self.add_file_log(
+                    format!(
+                        "{} {}: {}",
+                        self.glyphs.status(&StatusType::Excluded),
+                        display_name(&path),
+                        reason
+                    ),
+                    path,
+                    Some(StatusType::Excluded),
+                );
+            }
+            WorkerMessage::SpokenWord { path, reason } => {
+                self.processed += 1;
+                self.skipped += 1;
+                self.add_file_log(
+                    format!(
+                        "{} {}: {}",
+                        self.glyphs.status(&StatusType::SpokenWord),
+                        display_name(&path),
+                        reason
+                    ),
+                    path,
+                    Some(StatusType::SpokenWord),
+                );
             }
-            WorkerMessage::Error { path, error } => {
+            WorkerMessage::NoMetadata { path, error } => {
                 self.processed += 1;
-                let filename = std::path::Path::new(&path)
-                    .file_name()
-                    .and_then(|n| n.to_str())
-                    .unwrap_or(&path);
-                self.add_log(format!("[!] {}: {}", filename, error));
+                self.untagged += 1;
+                self.add_file_log(
+                    format!(
+                        "{} {}: {}",
+                        self.glyphs.status(&StatusType::NoMetadata),
+                        display_name(&path),
+                        error
+                    ),
+                    path,
+                    Some(StatusType::NoMetadata),
+                );
+            }
+            WorkerMessage::Warning { path, message } => {
+                self.add_file_log(
+                    format!("[*] {}: {}", display_name(&path), message),
+                    path,
+                    None,
+                );
+            }
+            WorkerMessage::AlbumStarted { dir } => {
+                self.add_log(format!("[album] {}", display_name(&dir)));
+            }
+            WorkerMessage::AlbumRetry { dir, tracks } => {
+                // The retried tracks were counted when they failed
+                self.processed = self.processed.saturating_sub(tracks);
+                self.errors
+                    .retain(|path, _| path.parent() != Some(dir.as_path()));
+                self.add_log(format!(
+                    "[album] {}: retrying {} failed tracks",
+                    display_name(&dir),
+                    tracks
+                ));
+            }
+            WorkerMessage::AlbumComplete {
+                dir,
+                with_lyrics,
+                total,
+            } => {
+                self.add_log(format!(
+                    "[album] {}: {}/{} tracks with lyrics",
+                    display_name(&dir),
+                    with_lyrics,
+                    total
+                ));
+            }
+            WorkerMessage::ConnectionLost { error, retry_in } => {
+                self.connection_lost = Some(Instant::now() + retry_in);
+                // Logged once per outage, the modal follows the failed attempts after it
+                match &mut self.outage {
+                    Some(outage) => {
+                        outage.error = error;
+                        outage.trips += 1;
+                        outage.open = !outage.waiting;
+                    }
+                    None => {
+                        self.add_log(format!("[!] Connection lost ({}): lookups paused", error));
+                        self.outage = Some(Outage {
+                            error,
+                            trips: 1,
+                            open: true,
+                            waiting: false,
+                            selected: 0,
+                        });
+                    }
+                }
+            }
+            WorkerMessage::ConnectionRestored => {
+                self.connection_lost = None;
+                self.outage = None;
+                self.add_log("Connection restored, resuming lookups".to_string());
+            }
+            WorkerMessage::ActiveHours { active, window } => {
+                self.paused = !active;
+                if active {
+                    self.add_log(format!("Active hours {} started, resuming", window));
+                } else {
+                    self.add_log(format!(
+                        "Outside active hours, paused until {}",
+                        window.start_time()
+                    ));
+                }
+            }
+            WorkerMessage::Throughput {
+                files_per_min,
+                pace,
+                requests,
+            } => {
+                self.files_per_min = Some(files_per_min);
+                self.pace = Some(pace);
+                self.requests = requests;
+            }
+            WorkerMessage::FileTimed { timing } => {
+                // On the file's latest entry, which has its outcome
+                if let Some(entry) = self
+                    .logs
+                    .iter_mut()
+                    .rev()
+                    .find(|entry| entry.path.as_ref() == Some(&timing.path))
+                {
+                    entry.text.push_str(&format!(" ({})", timing));
+                }
+            }
+            WorkerMessage::Timings { summary } => {
+                self.add_log("Time per stage:".to_string());
+                for line in summary.lines() {
+                    self.add_log(format!("  {}", line));
+                }
+                self.timings = Some(summary);
+            }
+            WorkerMessage::StageChanged {
+                worker,
+                path,
+                stage,
+            } => {
+                // A file just taken up is the current track, by name until its tags are read
+                if let Some(path) = path.as_ref().filter(|_| stage == WorkerStage::Reading) {
+                    self.current_track = Some(display_name(path));
+                    self.current_path = Some(path.clone());
+                }
+                self.workers.insert(
+                    worker,
+                    WorkerStatus {
+                        path,
+                        stage,
+                        in_stage: Duration::ZERO,
+                    },
+                );
+            }
+            WorkerMessage::WorkerHeartbeat {
+                worker,
+                path,
+                stage,
+                in_stage,
+            } => {
+                let status = WorkerStatus {
+                    path,
+                    stage,
+                    in_stage,
+                };
+                let was_stuck = self.workers.get(&worker).is_some_and(|previous| {
+                    previous.is_stuck()
+                        && previous.stage == status.stage
+                        && previous.path == status.path
+                });
+                if status.is_stuck() && !was_stuck {
+                    self.add_log(format!(
+                        "[!] worker {} stuck {} {} for {}s",
+                        worker + 1,
+                        status.stage.label(),
+                        status.path.as_deref().map(display_name).unwrap_or_default(),
+                        status.in_stage.as_secs()
+                    ));
+                }
+                self.workers.insert(worker, status);
+            }
+            WorkerMessage::PoolStatus {
+                queued,
+                requests_per_sec,
+                tokens,
+                rate_limit,
+            } => {
+                self.pool = Some(PoolStatus {
+                    queued,
+                    requests_per_sec,
+                    tokens,
+                    rate_limit,
+                });
             }
             WorkerMessage::LogRestore { filename, status } => {
                 // Restore log entry from session without updating counts
-                use crate::session::StatusType;
-                let log_msg = match status {
-                    StatusType::Downloaded => format!("[✓] {}", filename),
-                    StatusType::Cached => format!("[~] {}", filename),
-                    StatusType::Existing => format!("[○] {}", filename),
-                    StatusType::NotFound => format!("[✗] {}", filename),
-                    StatusType::Error => format!("[!] {}", filename),
-                };
-                self.add_log(log_msg);
+                let log_msg = format!("{} {}", self.glyphs.status(&status), filename);
+                self.push_log(LogEntry {
+                    text: log_msg,
+                    path: None,
+                    status: Some(status),
+                    retryable: false,
+                });
             }
 
             WorkerMessage::ScanComplete { processed, found } => {
                 self.processed = processed;
                 self.found = found;
                 self.status = Status::Complete;
+                self.finished = Some(Instant::now());
+                self.alert();
                 self.current_track = None;
+                self.current_path = None;
+                self.selected = None;
                 self.add_log(format!(
                     "Scan complete: {} lyrics downloaded, {} files total",
                     found, processed
                 ));
+                let errors = self.error_counts();
+                if !errors.is_empty() {
+                    self.add_log(format!(
+                        "Errors: {}",
+                        messages::format_error_counts(&errors)
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Files that failed with an error, counted by kind
+    pub fn error_counts(&self) -> Vec<(ErrorKind, usize)> {
+        messages::error_counts(self.errors.values().copied())
+    }
+
+    /// Workers that have been in one stage for too long
+    pub fn stuck_workers(&self) -> Vec<usize> {
+        self.workers
+            .iter()
+            .filter(|(_, status)| status.is_stuck())
+            .map(|(worker, _)| *worker)
+            .collect()
+    }
+
+    /// Move the selection to the previous (or next) entry about a file
+    /// The log scrolls to keep the selected entry in view.
+    pub fn select(&mut self, backwards: bool) {
+        let shown = self.shown_logs();
+        let has_path = |position: &usize| self.logs[shown[*position]].path.is_some();
+        let current = self
+            .selected
+            .and_then(|index| shown.iter().position(|shown| *shown == index));
+        let visible = self.visible_range(shown.len());
+        let next = match (current, backwards) {
+            (Some(current), true) => (0..current).rev().find(has_path),
+            (Some(current), false) => (current + 1..shown.len()).find(has_path),
+            // Start from the newest entry in view
+            (None, _) => visible.clone().rev().find(has_path),
+        };
+        if let Some(position) = next {
+            self.selected = Some(shown[position]);
+            self.scroll_into_view(&shown, position);
+        }
+    }
+
+    /// Scroll just far enough for a shown entry to be in view
+    fn scroll_into_view(&mut self, shown: &[usize], position: usize) {
+        let visible = self.visible_range(shown.len());
+        if position < visible.start {
+            self.scroll_offset += visible.start - position;
+        } else if position >= visible.end {
+            self.scroll_offset -= position + 1 - visible.end;
+        }
+    }
+
+    /// Open the search prompt, replacing any earlier search
+    pub fn start_search(&mut self) {
+        self.search = Some(LogSearch::default());
+    }
+
+    /// Type into the search prompt; matches are highlighted as the query changes
+    pub fn search_input(&mut self, input: SearchInput) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        match input {
+            SearchInput::Char(c) => search.query.push(c),
+            SearchInput::Backspace => {
+                search.query.pop();
+            }
+        }
+        search.compile();
+    }
+
+    /// Close the prompt and jump to the newest match, or drop an empty search
+    pub fn finish_search(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if search.query.is_empty() {
+            self.search = None;
+            return;
+        }
+        search.editing = false;
+        search.current = None;
+        self.next_match(true);
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+    }
+
+    /// Jump to the next older (or newer) match among the shown entries, wrapping around
+    /// The first jump goes to the newest match.
+    pub fn next_match(&mut self, older: bool) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let shown = self.shown_logs();
+        let matches: Vec<usize> = (0..shown.len())
+            .filter(|position| search.is_match(&self.logs[shown[*position]].text))
+            .collect();
+        let current = search
+            .current
+            .and_then(|index| shown.iter().position(|shown| *shown == index));
+        let next = match (current, older) {
+            (None, _) => matches.last(),
+            (Some(current), true) => matches
+                .iter()
+                .rev()
+                .find(|position| **position < current)
+                .or(matches.last()),
+            (Some(current), false) => matches
+                .iter()
+                .find(|position| **position > current)
+                .or(matches.first()),
+        };
+        if let Some(&position) = next {
+            if let Some(search) = &mut self.search {
+                search.current = Some(shown[position]);
+            }
+            self.scroll_into_view(&shown, position);
+        }
+    }
+
+    /// Shown entries matching the search, and which of them is the current match (from 1)
+    pub fn search_position(&self) -> Option<(usize, Option<usize>)> {
+        let search = self.search.as_ref()?;
+        let matches: Vec<usize> = self
+            .shown_logs()
+            .into_iter()
+            .filter(|index| search.is_match(&self.logs[*index].text))
+            .collect();
+        let current = search
+            .current
+            .and_then(|index| matches.iter().position(|found| *found == index));
+        Some((matches.len(), current.map(|position| position + 1)))
+    }
+
+    /// Indexes of the log entries the filter lets through, oldest first
+    fn shown_logs(&self) -> Vec<usize> {
+        (0..self.logs.len())
+            .filter(|index| {
+                self.log_filter.is_none_or(|filter| {
+                    self.logs[*index]
+                        .status
+                        .as_ref()
+                        .is_some_and(|status| filter.matches(status))
+                })
+            })
+            .collect()
+    }
+
+    /// Indexes of the log entries in view, oldest first
+    pub fn visible_logs(&self) -> Vec<usize> {
+        let shown = self.shown_logs();
+        let visible = self.visible_range(shown.len());
+        shown[visible].to_vec()
+    }
+
+    /// Entries the filter lets through, and how many of them are newer than the view
+    pub fn log_position(&self) -> (usize, usize) {
+        let shown = self.shown_logs().len();
+        (shown, shown - self.visible_range(shown).end)
+    }
+
+    /// Positions in view among `shown` entries
+    fn visible_range(&self, shown: usize) -> Range<usize> {
+        let end = shown
+            - self
+                .scroll_offset
+                .min(Self::max_scroll(shown, self.log_height));
+        end.saturating_sub(self.log_height.max(1))..end
+    }
+
+    /// Furthest the log can scroll back, with the oldest entry at the top
+    fn max_scroll(shown: usize, height: usize) -> usize {
+        shown.saturating_sub(height.max(1))
+    }
+
+    /// Scroll back through older log entries
+    pub fn scroll_up(&mut self, lines: usize) {
+        let max = Self::max_scroll(self.shown_logs().len(), self.log_height);
+        self.scroll_offset = (self.scroll_offset + lines).min(max);
+        self.drop_hidden_selection();
+    }
+
+    /// Scroll towards the newest log entries
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.scroll_offset = self.scroll_offset.saturating_sub(lines);
+        self.drop_hidden_selection();
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_offset = Self::max_scroll(self.shown_logs().len(), self.log_height);
+        self.drop_hidden_selection();
+    }
+
+    /// Jump to the newest entry and follow the log again
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_offset = 0;
+        self.drop_hidden_selection();
+    }
+
+    /// Show only entries with one outcome, or every entry with None
+    /// The view jumps back to the newest matching entry.
+    pub fn set_log_filter(&mut self, filter: Option<LogFilter>) {
+        self.log_filter = filter;
+        self.scroll_offset = 0;
+        self.selected = None;
+    }
+
+    /// Scrolled entries out of view can't be retried or moved forward by accident
+    fn drop_hidden_selection(&mut self) {
+        if self
+            .selected
+            .is_some_and(|index| !self.visible_logs().contains(&index))
+        {
+            self.selected = None;
+        }
+    }
+    /// Highlight the previous (or next) candidate of the pick being shown
+    pub fn select_pick(&mut self, backwards: bool) {
+        if let Some(pick) = self.picks.front_mut() {
+            pick.selected = match backwards {
+                true => pick.selected.saturating_sub(1),
+                false => (pick.selected + 1).min(pick.candidates.len().saturating_sub(1)),
+            };
+        }
+    }
+
+    /// Answer the pick being shown with a candidate, or None to skip the track
+    /// Returns the file and the answer for the worker; a candidate that doesn't exist
+    /// leaves the pick open.
+    pub fn take_pick(&mut self, choice: Option<usize>) -> Option<(PathBuf, Option<usize>)> {
+        let pick = self.picks.front()?;
+        if choice.is_some_and(|choice| choice >= pick.candidates.len()) {
+            return None;
+        }
+        let pick = self.picks.pop_front()?;
+        Some((pick.path, choice))
+    }
+
+    /// Ask what to do with the session before quitting while work is pending
+    /// Returns true when nothing is pending and the TUI can close straight away. Saving
+    /// is only offered once the scan is done, as the queue doesn't hold every file before.
+    pub fn request_quit(&mut self) -> bool {
+        if self.status == Status::Complete {
+            return true;
+        }
+        let mut choices = vec![QuitChoice::Discard, QuitChoice::Cancel];
+        if self.scan_complete {
+            choices.insert(0, QuitChoice::Save);
+        }
+        self.quit_prompt = Some(QuitPrompt {
+            choices,
+            selected: 0,
+        });
+        false
+    }
+
+    /// Move the quit prompt's highlight up or down
+    pub fn select_quit_choice(&mut self, backwards: bool) {
+        if let Some(prompt) = &mut self.quit_prompt {
+            prompt.selected = match backwards {
+                true => prompt.selected.saturating_sub(1),
+                false => (prompt.selected + 1).min(prompt.choices.len() - 1),
+            };
+        }
+    }
+
+    /// Answer the quit prompt with a choice, or the highlighted one for None
+    /// A choice the prompt doesn't offer leaves it open.
+    pub fn take_quit_choice(&mut self, choice: Option<QuitChoice>) -> Option<QuitChoice> {
+        let prompt = self.quit_prompt.as_ref()?;
+        let choice = match choice {
+            Some(choice) if !prompt.choices.contains(&choice) => return None,
+            Some(choice) => choice,
+            None => prompt.choices[prompt.selected],
+        };
+        self.quit_prompt = None;
+        Some(choice)
+    }
+
+    /// Whether the connection lost modal is shown, taking every key
+    pub fn outage_open(&self) -> bool {
+        self.outage.as_ref().is_some_and(|outage| outage.open)
+    }
+
+    /// Move the connection lost modal's highlight up or down
+    pub fn select_outage_choice(&mut self, backwards: bool) {
+        if let Some(outage) = &mut self.outage {
+            outage.selected = match backwards {
+                true => outage.selected.saturating_sub(1),
+                false => (outage.selected + 1).min(OutageChoice::ALL.len() - 1),
+            };
+        }
+    }
+
+    /// Answer the connection lost modal with a choice, or the highlighted one for None
+    pub fn take_outage_choice(&mut self, choice: Option<OutageChoice>) -> Option<OutageChoice> {
+        let outage = self.outage.as_mut().filter(|outage| outage.open)?;
+        let choice = choice.unwrap_or(OutageChoice::ALL[outage.selected]);
+        outage.open = false;
+        match choice {
+            OutageChoice::RetryNow => {
+                self.connection_lost = Some(Instant::now());
+                self.add_log("Retrying the connection now".to_string());
+            }
+            OutageChoice::Wait => outage.waiting = true,
+            OutageChoice::Quit => {}
+        }
+        Some(choice)
+    }
+
+    /// Play the selected entry's `.lrc` sidecar, noting in the log why if it can't be
+    pub fn open_preview(&mut self) {
+        let Some(path) = self
+            .selected
+            .and_then(|index| self.logs.get(index)?.path.clone())
+        else {
+            return;
+        };
+        let lrc_path = crate::lrc::sidecar_path(&path);
+        self.preview = match std::fs::read_to_string(&lrc_path) {
+            Ok(lyrics) => LyricsPreview::new(lrc_path, &lyrics),
+            Err(e) => {
+                self.add_log(format!(
+                    "[!] No lyrics to preview for {}: {}",
+                    display_name(&path),
+                    e
+                ));
+                return;
+            }
+        };
+        if self.preview.is_none() {
+            self.add_log(format!(
+                "[!] No lyrics to preview for {}: not synced",
+                display_name(&path)
+            ));
+        }
+    }
+
+    pub fn close_preview(&mut self) {
+        self.preview = None;
+    }
+
+    /// Open or close the details pane
+    pub fn toggle_details(&mut self) {
+        self.show_details = !self.show_details;
+    }
+
+    /// Details of the selected entry's track, or else of the track being processed
+    pub fn details_track(&self) -> Option<&TrackDetails> {
+        let selected = self
+            .selected
+            .and_then(|index| self.logs.get(index)?.path.as_ref());
+        self.details.get(selected.or(self.current_path.as_ref())?)
+    }
+
+    /// Take the selected entry's file for a retry
+    /// The file no longer counts as processed until its retry is done.
+    pub fn take_retry(&mut self) -> Option<PathBuf> {
+        let entry = self.logs.get(self.selected?)?;
+        if !entry.retryable {
+            return None;
+        }
+        let path = entry.path.clone()?;
+        self.selected = None;
+        self.queue_retry(&path, "queued for retry");
+        Some(path)
+    }
+
+    /// Open the manual search for the selected (or on the failures screen, highlighted)
+    /// not found file, filled in with the tags it was searched for
+    pub fn open_manual_search(&mut self) {
+        // Searches run on the workers, which stop with the run
+        if self.status == Status::Complete {
+            return;
+        }
+        let path = match self.screen {
+            Screen::Log => self
+                .selected
+                .map(|index| &self.logs[index])
+                .filter(|entry| entry.retryable && entry.status == Some(StatusType::NotFound))
+                .and_then(|entry| entry.path.clone()),
+            Screen::Failures => self
+                .failures
+                .iter()
+                .nth(self.failure_cursor)
+                .filter(|(_, failure)| failure.status == StatusType::NotFound)
+                .map(|(path, _)| path.clone()),
+            Screen::Albums | Screen::History => None,
+        };
+        let Some(path) = path else {
+            return;
+        };
+        let fields = match self.details.get(&path) {
+            Some(details) => {
+                let track = &details.track;
+                let duration = match track.duration_secs {
+                    0 => String::new(),
+                    secs => format!("{}:{:02}", secs / 60, secs % 60),
+                };
+                [
+                    track.artist.clone(),
+                    track.title.clone(),
+                    track.album.clone(),
+                    duration,
+                ]
+            }
+            None => Default::default(),
+        };
+        self.manual_search = Some(ManualSearch {
+            path,
+            fields,
+            focus: 0,
+            error: None,
+        });
+    }
+
+    /// Type into the manual search's field being edited
+    pub fn manual_search_input(&mut self, input: SearchInput) {
+        if let Some(form) = &mut self.manual_search {
+            let field = &mut form.fields[form.focus];
+            match input {
+                SearchInput::Char(c) => field.push(c),
+                SearchInput::Backspace => {
+                    field.pop();
+                }
+            }
+            form.error = None;
+        }
+    }
+
+    /// Edit the next or previous field of the manual search, wrapping around
+    pub fn focus_manual_field(&mut self, backwards: bool) {
+        if let Some(form) = &mut self.manual_search {
+            let count = ManualSearch::LABELS.len();
+            form.focus = match backwards {
+                true => (form.focus + count - 1) % count,
+                false => (form.focus + 1) % count,
+            };
+        }
+    }
+
+    pub fn close_manual_search(&mut self) {
+        self.manual_search = None;
+    }
+
+    /// Take the manual search's file and fields to search for it again
+    /// Fields that can't be searched for leave the form open with the reason.
+    pub fn take_manual_search(&mut self) -> Option<(PathBuf, TagOverride)> {
+        let form = self.manual_search.as_mut()?;
+        let tags = match form.tags() {
+            Ok(tags) => tags,
+            Err(error) => {
+                form.error = Some(error.to_string());
+                return None;
+            }
+        };
+        let path = self.manual_search.take()?.path;
+        self.selected = None;
+        self.queue_retry(
+            &path,
+            &format!("searching as {} - {}", tags.artist, tags.title),
+        );
+        self.failure_cursor = self
+            .failure_cursor
+            .min(self.failures.len().saturating_sub(1));
+        Some((path, tags))
+    }
+
+    /// Take the marked failures, or else the highlighted one, for a retry
+    pub fn take_failure_retries(&mut self) -> Vec<PathBuf> {
+        let paths: Vec<PathBuf> = if self.marked.is_empty() {
+            self.failures
+                .keys()
+                .nth(self.failure_cursor)
+                .cloned()
+                .into_iter()
+                .collect()
+        } else {
+            std::mem::take(&mut self.marked).into_iter().collect()
+        };
+        for path in &paths {
+            self.queue_retry(path, "queued for retry");
+        }
+        self.failure_cursor = self
+            .failure_cursor
+            .min(self.failures.len().saturating_sub(1));
+        paths
+    }
+
+    /// Note a failed file as queued again, no longer counted as processed or failed
+    fn queue_retry(&mut self, path: &Path, note: &str) {
+        for entry in &mut self.logs {
+            if entry.path.as_deref() == Some(path) {
+                entry.retryable = false;
+            }
+        }
+        if let Some(failure) = self.failures.remove(path) {
+            if failure.status == StatusType::NotFound {
+                self.not_found = self.not_found.saturating_sub(1);
+            }
+        }
+        self.marked.remove(path);
+        self.processed = self.processed.saturating_sub(1);
+        self.errors.remove(path);
+        self.add_log(format!(
+            "{} {}: {}",
+            self.glyphs.retry,
+            display_name(path),
+            note
+        ));
+    }
+
+    /// Switch to the failures screen, or back to the log from it
+    pub fn toggle_screen(&mut self) {
+        self.screen = match self.screen {
+            Screen::Failures => Screen::Log,
+            _ => Screen::Failures,
+        };
+    }
+
+    /// Switch to the album view, or back to the log from it
+    pub fn toggle_albums(&mut self) {
+        self.screen = match self.screen {
+            Screen::Albums => Screen::Log,
+            _ => Screen::Albums,
+        };
+    }
+
+    /// Switch to the history of past runs, reading it afresh, or back to the log from it
+    pub fn toggle_history(&mut self, load: impl FnOnce() -> anyhow::Result<Vec<RunRecord>>) {
+        if self.screen == Screen::History {
+            self.screen = Screen::Log;
+            return;
+        }
+        match load() {
+            Ok(runs) => {
+                self.history = runs;
+                self.history_cursor = 0;
+                self.history_report = false;
+                self.screen = Screen::History;
+            }
+            Err(e) => self.add_log(format!("[!] Couldn't read the run history: {:#}", e)),
+        }
+    }
+
+    /// Move the highlight on the history screen up or down
+    pub fn select_run(&mut self, backwards: bool) {
+        if self.history_report {
+            return;
+        }
+        self.history_cursor = match backwards {
+            true => self.history_cursor.saturating_sub(1),
+            false => (self.history_cursor + 1).min(self.history.len().saturating_sub(1)),
+        };
+    }
+
+    /// Show the highlighted run's report, or go back to the list of runs
+    pub fn open_run(&mut self, open: bool) {
+        self.history_report = open && self.history_cursor < self.history.len();
+    }
+
+    /// The shown log entries about files, grouped by directory in order of first entry
+    pub fn albums(&self) -> Vec<AlbumGroup> {
+        let mut albums: Vec<AlbumGroup> = Vec::new();
+        let mut positions: HashMap<&Path, usize> = HashMap::new();
+        // Latest outcome of each file, as a retry can change it
+        let mut outcomes: HashMap<&Path, &StatusType> = HashMap::new();
+        for index in self.shown_logs() {
+            let log = &self.logs[index];
+            let Some(path) = log.path.as_deref() else {
+                continue;
+            };
+            let dir = path.parent().unwrap_or(Path::new(""));
+            let position = *positions.entry(dir).or_insert_with(|| {
+                albums.push(AlbumGroup {
+                    dir: dir.to_path_buf(),
+                    entries: Vec::new(),
+                    with_lyrics: 0,
+                    total: 0,
+                });
+                albums.len() - 1
+            });
+            albums[position].entries.push(index);
+            if let Some(status) = &log.status {
+                outcomes.insert(path, status);
+            }
+        }
+        for (path, status) in outcomes {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            let album = &mut albums[positions[dir]];
+            album.total += 1;
+            if matches!(
+                status,
+                StatusType::Downloaded
+                    | StatusType::Cached
+                    | StatusType::Existing
+                    | StatusType::EmbeddedExists
+            ) {
+                album.with_lyrics += 1;
+            }
+        }
+        albums
+    }
+
+    /// Lines of the album view: each album, followed by its entries when expanded
+    pub fn album_rows(&self, albums: &[AlbumGroup]) -> Vec<AlbumRow> {
+        let mut rows = Vec::new();
+        for (position, album) in albums.iter().enumerate() {
+            rows.push(AlbumRow::Album(position));
+            if self.expanded.contains(&album.dir) {
+                rows.extend(album.entries.iter().map(|index| AlbumRow::Entry(*index)));
+            }
+        }
+        rows
+    }
+
+    /// Move the highlight in the album view up or down
+    pub fn select_album_row(&mut self, backwards: bool) {
+        let rows = self.album_rows(&self.albums()).len();
+        self.album_cursor = match backwards {
+            true => self.album_cursor.saturating_sub(1),
+            false => (self.album_cursor + 1).min(rows.saturating_sub(1)),
+        };
+    }
+
+    /// Open or close the highlighted album (or the album of the highlighted entry)
+    pub fn expand_album(&mut self, expand: bool) {
+        let albums = self.albums();
+        let rows = self.album_rows(&albums);
+        let Some(row) = rows.get(self.album_cursor) else {
+            return;
+        };
+        let position = match *row {
+            AlbumRow::Album(position) => position,
+            AlbumRow::Entry(index) => {
+                let dir = self.logs[index].path.as_deref().and_then(Path::parent);
+                match albums
+                    .iter()
+                    .position(|album| Some(album.dir.as_path()) == dir)
+                {
+                    Some(position) => position,
+                    None => return,
+                }
+            }
+        };
+        let dir = &albums[position].dir;
+        if expand {
+            self.expanded.insert(dir.clone());
+        } else if self.expanded.remove(dir) {
+            // Keep the highlight on the closed album
+            self.album_cursor = rows
+                .iter()
+                .position(|row| *row == AlbumRow::Album(position))
+                .unwrap_or(0);
+        }
+    }
+
+    /// Move the highlight on the failures screen up or down
+    pub fn select_failure(&mut self, backwards: bool) {
+        self.failure_cursor = match backwards {
+            true => self.failure_cursor.saturating_sub(1),
+            false => (self.failure_cursor + 1).min(self.failures.len().saturating_sub(1)),
+        };
+    }
+
+    /// Mark the highlighted failure, or unmark it
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self.failures.keys().nth(self.failure_cursor) else {
+            return;
+        };
+        if !self.marked.remove(path) {
+            self.marked.insert(path.clone());
+        }
+    }
+
+    /// Write the marked failures, or all of them, to a playlist that can be run again
+    pub fn export_failures(&mut self, list_path: &Path) {
+        let files: Vec<PathBuf> = if self.marked.is_empty() {
+            self.failures.keys().cloned().collect()
+        } else {
+            self.marked.iter().cloned().collect()
+        };
+        if files.is_empty() {
+            return;
+        }
+        match crate::scanner::playlist::write(list_path, &files) {
+            Ok(()) => self.add_log(format!(
+                "Exported {} failed files to {}",
+                files.len(),
+                list_path.display()
+            )),
+            Err(e) => self.add_log(format!("[!] {:#}", e)),
+        }
+    }
+
+    /// Copy the full path of the selected file, album or run to the clipboard, or on the
+    /// failures screen the marked failures or else all of them, one per line
+    pub fn copy_paths(&mut self, copy: impl FnOnce(&str) -> io::Result<()>) {
+        let paths: Vec<PathBuf> = match self.screen {
+            Screen::Log => self
+                .selected
+                .and_then(|index| self.logs[index].path.clone())
+                .into_iter()
+                .collect(),
+            Screen::Failures if self.marked.is_empty() => self.failures.keys().cloned().collect(),
+            Screen::Failures => self.marked.iter().cloned().collect(),
+            Screen::Albums => {
+                let albums = self.albums();
+                match self.album_rows(&albums).get(self.album_cursor) {
+                    Some(AlbumRow::Album(position)) => vec![albums[*position].dir.clone()],
+                    Some(AlbumRow::Entry(index)) => {
+                        self.logs[*index].path.clone().into_iter().collect()
+                    }
+                    None => Vec::new(),
+                }
+            }
+            Screen::History => self
+                .history
+                .get(self.history_cursor)
+                .map(|run| run.target.clone())
+                .into_iter()
+                .collect(),
+        };
+        if paths.is_empty() {
+            return;
+        }
+        let text = paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        match copy(&text) {
+            Ok(()) if paths.len() == 1 => {
+                self.add_log(format!("Copied {} to the clipboard", paths[0].display()))
+            }
+            Ok(()) => self.add_log(format!("Copied {} paths to the clipboard", paths.len())),
+            Err(e) => self.add_log(format!("[!] Couldn't copy to the clipboard: {}", e)),
+        }
+    }
+
+    /// Switch the log file between debug and info, to capture the details of a stretch of
+    /// the run without restarting it
+    pub fn toggle_log_level(&mut self, set: impl FnOnce(bool) -> anyhow::Result<()>) {
+        let debug = !self.debug_log;
+        match set(debug) {
+            Ok(()) => {
+                self.debug_log = debug;
+                self.add_log(format!(
+                    "Logging {} to the log file",
+                    if debug {
+                        "debug details"
+                    } else {
+                        "info and above"
+                    }
+                ));
             }
+            Err(e) => self.add_log(format!("[!] Couldn't change the log level: {:#}", e)),
         }
     }
 
+    /// Take the album (directory) of the selected entry's file, to move it to the front
+    pub fn take_album(&mut self) -> Option<PathBuf> {
+        let path = self.logs.get(self.selected?)?.path.as_ref()?;
+        let dir = path.parent()?.to_path_buf();
+        self.selected = None;
+        self.add_log(format!(
+            "{} {}: moved to the front of the queue",
+            self.glyphs.moved,
+            display_name(&dir)
+        ));
+        Some(dir)
+    }
+
     fn add_log(&mut self, msg: String) {
+        self.push_log(LogEntry {
+            text: msg,
+            path: None,
+            status: None,
+            retryable: false,
+        });
+    }
+
+    fn add_file_log(&mut self, msg: String, path: PathBuf, status: Option<StatusType>) {
+        self.push_log(LogEntry {
+            text: msg,
+            path: Some(path),
+            status,
+            retryable: false,
+        });
+    }
+
+    fn add_retryable_log(&mut self, msg: String, path: PathBuf, status: StatusType) {
+        self.push_log(LogEntry {
+            text: msg,
+            path: Some(path),
+            status: Some(status),
+            retryable: true,
+        });
+    }
+
+    fn push_log(&mut self, entry: LogEntry) {
         if self.logs.len() >= MAX_LOG_LINES {
-            self.logs.pop_front();
+            // Details are kept as long as an entry about their file is
+            if let Some(path) = self.logs.pop_front().and_then(|log| log.path) {
+                let logged = self.logs.iter().any(|log| log.path.as_ref() == Some(&path));
+                if !logged && self.current_path.as_ref() != Some(&path) {
+                    self.details.remove(&path);
+                }
+            }
+            self.selected = self.selected.and_then(|index| index.checked_sub(1));
+            if let Some(search) = &mut self.search {
+                search.current = search.current.and_then(|index| index.checked_sub(1));
+            }
         }
-        self.logs.push_back(msg);
-        // Auto-scroll to bottom when new log is added
-        self.scroll_offset = 0;
+        // A worker that stopped waiting for a pick has used the best result; a file
+        // with a new outcome is no longer a failure until it fails again
+        if let Some(path) = entry.path.as_ref().filter(|_| entry.status.is_some()) {
+            self.picks.retain(|pick| pick.path != *path);
+            if self.failures.remove(path).is_some() {
+                self.marked.remove(path);
+                self.failure_cursor = self
+                    .failure_cursor
+                    .min(self.failures.len().saturating_sub(1));
+            }
+        }
+        let shown = self
+            .log_filter
+            .is_none_or(|filter| entry.status.as_ref().is_some_and(|s| filter.matches(s)));
+        self.logs.push_back(entry);
+        // Follow new entries, unless scrolled back or picking an entry
+        if shown && (self.scroll_offset > 0 || self.selected.is_some()) {
+            let max = Self::max_scroll(self.shown_logs().len(), self.log_height);
+            self.scroll_offset = (self.scroll_offset + 1).min(max);
+        }
+    }
+
+    /// Time since the TUI started, up to the end of the run
+    pub fn elapsed(&self) -> Duration {
+        self.finished
+            .unwrap_or_else(Instant::now)
+            .duration_since(self.started)
+    }
+
+    /// Ring the bell or flash the screen, as configured
+    fn alert(&mut self) {
+        match self.notify {
+            Notify::Off => {}
+            Notify::Bell => self.bell = true,
+            Notify::Flash => self.flash_until = Some(Instant::now() + notify::FLASH),
+        }
+    }
+
+    /// Whether the screen is inverted for a flash, clearing the flash once it's over
+    pub fn flashing(&mut self) -> bool {
+        match self.flash_until {
+            Some(until) if until > Instant::now() => true,
+            Some(_) => {
+                self.flash_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Stage of the current track, while a worker has it
+    pub fn current_stage(&self) -> Option<WorkerStage> {
+        let path = self.current_path.as_ref()?;
+        self.workers
+            .values()
+            .find(|status| status.path.as_ref() == Some(path))
+            .map(|status| status.stage)
+    }
+
+    /// Workers busy with a file, and how many of them are waiting for the rate limit
+    pub fn busy_workers(&self) -> (usize, usize) {
+        let busy = self
+            .workers
+            .values()
+            .filter(|status| status.stage != WorkerStage::Idle)
+            .count();
+        let limited = self
+            .workers
+            .values()
+            .filter(|status| status.stage == WorkerStage::RateLimited)
+            .count();
+        (busy, limited)
+    }
+
+    /// Estimated time until the remaining files are processed, at the workers' current pace
+    /// Unknown while the scan is still counting files.
+    pub fn eta(&self) -> Option<Duration> {
+        if !self.scan_complete || self.status != Status::Processing {
+            return None;
+        }
+        let remaining = self
+            .total_files
+            .saturating_sub(self.processed + self.existing);
+        Some(self.pace? * remaining as u32)
     }
 
     pub fn progress(&self) -> f64 {
@@ -172,3 +1904,897 @@ impl Default for AppState {
         Self::new()
     }
 }
+
+/// File name for the activity log (lossy for non-UTF-8 names)
+fn display_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_selected_failure() {
+        let mut state = AppState::new();
+        state.update(WorkerMessage::Error {
+            path: PathBuf::from("/music/a.flac"),
+            kind: ErrorKind::Network,
+            error: "timeout".to_string(),
+        });
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/b.flac"),
+        });
+        state.update(WorkerMessage::LyricsNotFound {
+            path: PathBuf::from("/music/c.flac"),
+        });
+
+        // Selection starts at the latest entry; only failures can be retried
+        state.select(true);
+        assert_eq!(state.selected, Some(2));
+        state.select(true);
+        assert_eq!(state.take_retry(), None);
+        state.select(true);
+        assert_eq!(state.selected, Some(0));
+
+        assert_eq!(state.take_retry(), Some(PathBuf::from("/music/a.flac")));
+        assert_eq!(state.processed, 2);
+        assert_eq!(state.selected, None);
+        // An entry can only be queued once
+        state.select(false);
+        state.select(true);
+        state.select(true);
+        assert_eq!(state.selected, Some(0));
+        assert_eq!(state.take_retry(), None);
+
+        assert_eq!(state.take_album(), Some(PathBuf::from("/music")));
+    }
+
+    #[test]
+    fn test_scroll_log() {
+        let mut state = AppState::new();
+        state.log_height = 10;
+        for i in 0..30 {
+            state.update(WorkerMessage::LyricsFound {
+                path: PathBuf::from(format!("/music/{}.flac", i)),
+            });
+        }
+        assert_eq!(state.visible_logs(), (20..30).collect::<Vec<_>>());
+
+        state.scroll_up(10);
+        assert_eq!(state.visible_logs(), (10..20).collect::<Vec<_>>());
+        // New entries don't move the view while scrolled back
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/new.flac"),
+        });
+        assert_eq!(state.visible_logs(), (10..20).collect::<Vec<_>>());
+
+        state.scroll_to_top();
+        assert_eq!(state.visible_logs(), (0..10).collect::<Vec<_>>());
+        state.scroll_up(5);
+        assert_eq!(state.visible_logs(), (0..10).collect::<Vec<_>>());
+
+        // Selecting starts in view, and the view follows the selection
+        state.select(true);
+        assert_eq!(state.selected, Some(9));
+        for _ in 0..10 {
+            state.select(false);
+        }
+        assert_eq!(state.selected, Some(19));
+        assert_eq!(state.visible_logs(), (10..20).collect::<Vec<_>>());
+
+        // Scrolling the selection out of view drops it
+        state.scroll_to_bottom();
+        assert_eq!(state.visible_logs(), (21..31).collect::<Vec<_>>());
+        assert_eq!(state.selected, None);
+    }
+
+    #[test]
+    fn test_filter_log_by_status() {
+        let mut state = AppState::new();
+        state.log_height = 2;
+        for name in ["a", "b", "c"] {
+            state.update(WorkerMessage::LyricsFound {
+                path: PathBuf::from(format!("/music/{}.flac", name)),
+            });
+            state.update(WorkerMessage::LyricsNotFound {
+                path: PathBuf::from(format!("/music/{}.mp3", name)),
+            });
+        }
+        state.update(WorkerMessage::AlreadyHasLrc {
+            path: PathBuf::from("/music/d.flac"),
+        });
+
+        state.set_log_filter(Some(LogFilter::NotFound));
+        assert_eq!(state.visible_logs(), vec![3, 5]);
+        assert_eq!(state.log_position(), (3, 0));
+
+        // Scrolling and selection only see matching entries
+        state.scroll_to_top();
+        assert_eq!(state.visible_logs(), vec![1, 3]);
+        assert_eq!(state.log_position(), (3, 1));
+        state.select(true);
+        assert_eq!(state.selected, Some(3));
+        state.select(true);
+        assert_eq!(state.selected, Some(1));
+
+        state.set_log_filter(Some(LogFilter::Skipped));
+        assert_eq!(state.visible_logs(), vec![6]);
+        assert_eq!(state.selected, None);
+        state.set_log_filter(None);
+        assert_eq!(state.visible_logs(), vec![5, 6]);
+    }
+
+    #[test]
+    fn test_search_log() {
+        let mut state = AppState::new();
+        state.log_height = 2;
+        for name in [
+            "Beatles - Help",
+            "Queen - Bicycle",
+            "beatles - Yesterday",
+            "ABBA - SOS",
+        ] {
+            state.update(WorkerMessage::LyricsFound {
+                path: PathBuf::from(format!("/music/{}.flac", name)),
+            });
+        }
+
+        state.start_search();
+        for c in "beatles".chars() {
+            state.search_input(SearchInput::Char(c));
+        }
+        assert!(state
+            .search
+            .as_ref()
+            .unwrap()
+            .is_match("[✓] Beatles - Help.flac"));
+        assert_eq!(state.search_position(), Some((2, None)));
+
+        // The first jump is to the newest match, then back through older ones, wrapping
+        state.finish_search();
+        assert_eq!(state.search_position(), Some((2, Some(2))));
+        state.next_match(true);
+        assert_eq!(state.search.as_ref().unwrap().current, Some(0));
+        assert_eq!(state.visible_logs(), vec![0, 1]);
+        state.next_match(true);
+        assert_eq!(state.search.as_ref().unwrap().current, Some(2));
+
+        // Regexes work, and text that isn't one is searched for as is
+        state.start_search();
+        for c in "^\\[.\\] (queen|abba)".chars() {
+            state.search_input(SearchInput::Char(c));
+        }
+        assert_eq!(state.search_position(), Some((2, None)));
+        state.start_search();
+        state.search_input(SearchInput::Char('('));
+        assert_eq!(state.search_position(), Some((0, None)));
+
+        // An empty search closes the prompt
+        state.search_input(SearchInput::Backspace);
+        state.finish_search();
+        assert!(state.search.is_none());
+    }
+
+    #[test]
+    fn test_track_details() {
+        use crate::scanner::metadata::{MetadataSource, TrackPosition};
+
+        let track = |name: &str| Track {
+            path: PathBuf::from(format!("/music/{}.flac", name)),
+            artist: "Artist".to_string(),
+            artists: Vec::new(),
+            sort_artist: String::new(),
+            title: name.to_string(),
+            album: String::new(),
+            genre: String::new(),
+            duration_secs: 200,
+            has_embedded_lyrics: false,
+            source: MetadataSource::Tags,
+            position: TrackPosition::default(),
+            compilation: false,
+        };
+        let mut state = AppState::new();
+        assert!(state.details_track().is_none());
+
+        state.update(WorkerMessage::TrackProcessing { track: track("a") });
+        state.update(WorkerMessage::SearchAttempts {
+            path: PathBuf::from("/music/a.flac"),
+            attempts: vec![SearchAttempt {
+                strategy: "normalized metadata",
+                artist: "artist".to_string(),
+                title: "a".to_string(),
+                album: String::new(),
+                candidate: None,
+            }],
+        });
+        state.update(WorkerMessage::LyricsNotFound {
+            path: PathBuf::from("/music/a.flac"),
+        });
+        state.update(WorkerMessage::TrackProcessing { track: track("b") });
+
+        // The track being processed, unless an entry is selected
+        assert_eq!(state.details_track().unwrap().track.title, "b");
+        assert!(state.details_track().unwrap().attempts.is_empty());
+        state.select(true);
+        let details = state.details_track().unwrap();
+        assert_eq!(details.track.title, "a");
+        assert_eq!(details.attempts.len(), 1);
+
+        // Details go with the last log entry about their file
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/b.flac"),
+        });
+        state.update(WorkerMessage::ScanComplete {
+            processed: 2,
+            found: 1,
+        });
+        for _ in 0..MAX_LOG_LINES {
+            state.add_log("line".to_string());
+        }
+        assert!(state.details.is_empty());
+    }
+
+    #[test]
+    fn test_pick_lyrics() {
+        let candidate = |title: &str| Candidate {
+            artist: "Queen".to_string(),
+            title: title.to_string(),
+            album: String::new(),
+            duration_secs: Some(181),
+            artist_similarity: 1.0,
+            title_similarity: 0.8,
+            synced: true,
+            instrumental: false,
+        };
+        let mut state = AppState::new();
+        for name in ["a", "b"] {
+            state.update(WorkerMessage::PickLyrics {
+                path: PathBuf::from(format!("/music/{}.flac", name)),
+                candidates: vec![candidate("Live"), candidate("Remaster")],
+            });
+        }
+
+        // The oldest pick is answered first; picks can't go past the candidates
+        state.select_pick(false);
+        state.select_pick(false);
+        assert_eq!(state.picks[0].selected, 1);
+        assert_eq!(state.take_pick(Some(2)), None);
+        assert_eq!(
+            state.take_pick(Some(1)),
+            Some((PathBuf::from("/music/a.flac"), Some(1)))
+        );
+
+        // Once the worker has gone ahead without an answer, the pick is dropped
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/b.flac"),
+        });
+        assert!(state.picks.is_empty());
+        assert_eq!(state.take_pick(None), None);
+    }
+
+    #[test]
+    fn test_failures_screen() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state = AppState::new();
+        for name in ["c", "a", "b"] {
+            state.update(WorkerMessage::LyricsNotFound {
+                path: PathBuf::from(format!("/music/{}.flac", name)),
+            });
+        }
+        state.update(WorkerMessage::Error {
+            path: PathBuf::from("/music/d.flac"),
+            kind: ErrorKind::Network,
+            error: "timed out".to_string(),
+        });
+        assert_eq!(state.failures.len(), 4);
+        assert_eq!(
+            state.failures[Path::new("/music/d.flac")].reason,
+            "timed out"
+        );
+
+        // Retrying without marks takes the highlighted failure, in path order
+        state.select_failure(false);
+        assert_eq!(
+            state.take_failure_retries(),
+            vec![PathBuf::from("/music/b.flac")]
+        );
+        assert_eq!(state.processed, 3);
+        assert!(state
+            .logs
+            .iter()
+            .all(|log| log.path.as_deref() != Some(Path::new("/music/b.flac")) || !log.retryable));
+
+        state.toggle_mark();
+        state.select_failure(true);
+        state.toggle_mark();
+        let list = dir.path().join("failures.m3u8");
+        state.export_failures(&list);
+        assert!(std::fs::read_to_string(&list)
+            .unwrap()
+            .contains("/music/a.flac\n/music/c.flac"));
+
+        // A file found on its retry is no longer a failure
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/a.flac"),
+        });
+        assert_eq!(state.marked.len(), 1);
+        assert_eq!(
+            state.take_failure_retries(),
+            vec![PathBuf::from("/music/c.flac")]
+        );
+        assert_eq!(state.failures.len(), 1);
+        assert_eq!(state.failure_cursor, 0);
+    }
+
+    #[test]
+    fn test_album_view() {
+        let mut state = AppState::new();
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/Queen/Jazz/01.flac"),
+        });
+        state.update(WorkerMessage::LyricsNotFound {
+            path: PathBuf::from("/music/Björk/Post/01.flac"),
+        });
+        state.update(WorkerMessage::LyricsNotFound {
+            path: PathBuf::from("/music/Queen/Jazz/02.flac"),
+        });
+        state.update(WorkerMessage::AlbumStarted {
+            dir: PathBuf::from("/music/Queen/Jazz"),
+        });
+
+        let albums = state.albums();
+        assert_eq!(albums.len(), 2);
+        assert_eq!(albums[0].dir, PathBuf::from("/music/Queen/Jazz"));
+        assert_eq!((albums[0].with_lyrics, albums[0].total), (1, 2));
+        assert_eq!(albums[0].entries, vec![0, 2]);
+
+        // A retry that finds lyrics replaces the earlier outcome
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/Queen/Jazz/02.flac"),
+        });
+        assert_eq!(
+            (state.albums()[0].with_lyrics, state.albums()[0].total),
+            (2, 2)
+        );
+
+        assert_eq!(state.album_rows(&state.albums()).len(), 2);
+        state.expand_album(true);
+        assert_eq!(
+            state.album_rows(&state.albums()),
+            vec![
+                AlbumRow::Album(0),
+                AlbumRow::Entry(0),
+                AlbumRow::Entry(2),
+                AlbumRow::Entry(4),
+                AlbumRow::Album(1),
+            ]
+        );
+        state.select_album_row(false);
+        state.select_album_row(false);
+        state.expand_album(false);
+        assert_eq!(state.album_cursor, 0);
+        assert_eq!(state.album_rows(&state.albums()).len(), 2);
+
+        state.set_log_filter(Some(LogFilter::Downloaded));
+        assert_eq!(state.albums().len(), 1);
+    }
+
+    #[test]
+    fn test_quit_prompt() {
+        let mut state = AppState::new();
+
+        // Saving isn't offered mid-scan
+        state.update(WorkerMessage::ScanProgress { files_found: 10 });
+        assert!(!state.request_quit());
+        assert_eq!(state.take_quit_choice(Some(QuitChoice::Save)), None);
+        assert_eq!(
+            state.take_quit_choice(Some(QuitChoice::Cancel)),
+            Some(QuitChoice::Cancel)
+        );
+        assert!(state.quit_prompt.is_none());
+
+        state.update(WorkerMessage::ScanStarted { total_files: 10 });
+        assert!(!state.request_quit());
+        state.select_quit_choice(false);
+        state.select_quit_choice(false);
+        state.select_quit_choice(false);
+        assert_eq!(state.take_quit_choice(None), Some(QuitChoice::Cancel));
+        assert!(!state.request_quit());
+        assert_eq!(state.take_quit_choice(None), Some(QuitChoice::Save));
+
+        // Nothing to lose once the run is complete
+        state.update(WorkerMessage::ScanComplete {
+            processed: 10,
+            found: 0,
+        });
+        assert!(state.request_quit());
+        assert!(state.quit_prompt.is_none());
+    }
+
+    #[test]
+    fn test_lyrics_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("a.flac");
+        std::fs::write(
+            dir.path().join("a.lrc"),
+            "[ar:Queen]\n[00:12.00]Second\n[00:02.50]First\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("b.lrc"), "Plain lyrics\n").unwrap();
+
+        let mut state = AppState::new();
+        state.update(WorkerMessage::LyricsFound { path: audio });
+        state.update(WorkerMessage::LyricsFound {
+            path: dir.path().join("b.flac"),
+        });
+
+        // Plain lyrics have no timing to play
+        state.select(true);
+        state.open_preview();
+        assert!(state.preview.is_none());
+        assert!(state.logs.back().unwrap().text.contains("not synced"));
+
+        state.select(true);
+        state.open_preview();
+        let preview = state.preview.as_mut().unwrap();
+        preview.toggle_pause();
+        assert!(preview.is_paused());
+        assert_eq!(preview.current_line(), None);
+        assert_eq!(preview.length(), Duration::from_secs(12));
+
+        // Lines follow the position, which stays within the lyrics
+        preview.seek(true);
+        assert_eq!(preview.lines[preview.current_line().unwrap()].1, "First");
+        preview.seek(true);
+        preview.seek(true);
+        assert_eq!(preview.position(), Duration::from_secs(12));
+        assert_eq!(preview.current_line(), Some(1));
+        preview.seek(false);
+        assert_eq!(preview.current_line(), Some(0));
+
+        state.close_preview();
+        assert!(state.preview.is_none());
+    }
+
+    #[test]
+    fn test_errors_counted_by_kind() {
+        let mut state = AppState::new();
+        for (name, kind) in [
+            ("/music/x/a.flac", ErrorKind::Network),
+            ("/music/x/b.flac", ErrorKind::Network),
+            ("/music/y/c.flac", ErrorKind::Io),
+        ] {
+            state.update(WorkerMessage::Error {
+                path: PathBuf::from(name),
+                kind,
+                error: "failed".to_string(),
+            });
+        }
+        assert_eq!(
+            state.error_counts(),
+            vec![(ErrorKind::Network, 2), (ErrorKind::Io, 1)]
+        );
+
+        // Retried files are counted again once processed
+        state.update(WorkerMessage::AlbumRetry {
+            dir: PathBuf::from("/music/x"),
+            tracks: 2,
+        });
+        assert_eq!(state.error_counts(), vec![(ErrorKind::Io, 1)]);
+        assert_eq!(messages::format_error_counts(&state.error_counts()), "1 io");
+    }
+
+    #[test]
+    fn test_not_found_counted_apart_from_errors() {
+        let mut state = AppState::new();
+        for name in ["/music/a.flac", "/music/b.flac"] {
+            state.update(WorkerMessage::LyricsNotFound {
+                path: PathBuf::from(name),
+            });
+        }
+        state.update(WorkerMessage::Error {
+            path: PathBuf::from("/music/c.flac"),
+            kind: ErrorKind::Network,
+            error: "failed".to_string(),
+        });
+        assert_eq!(state.processed, 3);
+        assert_eq!(state.not_found, 2);
+        assert_eq!(state.errors.len(), 1);
+
+        // The highlighted failure is a.flac
+        assert_eq!(
+            state.take_failure_retries(),
+            vec![PathBuf::from("/music/a.flac")]
+        );
+        assert_eq!(state.not_found, 1);
+        assert_eq!(state.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_copy_paths() {
+        let mut state = AppState::new();
+        for name in ["/music/a.flac", "/music/b.flac"] {
+            state.update(WorkerMessage::LyricsNotFound {
+                path: PathBuf::from(name),
+            });
+        }
+        let mut copied = None;
+        // Nothing selected in the log
+        state.copy_paths(|text| {
+            copied = Some(text.to_string());
+            Ok(())
+        });
+        assert_eq!(copied, None);
+
+        state.selected = Some(0);
+        state.copy_paths(|text| {
+            copied = Some(text.to_string());
+            Ok(())
+        });
+        assert_eq!(copied.as_deref(), Some("/music/a.flac"));
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "Copied /music/a.flac to the clipboard"
+        );
+
+        state.toggle_screen();
+        state.copy_paths(|text| {
+            copied = Some(text.to_string());
+            Ok(())
+        });
+        assert_eq!(copied.as_deref(), Some("/music/a.flac\n/music/b.flac"));
+
+        state.copy_paths(|_| Err(io::Error::other("not a terminal")));
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "[!] Couldn't copy to the clipboard: not a terminal"
+        );
+    }
+
+    #[test]
+    fn test_history_screen() {
+        let mut state = AppState::new();
+        state.toggle_history(|| anyhow::bail!("permission denied"));
+        assert_eq!(state.screen, Screen::Log);
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "[!] Couldn't read the run history: permission denied"
+        );
+
+        let runs = vec![
+            RunRecord {
+                target: PathBuf::from("/music/new"),
+                ..Default::default()
+            },
+            RunRecord {
+                target: PathBuf::from("/music/old"),
+                ..Default::default()
+            },
+        ];
+        state.toggle_history(|| Ok(runs));
+        assert_eq!(state.screen, Screen::History);
+        state.select_run(false);
+        state.select_run(false);
+        assert_eq!(state.history_cursor, 1);
+
+        state.open_run(true);
+        assert!(state.history_report);
+        // The list stays put while a report is open
+        state.select_run(true);
+        assert_eq!(state.history_cursor, 1);
+        let mut copied = None;
+        state.copy_paths(|text| {
+            copied = Some(text.to_string());
+            Ok(())
+        });
+        assert_eq!(copied.as_deref(), Some("/music/old"));
+
+        state.open_run(false);
+        assert!(!state.history_report);
+        state.toggle_history(|| unreachable!());
+        assert_eq!(state.screen, Screen::Log);
+
+        // Reopening reads the history afresh, from the top
+        state.toggle_history(|| Ok(Vec::new()));
+        assert_eq!(state.history_cursor, 0);
+        state.open_run(true);
+        assert!(!state.history_report);
+    }
+
+    #[test]
+    fn test_toggle_log_level() {
+        let mut state = AppState::new();
+        let mut levels = Vec::new();
+        state.toggle_log_level(|debug| {
+            levels.push(debug);
+            Ok(())
+        });
+        assert!(!state.debug_log);
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "Logging info and above to the log file"
+        );
+        state.toggle_log_level(|debug| {
+            levels.push(debug);
+            Ok(())
+        });
+        assert!(state.debug_log);
+        assert_eq!(levels, vec![false, true]);
+
+        // The level stays as it was when it can't be changed
+        state.toggle_log_level(|_| anyhow::bail!("subscriber dropped"));
+        assert!(state.debug_log);
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "[!] Couldn't change the log level: subscriber dropped"
+        );
+    }
+
+    #[test]
+    fn test_notify() {
+        let pick = |name: &str| WorkerMessage::PickLyrics {
+            path: PathBuf::from(format!("/music/{}.flac", name)),
+            candidates: Vec::new(),
+        };
+        let complete = WorkerMessage::ScanComplete {
+            processed: 2,
+            found: 1,
+        };
+
+        let mut state = AppState::new();
+        state.update(complete.clone());
+        assert!(!state.bell);
+        assert!(!state.flashing());
+
+        // The bell rings once for a batch of picks, and again when the run completes
+        state.notify = Notify::Bell;
+        state.update(pick("a"));
+        assert!(std::mem::take(&mut state.bell));
+        state.update(pick("b"));
+        assert!(!state.bell);
+        state.update(complete.clone());
+        assert!(state.bell);
+
+        let mut state = AppState::new();
+        state.notify = Notify::Flash;
+        state.update(complete);
+        assert!(state.flashing());
+        assert!(!state.bell);
+        state.flash_until = Some(Instant::now());
+        assert!(!state.flashing());
+        assert_eq!(state.flash_until, None);
+    }
+
+    #[test]
+    fn test_current_track_stage() {
+        let mut state = AppState::new();
+        let path = PathBuf::from("/music/01 - Intro.flac");
+        state.update(WorkerMessage::StageChanged {
+            worker: 0,
+            path: Some(path.clone()),
+            stage: WorkerStage::Reading,
+        });
+        assert_eq!(state.current_track.as_deref(), Some("01 - Intro.flac"));
+        assert_eq!(state.current_stage(), Some(WorkerStage::Reading));
+
+        state.update(WorkerMessage::StageChanged {
+            worker: 0,
+            path: Some(path.clone()),
+            stage: WorkerStage::RateLimited,
+        });
+        assert_eq!(state.current_stage().and_then(WorkerStage::step), Some(2));
+        // Another worker's later stages don't take the current track over
+        state.update(WorkerMessage::StageChanged {
+            worker: 1,
+            path: Some(PathBuf::from("/music/02.flac")),
+            stage: WorkerStage::Writing,
+        });
+        assert_eq!(state.current_path.as_ref(), Some(&path));
+
+        state.update(WorkerMessage::StageChanged {
+            worker: 0,
+            path: None,
+            stage: WorkerStage::Idle,
+        });
+        assert_eq!(state.current_stage(), None);
+    }
+
+    #[test]
+    fn test_file_time_in_log() {
+        let mut state = AppState::new();
+        let path = PathBuf::from("/music/a.flac");
+        state.update(WorkerMessage::LyricsNotFound { path: path.clone() });
+        state.update(WorkerMessage::FileTimed {
+            timing: crate::timing::FileTiming {
+                path,
+                total: Duration::from_millis(2400),
+                rate_limited: Duration::from_millis(1900),
+            },
+        });
+        assert!(state
+            .logs
+            .back()
+            .unwrap()
+            .text
+            .ends_with("a.flac (2.4s, 1.9s rate limited)"));
+    }
+
+    #[test]
+    fn test_pool_status() {
+        let mut state = AppState::new();
+        for (worker, stage) in [
+            WorkerStage::Searching,
+            WorkerStage::RateLimited,
+            WorkerStage::RateLimited,
+            WorkerStage::Idle,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            state.update(WorkerMessage::WorkerHeartbeat {
+                worker,
+                path: None,
+                stage,
+                in_stage: Duration::ZERO,
+            });
+        }
+        assert_eq!(state.busy_workers(), (3, 2));
+
+        state.update(WorkerMessage::PoolStatus {
+            queued: 120,
+            requests_per_sec: 9.5,
+            tokens: Some(0.4),
+            rate_limit: 10,
+        });
+        assert_eq!(state.pool.map(|pool| pool.queued), Some(120));
+    }
+
+    #[test]
+    fn test_outage_modal() {
+        let mut state = AppState::new();
+        let lost = |state: &mut AppState| {
+            state.update(WorkerMessage::ConnectionLost {
+                error: "connection refused".to_string(),
+                retry_in: Duration::from_secs(5),
+            })
+        };
+        lost(&mut state);
+        assert!(state.outage_open());
+        assert_eq!(state.take_outage_choice(None), Some(OutageChoice::RetryNow));
+        assert!(!state.outage_open());
+
+        // Failing again reopens it, logging the outage only once
+        lost(&mut state);
+        assert!(state.outage_open());
+        assert_eq!(state.outage.as_ref().unwrap().trips, 2);
+        let lost_logs = |state: &AppState| {
+            state
+                .logs
+                .iter()
+                .filter(|log| log.text.starts_with("[!] Connection lost"))
+                .count()
+        };
+        assert_eq!(lost_logs(&state), 1);
+
+        // Keeping waiting leaves it closed until the connection is back
+        state.select_outage_choice(false);
+        assert_eq!(state.take_outage_choice(None), Some(OutageChoice::Wait));
+        lost(&mut state);
+        assert!(!state.outage_open());
+        assert_eq!(state.take_outage_choice(None), None);
+
+        state.update(WorkerMessage::ConnectionRestored);
+        assert!(state.outage.is_none());
+        lost(&mut state);
+        assert!(state.outage_open());
+        assert_eq!(lost_logs(&state), 2);
+    }
+
+    #[test]
+    fn test_manual_search() {
+        use crate::scanner::metadata::{MetadataSource, TrackPosition};
+
+        let path = PathBuf::from("/music/song.flac");
+        let mut state = AppState::new();
+        state.update(WorkerMessage::TrackProcessing {
+            track: Track {
+                path: path.clone(),
+                artist: "Artst".to_string(),
+                artists: Vec::new(),
+                sort_artist: String::new(),
+                title: "Song (Live)".to_string(),
+                album: "Album".to_string(),
+                genre: String::new(),
+                duration_secs: 225,
+                has_embedded_lyrics: false,
+                source: MetadataSource::Tags,
+                position: TrackPosition::default(),
+                compilation: false,
+            },
+        });
+        state.update(WorkerMessage::LyricsNotFound { path: path.clone() });
+
+        // Only for a selected not found entry
+        state.open_manual_search();
+        assert!(state.manual_search.is_none());
+        state.selected = Some(0);
+        state.open_manual_search();
+        let form = state.manual_search.as_ref().unwrap();
+        assert_eq!(
+            form.fields,
+            ["Artst", "Song (Live)", "Album", "3:45"].map(String::from)
+        );
+
+        // Fix the artist, then clear the title by mistake
+        state.focus_manual_field(true);
+        state.focus_manual_field(false);
+        state.manual_search_input(SearchInput::Backspace);
+        state.manual_search_input(SearchInput::Backspace);
+        for c in "ist".chars() {
+            state.manual_search_input(SearchInput::Char(c));
+        }
+        state.focus_manual_field(false);
+        for _ in 0..11 {
+            state.manual_search_input(SearchInput::Backspace);
+        }
+        assert_eq!(state.take_manual_search(), None);
+        assert!(state.manual_search.as_ref().unwrap().error.is_some());
+
+        for c in "Song".chars() {
+            state.manual_search_input(SearchInput::Char(c));
+        }
+        state.focus_manual_field(false);
+        state.focus_manual_field(false);
+        state.manual_search_input(SearchInput::Char('x'));
+        assert_eq!(state.take_manual_search(), None);
+        state.manual_search_input(SearchInput::Backspace);
+
+        assert_eq!(
+            state.take_manual_search(),
+            Some((
+                path,
+                TagOverride {
+                    artist: "Artist".to_string(),
+                    title: "Song".to_string(),
+                    album: "Album".to_string(),
+                    duration_secs: Some(225),
+                }
+            ))
+        );
+        assert!(state.manual_search.is_none());
+        assert_eq!(state.not_found, 0);
+        assert_eq!(
+            state.logs.back().unwrap().text,
+            "[↻] song.flac: searching as Artist - Song"
+        );
+    }
+
+    #[test]
+    fn test_eta_from_reported_pace() {
+        let mut state = AppState::new();
+        state.update(WorkerMessage::Throughput {
+            files_per_min: 120.0,
+            pace: Duration::from_millis(500),
+            requests: 3,
+        });
+        state.update(WorkerMessage::ScanProgress { files_found: 40 });
+        // The total isn't final until the scan is done
+        assert_eq!(state.eta(), None);
+
+        state.update(WorkerMessage::ScanStarted { total_files: 40 });
+        for name in ["a.flac", "b.flac"] {
+            state.update(WorkerMessage::LyricsFound {
+                path: PathBuf::from(name),
+            });
+        }
+        state.update(WorkerMessage::AlreadyHasLrc {
+            path: PathBuf::from("c.flac"),
+        });
+        // 37 files left at half a second each
+        assert_eq!(state.eta(), Some(Duration::from_millis(18_500)));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::messages::ErrorKind;
+use crate::tui::state::{AppState, Status};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Progress snapshot written to `status.json` for dashboards, status bars and scripts
+#[derive(Serialize, Debug)]
+pub struct LiveStatus {
+    /// idle, restoring, scanning, processing, paused, offline, complete, stopped or error
+    pub state: &'static str,
+    pub total_files: usize,
+    pub processed: usize,
+    pub downloaded: usize,
+    pub cached: usize,
+    pub existing: usize,
+    pub skipped: usize,
+    pub untagged: usize,
+    /// Files that failed with an error, by what went wrong
+    pub errors: BTreeMap<ErrorKind, usize>,
+    pub percent: f64,
+    /// Files the workers are on right now
+    pub current_files: Vec<PathBuf>,
+    pub current_track: Option<String>,
+    pub files_per_min: Option<f64>,
+    pub eta_secs: Option<u64>,
+    /// Process writing the file, to tell a finished run from a crashed one
+    pub pid: u32,
+    /// Unix seconds
+    pub updated_at: i64,
+}
+
+impl LiveStatus {
+    /// Snapshot the TUI state
+    /// `stopped` marks a run the user quit before it completed.
+    pub fn from_state(state: &AppState, stopped: bool) -> Self {
+        let label = match &state.status {
+            Status::Complete => "complete",
+            Status::Error(_) => "error",
+            _ if stopped => "stopped",
+            _ if state.paused => "paused",
+            _ if state.connection_lost.is_some() => "offline",
+            Status::Idle => "idle",
+            Status::Restoring => "restoring",
+            Status::Scanning => "scanning",
+            Status::Processing => "processing",
+        };
+        Self {
+            state: label,
+            total_files: state.total_files,
+            processed: state.processed,
+            downloaded: state.downloaded,
+            cached: state.cached,
+            existing: state.existing,
+            skipped: state.skipped,
+            untagged: state.untagged,
+            errors: state.error_counts().into_iter().collect(),
+            percent: (state.progress() * 1000.0).round() / 10.0,
+            current_files: state
+                .workers
+                .values()
+                .filter_map(|worker| worker.path.clone())
+                .collect(),
+            current_track: state.current_track.clone(),
+            files_per_min: state.files_per_min.map(|rate| rate.round()),
+            eta_secs: state.eta().map(|eta| eta.as_secs()),
+            pid: std::process::id(),
+            updated_at: crate::cache::now_secs().unwrap_or_default(),
+        }
+    }
+
+    /// Replace the status file, so readers never see it half written
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", temp_path.display()))?;
+        std::fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::WorkerMessage;
+
+    #[test]
+    fn test_write_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.json");
+
+        let mut state = AppState::new();
+        state.update(WorkerMessage::ScanStarted { total_files: 4 });
+        state.update(WorkerMessage::LyricsFound {
+            path: PathBuf::from("/music/a.flac"),
+        });
+        LiveStatus::from_state(&state, false).write(&path).unwrap();
+
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["state"], "processing");
+        assert_eq!(written["downloaded"], 1);
+        assert_eq!(written["percent"], 25.0);
+
+        LiveStatus::from_state(&state, true).write(&path).unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["state"], "stopped");
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{bail, Context, Result};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders},
+};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// The `[theme]` section of the config: a preset, and colors that replace its own
+/// Colors are names (`green`, `light-blue`), `#rrggbb` or a 256-color index.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// `dark` (the default) or `light`
+    pub preset: Option<String>,
+    pub downloaded: Option<String>,
+    pub cached: Option<String>,
+    pub existing: Option<String>,
+    pub not_found: Option<String>,
+    pub error: Option<String>,
+    pub untagged: Option<String>,
+    pub excluded: Option<String>,
+    pub header: Option<String>,
+    pub border: Option<String>,
+    pub text: Option<String>,
+    pub dim: Option<String>,
+    pub key: Option<String>,
+    pub highlight: Option<String>,
+    pub alert: Option<String>,
+}
+
+/// Colors of the TUI
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub downloaded: Color,
+    pub cached: Color,
+    /// Existing sidecars and embedded lyrics
+    pub existing: Color,
+    pub not_found: Color,
+    pub error: Color,
+    pub untagged: Color,
+    /// Excluded and spoken word files
+    pub excluded: Color,
+    /// Title bar and labels
+    pub header: Color,
+    pub border: Color,
+    /// Log lines and other plain text
+    pub text: Color,
+    /// Secondary text: the empty part of the progress bar, pace, lyrics already sung
+    pub dim: Color,
+    /// Key hints in the footer
+    pub key: Color,
+    /// Search matches, the picker and the connection warning
+    pub highlight: Color,
+    /// Errors and stuck workers
+    pub alert: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// Bright colors for dark terminals
+    pub fn dark() -> Self {
+        Self {
+            downloaded: Color::Green,
+            cached: Color::Yellow,
+            existing: Color::Blue,
+            not_found: Color::Red,
+            error: Color::Magenta,
+            untagged: Color::Cyan,
+            excluded: Color::DarkGray,
+            header: Color::Cyan,
+            border: Color::Reset,
+            text: Color::White,
+            dim: Color::DarkGray,
+            key: Color::Yellow,
+            highlight: Color::Yellow,
+            alert: Color::Red,
+        }
+    }
+
+    /// Darker colors that stay readable on light terminals, with text in the terminal's own
+    pub fn light() -> Self {
+        Self {
+            downloaded: Color::Rgb(0x00, 0x87, 0x00),
+            cached: Color::Rgb(0xaf, 0x5f, 0x00),
+            existing: Color::Rgb(0x00, 0x5f, 0xaf),
+            not_found: Color::Rgb(0xaf, 0x00, 0x00),
+            error: Color::Rgb(0x87, 0x00, 0x87),
+            untagged: Color::Rgb(0x00, 0x87, 0x87),
+            excluded: Color::Gray,
+            header: Color::Rgb(0x00, 0x5f, 0xaf),
+            border: Color::Reset,
+            text: Color::Reset,
+            dim: Color::Gray,
+            key: Color::Rgb(0xaf, 0x5f, 0x00),
+            highlight: Color::Rgb(0xff, 0xd7, 0x00),
+            alert: Color::Rgb(0xaf, 0x00, 0x00),
+        }
+    }
+
+    /// The terminal's own colors throughout, for `NO_COLOR`
+    pub fn monochrome() -> Self {
+        Self {
+            downloaded: Color::Reset,
+            cached: Color::Reset,
+            existing: Color::Reset,
+            not_found: Color::Reset,
+            error: Color::Reset,
+            untagged: Color::Reset,
+            excluded: Color::Reset,
+            header: Color::Reset,
+            border: Color::Reset,
+            text: Color::Reset,
+            dim: Color::Reset,
+            key: Color::Reset,
+            highlight: Color::Reset,
+            alert: Color::Reset,
+        }
+    }
+
+    /// Start from the configured preset and apply the colors set on top of it
+    pub fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let mut theme = match config.preset.as_deref() {
+            None | Some("dark") => Self::dark(),
+            Some("light") => Self::light(),
+            Some(other) => bail!("Unknown theme preset {:?} (expected dark or light)", other),
+        };
+        for (name, value, color) in [
+            ("downloaded", &config.downloaded, &mut theme.downloaded),
+            ("cached", &config.cached, &mut theme.cached),
+            ("existing", &config.existing, &mut theme.existing),
+            ("not_found", &config.not_found, &mut theme.not_found),
+            ("error", &config.error, &mut theme.error),
+            ("untagged", &config.untagged, &mut theme.untagged),
+            ("excluded", &config.excluded, &mut theme.excluded),
+            ("header", &config.header, &mut theme.header),
+            ("border", &config.border, &mut theme.border),
+            ("text", &config.text, &mut theme.text),
+            ("dim", &config.dim, &mut theme.dim),
+            ("key", &config.key, &mut theme.key),
+            ("highlight", &config.highlight, &mut theme.highlight),
+            ("alert", &config.alert, &mut theme.alert),
+        ] {
+            if let Some(value) = value {
+                *color = Color::from_str(value)
+                    .ok()
+                    .with_context(|| format!("Invalid color for {}: {}", name, value))?;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// Whether the user asked for no colors by setting `NO_COLOR` (see no-color.org)
+    pub fn no_color() -> bool {
+        std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+    }
+
+    /// Black on the highlight color, or reversed when there are no colors
+    pub fn highlighted(&self) -> Style {
+        match self.highlight {
+            Color::Reset => Style::default().add_modifier(Modifier::REVERSED),
+            highlight => Style::default().fg(Color::Black).bg(highlight),
+        }
+    }
+
+    /// A bordered block in the theme's border color
+    pub fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.border))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_config() {
+        assert_eq!(
+            Theme::from_config(&ThemeConfig::default()).unwrap(),
+            Theme::dark()
+        );
+
+        let config = ThemeConfig {
+            preset: Some("light".to_string()),
+            downloaded: Some("#00ff00".to_string()),
+            border: Some("dark-gray".to_string()),
+            ..Default::default()
+        };
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.downloaded, Color::Rgb(0, 0xff, 0));
+        assert_eq!(theme.border, Color::DarkGray);
+        assert_eq!(theme.cached, Theme::light().cached);
+
+        let config = ThemeConfig {
+            error: Some("ultraviolet".to_string()),
+            ..Default::default()
+        };
+        assert!(Theme::from_config(&config).is_err());
+        let config = ThemeConfig {
+            preset: Some("solarized".to_string()),
+            ..Default::default()
+        };
+        assert!(Theme::from_config(&config).is_err());
+    }
+}
@@ -1,14 +1,55 @@
+use crate::api::{Candidate, SearchAttempt};
+use crate::clock;
+use crate::messages::WorkerStage;
+use crate::scanner::metadata::MetadataSource;
+use crate::session::StatusType;
 use crate::tui::{
-    state::{AppState, Status},
+    glyphs::Glyphs,
+    keys,
+    state::{
+        AlbumRow, AppState, LogFilter, ManualSearch, OutageChoice, QuitChoice, Screen, Status,
+        TrackDetails,
+    },
+    theme::Theme,
     widgets::{MultiProgress, StatusLegend},
 };
+use crossterm::event::KeyCode;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Clear, List, ListItem, ListState, Paragraph, Wrap},
     Frame,
 };
+use std::rc::Rc;
+
+/// Smallest terminal the full layout fits: header, progress, legend, five log lines and footer
+const FULL_SIZE: (u16, u16) = (60, 17);
+/// Smallest terminal the compact layout fits: a status line, the bar, one log line and hints
+const COMPACT_SIZE: (u16, u16) = (20, 6);
+
+/// How much of the TUI fits in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    Full,
+    /// One line each for the status, bar and footer around the log
+    Compact,
+    /// Only a notice to enlarge the terminal
+    TooSmall,
+}
+
+impl Fit {
+    fn of(area: Rect) -> Self {
+        let fits = |(width, height): (u16, u16)| area.width >= width && area.height >= height;
+        if fits(FULL_SIZE) {
+            Fit::Full
+        } else if fits(COMPACT_SIZE) {
+            Fit::Compact
+        } else {
+            Fit::TooSmall
+        }
+    }
+}
 
 /// Render the TUI (Elm Architecture - View)
 pub fn render(frame: &mut Frame, state: &AppState) {
@@ -18,113 +59,1310 @@ pub fn render(frame: &mut Frame, state: &AppState) {
         frame.area(),
     );
 
-    let chunks = Layout::default()
+    match Fit::of(frame.area()) {
+        Fit::Full => {}
+        Fit::Compact => return render_compact(frame, state),
+        Fit::TooSmall => return render_too_small(frame, state),
+    }
+
+    let chunks = layout(frame.area(), state);
+
+    render_header(frame, chunks[0], state);
+    render_progress(frame, chunks[1], state);
+    StatusLegend::render(frame, chunks[2], &state.theme, &state.glyphs);
+    if chunks[3].height > 0 {
+        render_workers(frame, chunks[3], state);
+    }
+    render_screen(frame, chunks[4], state);
+    render_footer(frame, chunks[5], state);
+}
+
+/// Invert everything drawn, to flash the screen
+pub fn flash(frame: &mut Frame) {
+    let area = frame.area();
+    frame
+        .buffer_mut()
+        .set_style(area, Style::default().add_modifier(Modifier::REVERSED));
+}
+
+/// The current screen, and whatever is open over it
+fn render_screen(frame: &mut Frame, area: Rect, state: &AppState) {
+    if state.screen == Screen::Failures {
+        render_failures(frame, area, state);
+    } else if state.screen == Screen::Albums {
+        render_albums(frame, area, state);
+    } else if state.screen == Screen::History {
+        render_history(frame, area, state);
+    } else if state.show_details {
+        // The details pane sits beside the log, which keeps its height
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(area);
+        render_logs(frame, panes[0], state);
+        render_details(frame, panes[1], state);
+    } else {
+        render_logs(frame, area, state);
+    }
+    if !state.picks.is_empty() {
+        render_pick(frame, area, state);
+    }
+    if state.preview.is_some() {
+        render_preview(frame, area, state);
+    }
+    if state.manual_search.is_some() {
+        render_manual_search(frame, area, state);
+    }
+    if state.outage_open() {
+        render_outage(frame, area, state);
+    }
+    if state.quit_prompt.is_some() {
+        render_quit_prompt(frame, area, state);
+    }
+}
+
+/// A status line and the bar above the screen, for terminals too small for the full layout
+fn render_compact(frame: &mut Frame, state: &AppState) {
+    let chunks = compact_layout(frame.area());
+    let theme = &state.theme;
+
+    let status = match &state.status {
+        Status::Error(e) => Span::styled(
+            format!("Error: {}", e),
+            Style::default()
+                .fg(theme.alert)
+                .add_modifier(Modifier::BOLD),
+        ),
+        status if state.connection_lost.is_some() && *status != Status::Complete => {
+            Span::styled("Connection lost", theme.highlighted())
+        }
+        _ => Span::styled(
+            format!(
+                "getlrc {}/{} ({:.0}%){}",
+                state.processed,
+                state.total_files,
+                state.progress() * 100.0,
+                if state.paused { " paused" } else { "" }
+            ),
+            Style::default()
+                .fg(theme.header)
+                .add_modifier(Modifier::BOLD),
+        ),
+    };
+    frame.render_widget(Line::from(status), chunks[0]);
+    if state.total_files > 0 {
+        let bar = progress_bar(state).bar(chunks[1].width as usize, theme, &state.glyphs);
+        frame.render_widget(bar, chunks[1]);
+    }
+    render_screen(frame, chunks[2], state);
+    render_footer(frame, chunks[3], state);
+}
+
+/// A notice in place of the TUI, which keeps taking keys
+fn render_too_small(frame: &mut Frame, state: &AppState) {
+    let area = frame.area();
+    let notice = Paragraph::new(format!(
+        "Terminal too small ({}x{}, needs {}x{}). {} quits",
+        area.width,
+        area.height,
+        COMPACT_SIZE.0,
+        COMPACT_SIZE.1,
+        keys::hint(&state.keys.quit)
+    ))
+    .style(Style::default().fg(state.theme.alert))
+    .wrap(Wrap { trim: true });
+    frame.render_widget(notice, area);
+}
+
+/// Status, bar, screen and footer areas of the compact layout
+fn compact_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Header
-            Constraint::Length(5), // Progress
-            Constraint::Length(1), // Status Legend
-            Constraint::Min(5),    // Logs (responsive)
-            Constraint::Length(3), // Footer
+            Constraint::Length(1), // Status
+            Constraint::Length(1), // Bar
+            Constraint::Min(3),    // Logs
+            Constraint::Length(1), // Footer, without its border
         ])
-        .split(frame.area());
+        .split(area)
+}
 
-    render_header(frame, chunks[0], state);
-    render_progress(frame, chunks[1], state);
-    StatusLegend::render(frame, chunks[2]);
-    render_logs(frame, chunks[3], state);
-    render_footer(frame, chunks[4], state);
+/// Header, progress, legend, workers, logs and footer areas
+fn layout(area: Rect, state: &AppState) -> Rc<[Rect]> {
+    // The worker panel is only shown while files are being processed, and room is left
+    let workers_height = match state.status {
+        Status::Scanning | Status::Processing if !state.workers.is_empty() => {
+            state.workers.len() as u16 + 2
+        }
+        _ => 0,
+    };
+    let workers_height = match area.height >= FULL_SIZE.1 + workers_height {
+        true => workers_height,
+        false => 0,
+    };
+
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),              // Header
+            Constraint::Length(5),              // Progress
+            Constraint::Length(1),              // Status Legend
+            Constraint::Length(workers_height), // Workers
+            Constraint::Min(5),                 // Logs (responsive)
+            Constraint::Length(3),              // Footer
+        ])
+        .split(area)
+}
+
+/// Lines of log entries that fit in a terminal of this size
+pub fn log_height(area: Rect, state: &AppState) -> usize {
+    let logs = match Fit::of(area) {
+        Fit::Full => layout(area, state)[4],
+        Fit::Compact => compact_layout(area)[2],
+        Fit::TooSmall => return 0,
+    };
+    logs.height.saturating_sub(2) as usize
 }
 
 fn render_header(frame: &mut Frame, area: Rect, state: &AppState) {
+    if let Some(retry_at) = state.connection_lost {
+        if state.status != Status::Complete {
+            let remaining = retry_at.saturating_duration_since(std::time::Instant::now());
+            let warning = format!(
+                "getlrc - Connection lost! Lookups paused, retrying in {}s",
+                remaining.as_secs()
+            );
+            return render_warning(frame, area, &warning, &state.theme);
+        }
+    }
+
     let title = match &state.status {
         Status::Idle => "getlrc - Idle",
         Status::Restoring => "getlrc - Restoring Session...",
         Status::Scanning => "getlrc - Scanning...",
         Status::Processing => "getlrc - Processing...",
-        Status::Complete => "getlrc - Complete ✓",
-        Status::Error(e) => return render_error(frame, area, e),
+        Status::Complete => "getlrc - Complete",
+        Status::Error(e) => return render_error(frame, area, e, &state.theme),
+    };
+    let title = match state.status {
+        Status::Complete => format!("{} {}", title, state.glyphs.done),
+        _ => title.to_string(),
     };
 
-    let header = Paragraph::new(title)
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .block(Block::default().borders(Borders::ALL));
+    let block = state.theme.block();
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
 
-    frame.render_widget(header, area);
+    // Run statistics on the right, in the space the title leaves
+    let title_width = title.chars().count() as u16 + 2;
+    let header = Paragraph::new(title).style(
+        Style::default()
+            .fg(state.theme.header)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_widget(header, inner);
+    let stats_area = Rect {
+        x: inner.x + title_width.min(inner.width),
+        width: inner.width.saturating_sub(title_width),
+        ..inner
+    };
+    let stats = Paragraph::new(header_stats(state))
+        .style(Style::default().fg(state.theme.dim))
+        .alignment(Alignment::Right);
+    frame.render_widget(stats, stats_area);
 }
 
-fn render_error(frame: &mut Frame, area: Rect, error: &str) {
+/// Elapsed time, speed, time left and requests sent, as far as they are known
+fn header_stats(state: &AppState) -> String {
+    let mut stats = vec![format!(
+        "{} elapsed",
+        clock::format_duration(state.elapsed())
+    )];
+    if state.status == Status::Processing {
+        if state.paused {
+            stats.push("paused".to_string());
+        } else if let Some(files_per_min) = state.files_per_min {
+            stats.push(format!("{:.0} files/min", files_per_min));
+            stats.push(match state.eta() {
+                Some(eta) => format!("{} left", clock::format_duration(eta)),
+                None => "estimating".to_string(),
+            });
+        }
+    }
+    if state.requests > 0 {
+        stats.push(format!("{} requests", state.requests));
+    }
+    stats.join(" | ")
+}
+
+fn render_warning(frame: &mut Frame, area: Rect, warning: &str, theme: &Theme) {
+    let widget = Paragraph::new(warning)
+        .style(theme.highlighted().add_modifier(Modifier::BOLD))
+        .block(theme.block());
+    frame.render_widget(widget, area);
+}
+
+fn render_error(frame: &mut Frame, area: Rect, error: &str, theme: &Theme) {
     let error_text = format!("Error: {}", error);
     let widget = Paragraph::new(error_text)
-        .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
-        .block(Block::default().borders(Borders::ALL));
+        .style(
+            Style::default()
+                .fg(theme.alert)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(theme.block());
     frame.render_widget(widget, area);
 }
 
 fn render_progress(frame: &mut Frame, area: Rect, state: &AppState) {
+    progress_bar(state).render(frame, area, &state.theme, &state.glyphs);
+
+    // The current track and its stage, under the legend
+    if let Some(line) = current_track_line(state).filter(|_| area.height >= 5) {
+        let line_area = Rect::new(area.x + 2, area.y + 3, area.width.saturating_sub(4), 1);
+        frame.render_widget(line, line_area);
+    }
+}
+
+/// The current track, then the steps of processing it with the one it's at highlighted
+fn current_track_line(state: &AppState) -> Option<Line<'static>> {
+    if state.status != Status::Processing {
+        return None;
+    }
+    let track = state.current_track.as_ref()?;
+    let theme = &state.theme;
+    let stage = state.current_stage();
+    let current = stage.and_then(WorkerStage::step);
+
+    let mut spans = vec![Span::styled(
+        format!("{}  ", track),
+        Style::default().fg(theme.text),
+    )];
+    for (step, name) in WorkerStage::STEPS.into_iter().enumerate() {
+        if step > 0 {
+            spans.push(Span::styled(
+                format!(" {} ", state.glyphs.arrow),
+                Style::default().fg(theme.dim),
+            ));
+        }
+        let span = match current {
+            // The stage itself, when it's a wait within the step ("rate limited")
+            Some(current) if current == step => Span::styled(
+                match stage {
+                    Some(
+                        wait @ (WorkerStage::RateLimited
+                        | WorkerStage::Backoff
+                        | WorkerStage::Picking),
+                    ) => wait.label(),
+                    _ => name,
+                },
+                theme.highlighted().add_modifier(Modifier::BOLD),
+            ),
+            Some(current) if step < current => Span::styled(name, Style::default().fg(theme.text)),
+            _ => Span::styled(name, Style::default().fg(theme.dim)),
+        };
+        spans.push(span);
+    }
+    Some(Line::from(spans))
+}
+
+/// The progress bar's outcomes
+fn progress_bar(state: &AppState) -> MultiProgress {
     // Force 100% completion if status is Complete
     let force_complete = state.status == Status::Complete;
 
     // Total should be exactly what was reported in ScanStarted
     let total = state.total_files;
 
-    let progress = MultiProgress::new(
-        state.downloaded,
-        state.cached,
-        state.skipped,
-        total,
-        force_complete,
-    );
+    let theme = &state.theme;
+    MultiProgress::new(total, force_complete)
+        .segment("Downloaded", state.downloaded, theme.downloaded)
+        .segment("Cached", state.cached, theme.cached)
+        .segment("Existing", state.skipped, theme.existing)
+        .segment("Not found", state.not_found, theme.not_found)
+        .segment("Errors", state.errors.len(), theme.error)
+        .segment("Untagged", state.untagged, theme.untagged)
+}
 
-    progress.render(frame, area);
+fn render_workers(frame: &mut Frame, area: Rect, state: &AppState) {
+    let items: Vec<ListItem> = state
+        .workers
+        .iter()
+        .map(|(worker, status)| {
+            let file = status
+                .path
+                .as_deref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let line = format!(
+                "#{} {:<12} {:>4}s  {}",
+                worker + 1,
+                status.stage.label(),
+                status.in_stage.as_secs(),
+                file
+            );
+            let color = if status.is_stuck() {
+                state.theme.alert
+            } else {
+                state.theme.text
+            };
+            ListItem::new(line).style(Style::default().fg(color))
+        })
+        .collect();
+
+    let list = List::new(items).block(state.theme.block().title(pool_stats(state)));
+
+    frame.render_widget(list, area);
+}
+
+/// "Workers 3/4 busy, 1 rate limited | 9.8/10 req/s, 0.2 free | 1204 queued"
+/// Workers waiting on the limit while requests run at it means the run is rate-limit
+/// bound; busy workers and spare requests mean it waits on the network.
+fn pool_stats(state: &AppState) -> String {
+    let (busy, limited) = state.busy_workers();
+    let mut stats = format!("Workers {}/{} busy", busy, state.workers.len());
+    if limited > 0 {
+        stats.push_str(&format!(", {} rate limited", limited));
+    }
+    if let Some(pool) = state.pool {
+        stats.push_str(&format!(
+            " | {:.1}/{} req/s",
+            pool.requests_per_sec, pool.rate_limit
+        ));
+        if let Some(tokens) = pool.tokens {
+            stats.push_str(&format!(", {:.1} free", tokens));
+        }
+        stats.push_str(&format!(" | {} queued", pool.queued));
+    }
+    stats
 }
 
 fn render_logs(frame: &mut Frame, area: Rect, state: &AppState) {
-    let block = Block::default().borders(Borders::ALL).title("Logs");
+    // Name the filter, and when scrolled back, how much is newer and how to follow again
+    let (shown, newer) = state.log_position();
+    let mut title = match state.log_filter {
+        Some(filter) => format!(
+            "Logs: {} ({}, {} for all)",
+            filter.label(),
+            shown,
+            keys::hint(&state.keys.filter_all)
+        ),
+        None => "Logs".to_string(),
+    };
+    if let Some((matches, current)) = state.search_position() {
+        let query = &state.search.as_ref().unwrap().query;
+        match current {
+            Some(current) => title.push_str(&format!(" /{}/ {} of {}", query, current, matches)),
+            None => title.push_str(&format!(" /{}/ {} matches", query, matches)),
+        }
+    }
+    if newer > 0 {
+        title.push_str(&format!(
+            " {} {} newer, {} to follow",
+            state.glyphs.down,
+            newer,
+            keys::hint(&state.keys.bottom)
+        ));
+    }
+    let block = state.theme.block().title(title);
     let inner = block.inner(area);
 
     // Calculate max width for log entries (account for borders and padding)
     let max_width = inner.width.saturating_sub(2) as usize;
 
-    // Calculate how many lines can fit in the visible area
-    let visible_lines = inner.height as usize;
+    // Long entries wrap, so the oldest ones in view give way until the newest fit
+    let mut entries: Vec<(usize, Vec<String>)> = state
+        .visible_logs()
+        .into_iter()
+        .map(|index| (index, wrap(&state.logs[index].text, max_width)))
+        .collect();
+    let mut lines: usize = entries.iter().map(|(_, lines)| lines.len()).sum();
+    while lines > inner.height as usize && entries.len() > 1 {
+        lines -= entries.remove(0).1.len();
+    }
+
+    let items: Vec<ListItem> = entries
+        .into_iter()
+        .map(|(index, lines)| {
+            let log = &state.logs[index];
+            let item = ListItem::new(lines.into_iter().map(Line::from).collect::<Vec<_>>());
+            let search = state
+                .search
+                .as_ref()
+                .filter(|search| search.is_match(&log.text));
+            if state.selected == Some(index) {
+                item.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else if let Some(search) = search {
+                // Search matches stand out, the one jumped to most of all
+                if search.current == Some(index) {
+                    item.style(state.theme.highlighted())
+                } else {
+                    item.style(Style::default().fg(state.theme.highlight))
+                }
+            } else {
+                item
+            }
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .style(Style::default().fg(state.theme.text));
+
+    frame.render_widget(list, area);
+}
+
+/// Split text into lines of at most `width` characters, at spaces where possible
+/// Lines after the first are indented to line up past the status symbol.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    const INDENT: &str = "    ";
+    let mut lines = Vec::new();
+    let mut rest: Vec<char> = text.chars().collect();
+    let mut indent = "";
+    while width > indent.len() && indent.len() + rest.len() > width {
+        let room = width - indent.len();
+        let (end, skip) = match rest[..=room].iter().rposition(|c| *c == ' ') {
+            Some(space) if space > 0 => (space, 1),
+            _ => (room, 0),
+        };
+        lines.push(format!(
+            "{}{}",
+            indent,
+            rest[..end].iter().collect::<String>()
+        ));
+        rest.drain(..end + skip);
+        if width > INDENT.len() * 2 {
+            indent = INDENT;
+        }
+    }
+    lines.push(format!(
+        "{}{}",
+        indent,
+        rest.into_iter().collect::<String>()
+    ));
+    lines
+}
+
+/// Every file without lyrics yet, in path order, with marks and why it failed
+fn render_failures(frame: &mut Frame, area: Rect, state: &AppState) {
+    let mut title = format!("Failures ({})", state.failures.len());
+    if !state.marked.is_empty() {
+        title.push_str(&format!(", {} marked", state.marked.len()));
+    }
+    let block = state.theme.block().title(title);
+
+    if state.failures.is_empty() {
+        let empty = Paragraph::new(Span::styled(
+            "No failed or missing lyrics so far",
+            Style::default().fg(state.theme.dim),
+        ))
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = state
+        .failures
+        .iter()
+        .map(|(path, failure)| {
+            let color = match failure.status {
+                StatusType::Error => state.theme.error,
+                _ => state.theme.not_found,
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(if state.marked.contains(path) {
+                    "[*] "
+                } else {
+                    "[ ] "
+                }),
+                Span::styled(
+                    format!("{} ", state.glyphs.status(&failure.status)),
+                    Style::default().fg(color),
+                ),
+                Span::styled(
+                    path.display().to_string(),
+                    Style::default().fg(state.theme.text),
+                ),
+                Span::styled(
+                    format!(" - {}", failure.reason),
+                    Style::default().fg(state.theme.dim),
+                ),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default().with_selected(Some(state.failure_cursor));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// The log grouped by album, each with how many of its files have lyrics
+fn render_albums(frame: &mut Frame, area: Rect, state: &AppState) {
+    let albums = state.albums();
+    let mut title = format!("Albums ({})", albums.len());
+    if let Some(filter) = state.log_filter {
+        title.push_str(&format!(", {} only", filter.label()));
+    }
+    let block = state.theme.block().title(title);
+
+    if albums.is_empty() {
+        let empty = Paragraph::new(Span::styled(
+            "No files processed yet",
+            Style::default().fg(state.theme.dim),
+        ))
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = state
+        .album_rows(&albums)
+        .into_iter()
+        .map(|row| match row {
+            AlbumRow::Album(position) => {
+                let album = &albums[position];
+                let marker = if state.expanded.contains(&album.dir) {
+                    state.glyphs.expanded
+                } else {
+                    state.glyphs.collapsed
+                };
+                // Complete albums in the downloaded color, those without any lyrics in red
+                let color = match (album.with_lyrics, album.total) {
+                    (_, 0) => state.theme.dim,
+                    (with, total) if with == total => state.theme.downloaded,
+                    (0, _) => state.theme.not_found,
+                    _ => state.theme.cached,
+                };
+                let ratio = match album.total {
+                    0 => String::new(),
+                    total => format!(
+                        "  {}/{} ({:.0}%)",
+                        album.with_lyrics,
+                        total,
+                        album.with_lyrics as f64 * 100.0 / total as f64
+                    ),
+                };
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{} ", marker)),
+                    Span::styled(
+                        album.dir.display().to_string(),
+                        Style::default()
+                            .fg(state.theme.text)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(ratio, Style::default().fg(color)),
+                ]))
+            }
+            AlbumRow::Entry(index) => ListItem::new(Line::from(Span::styled(
+                format!("    {}", state.logs[index].text),
+                Style::default().fg(state.theme.text),
+            ))),
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default().with_selected(Some(state.album_cursor));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
+
+/// Past runs with their summaries, or the report of the highlighted one
+fn render_history(frame: &mut Frame, area: Rect, state: &AppState) {
+    if let Some(run) = state
+        .history
+        .get(state.history_cursor)
+        .filter(|_| state.history_report)
+    {
+        let lines: Vec<Line> = run
+            .report()
+            .into_iter()
+            .map(|line| Line::from(Span::styled(line, Style::default().fg(state.theme.text))))
+            .collect();
+        let report = Paragraph::new(lines).block(state.theme.block().title(format!(
+            "Run {} of {}",
+            state.history_cursor + 1,
+            state.history.len()
+        )));
+        frame.render_widget(report, area);
+        return;
+    }
 
-    // Take only the most recent entries that fit in the visible area
-    let start_index = state.logs.len().saturating_sub(visible_lines);
+    let block = state
+        .theme
+        .block()
+        .title(format!("History ({} runs)", state.history.len()));
+    if state.history.is_empty() {
+        let empty = Paragraph::new(Span::styled(
+            "No runs recorded yet",
+            Style::default().fg(state.theme.dim),
+        ))
+        .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
 
     let items: Vec<ListItem> = state
-        .logs
+        .history
         .iter()
-        .skip(start_index)
-        .map(|log| {
-            let truncated = if log.len() > max_width {
-                format!("{}...", &log[..max_width.saturating_sub(3)])
+        .map(|run| {
+            // Runs that were quit, or left lyrics missing, stand out
+            let color = if !run.completed {
+                state.theme.dim
+            } else if run.errors > 0 {
+                state.theme.error
+            } else if run.not_found > 0 {
+                state.theme.cached
             } else {
-                log.clone()
+                state.theme.downloaded
             };
-            ListItem::new(truncated)
+            ListItem::new(Line::from(Span::styled(
+                run.summary(),
+                Style::default().fg(color),
+            )))
         })
         .collect();
 
     let list = List::new(items)
         .block(block)
-        .style(Style::default().fg(Color::White));
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut list_state = ListState::default().with_selected(Some(state.history_cursor));
+    frame.render_stateful_widget(list, area, &mut list_state);
+}
 
-    frame.render_widget(list, area);
+fn render_details(frame: &mut Frame, area: Rect, state: &AppState) {
+    let block = state.theme.block().title(format!(
+        "Details ({} to close)",
+        keys::hint(&state.keys.details)
+    ));
+    let lines = match state.details_track() {
+        Some(details) => details_lines(details, &state.theme, &state.glyphs),
+        None => vec![Line::from(Span::styled(
+            format!(
+                "No track yet; select one in the log with {}",
+                select_hint(state)
+            ),
+            Style::default().fg(state.theme.dim),
+        ))],
+    };
+    let widget = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(widget, area);
+}
+
+/// Path, tags and every query tried for a track, with what LRCLIB returned
+fn details_lines(details: &TrackDetails, theme: &Theme, glyphs: &Glyphs) -> Vec<Line<'static>> {
+    let label = Style::default().fg(theme.header);
+    let field = |name: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", name), label),
+            Span::raw(value),
+        ])
+    };
+
+    let track = &details.track;
+    let mut lines = vec![
+        Line::from(track.path.display().to_string()),
+        field("Artist", track.artist.clone()),
+        field("Title", track.title.clone()),
+        field("Album", track.album.clone()),
+        field(
+            "Duration",
+            format!(
+                "{}:{:02}",
+                track.duration_secs / 60,
+                track.duration_secs % 60
+            ),
+        ),
+        field(
+            "From",
+            match track.source {
+                MetadataSource::Tags => "tags",
+                MetadataSource::Path => "file path (no usable tags)",
+                MetadataSource::Fingerprint => "audio fingerprint (AcoustID)",
+                MetadataSource::Manual => "entered by hand",
+            }
+            .to_string(),
+        ),
+    ];
+    if track.artists.len() > 1 {
+        lines.push(field("Artists", track.artists.join(", ")));
+    }
+    if !track.sort_artist.is_empty() {
+        lines.push(field("Sort", track.sort_artist.clone()));
+    }
+    if track.compilation {
+        lines.push(field("", "compilation, searched without album".to_string()));
+    }
+
+    lines.push(Line::from(""));
+    if details.attempts.is_empty() {
+        lines.push(Line::from(Span::styled(
+            "Not searched on LRCLIB",
+            Style::default().fg(theme.dim),
+        )));
+    }
+    let last = details.attempts.len().saturating_sub(1);
+    for (number, attempt) in details.attempts.iter().enumerate() {
+        lines.extend(attempt_lines(
+            number,
+            attempt,
+            number == last,
+            theme,
+            glyphs,
+        ));
+    }
+    lines
+}
+
+/// A query, the result it got, and whether that result was used
+fn attempt_lines(
+    number: usize,
+    attempt: &SearchAttempt,
+    last: bool,
+    theme: &Theme,
+    glyphs: &Glyphs,
+) -> Vec<Line<'static>> {
+    let mut query = format!("{} - {}", attempt.artist, attempt.title);
+    if !attempt.album.is_empty() {
+        query.push_str(&format!(" [{}]", attempt.album));
+    }
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("{}. {}", number + 1, attempt.strategy),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(format!("   {}", query)),
+    ];
+
+    let Some(candidate) = &attempt.candidate else {
+        lines.push(Line::from(Span::styled(
+            format!("   {} nothing found", glyphs.arrow),
+            Style::default().fg(theme.dim),
+        )));
+        return lines;
+    };
+    lines.push(Line::from(format!(
+        "   {} {} - {}",
+        glyphs.arrow, candidate.artist, candidate.title
+    )));
+    let scores = format!(
+        "     artist {:.2}, title {:.2}, average {:.2}",
+        candidate.artist_similarity,
+        candidate.title_similarity,
+        candidate.similarity()
+    );
+    // Only the last query can have been used; the search stops at the first match
+    let verdict = if !candidate.is_match() {
+        Span::styled(" too different", Style::default().fg(theme.not_found))
+    } else if last && candidate.instrumental {
+        Span::styled(" chosen, instrumental", Style::default().fg(theme.cached))
+    } else if last && !candidate.synced {
+        Span::styled(
+            " chosen, no synced lyrics",
+            Style::default().fg(theme.cached),
+        )
+    } else {
+        Span::styled(" chosen", Style::default().fg(theme.downloaded))
+    };
+    lines.push(Line::from(vec![Span::raw(scores), verdict]));
+    lines
+}
+
+/// The oldest pick waiting for an answer, over the log
+fn render_pick(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some(pick) = state.picks.front() else {
+        return;
+    };
+    let mut title = format!(
+        "Pick lyrics for {}",
+        pick.path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default()
+    );
+    if state.picks.len() > 1 {
+        title.push_str(&format!(" ({} more waiting)", state.picks.len() - 1));
+    }
+
+    let items: Vec<ListItem> = pick
+        .candidates
+        .iter()
+        .enumerate()
+        .map(|(number, candidate)| {
+            let item = ListItem::new(candidate_line(number, candidate));
+            if number == pick.selected {
+                item.style(Style::default().add_modifier(Modifier::REVERSED))
+            } else {
+                item
+            }
+        })
+        .collect();
+
+    let height = (pick.candidates.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(
+        area.x + 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        area.width.saturating_sub(4),
+        height,
+    );
+    let list = List::new(items).block(
+        state
+            .theme
+            .block()
+            .border_style(Style::default().fg(state.theme.highlight))
+            .title(title),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(list, popup);
+}
+
+/// The quit prompt in the middle of the log, its choices with the keys that answer it
+fn render_quit_prompt(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some(prompt) = &state.quit_prompt else {
+        return;
+    };
+    let mut lines = vec![Line::from(if prompt.choices.contains(&QuitChoice::Save) {
+        "Files are still pending."
+    } else {
+        "The scan is still running, so the next run starts over."
+    })];
+    lines.push(Line::from(""));
+    for (index, choice) in prompt.choices.iter().enumerate() {
+        let line = Line::from(format!("[{}] {}", choice.key(), choice.label()));
+        lines.push(if index == prompt.selected {
+            line.style(Style::default().add_modifier(Modifier::REVERSED))
+        } else {
+            line
+        });
+    }
+
+    let width = 60.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    let widget = Paragraph::new(lines).block(
+        state
+            .theme
+            .block()
+            .border_style(Style::default().fg(state.theme.alert))
+            .title("Quit?"),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+/// The manual search form: each field with the one being edited highlighted
+fn render_manual_search(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some(form) = &state.manual_search else {
+        return;
+    };
+    let label_width = ManualSearch::LABELS
+        .iter()
+        .map(|label| label.len())
+        .max()
+        .unwrap_or_default();
+    let mut lines: Vec<Line> = ManualSearch::LABELS
+        .iter()
+        .zip(&form.fields)
+        .enumerate()
+        .map(|(index, (label, value))| {
+            let label = Span::styled(
+                format!("{:>width$}: ", label, width = label_width),
+                Style::default().fg(state.theme.dim),
+            );
+            if index == form.focus {
+                Line::from(vec![
+                    label,
+                    Span::styled(
+                        format!("{}{}", value, state.glyphs.cursor),
+                        Style::default().fg(state.theme.highlight),
+                    ),
+                ])
+            } else {
+                Line::from(vec![label, Span::raw(value.clone())])
+            }
+        })
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(match &form.error {
+        Some(error) => Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(state.theme.alert),
+        )),
+        None => Line::from(Span::styled(
+            "Duration is m:ss or seconds, left empty to keep the file's",
+            Style::default().fg(state.theme.dim),
+        )),
+    });
+
+    let width = 70.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    let name = form
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let widget = Paragraph::new(lines).block(
+        state
+            .theme
+            .block()
+            .border_style(Style::default().fg(state.theme.header))
+            .title(format!("Search manually: {}", name)),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+/// What went wrong with the connection and when lookups resume, with what can be done
+fn render_outage(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some(outage) = &state.outage else {
+        return;
+    };
+    let remaining = state
+        .connection_lost
+        .map(|retry_at| retry_at.saturating_duration_since(std::time::Instant::now()))
+        .unwrap_or_default();
+    let mut lines = vec![
+        Line::from(outage.error.clone()),
+        Line::from(""),
+        Line::from(match outage.trips {
+            1 => format!("Lookups are paused, retrying in {}s.", remaining.as_secs()),
+            trips => format!(
+                "Lookups are paused after {} failed attempts, retrying in {}s.",
+                trips,
+                remaining.as_secs()
+            ),
+        }),
+        Line::from(""),
+    ];
+    for (index, choice) in OutageChoice::ALL.into_iter().enumerate() {
+        let line = Line::from(format!(
+            "[{}] {}",
+            choice.key(),
+            choice.label(state.scan_complete)
+        ));
+        lines.push(if index == outage.selected {
+            line.style(Style::default().add_modifier(Modifier::REVERSED))
+        } else {
+            line
+        });
+    }
+
+    let width = 70.min(area.width);
+    let inner_width = width.saturating_sub(2).max(1) as usize;
+    // The error wraps, so it may take more than its line
+    let error_lines = outage.error.chars().count().div_ceil(inner_width).max(1) as u16;
+    let height = (lines.len() as u16 + error_lines + 1).min(area.height);
+    let popup = Rect::new(
+        area.x + area.width.saturating_sub(width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    );
+    let widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+        state
+            .theme
+            .block()
+            .border_style(Style::default().fg(state.theme.alert))
+            .title("Connection lost"),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+/// Lyrics playing over the log, the current line kept in the middle and highlighted
+fn render_preview(frame: &mut Frame, area: Rect, state: &AppState) {
+    let Some(preview) = &state.preview else {
+        return;
+    };
+    let time = |duration: std::time::Duration| {
+        let secs = duration.as_secs();
+        format!("{}:{:02}", secs / 60, secs % 60)
+    };
+    let title = format!(
+        "{} {} {} / {}",
+        if preview.is_paused() {
+            state.glyphs.paused
+        } else {
+            state.glyphs.playing
+        },
+        preview
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default(),
+        time(preview.position().min(preview.length())),
+        time(preview.length())
+    );
+
+    let popup = Rect::new(
+        area.x + 2,
+        area.y,
+        area.width.saturating_sub(4),
+        area.height,
+    );
+    let height = popup.height.saturating_sub(2) as usize;
+    let current = preview.current_line();
+    let first = current.unwrap_or(0).saturating_sub(height / 2);
+    let lines: Vec<Line> = preview
+        .lines
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(height)
+        .map(|(index, (start, text))| {
+            let style = match current {
+                Some(current) if index == current => Style::default()
+                    .fg(state.theme.highlight)
+                    .add_modifier(Modifier::BOLD),
+                Some(current) if index < current => Style::default().fg(state.theme.dim),
+                _ => Style::default(),
+            };
+            Line::from(vec![
+                Span::styled(
+                    format!("{:>6} ", time(*start)),
+                    Style::default().fg(state.theme.dim),
+                ),
+                Span::styled(text.clone(), style),
+            ])
+        })
+        .collect();
+
+    let widget = Paragraph::new(lines).block(
+        state
+            .theme
+            .block()
+            .border_style(Style::default().fg(state.theme.header))
+            .title(title),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(widget, popup);
+}
+
+/// A search result as "1. Artist - Title [Album] 3:45 synced 92%"
+fn candidate_line(number: usize, candidate: &Candidate) -> String {
+    let mut line = format!("{}. {} - {}", number + 1, candidate.artist, candidate.title);
+    if !candidate.album.is_empty() {
+        line.push_str(&format!(" [{}]", candidate.album));
+    }
+    if let Some(secs) = candidate.duration_secs {
+        line.push_str(&format!(" {}:{:02}", secs / 60, secs % 60));
+    }
+    line.push_str(if candidate.synced {
+        " synced"
+    } else {
+        " plain only"
+    });
+    line.push_str(&format!(" {:.0}%", candidate.similarity() * 100.0));
+    line
 }
 
 fn render_footer(frame: &mut Frame, area: Rect, state: &AppState) {
+    let theme = &state.theme;
+    let key = Style::default().fg(theme.key).add_modifier(Modifier::BOLD);
+    // The compact layout leaves a single line, without room for a border
+    let block = match area.height {
+        0..=2 => Block::default(),
+        _ => theme.block(),
+    };
+
+    // The search prompt replaces the key hints while a query is typed
+    if let Some(search) = state.search.as_ref().filter(|search| search.editing) {
+        let prompt = Line::from(vec![
+            Span::styled(
+                format!("/{}{}", search.query, state.glyphs.cursor),
+                Style::default().fg(theme.highlight),
+            ),
+            Span::raw("  Enter Search | Esc Cancel"),
+        ]);
+        let footer = Paragraph::new(prompt).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // The manual search form takes every key while it is open
+    if state.manual_search.is_some() {
+        let hints = Line::from(vec![
+            Span::styled(format!("Tab/{}", state.glyphs.up_down), key),
+            Span::raw(" Field | "),
+            Span::styled("Enter", key),
+            Span::raw(" Search | "),
+            Span::styled("Esc", key),
+            Span::raw(" Cancel"),
+        ]);
+        let footer = Paragraph::new(hints).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // The quit prompt and the connection lost modal are answered before anything else
+    if state.quit_prompt.is_some() || state.outage_open() {
+        let hints = Line::from(vec![
+            Span::styled(state.glyphs.up_down, key),
+            Span::raw(" Select | "),
+            Span::styled("Enter", key),
+            Span::raw(" Confirm | "),
+            Span::styled("Esc", key),
+            Span::raw(if state.quit_prompt.is_some() {
+                " Cancel"
+            } else {
+                " Keep waiting"
+            }),
+        ]);
+        let footer = Paragraph::new(hints).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // Working through failures
+    if state.screen == Screen::Failures {
+        let mut spans = vec![
+            Span::styled(keys::hint(&state.keys.failures), key),
+            Span::raw(" Log | "),
+            Span::styled(keys::hint(&state.keys.quit), key),
+            Span::raw(" Quit"),
+        ];
+        if !state.failures.is_empty() {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(select_hint(state), key));
+            spans.push(Span::raw(" Select | "));
+            spans.push(Span::styled(keys::hint(&state.keys.mark), key));
+            spans.push(Span::raw(" Mark | "));
+            if state.status != Status::Complete {
+                spans.push(Span::styled(keys::hint(&state.keys.retry), key));
+                spans.push(Span::raw(" Retry | "));
+                spans.push(Span::styled(keys::hint(&state.keys.manual_search), key));
+                spans.push(Span::raw(" Search manually | "));
+            }
+            spans.push(Span::styled(keys::hint(&state.keys.export), key));
+            spans.push(Span::raw(if state.marked.is_empty() {
+                " Export all | "
+            } else {
+                " Export marked | "
+            }));
+            spans.push(Span::styled(keys::hint(&state.keys.copy), key));
+            spans.push(Span::raw(" Copy"));
+        }
+        let footer = Paragraph::new(Line::from(spans)).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // Browsing albums
+    if state.screen == Screen::Albums {
+        let spans = vec![
+            Span::styled(keys::hint(&state.keys.albums), key),
+            Span::raw(" Log | "),
+            Span::styled(keys::hint(&state.keys.quit), key),
+            Span::raw(" Quit | "),
+            Span::styled(select_hint(state), key),
+            Span::raw(" Select | "),
+            Span::styled(
+                format!(
+                    "{}/{}",
+                    keys::hint(&state.keys.expand),
+                    keys::hint(&state.keys.collapse)
+                ),
+                key,
+            ),
+            Span::raw(" Open/Close | "),
+            Span::styled(keys::hint(&state.keys.copy), key),
+            Span::raw(" Copy path | "),
+            Span::styled(keys::hint(&state.keys.failures), key),
+            Span::raw(" Failures | "),
+            Span::styled(
+                format!(
+                    "{}-{}",
+                    keys::hint(&state.keys.filters[..1]),
+                    keys::hint(&state.keys.filters[LogFilter::ALL.len() - 1..])
+                ),
+                key,
+            ),
+            Span::raw(" Filter"),
+        ];
+        let footer = Paragraph::new(Line::from(spans)).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // Browsing past runs
+    if state.screen == Screen::History {
+        let mut spans = vec![
+            Span::styled(keys::hint(&state.keys.history), key),
+            Span::raw(" Log | "),
+            Span::styled(keys::hint(&state.keys.quit), key),
+            Span::raw(" Quit"),
+        ];
+        if state.history_report {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(keys::hint(&state.keys.collapse), key));
+            spans.push(Span::raw(" Back"));
+        } else if !state.history.is_empty() {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(select_hint(state), key));
+            spans.push(Span::raw(" Select | "));
+            spans.push(Span::styled(keys::hint(&state.keys.expand), key));
+            spans.push(Span::raw(" Report"));
+        }
+        if !state.history.is_empty() {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(keys::hint(&state.keys.copy), key));
+            spans.push(Span::raw(" Copy target"));
+        }
+        let footer = Paragraph::new(Line::from(spans)).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // Playback controls while the preview is open
+    if state.preview.is_some() {
+        let hints = Line::from(vec![
+            Span::styled("Space", key),
+            Span::raw(" Pause/Play | "),
+            Span::styled(state.glyphs.left_right, key),
+            Span::raw(" Seek 5s | "),
+            Span::styled("Esc", key),
+            Span::raw(" Close"),
+        ]);
+        let footer = Paragraph::new(hints).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
+    // Answering a pick comes first, quitting and pausing still work
+    if !state.picks.is_empty() {
+        let hints = Line::from(vec![
+            Span::styled(format!("1-9/{}", state.glyphs.up_down), key),
+            Span::raw(" Pick | "),
+            Span::styled("Enter", key),
+            Span::raw(" Write | "),
+            Span::styled("Esc", key),
+            Span::raw(" Skip | "),
+            Span::styled(keys::hint(&state.keys.quit), key),
+            Span::raw(" Quit"),
+        ]);
+        let footer = Paragraph::new(hints).block(block.clone());
+        frame.render_widget(footer, area);
+        return;
+    }
+
     let mut spans = vec![
-        Span::styled(
-            "q",
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD),
-        ),
+        Span::styled(keys::hint(&state.keys.quit), key),
         Span::raw(" Quit"),
     ];
 
@@ -135,24 +1373,211 @@ fn render_footer(frame: &mut Frame, area: Rect, state: &AppState) {
         // Add Pause/Resume control based on state
         if state.paused {
             spans.push(Span::styled(
-                "r",
+                keys::hint(&state.keys.resume),
                 Style::default()
-                    .fg(Color::Green)
+                    .fg(theme.downloaded)
                     .add_modifier(Modifier::BOLD),
             ));
             spans.push(Span::raw(" Resume"));
         } else {
             spans.push(Span::styled(
-                "p",
+                keys::hint(&state.keys.pause),
                 Style::default()
-                    .fg(Color::Cyan)
+                    .fg(theme.header)
                     .add_modifier(Modifier::BOLD),
             ));
             spans.push(Span::raw(" Pause"));
         }
+
+        // Files can be picked from the log to retry them or move their album forward
+        if state.logs.iter().any(|log| log.path.is_some()) {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(select_hint(state), key));
+            spans.push(Span::raw(" Select | "));
+            spans.push(Span::styled(keys::hint(&state.keys.retry), key));
+            spans.push(Span::raw(" Retry | "));
+            spans.push(Span::styled(keys::hint(&state.keys.album_first), key));
+            spans.push(Span::raw(" Album first"));
+            if state
+                .selected
+                .is_some_and(|index| state.logs[index].status == Some(StatusType::NotFound))
+            {
+                spans.push(Span::raw(" | "));
+                spans.push(Span::styled(keys::hint(&state.keys.manual_search), key));
+                spans.push(Span::raw(" Search manually"));
+            }
+        }
+    }
+
+    // Sidecars can be played back during the run and after it
+    if state.logs.iter().any(|log| log.path.is_some()) {
+        spans.push(Span::raw(" | "));
+        if state.status == Status::Complete {
+            spans.push(Span::styled(select_hint(state), key));
+            spans.push(Span::raw(" Select | "));
+        }
+        spans.push(Span::styled(keys::hint(&state.keys.preview), key));
+        spans.push(Span::raw(" Preview"));
+        if state.selected.is_some() {
+            spans.push(Span::raw(" | "));
+            spans.push(Span::styled(keys::hint(&state.keys.copy), key));
+            spans.push(Span::raw(" Copy path"));
+        }
+    }
+
+    // The log can be browsed after the run too, scrolling once it no longer fits
+    if state.logs.len() > state.log_height {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(
+            format!(
+                "{}/{}",
+                keys::hint(&state.keys.page_up),
+                keys::hint(&state.keys.page_down)
+            ),
+            key,
+        ));
+        spans.push(Span::raw(" Scroll"));
+    }
+
+    // Search, with jumps between matches once there is one
+    spans.push(Span::raw(" | "));
+    if state.search.is_some() {
+        spans.push(Span::styled(
+            format!(
+                "{}/{}",
+                keys::hint(&state.keys.next_match),
+                keys::hint(&state.keys.previous_match)
+            ),
+            key,
+        ));
+        spans.push(Span::raw(" Older/newer match"));
+    } else {
+        spans.push(Span::styled(keys::hint(&state.keys.search), key));
+        spans.push(Span::raw(" Search"));
+    }
+
+    spans.push(Span::raw(" | "));
+    spans.push(Span::styled(keys::hint(&state.keys.details), key));
+    spans.push(Span::raw(" Details"));
+    spans.push(Span::raw(" | "));
+    spans.push(Span::styled(keys::hint(&state.keys.log_level), key));
+    spans.push(Span::raw(if state.debug_log {
+        " Log info only"
+    } else {
+        " Log debug"
+    }));
+
+    if state.logs.iter().any(|log| log.path.is_some()) {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(keys::hint(&state.keys.albums), key));
+        spans.push(Span::raw(" Albums"));
+    }
+
+    if !state.failures.is_empty() {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(keys::hint(&state.keys.failures), key));
+        spans.push(Span::raw(format!(" Failures ({})", state.failures.len())));
+    }
+
+    spans.push(Span::raw(" | "));
+    spans.push(Span::styled(keys::hint(&state.keys.history), key));
+    spans.push(Span::raw(" History"));
+
+    // Filters, once there are outcomes to pick from
+    if state.logs.iter().any(|log| log.status.is_some()) {
+        spans.push(Span::raw(" | "));
+        spans.push(Span::styled(
+            format!(
+                "{}-{}",
+                keys::hint(&state.keys.filters[..1]),
+                keys::hint(&state.keys.filters[LogFilter::ALL.len() - 1..])
+            ),
+            key,
+        ));
+        spans.push(Span::raw(" Filter"));
     }
 
-    let footer = Paragraph::new(Line::from(spans)).block(Block::default().borders(Borders::ALL));
+    let footer = Paragraph::new(Line::from(spans)).block(block.clone());
 
     frame.render_widget(footer, area);
 }
+
+/// Keys that move the selection, as arrows unless they have been remapped
+fn select_hint(state: &AppState) -> String {
+    match (state.keys.up.as_slice(), state.keys.down.as_slice()) {
+        ([KeyCode::Up], [KeyCode::Down]) => state.glyphs.up_down.to_string(),
+        (up, down) => format!("{}/{}", keys::hint(up), keys::hint(down)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_log_entries() {
+        assert_eq!(wrap("[✓] short.flac", 20), vec!["[✓] short.flac"]);
+        // At spaces where possible, by characters rather than bytes otherwise
+        assert_eq!(
+            wrap("[!] Björk - Jóga.flac: connection reset", 20),
+            vec!["[!] Björk -", "    Jóga.flac:", "    connection reset"]
+        );
+        assert_eq!(wrap("ééééééééééééé", 10), vec!["éééééééééé", "    ééé"]);
+        // Too narrow to indent
+        assert_eq!(wrap("abcdefg", 3), vec!["abc", "def", "g"]);
+    }
+
+    #[test]
+    fn test_render_any_terminal_size() {
+        use crate::messages::{WorkerMessage, WorkerStage};
+        use ratatui::{backend::TestBackend, Terminal};
+        use std::path::PathBuf;
+        use std::time::Duration;
+
+        let mut state = AppState::new();
+        state.update(WorkerMessage::ScanStarted { total_files: 10 });
+        for worker in 0..4 {
+            state.update(WorkerMessage::WorkerHeartbeat {
+                worker,
+                path: None,
+                stage: WorkerStage::Searching,
+                in_stage: Duration::ZERO,
+            });
+        }
+        state.update(WorkerMessage::LyricsNotFound {
+            path: PathBuf::from("/music/Artist/Album/01 - A rather long track title.flac"),
+        });
+        state.request_quit();
+
+        let screen = |width, height| {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|frame| render(frame, &state)).unwrap();
+            let buffer = terminal.backend().buffer().clone();
+            buffer
+                .content()
+                .chunks(width as usize)
+                .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        for (width, height) in [
+            (1, 1),
+            (19, 5),
+            (20, 6),
+            (59, 16),
+            (60, 17),
+            (60, 20),
+            (120, 40),
+        ] {
+            screen(width, height);
+        }
+
+        assert!(screen(30, 4).starts_with("Terminal too small (30x4,"));
+        assert!(screen(40, 10).starts_with("getlrc 1/10 (10%)"));
+        // The worker panel gives way to the log when both don't fit
+        assert!(!screen(80, 20).contains("Workers"));
+        assert!(screen(80, 30).contains("Workers"));
+        assert_eq!(log_height(Rect::new(0, 0, 40, 10), &state), 5);
+        assert_eq!(log_height(Rect::new(0, 0, 10, 3), &state), 0);
+    }
+}
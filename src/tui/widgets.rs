@@ -1,106 +1,117 @@
+use crate::session::StatusType;
+use crate::tui::{glyphs::Glyphs, theme::Theme};
 use ratatui::{
     layout::Rect,
     style::{Color, Style},
     text::{Line, Span},
-    widgets::{Block, Borders},
     Frame,
 };
 
+/// One kind of outcome in the progress bar and its legend
+struct Segment {
+    label: &'static str,
+    count: usize,
+    color: Color,
+}
+
 pub struct MultiProgress {
-    downloaded: usize,
-    cached: usize,
-    skipped: usize,
+    segments: Vec<Segment>,
     total: usize,
     force_complete: bool,
 }
 
 impl MultiProgress {
-    pub fn new(
-        downloaded: usize,
-        cached: usize,
-        skipped: usize,
-        total: usize,
-        force_complete: bool,
-    ) -> Self {
+    pub fn new(total: usize, force_complete: bool) -> Self {
         Self {
-            downloaded,
-            cached,
-            skipped,
+            segments: Vec::new(),
             total,
             force_complete,
         }
     }
 
-    pub fn render(&self, frame: &mut Frame, area: Rect) {
-        let block = Block::default().borders(Borders::ALL).title("Progress");
-
-        let inner = block.inner(area);
-        frame.render_widget(block, area);
-
-        // Calculate bar width (leave 2 chars for borders)
-        let bar_width = inner.width.saturating_sub(2) as usize;
+    /// Add a part of the bar, drawn after the ones added before it
+    pub fn segment(mut self, label: &'static str, count: usize, color: Color) -> Self {
+        self.segments.push(Segment {
+            label,
+            count,
+            color,
+        });
+        self
+    }
 
-        if bar_width == 0 || self.total == 0 {
-            return;
-        }
+    /// Width of each bar segment, and of the empty rest
+    fn widths(&self, bar_width: usize) -> (Vec<usize>, usize) {
+        let counts: Vec<usize> = self.segments.iter().map(|segment| segment.count).collect();
+        let filled: usize = counts.iter().sum();
 
-        // If force_complete, fill the entire bar
-        let (downloaded_width, cached_width, skipped_width, empty_width) = if self.force_complete {
+        if self.force_complete {
             // Fill bar proportionally but ensure it reaches 100%
-            let total_processed = self.downloaded + self.cached + self.skipped;
-            if total_processed > 0 {
-                let downloaded_width = (self.downloaded * bar_width) / total_processed;
-                let cached_width = (self.cached * bar_width) / total_processed;
-                let skipped_width = (self.skipped * bar_width) / total_processed;
-                let filled = downloaded_width + cached_width + skipped_width;
-                // Add any remainder to downloaded to ensure full bar
-                let remainder = bar_width.saturating_sub(filled);
-                (downloaded_width + remainder, cached_width, skipped_width, 0)
-            } else {
-                (bar_width, 0, 0, 0)
+            if filled == 0 {
+                let mut widths = vec![0; counts.len()];
+                if let Some(first) = widths.first_mut() {
+                    *first = bar_width;
+                }
+                return (widths, 0);
+            }
+            let mut widths: Vec<usize> = counts
+                .iter()
+                .map(|count| count * bar_width / filled)
+                .collect();
+            // Add any remainder to the first segment to ensure full bar
+            let remainder = bar_width.saturating_sub(widths.iter().sum());
+            if let Some(first) = widths.first_mut() {
+                *first += remainder;
             }
+            show_every_outcome(&mut widths, &counts, bar_width);
+            (widths, 0)
         } else {
             // Normal calculation
-            let downloaded_width = (self.downloaded * bar_width) / self.total.max(1);
-            let cached_width = (self.cached * bar_width) / self.total.max(1);
-            let skipped_width = (self.skipped * bar_width) / self.total.max(1);
-            let filled_width = downloaded_width + cached_width + skipped_width;
-            let empty_width = bar_width.saturating_sub(filled_width);
-            (downloaded_width, cached_width, skipped_width, empty_width)
-        };
+            let mut widths: Vec<usize> = counts
+                .iter()
+                .map(|count| count * bar_width / self.total.max(1))
+                .collect();
+            show_every_outcome(&mut widths, &counts, bar_width);
+            let empty_width = bar_width.saturating_sub(widths.iter().sum());
+            (widths, empty_width)
+        }
+    }
 
-        // Build progress bar spans
-        let mut spans = Vec::new();
+    /// The bar alone, this many cells wide
+    pub fn bar(&self, bar_width: usize, theme: &Theme, glyphs: &Glyphs) -> Line<'static> {
+        let (widths, empty_width) = self.widths(bar_width);
+        let mut spans: Vec<Span> = self
+            .segments
+            .iter()
+            .zip(widths)
+            .filter(|(_, width)| *width > 0)
+            .map(|(segment, width)| {
+                Span::styled(glyphs.bar.repeat(width), Style::default().fg(segment.color))
+            })
+            .collect();
 
-        if downloaded_width > 0 {
+        if empty_width > 0 {
             spans.push(Span::styled(
-                "█".repeat(downloaded_width),
-                Style::default().fg(Color::Green),
+                glyphs.bar_empty.repeat(empty_width),
+                Style::default().fg(theme.dim),
             ));
         }
+        Line::from(spans)
+    }
 
-        if cached_width > 0 {
-            spans.push(Span::styled(
-                "█".repeat(cached_width),
-                Style::default().fg(Color::Yellow),
-            ));
-        }
+    pub fn render(&self, frame: &mut Frame, area: Rect, theme: &Theme, glyphs: &Glyphs) {
+        let block = theme.block().title("Progress");
 
-        if skipped_width > 0 {
-            spans.push(Span::styled(
-                "█".repeat(skipped_width),
-                Style::default().fg(Color::Blue),
-            ));
-        }
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
 
-        if empty_width > 0 {
-            spans.push(Span::styled(
-                "░".repeat(empty_width),
-                Style::default().fg(Color::DarkGray),
-            ));
+        // Calculate bar width (leave 2 chars for borders)
+        let bar_width = inner.width.saturating_sub(2) as usize;
+
+        if bar_width == 0 || self.total == 0 {
+            return;
         }
 
-        let bar_line = Line::from(spans);
+        let bar_line = self.bar(bar_width, theme, glyphs);
 
         // Render bar
         let bar_area = Rect {
@@ -113,14 +124,18 @@ impl MultiProgress {
         frame.render_widget(bar_line, bar_area);
 
         // Render legend
-        let legend = Line::from(vec![
-            Span::styled("● ", Style::default().fg(Color::Green)),
-            Span::raw(format!("Downloaded: {} ", self.downloaded)),
-            Span::styled("● ", Style::default().fg(Color::Yellow)),
-            Span::raw(format!("Cached: {} ", self.cached)),
-            Span::styled("● ", Style::default().fg(Color::Blue)),
-            Span::raw(format!("Existing: {}", self.skipped)),
-        ]);
+        let mut legend = Vec::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            legend.push(Span::styled(
+                glyphs.swatch,
+                Style::default().fg(segment.color),
+            ));
+            let separator = if i + 1 < self.segments.len() { " " } else { "" };
+            legend.push(Span::raw(format!(
+                "{}: {}{}",
+                segment.label, segment.count, separator
+            )));
+        }
 
         if inner.height > 1 {
             let legend_area = Rect {
@@ -129,7 +144,23 @@ impl MultiProgress {
                 width: inner.width.saturating_sub(2),
                 height: 1,
             };
-            frame.render_widget(legend, legend_area);
+            frame.render_widget(Line::from(legend), legend_area);
+        }
+    }
+}
+
+/// Give each outcome that happened at least one cell, so a few failures in a large
+/// library still show, taking the cells from the widest segments if the bar is full
+fn show_every_outcome(widths: &mut [usize], counts: &[usize], bar_width: usize) {
+    for (width, count) in widths.iter_mut().zip(counts) {
+        if *count > 0 && *width == 0 {
+            *width = 1;
+        }
+    }
+    while widths.iter().sum::<usize>() > bar_width {
+        match widths.iter_mut().max() {
+            Some(widest) if *widest > 1 => *widest -= 1,
+            _ => break,
         }
     }
 }
@@ -137,20 +168,85 @@ impl MultiProgress {
 pub struct StatusLegend;
 
 impl StatusLegend {
-    pub fn render(frame: &mut Frame, area: Rect) {
+    pub fn render(frame: &mut Frame, area: Rect, theme: &Theme, glyphs: &Glyphs) {
         let legend = Line::from(vec![
-            Span::styled("[✓]", Style::default().fg(Color::Green)),
+            Span::styled(
+                glyphs.status(&StatusType::Downloaded),
+                Style::default().fg(theme.downloaded),
+            ),
             Span::raw(" Downloaded | "),
-            Span::styled("[~]", Style::default().fg(Color::Yellow)),
+            Span::styled(
+                glyphs.status(&StatusType::Cached),
+                Style::default().fg(theme.cached),
+            ),
             Span::raw(" Cached | "),
-            Span::styled("[○]", Style::default().fg(Color::Blue)),
+            Span::styled(
+                glyphs.status(&StatusType::Existing),
+                Style::default().fg(theme.existing),
+            ),
             Span::raw(" Existing | "),
-            Span::styled("[✗]", Style::default().fg(Color::Red)),
+            Span::styled(
+                glyphs.status(&StatusType::EmbeddedExists),
+                Style::default().fg(theme.existing),
+            ),
+            Span::raw(" Embedded | "),
+            Span::styled(
+                glyphs.status(&StatusType::NotFound),
+                Style::default().fg(theme.not_found),
+            ),
             Span::raw(" Not Found | "),
-            Span::styled("[!]", Style::default().fg(Color::Magenta)),
-            Span::raw(" Error"),
+            Span::styled(
+                glyphs.status(&StatusType::Error),
+                Style::default().fg(theme.error),
+            ),
+            Span::raw(" Error | "),
+            Span::styled(
+                glyphs.status(&StatusType::NoMetadata),
+                Style::default().fg(theme.untagged),
+            ),
+            Span::raw(" Untagged | "),
+            Span::styled(
+                glyphs.status(&StatusType::Excluded),
+                Style::default().fg(theme.excluded),
+            ),
+            Span::raw(" Excluded | "),
+            Span::styled(
+                glyphs.status(&StatusType::SpokenWord),
+                Style::default().fg(theme.excluded),
+            ),
+            Span::raw(" Spoken"),
         ]);
 
         frame.render_widget(legend, area);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(total: usize, force_complete: bool, counts: [usize; 3]) -> MultiProgress {
+        MultiProgress::new(total, force_complete)
+            .segment("Downloaded", counts[0], Color::Green)
+            .segment("Not found", counts[1], Color::Red)
+            .segment("Errors", counts[2], Color::Magenta)
+    }
+
+    #[test]
+    fn test_failures_fill_the_bar() {
+        // A run that finds nothing still moves the bar
+        assert_eq!(
+            progress(10, false, [0, 5, 0]).widths(20),
+            (vec![0, 10, 0], 10)
+        );
+        // A few failures in a large library get a cell each
+        assert_eq!(
+            progress(10_000, false, [5_000, 3, 1]).widths(20),
+            (vec![10, 1, 1], 8)
+        );
+        assert_eq!(
+            progress(10_000, true, [9_996, 3, 1]).widths(20),
+            (vec![18, 1, 1], 0)
+        );
+    }
+}
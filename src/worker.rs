@@ -1,28 +1,566 @@
 use crate::{
-    api::LrcLibClient,
-    cache::{signature::TrackSignature, NegativeCache},
-    messages::{UiMessage, WorkerMessage},
-    scanner::{self, metadata, parallel},
-    session::{PersistentSession, StatusType},
+    api::{
+        self,
+        acoustid::AcoustIdClient,
+        breaker::{CircuitBreaker, Failure},
+        ratelimit::SharedRateLimiter,
+        retry::{self, RetryPolicy},
+        types::LyricsResponse,
+        LrcLibClient,
+    },
+    cache::{
+        handle::CacheHandle, index::IndexEntry, signature::TrackSignature, Expiry, FailedLookup,
+        NegativeCache, NegativeReason,
+    },
+    history::{self, RunRecord},
+    lrc::{self, WriteOptions},
+    messages::{self, ErrorKind, UiMessage, WorkerMessage, WorkerStage},
+    nice, path_encoding, paths,
+    scanner::{
+        self, audio_hash,
+        cue::{self, CueLayout, CueSheet, DirCueSheets},
+        incremental::{self, DirJournal, Discovered},
+        metadata::{self, MetadataSource, TagOverride, Track},
+        parallel::ProgressThrottle,
+        playlist, spoken,
+        track_filter::TrackFilter,
+    },
+    schedule::{self, TimeWindow},
+    session::{self, PersistentSession, StatusType},
+    throughput::Throughput,
+    timing::{Stage, StageTimings},
 };
 use anyhow::Result;
-use governor::{Quota, RateLimiter};
+use dashmap::{DashMap, DashSet};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::num::NonZeroU32;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{mpsc, Mutex, Semaphore};
+use std::time::Instant;
+use tokio::sync::{mpsc, oneshot, Mutex, Notify, Semaphore};
+use tokio_util::sync::CancellationToken;
 
-const RATE_LIMIT_PER_SEC: u32 = 10; // 10 requests/second max
+const RATE_LIMIT_PER_SEC: u32 = 10; // 10 requests/second max, across all getlrc processes
 const MAX_CONCURRENT_WORKERS: usize = 5; // Number of concurrent API workers
+const ALBUM_RETRY_DELAY_SECS: u64 = 5; // Pause before retrying an album's failed tracks
+const BREAKER_THRESHOLD: usize = 5; // Connection failures in a row before lookups are paused
+const HEARTBEAT_INTERVAL_SECS: u64 = 1; // How often each worker's activity is reported
+const SCHEDULE_CHECK_SECS: u64 = 60; // How often the active hours are checked
+const SESSION_FLUSH_SECS: u64 = 5; // How often buffered outcomes are moved into the session
+const PICK_TIMEOUT_SECS: u64 = 300; // How long a worker waits for lyrics to be picked
+
+/// Options for a worker pool run, usually derived from CLI flags
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Bypass the negative cache and retry every file
+    pub force_retry: bool,
+    /// Re-process only files whose last check ended in an error (plus a resumed session's pending files)
+    pub retry_errors: bool,
+    /// Fetch lyrics even for tracks that already have embedded lyrics
+    pub ignore_embedded: bool,
+    /// Write plain lyrics when no synced version exists, retrying `plain_only` cache entries
+    pub allow_plain: bool,
+    /// Re-check every file instead of skipping ones unchanged since the last scan
+    pub full_scan: bool,
+    /// When negative cache entries become due for a retry
+    pub cache_expiry: Expiry,
+    /// How `.lrc` sidecars are written
+    pub write: WriteOptions,
+    /// AcoustID application key for identifying untagged files by fingerprint
+    pub acoustid_key: Option<String>,
+    /// How lyrics for single-file albums with a cue sheet are written
+    pub cue_layout: CueLayout,
+    /// Process only these files (from a playlist) instead of walking the target directory
+    pub playlist: Option<Vec<PathBuf>>,
+    /// Tracks to leave out once their tags are read
+    pub filter: TrackFilter,
+    /// Reuse lyrics across files with identical audio, even when their tags differ
+    pub dedupe_audio: bool,
+    /// Skip tracks that look like podcast episodes or audiobooks
+    pub detect_spoken: bool,
+    /// Have one worker process each album (directory) in turn, retrying its errors together
+    pub group_albums: bool,
+    /// Order in which queued files are processed
+    pub queue_order: QueueOrder,
+    /// One worker, files sorted by path and no shortcuts from earlier scans, so repeated
+    /// runs over the same library process it identically
+    pub deterministic: bool,
+    /// Measure time spent reading tags, in the caches, waiting for the rate limit, in
+    /// requests and writing sidecars, summarized once the run is complete
+    pub timing: bool,
+    /// How failed lookups are retried before a file is recorded as an error
+    pub retry: RetryPolicy,
+    /// Fewer workers, to leave the machine to other services (see `nice::lower_priority`)
+    pub nice: bool,
+    /// Times of day to process files in; outside them the pool pauses and saves its session
+    pub active_hours: Vec<TimeWindow>,
+    /// Search for other uploads of tracks without a confident match, asking which to use
+    /// when several fit
+    pub pick: bool,
+}
 
 /// Shared state for worker pool
 struct WorkerPoolState {
     session: Mutex<PersistentSession>,
-    cache: Mutex<NegativeCache>,
-    downloaded: Mutex<usize>,
-    cached: Mutex<usize>,
-    failed: Mutex<usize>,
+    /// Held while a snapshot of the session is written, so saves don't share the temp file
+    session_write: Mutex<()>,
+    /// Log entries not yet in the session, so workers don't take its lock for every file
+    session_log: (
+        crossbeam_channel::Sender<session::LogEntry>,
+        crossbeam_channel::Receiver<session::LogEntry>,
+    ),
+    cache: CacheHandle,
+    /// Outcome counts, totalled once the workers have finished
+    downloaded: AtomicUsize,
+    existing: AtomicUsize,
+    cached: AtomicUsize,
+    failed: AtomicUsize,
+    embedded: AtomicUsize,
+    untagged: AtomicUsize,
+    excluded: AtomicUsize,
+    spoken: AtomicUsize,
     force_retry: bool,
+    ignore_embedded: bool,
+    allow_plain: bool,
+    full_scan: bool,
+    write_options: WriteOptions,
+    cue_layout: CueLayout,
+    /// Cue sheets of the directories seen this run, so each directory is read once
+    cue_sheets: DashMap<PathBuf, DirCueSheets>,
+    filter: TrackFilter,
+    detect_spoken: bool,
+    /// Identifies untagged files by fingerprint, when configured
+    acoustid: Option<AcoustIdClient>,
+    /// Outcome of each signature's lookup this run, shared with files that have the same signature
+    lookups: DashMap<String, Arc<Mutex<Option<SharedLookup>>>>,
+    /// Identify files by their audio content as well as their tags
+    dedupe_audio: bool,
+    /// File whose sidecar was written this run, by audio content key
+    content_written: DashMap<String, PathBuf>,
+    /// What each worker is doing, reported in heartbeats
+    activity: DashMap<usize, Activity>,
+    /// Reports stage changes and, with `--timing`, each file's time to the UI
+    tx: mpsc::UnboundedSender<WorkerMessage>,
+    /// Files queued again from the UI, looked up even if negative cached
+    retry_requested: DashSet<PathBuf>,
+    /// Fields entered in the UI's manual search, searched for instead of the file's tags
+    manual: DashMap<PathBuf, TagOverride>,
+    /// Search for other uploads when no lookup matches confidently
+    pick: bool,
+    /// Workers waiting for lyrics to be picked in the UI, by file
+    picks: DashMap<PathBuf, oneshot::Sender<Option<usize>>>,
+    /// Holds lookups back while LRCLIB can't be reached
+    breaker: CircuitBreaker,
+    /// Files whose processing needed a lookup, until their time is recorded
+    fetched: DashSet<PathBuf>,
+    /// Pace of finished files, for the time left
+    throughput: Throughput,
+    /// Time spent in each stage, with `--timing`
+    timings: Option<StageTimings>,
+    retry: RetryPolicy,
+    /// What went wrong with each file that failed with an error this run
+    errors: DashMap<PathBuf, ErrorKind>,
+}
+
+/// A worker's current file and stage, and when it entered that stage
+#[derive(Debug, Clone)]
+struct Activity {
+    path: Option<PathBuf>,
+    stage: WorkerStage,
+    since: Instant,
+}
+
+/// A lookup outcome that duplicates of the same track can reuse
+#[derive(Debug, Clone)]
+enum SharedLookup {
+    /// Lyrics were written to this audio file's sidecar
+    Written(PathBuf),
+    /// Nothing usable was found and the signature is now negative cached
+    NotFound,
+}
+
+impl WorkerPoolState {
+    /// Read a file's tags, falling back to AcoustID when they're missing
+    async fn read_track(&self, path: &Path) -> Result<Track> {
+        self.timed_async(path, Stage::Metadata, async {
+            let extracted = metadata::extract_or_guess(path);
+            match &self.acoustid {
+                Some(acoustid) => acoustid.identify_track(path, extracted).await,
+                None => extracted,
+            }
+        })
+        .await
+    }
+
+    /// Run a step of a file's processing, adding its duration to the stage with `--timing`
+    fn timed<T>(&self, path: &Path, stage: Stage, step: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = step();
+        if let Some(timings) = &self.timings {
+            timings.add(path, stage, started.elapsed());
+        }
+        result
+    }
+
+    async fn timed_async<T>(
+        &self,
+        path: &Path,
+        stage: Stage,
+        step: impl std::future::Future<Output = T>,
+    ) -> T {
+        let started = Instant::now();
+        let result = step.await;
+        if let Some(timings) = &self.timings {
+            timings.add(path, stage, started.elapsed());
+        }
+        result
+    }
+
+    /// Record how long a finished file took, for the time left and `--timing`
+    fn file_done(&self, path: &Path, started: Instant) {
+        let fetched = self.fetched.remove(path).is_some();
+        self.throughput.record(started.elapsed(), fetched);
+        if let Some(timings) = &self.timings {
+            let timing = timings.finish(path, started.elapsed());
+            let _ = self.tx.send(WorkerMessage::FileTimed { timing });
+        }
+    }
+
+    /// The cue sheet splitting a single-file album into its tracks, if it has one
+    fn cue_sheet(&self, path: &Path) -> Option<CueSheet> {
+        let dir = path.parent()?;
+        if let Some(sheets) = self.cue_sheets.get(dir) {
+            return sheets.find_for(path);
+        }
+        // Read outside the map's lock, so workers in other directories don't wait on it
+        let sheets = DirCueSheets::read(dir);
+        let sheet = sheets.find_for(path);
+        self.cue_sheets.insert(dir.to_path_buf(), sheets);
+        sheet
+    }
+
+    /// Process a failed file again ahead of the rest, bypassing the negative cache
+    fn retry(&self, queue: &WorkQueue, path: PathBuf) {
+        // The file is counted again once processed
+        let _ = self
+            .failed
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failed| {
+                Some(failed.saturating_sub(1))
+            });
+        self.errors.remove(&path);
+        self.retry_requested.insert(path.clone());
+        queue.push_retry(path);
+    }
+
+    /// Record the stage a worker has moved on to
+    fn set_stage(&self, worker: usize, path: Option<&Path>, stage: WorkerStage) {
+        let _ = self.tx.send(WorkerMessage::StageChanged {
+            worker,
+            path: path.map(Path::to_path_buf),
+            stage,
+        });
+        self.activity.insert(
+            worker,
+            Activity {
+                path: path.map(Path::to_path_buf),
+                stage,
+                since: Instant::now(),
+            },
+        );
+    }
+
+    /// Save the session with the files in progress and those the queue still holds
+    /// Files in progress are included since a quit abandons them. The list only exists in
+    /// the snapshot being written: the queue owns the paths the rest of the time.
+    async fn save_session(&self, queue: &WorkQueue, path: &Path) -> Result<()> {
+        let mut pending: Vec<PathBuf> = self
+            .activity
+            .iter()
+            .filter_map(|activity| activity.path.clone())
+            .collect();
+        pending.extend(queue.pending());
+
+        let _writing = self.session_write.lock().await;
+        let mut snapshot = {
+            let mut sess = self.session.lock().await;
+            self.flush_into(&mut sess);
+            sess.clone()
+        };
+        snapshot.pending_files = pending;
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || snapshot.save(&path)).await?
+    }
+
+    /// Move buffered log entries and the current counts into the session
+    async fn flush_session(&self) {
+        let mut sess = self.session.lock().await;
+        self.flush_into(&mut sess);
+    }
+
+    fn flush_into(&self, sess: &mut PersistentSession) {
+        for entry in self.session_log.1.try_iter() {
+            sess.add_log(entry.filename, entry.status);
+        }
+        sess.downloaded_count = self.downloaded.load(Ordering::Relaxed);
+        sess.cached_count = self.cached.load(Ordering::Relaxed);
+        sess.existing_count = self.existing.load(Ordering::Relaxed);
+        sess.failed_count = self.failed.load(Ordering::Relaxed);
+        sess.embedded_count = self.embedded.load(Ordering::Relaxed);
+        sess.untagged_count = self.untagged.load(Ordering::Relaxed);
+        sess.excluded_count = self.excluded.load(Ordering::Relaxed);
+        sess.spoken_count = self.spoken.load(Ordering::Relaxed);
+    }
+
+    /// Add the run to the history, for `getlrc history` and the TUI's history screen
+    /// `started` is when it started, as Unix seconds and as an instant for its duration.
+    fn record_run(&self, target: &Path, started: (i64, Instant), completed: bool) {
+        let mut run = RunRecord {
+            started_at: started.0,
+            duration_secs: started.1.elapsed().as_secs(),
+            target: target.to_path_buf(),
+            completed,
+            downloaded: self.downloaded.load(Ordering::Relaxed),
+            cached: self.cached.load(Ordering::Relaxed),
+            existing: self.existing.load(Ordering::Relaxed),
+            embedded: self.embedded.load(Ordering::Relaxed),
+            untagged: self.untagged.load(Ordering::Relaxed),
+            excluded: self.excluded.load(Ordering::Relaxed),
+            spoken: self.spoken.load(Ordering::Relaxed),
+            timings: self
+                .timings
+                .as_ref()
+                .map(|timings| timings.summary().lines())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        run.set_errors(self.errors.iter().map(|entry| *entry.value()));
+        // Not found files and errors are counted together as failed
+        run.not_found = self
+            .failed
+            .load(Ordering::Relaxed)
+            .saturating_sub(run.errors);
+        if let Err(e) = paths::get_history_path().and_then(|path| history::append(&path, &run)) {
+            tracing::warn!("Failed to record the run in the history: {}", e);
+        }
+    }
+
+    /// Send a heartbeat for each worker with its current activity
+    fn send_heartbeats(&self, tx: &mpsc::UnboundedSender<WorkerMessage>) {
+        for entry in self.activity.iter() {
+            let _ = tx.send(WorkerMessage::WorkerHeartbeat {
+                worker: *entry.key(),
+                path: entry.path.clone(),
+                stage: entry.stage,
+                in_stage: entry.since.elapsed(),
+            });
+        }
+    }
+
+    /// Log a file's outcome to the session history and the library index
+    async fn log(
+        &self,
+        path: &Path,
+        filename: String,
+        status: StatusType,
+        signature: Option<&str>,
+    ) {
+        let recorded = match IndexEntry::new(path, signature, status.clone()) {
+            Ok(entry) => self.cache.record(entry).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = recorded {
+            tracing::warn!("Failed to index {}: {}", path.display(), e);
+        }
+
+        // Moved into the session by `flush_session`
+        let _ = self
+            .session_log
+            .0
+            .send(session::LogEntry { filename, status });
+    }
+
+    /// Report a file that failed with an error, counted by what went wrong
+    async fn fail(
+        &self,
+        path: &Path,
+        filename: String,
+        kind: ErrorKind,
+        error: String,
+        signature: Option<&str>,
+        tx: &mpsc::UnboundedSender<WorkerMessage>,
+    ) -> Result<()> {
+        tx.send(WorkerMessage::Error {
+            path: path.to_path_buf(),
+            kind,
+            error,
+        })?;
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        self.errors.insert(path.to_path_buf(), kind);
+        self.log(path, filename, StatusType::Error, signature).await;
+        Ok(())
+    }
+
+    /// The file's library index entry from a previous run, if any
+    async fn indexed(&self, path: &Path) -> Option<IndexEntry> {
+        self.cache.indexed_file(path).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to read index for {}: {}", path.display(), e);
+            None
+        })
+    }
+
+    /// Signature of an unchanged file whose last lookup failed, so its tags needn't be read again
+    fn journaled_signature<'a>(&self, previous: &'a IndexEntry, path: &Path) -> Option<&'a str> {
+        let failed = matches!(previous.status, StatusType::NotFound | StatusType::Cached);
+        if self.force_retry || self.full_scan || !failed || !previous.is_current(path) {
+            return None;
+        }
+        previous.signature.as_deref()
+    }
+
+    /// Whether a negative cache entry means the lookup should be skipped
+    fn skip_cached(&self, path: &Path, signature: &str) -> Result<bool> {
+        match self.timed(path, Stage::Cache, || self.cache.cached_reason(signature))? {
+            // Plain lyrics are acceptable now, so look these up again
+            Some(NegativeReason::PlainOnly) if self.allow_plain => {
+                tracing::debug!("Retrying plain-only entry: {}", path.display());
+                Ok(false)
+            }
+            Some(_) => Ok(true),
+            None => Ok(false),
+        }
+    }
+
+    /// Count and log a file skipped because of the negative cache
+    async fn cache_hit(
+        &self,
+        path: &Path,
+        filename: String,
+        signature: &str,
+        tx: &mpsc::UnboundedSender<WorkerMessage>,
+    ) -> Result<()> {
+        tx.send(WorkerMessage::CacheHit {
+            path: path.to_path_buf(),
+        })?;
+        self.cached.fetch_add(1, Ordering::Relaxed);
+        self.log(path, filename, StatusType::Cached, Some(signature))
+            .await;
+        Ok(())
+    }
+
+    /// Count and log a file left out by the track filter
+    async fn exclude(
+        &self,
+        path: &Path,
+        filename: String,
+        reason: String,
+        tx: &mpsc::UnboundedSender<WorkerMessage>,
+    ) -> Result<()> {
+        tracing::debug!("Excluded {}: {}", path.display(), reason);
+        tx.send(WorkerMessage::Excluded {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+        self.excluded.fetch_add(1, Ordering::Relaxed);
+        self.log(path, filename, StatusType::Excluded, None).await;
+        Ok(())
+    }
+
+    /// Count and log a file skipped as a podcast episode or audiobook
+    /// It isn't negative cached: there was never anything to look up.
+    async fn skip_spoken(
+        &self,
+        path: &Path,
+        filename: String,
+        reason: String,
+        tx: &mpsc::UnboundedSender<WorkerMessage>,
+    ) -> Result<()> {
+        tracing::debug!("Spoken word {}: {}", path.display(), reason);
+        tx.send(WorkerMessage::SpokenWord {
+            path: path.to_path_buf(),
+            reason,
+        })?;
+        self.spoken.fetch_add(1, Ordering::Relaxed);
+        self.log(path, filename, StatusType::SpokenWord, None).await;
+        Ok(())
+    }
+
+    /// Give a duplicate the lyrics already written for another file with the same signature
+    async fn copy_lyrics(
+        &self,
+        source: &Path,
+        path: &Path,
+        filename: String,
+        signature: &str,
+        tx: &mpsc::UnboundedSender<WorkerMessage>,
+    ) -> Result<()> {
+        tracing::debug!(
+            "Reusing lyrics from {} for {}",
+            source.display(),
+            path.display()
+        );
+        if let Err(e) = self.timed(path, Stage::Write, || {
+            lrc::copy_sidecar(source, path, &self.write_options)
+        }) {
+            self.fail(
+                path,
+                filename,
+                ErrorKind::Io,
+                e.to_string(),
+                Some(signature),
+                tx,
+            )
+            .await?;
+        } else {
+            tx.send(WorkerMessage::LyricsFound {
+                path: path.to_path_buf(),
+            })?;
+            self.downloaded.fetch_add(1, Ordering::Relaxed);
+            self.log(path, filename, StatusType::Downloaded, Some(signature))
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Drop a track's negative cache entry once its lyrics are saved, whether it was
+    /// bypassed or a plain-only entry looked up again with `--allow-plain`
+    async fn clear_negative(&self, path: &Path, signature: &str) {
+        if !self.cache.contains(signature) {
+            return;
+        }
+        if let Err(e) = self.cache.remove(signature).await {
+            tracing::warn!(
+                "Failed to remove {} from negative cache: {}",
+                path.display(),
+                e
+            );
+        } else {
+            tracing::info!(
+                "Retry success: removed {} from negative cache",
+                path.display()
+            );
+        }
+    }
+
+    /// Whether the file changed since it was last indexed (e.g. after fixing its tags)
+    /// Drops the negative entry for its old signature so stale failures don't linger.
+    async fn retagged(&self, previous: Option<IndexEntry>, path: &Path, signature: &str) -> bool {
+        let Some(previous) = previous.filter(|previous| previous.file_changed(path)) else {
+            return false;
+        };
+
+        tracing::info!("File changed since last check: {}", path.display());
+        if let Some(old) = previous.signature.filter(|old| old != signature) {
+            if let Err(e) = self.cache.remove(&old).await {
+                tracing::warn!(
+                    "Failed to remove old signature for {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        true
+    }
 }
 
 pub async fn run(
@@ -32,12 +570,13 @@ pub async fn run(
     mut ui_rx: mpsc::UnboundedReceiver<UiMessage>,
     session_path: PathBuf,
     mut session: Option<PersistentSession>,
-    force_retry: bool,
+    options: RunOptions,
 ) -> Result<()> {
+    let started = (crate::cache::now_secs().unwrap_or_default(), Instant::now());
     tracing::info!(
         "Worker pool started for directory: {} (force_retry: {})",
         target_dir.display(),
-        force_retry
+        options.force_retry
     );
 
     // Track if we're resuming from a session
@@ -47,10 +586,27 @@ pub async fn run(
     let force_retry = session
         .as_ref()
         .map(|s| s.force_retry)
-        .unwrap_or(force_retry);
+        .unwrap_or(options.force_retry);
+
+    // Deterministic runs don't depend on timing or on what earlier scans recorded
+    let (workers, queue_order, full_scan) = if options.deterministic {
+        tracing::info!("Deterministic run: one worker, files sorted by path, full scan");
+        (1, QueueOrder::Path, true)
+    } else {
+        let workers = if options.nice {
+            nice::NICE_WORKERS
+        } else {
+            MAX_CONCURRENT_WORKERS
+        };
+        (workers, options.queue_order, options.full_scan)
+    };
+
+    // Set when the directory walk runs alongside the workers
+    let mut fresh_scan = None;
 
     // Determine if we're resuming or starting fresh
-    let (files_to_process, downloaded, cached, existing, failed) = if let Some(ref sess) = session {
+    let (files_to_process, downloaded, cached, existing, failed) = if let Some(sess) = &mut session
+    {
         tracing::info!(
             "Resuming from saved session with {} pending files",
             sess.pending_files.len()
@@ -59,18 +615,42 @@ pub async fn run(
         // Send restoring message to TUI
         tx.send(WorkerMessage::SessionRestoring)?;
 
-        // Restore counts
+        // The queue owns the pending files from here on; saves take them back from it
+        let mut pending_files = std::mem::take(&mut sess.pending_files);
+
+        // Re-queue files that failed with errors, after the ones still pending
+        let mut requeued = 0;
+        if options.retry_errors {
+            let errored: Vec<PathBuf> = {
+                let queued: HashSet<&PathBuf> = pending_files.iter().collect();
+                cache
+                    .files_with_status(&target_dir, StatusType::Error)?
+                    .into_iter()
+                    .filter(|path| !queued.contains(path))
+                    .collect()
+            };
+            requeued = errored.len();
+            pending_files.extend(errored);
+            tracing::info!("Re-queued {} files that failed with errors", requeued);
+        }
+
+        // Restore counts (re-queued errors no longer count as processed)
         let downloaded = sess.downloaded_count;
         let cached = sess.cached_count;
         let existing = sess.existing_count;
-        let failed = sess.failed_count;
+        let failed = sess.failed_count.saturating_sub(requeued);
+        let embedded = sess.embedded_count;
+        let untagged = sess.untagged_count;
+        let excluded = sess.excluded_count;
+        let spoken = sess.spoken_count;
 
         // Send counts to TUI for progress bar
         tx.send(WorkerMessage::CountsRestored {
             downloaded,
             cached,
-            skipped: existing,
-            processed: downloaded + cached + failed,
+            skipped: existing + embedded + excluded + spoken,
+            untagged,
+            processed: downloaded + cached + failed + embedded + untagged + excluded + spoken,
         })?;
 
         // Restore log history
@@ -81,99 +661,208 @@ pub async fn run(
             })?;
         }
 
-        (
-            sess.pending_files.clone(),
-            downloaded,
-            cached,
-            existing,
-            failed,
-        )
-    } else {
-        // Fresh scan - use parallel directory walker
-        tracing::info!("Starting fresh parallel scan");
-
-        // Spawn scanning task to avoid blocking
-        let target_dir_clone = target_dir.clone();
-        let tx_clone = tx.clone();
-        let scan_handle = tokio::task::spawn_blocking(move || {
-            let all_audio_files = parallel::walk_directory_parallel(&target_dir_clone);
+        (pending_files, downloaded, cached, existing, failed)
+    } else if let Some(files) = options.playlist.clone() {
+        // Playlist - only the files it references, no directory walk
+        let mut files = files;
+        if options.retry_errors {
+            let errored: HashSet<PathBuf> = cache
+                .files_with_status(Path::new(""), StatusType::Error)?
+                .into_iter()
+                .collect();
+            files.retain(|path| errored.contains(path));
+        }
 
-            // Send progress update
-            let _ = tx_clone.send(WorkerMessage::ScanProgress {
-                files_found: all_audio_files.len(),
-            });
+        let (existing_files, files_to_process): (Vec<PathBuf>, Vec<PathBuf>) = files
+            .into_iter()
+            .partition(|path| scanner::has_lrc_sidecar(path));
+        for path in &existing_files {
+            tx.send(WorkerMessage::AlreadyHasLrc {
+                path: path.to_path_buf(),
+            })?;
+        }
+        tracing::info!(
+            "Playlist: {} files to process, {} already have lyrics",
+            files_to_process.len(),
+            existing_files.len()
+        );
+        tx.send(WorkerMessage::ScanProgress {
+            files_found: files_to_process.len() + existing_files.len(),
+        })?;
 
-            all_audio_files
-        });
+        session = Some(PersistentSession::new(
+            target_dir.clone(),
+            Vec::new(),
+            force_retry,
+        ));
 
-        let all_audio_files = scan_handle.await?;
+        (files_to_process, 0, 0, existing_files.len(), 0)
+    } else if options.retry_errors {
+        // Rerun of errors only - the library index already knows which files failed
+        let files_to_process = cache.files_with_status(&target_dir, StatusType::Error)?;
+        tracing::info!(
+            "Retrying {} files that failed with errors",
+            files_to_process.len()
+        );
+        tx.send(WorkerMessage::ScanProgress {
+            files_found: files_to_process.len(),
+        })?;
 
-        let mut files_to_process = Vec::new();
-        let mut files_with_lrc = Vec::new();
+        session = Some(PersistentSession::new(
+            target_dir.clone(),
+            Vec::new(),
+            force_retry,
+        ));
 
-        // Filter files that already have .lrc sidecars
-        for path in all_audio_files {
-            if scanner::has_lrc_sidecar(&path) {
-                files_with_lrc.push(path);
-            } else {
-                files_to_process.push(path);
-            }
-        }
+        (files_to_process, 0, 0, 0, 0)
+    } else {
+        // Fresh scan - only read directories that changed since the last completed scan;
+        // files are queued as the walk finds them (see `feed_scan`)
+        tracing::info!("Starting fresh incremental scan");
 
-        // Send messages for files that already have .lrc
-        let existing_count = files_with_lrc.len();
-        for path in files_with_lrc {
-            tx.send(WorkerMessage::AlreadyHasLrc {
-                path: path.display().to_string(),
-            })?;
-        }
+        // Files indexed by earlier runs, used to skip ones that haven't changed since
+        let journal: HashMap<String, IndexEntry> = if full_scan {
+            HashMap::new()
+        } else {
+            cache
+                .indexed_files()?
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect()
+        };
+        let dir_journal = if full_scan {
+            DirJournal::default()
+        } else {
+            DirJournal::new(
+                cache.scanned_dirs(scanner::scan_fingerprint())?,
+                journal.keys().map(|path| path_encoding::decode(path)),
+            )
+        };
+        fresh_scan = Some(FreshScan {
+            root: target_dir.clone(),
+            journal,
+            dir_journal,
+            ignore_embedded: options.ignore_embedded,
+        });
 
         // Create new session
         session = Some(PersistentSession::new(
             target_dir.clone(),
-            files_to_process.clone(),
+            Vec::new(),
             force_retry,
         ));
 
-        (files_to_process, 0, 0, existing_count, 0)
+        (Vec::new(), 0, 0, 0, 0)
     };
 
+    // Embedded-lyrics skips only happen during processing, so only a resumed session has any
+    let embedded = session.as_ref().map(|s| s.embedded_count).unwrap_or(0);
+    let untagged = session.as_ref().map(|s| s.untagged_count).unwrap_or(0);
+    let excluded = session.as_ref().map(|s| s.excluded_count).unwrap_or(0);
+    let spoken = session.as_ref().map(|s| s.spoken_count).unwrap_or(0);
+
     // Calculate total files: already processed + existing + pending
-    let total_files = downloaded + cached + failed + existing + files_to_process.len();
-    tx.send(WorkerMessage::ScanStarted { total_files })?;
+    // (a fresh scan reports its total once the walk is done)
+    if fresh_scan.is_none() {
+        let total_files = downloaded
+            + cached
+            + failed
+            + embedded
+            + untagged
+            + excluded
+            + spoken
+            + existing
+            + files_to_process.len();
+        tx.send(WorkerMessage::ScanStarted { total_files })?;
+    }
 
     // Create shared state
     let shared_state = Arc::new(WorkerPoolState {
         session: Mutex::new(session.unwrap()),
-        cache: Mutex::new(cache),
-        downloaded: Mutex::new(downloaded),
-        cached: Mutex::new(cached),
-        failed: Mutex::new(failed),
+        session_write: Mutex::new(()),
+        session_log: crossbeam_channel::unbounded(),
+        cache: CacheHandle::spawn(cache)?,
+        downloaded: AtomicUsize::new(downloaded),
+        existing: AtomicUsize::new(existing),
+        cached: AtomicUsize::new(cached),
+        failed: AtomicUsize::new(failed),
+        embedded: AtomicUsize::new(embedded),
+        untagged: AtomicUsize::new(untagged),
+        excluded: AtomicUsize::new(excluded),
+        spoken: AtomicUsize::new(spoken),
         force_retry,
+        ignore_embedded: options.ignore_embedded,
+        allow_plain: options.allow_plain,
+        full_scan,
+        write_options: options.write,
+        cue_layout: options.cue_layout,
+        cue_sheets: DashMap::new(),
+        filter: options.filter,
+        detect_spoken: options.detect_spoken,
+        acoustid: options
+            .acoustid_key
+            .as_deref()
+            .and_then(|key| match AcoustIdClient::new(key) {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    tracing::warn!("AcoustID identification disabled: {:#}", e);
+                    None
+                }
+            }),
+        lookups: DashMap::new(),
+        dedupe_audio: options.dedupe_audio,
+        content_written: DashMap::new(),
+        activity: DashMap::new(),
+        tx: tx.clone(),
+        retry_requested: DashSet::new(),
+        manual: DashMap::new(),
+        pick: options.pick,
+        picks: DashMap::new(),
+        breaker: CircuitBreaker::new(BREAKER_THRESHOLD),
+        fetched: DashSet::new(),
+        throughput: Throughput::new(workers),
+        timings: options.timing.then(StageTimings::new),
+        errors: DashMap::new(),
+        retry: options.retry.clone(),
     });
 
-    // Create rate limiter (10 requests per second)
-    let rate_limiter = Arc::new(RateLimiter::direct(Quota::per_second(
+    // Create rate limiter (10 requests per second, shared with other getlrc processes)
+    let rate_limiter = Arc::new(SharedRateLimiter::new(
+        crate::paths::get_rate_limit_path()?,
         NonZeroU32::new(RATE_LIMIT_PER_SEC).unwrap(),
-    )));
+    ));
 
     // Create semaphore for concurrent worker limit
-    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_WORKERS));
+    let semaphore = Arc::new(Semaphore::new(workers));
 
-    // Create work queue using Arc<Mutex<VecDeque>> for work-stealing
-    let work_queue = Arc::new(Mutex::new(
-        files_to_process
-            .into_iter()
-            .collect::<std::collections::VecDeque<_>>(),
+    // Work is handed out over a channel, so workers and the scan never wait on each other
+    let group_albums = options.group_albums;
+    let work_queue = Arc::new(WorkQueue::new(
+        files_to_process,
+        group_albums,
+        queue_order,
+        fresh_scan.is_some(),
     ));
 
     // Control flags
-    let paused = Arc::new(Mutex::new(is_resuming));
-    let should_quit = Arc::new(Mutex::new(false));
+    let pause = Arc::new(PauseGate::new(is_resuming));
+    let cancel = CancellationToken::new();
+
+    // Start processing while the walk is still discovering files
+    let scan_task = fresh_scan.map(|scan| {
+        let shared_state = shared_state.clone();
+        let work_queue = work_queue.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let result = feed_scan(scan, &shared_state, &work_queue, &tx).await;
+            work_queue.finish_scan();
+            result
+        })
+    });
 
     // Spawn control message handler
-    let paused_clone = paused.clone();
-    let should_quit_clone = should_quit.clone();
+    let pause_clone = pause.clone();
+    let cancel_clone = cancel.clone();
     let shared_state_clone = shared_state.clone();
     let session_path_clone = session_path.clone();
     let work_queue_clone = work_queue.clone();
@@ -182,35 +871,57 @@ pub async fn run(
         while let Some(msg) = ui_rx.recv().await {
             match msg {
                 UiMessage::Pause => {
-                    *paused_clone.lock().await = true;
-                    tracing::info!("Worker pool paused");
-
-                    // Save session state with remaining work queue
-                    let mut sess = shared_state_clone.session.lock().await;
-                    let remaining_files: Vec<PathBuf> =
-                        work_queue_clone.lock().await.iter().cloned().collect();
-                    sess.pending_files = remaining_files;
-
-                    if let Err(e) = sess.save(&session_path_clone) {
-                        tracing::error!("Failed to save session: {}", e);
-                    }
+                    pause_and_save(
+                        &pause_clone,
+                        &shared_state_clone,
+                        &work_queue_clone,
+                        &session_path_clone,
+                    )
+                    .await;
                 }
                 UiMessage::Resume => {
-                    *paused_clone.lock().await = false;
+                    pause_clone.resume();
                     tracing::info!("Worker pool resumed");
                 }
-                UiMessage::Quit => {
-                    tracing::info!("Worker pool received quit signal");
-                    *should_quit_clone.lock().await = true;
-
-                    // Save session if paused
-                    if *paused_clone.lock().await {
-                        let mut sess = shared_state_clone.session.lock().await;
-                        let remaining_files: Vec<PathBuf> =
-                            work_queue_clone.lock().await.iter().cloned().collect();
-                        sess.pending_files = remaining_files;
-
-                        if let Err(e) = sess.save(&session_path_clone) {
+                UiMessage::Prioritize { dir } => {
+                    tracing::info!("Moving {} to the front of the queue", dir.display());
+                    work_queue_clone.prioritize(dir);
+                }
+                UiMessage::Retry { path } => {
+                    tracing::info!("Retrying {} on request", path.display());
+                    shared_state_clone.retry(&work_queue_clone, path);
+                }
+                UiMessage::ManualSearch { path, tags } => {
+                    tracing::info!(
+                        "Searching for {} as {} - {} on request",
+                        path.display(),
+                        tags.artist,
+                        tags.title
+                    );
+                    shared_state_clone.manual.insert(path.clone(), tags);
+                    shared_state_clone.retry(&work_queue_clone, path);
+                }
+                UiMessage::RetryConnection => {
+                    tracing::info!("Retrying the connection to LRCLIB on request");
+                    shared_state_clone.breaker.retry_now();
+                }
+                UiMessage::PickLyrics { path, choice } => {
+                    if let Some((_, reply)) = shared_state_clone.picks.remove(&path) {
+                        let _ = reply.send(choice);
+                    }
+                }
+                UiMessage::Quit { save } => {
+                    tracing::info!("Worker pool received quit signal (save: {})", save);
+                    cancel_clone.cancel();
+                    // Workers waiting for a pick take the best result instead
+                    shared_state_clone.picks.clear();
+
+                    // Save session if asked to (and the queue holds every remaining file)
+                    if save && !work_queue_clone.is_scanning() {
+                        if let Err(e) = shared_state_clone
+                            .save_session(&work_queue_clone, &session_path_clone)
+                            .await
+                        {
                             tracing::error!("Failed to save session on quit: {}", e);
                         }
                     }
@@ -220,108 +931,321 @@ pub async fn run(
         }
     });
 
+    // Pause outside the active hours and resume in them, checked every minute
+    let scheduler = (!options.active_hours.is_empty()).then(|| {
+        let windows = options.active_hours.clone();
+        let pause = pause.clone();
+        let shared_state = shared_state.clone();
+        let work_queue = work_queue.clone();
+        let session_path = session_path.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(SCHEDULE_CHECK_SECS));
+            // Only changes are acted on, so a paused restore or a manual resume is left alone
+            let mut active = true;
+            loop {
+                interval.tick().await;
+                let minute = schedule::local_minute();
+                let now_active = schedule::is_active(&windows, minute);
+                if now_active == active {
+                    continue;
+                }
+                active = now_active;
+                let Some(window) = schedule::next_window(&windows, minute) else {
+                    continue;
+                };
+                if active {
+                    tracing::info!("Active hours {} started, resuming", window);
+                    pause.resume();
+                } else {
+                    tracing::info!(
+                        "Outside active hours, pausing until {}",
+                        window.start_time()
+                    );
+                    pause_and_save(&pause, &shared_state, &work_queue, &session_path).await;
+                }
+                let _ = tx.send(WorkerMessage::ActiveHours { active, window });
+            }
+        })
+    });
+
     // Spawn worker tasks
     let mut worker_handles = Vec::new();
 
-    for worker_id in 0..MAX_CONCURRENT_WORKERS {
+    for worker_id in 0..workers {
         let work_queue_clone = work_queue.clone();
         let tx_clone = tx.clone();
         let shared_state_clone = shared_state.clone();
         let rate_limiter_clone = rate_limiter.clone();
         let semaphore_clone = semaphore.clone();
-        let paused_clone = paused.clone();
-        let should_quit_clone = should_quit.clone();
+        let pause_clone = pause.clone();
+        let cancel_clone = cancel.clone();
 
         let handle = tokio::spawn(async move {
             let client = LrcLibClient::new();
 
-            loop {
-                // Check for quit signal
-                if *should_quit_clone.lock().await {
+            'work: loop {
+                // Wait while paused, stopping on the quit signal
+                if cancel_clone.is_cancelled() || !pause_clone.wait(&cancel_clone).await {
                     tracing::debug!("Worker {} received quit signal", worker_id);
                     break;
                 }
 
-                // Wait while paused
-                while *paused_clone.lock().await {
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    if *should_quit_clone.lock().await {
-                        return;
+                // Take the next file, or the next album when grouping by album
+                shared_state_clone.set_stage(worker_id, None, WorkerStage::Idle);
+                let (files, album) = match work_queue_clone.next(&cancel_clone).await {
+                    Some(Work::Item(files)) => {
+                        let album = group_albums
+                            .then(|| files.first().map(|path| album_dir(path).to_path_buf()))
+                            .flatten();
+                        (files, album)
+                    }
+                    Some(Work::Retry(path)) => (vec![path], None),
+                    None => {
+                        tracing::debug!("Worker {} finished (no more work)", worker_id);
+                        break;
                     }
+                };
+                if let Some(dir) = &album {
+                    tracing::info!("Worker {} processing album {}", worker_id, dir.display());
+                    let _ = tx_clone.send(WorkerMessage::AlbumStarted { dir: dir.clone() });
                 }
 
-                // Try to get work from queue
-                let path = {
-                    let mut queue = work_queue_clone.lock().await;
-                    queue.pop_front()
-                };
+                // Files not started yet stay visible to session saves
+                work_queue_clone.hold(worker_id, files);
+                let mut album_tracks = Vec::new();
+                while let Some(path) = work_queue_clone.take_held(worker_id) {
+                    // Acquire semaphore permit
+                    let _permit = semaphore_clone.acquire().await.unwrap();
+                    let started = Instant::now();
+                    shared_state_clone.fetched.remove(&path);
 
-                let Some(path) = path else {
-                    // No more work
-                    tracing::debug!("Worker {} finished (no more work)", worker_id);
-                    break;
-                };
+                    // Process the file, abandoning it (even mid-request) on quit
+                    let processed = process_file(
+                        &path,
+                        &client,
+                        &shared_state_clone,
+                        &rate_limiter_clone,
+                        &tx_clone,
+                        worker_id,
+                    );
+                    let Some(result) = cancel_clone.run_until_cancelled(processed).await else {
+                        break 'work;
+                    };
+                    if let Err(e) = result {
+                        tracing::error!(
+                            "Worker {} error processing {}: {}",
+                            worker_id,
+                            path.display(),
+                            e
+                        );
+                    }
+                    shared_state_clone.file_done(&path, started);
+                    shared_state_clone.set_stage(worker_id, None, WorkerStage::Idle);
+                    album_tracks.push(path);
 
-                // Acquire semaphore permit
-                let _permit = semaphore_clone.acquire().await.unwrap();
+                    // Stop between an album's tracks as well when paused
+                    if !pause_clone.wait(&cancel_clone).await {
+                        break 'work;
+                    }
+                }
 
-                // Process the file
-                if let Err(e) = process_file(
-                    &path,
+                let Some(dir) = album else { continue };
+                let _permit = semaphore_clone.acquire().await.unwrap();
+                let finished = finish_album(
+                    &dir,
+                    album_tracks,
                     &client,
                     &shared_state_clone,
                     &rate_limiter_clone,
                     &tx_clone,
-                )
-                .await
-                {
+                    worker_id,
+                );
+                let Some(result) = cancel_clone.run_until_cancelled(finished).await else {
+                    break;
+                };
+                if let Err(e) = result {
                     tracing::error!(
-                        "Worker {} error processing {}: {}",
+                        "Worker {} error finishing album {}: {}",
                         worker_id,
-                        path.display(),
+                        dir.display(),
                         e
                     );
                 }
             }
 
+            shared_state_clone.set_stage(worker_id, None, WorkerStage::Idle);
             tracing::debug!("Worker {} shutting down", worker_id);
         });
 
         worker_handles.push(handle);
     }
 
+    // Report what each worker is doing until they have all finished
+    let heartbeat = {
+        let shared_state = shared_state.clone();
+        let work_queue = work_queue.clone();
+        let rate_limiter = rate_limiter.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(HEARTBEAT_INTERVAL_SECS));
+            let mut last = (Instant::now(), api::requests_sent());
+            loop {
+                interval.tick().await;
+                shared_state.send_heartbeats(&tx);
+                let requests = api::requests_sent();
+                if let Some(estimate) = shared_state.throughput.estimate() {
+                    let _ = tx.send(WorkerMessage::Throughput {
+                        files_per_min: estimate.files_per_min,
+                        pace: estimate.pace,
+                        requests,
+                    });
+                }
+                let elapsed = last.0.elapsed().as_secs_f64();
+                let _ = tx.send(WorkerMessage::PoolStatus {
+                    queued: work_queue.len(),
+                    requests_per_sec: (requests - last.1) as f64 / elapsed.max(f64::EPSILON),
+                    tokens: rate_limiter.available().await,
+                    rate_limit: rate_limiter.per_second(),
+                });
+                last = (Instant::now(), requests);
+            }
+        })
+    };
+
+    // Move finished files into the session every few seconds
+    let session_flush = {
+        let shared_state = shared_state.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(tokio::time::Duration::from_secs(SESSION_FLUSH_SECS));
+            loop {
+                interval.tick().await;
+                shared_state.flush_session().await;
+            }
+        })
+    };
+
     // Wait for all workers to complete
     for handle in worker_handles {
         let _ = handle.await;
     }
+    heartbeat.abort();
+    session_flush.abort();
+    if let Some(scheduler) = scheduler {
+        scheduler.abort();
+    }
+    shared_state.send_heartbeats(&tx);
 
-    // Check if we should quit early
-    if *should_quit.lock().await {
+    // Stop early if the user quit, without waiting for the walk
+    if cancel.is_cancelled() {
+        if let Some(task) = scan_task {
+            task.abort();
+        }
+        if let Err(e) = shared_state.cache.flush().await {
+            tracing::error!("Failed to flush negative cache: {}", e);
+        }
+        shared_state.record_run(&target_dir, started, false);
         tracing::info!("Worker pool terminated early by user");
         return Ok(());
     }
 
+    // Workers only finish once the scan has, so this returns straight away
+    let scan_summary = match scan_task {
+        Some(task) => task.await?.unwrap_or_else(|e| {
+            tracing::error!("Directory scan failed: {}", e);
+            ScanSummary::default()
+        }),
+        None => ScanSummary::default(),
+    };
+    let existing = shared_state.existing.load(Ordering::Relaxed);
+
+    // Persist any buffered negative cache writes
+    if let Err(e) = shared_state.cache.flush().await {
+        tracing::error!("Failed to flush negative cache: {}", e);
+    }
+
     // Final counts
-    let final_downloaded = *shared_state.downloaded.lock().await;
-    let final_cached = *shared_state.cached.lock().await;
-    let final_failed = *shared_state.failed.lock().await;
-    let processed = final_downloaded + final_cached + final_failed;
+    let final_downloaded = shared_state.downloaded.load(Ordering::Relaxed);
+    let final_cached = shared_state.cached.load(Ordering::Relaxed);
+    let final_failed = shared_state.failed.load(Ordering::Relaxed);
+    let final_embedded = shared_state.embedded.load(Ordering::Relaxed);
+    let final_untagged = shared_state.untagged.load(Ordering::Relaxed);
+    let final_excluded = shared_state.excluded.load(Ordering::Relaxed);
+    let final_spoken = shared_state.spoken.load(Ordering::Relaxed);
+    let processed = final_downloaded
+        + final_cached
+        + final_failed
+        + final_embedded
+        + final_untagged
+        + final_excluded
+        + final_spoken;
     let total_files_processed = processed + existing;
 
+    if let Some(timings) = &shared_state.timings {
+        let summary = timings.summary();
+        for line in summary.lines() {
+            tracing::info!("Timing: {}", line);
+        }
+        tx.send(WorkerMessage::Timings { summary })?;
+    }
+
     tx.send(WorkerMessage::ScanComplete {
         processed: total_files_processed,
         found: final_downloaded,
     })?;
 
     tracing::info!(
-        "Worker pool complete: {} lyrics downloaded, {} total files ({} downloaded, {} cached, {} existing, {} failed)",
+        "Worker pool complete: {} lyrics downloaded, {} total files ({} downloaded, {} cached, {} existing, {} embedded, {} failed, {} untagged, {} excluded, {} spoken word)",
         final_downloaded,
         total_files_processed,
         final_downloaded,
         final_cached,
         existing,
-        final_failed
+        final_embedded,
+        final_failed,
+        final_untagged,
+        final_excluded,
+        final_spoken
     );
+    let error_counts =
+        messages::error_counts(shared_state.errors.iter().map(|entry| *entry.value()));
+    if !error_counts.is_empty() {
+        tracing::info!("Errors: {}", messages::format_error_counts(&error_counts));
+    }
+
+    // List files without readable tags so they can be tagged by hand
+    match shared_state
+        .cache
+        .files_with_status(&target_dir, StatusType::NoMetadata)
+        .await
+        .and_then(|mut files| {
+            // Sorted, so the list doesn't change with the order files were processed in
+            files.sort();
+            write_untagged_list(&files)
+        }) {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("{} files without readable tags listed", count),
+        Err(e) => tracing::warn!("Failed to write the untagged files list: {}", e),
+    }
+
+    // Remember directory mtimes (after this run's sidecar writes) for the next incremental scan
+    let dir_stamps = scan_summary
+        .dirs
+        .iter()
+        .filter_map(|dir| Some((path_encoding::encode(dir), incremental::dir_stamp(dir)?)))
+        .collect();
+    if let Err(e) = shared_state
+        .cache
+        .record_dirs(dir_stamps, scanner::scan_fingerprint())
+        .await
+    {
+        tracing::warn!("Failed to record scanned directories: {}", e);
+    }
+
+    shared_state.record_run(&target_dir, started, true);
 
     // Delete session file on successful completion
     if let Err(e) = PersistentSession::delete(&session_path) {
@@ -331,139 +1255,814 @@ pub async fn run(
     Ok(())
 }
 
-/// Process a single file
-async fn process_file(
-    path: &Path,
-    client: &LrcLibClient,
+/// Pause the pool and save its session, so the run can resume later or after a restart
+async fn pause_and_save(
+    pause: &PauseGate,
     shared_state: &WorkerPoolState,
-    rate_limiter: &RateLimiter<
-        governor::state::direct::NotKeyed,
-        governor::state::InMemoryState,
-        governor::clock::DefaultClock,
-    >,
-    tx: &mpsc::UnboundedSender<WorkerMessage>,
-) -> Result<()> {
-    let filename = path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
-
-    // Extract metadata
-    let track = match metadata::extract(path) {
-        Ok(t) => t,
-        Err(e) => {
-            tx.send(WorkerMessage::Error {
-                path: path.display().to_string(),
-                error: e.to_string(),
-            })?;
+    work_queue: &WorkQueue,
+    session_path: &Path,
+) {
+    pause.pause();
+    tracing::info!("Worker pool paused");
 
-            *shared_state.failed.lock().await += 1;
-            shared_state
-                .session
-                .lock()
-                .await
-                .add_log(filename, StatusType::Error);
-            return Ok(());
-        }
-    };
+    if let Err(e) = shared_state.cache.flush().await {
+        tracing::error!("Failed to flush negative cache: {}", e);
+    }
 
-    tx.send(WorkerMessage::TrackProcessing {
-        track: track.clone(),
-    })?;
+    // The queue is incomplete mid-scan; the next run rescans instead
+    if work_queue.is_scanning() {
+        tracing::warn!("Scan still running, not saving session");
+        return;
+    }
 
-    // Generate signature
-    let signature = TrackSignature {
-        artist: track.artist.clone(),
-        title: track.title.clone(),
-        album: Some(track.album.clone()),
-        duration_sec: track.duration_secs as u32,
-    };
-    let sig_hash = signature.generate_hash();
+    // Save session state with remaining work queue
+    if let Err(e) = shared_state.save_session(work_queue, session_path).await {
+        tracing::error!("Failed to save session: {}", e);
+    }
+}
 
-    // Check negative cache (bypass if force_retry is enabled)
-    if !shared_state.force_retry {
-        if shared_state.cache.lock().await.is_cached(&sig_hash)? {
-            tx.send(WorkerMessage::CacheHit {
-                path: path.display().to_string(),
-            })?;
-            *shared_state.cached.lock().await += 1;
-            shared_state
-                .session
-                .lock()
-                .await
-                .add_log(filename, StatusType::Cached);
-            return Ok(());
+/// Pause switch shared by the control handler and the workers
+/// Workers check the flag without locking and sleep until resumed instead of polling.
+struct PauseGate {
+    paused: AtomicBool,
+    resumed: Notify,
+}
+
+impl PauseGate {
+    fn new(paused: bool) -> Self {
+        Self {
+            paused: AtomicBool::new(paused),
+            resumed: Notify::new(),
         }
-    } else {
-        // Force retry mode - check if in cache and log bypass
-        if shared_state.cache.lock().await.is_cached(&sig_hash)? {
-            tracing::info!(
-                "Force retry: bypassing negative cache for {}",
-                path.display()
-            );
+    }
+
+    fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the pool is not paused
+    /// Returns false if the pool was cancelled first.
+    async fn wait(&self, cancel: &CancellationToken) -> bool {
+        loop {
+            // Register before checking the flag so a resume in between isn't missed
+            let resumed = self.resumed.notified();
+            if !self.is_paused() {
+                return true;
+            }
+            tokio::select! {
+                _ = resumed => {}
+                _ = cancel.cancelled() => return false,
+            }
         }
     }
+}
 
-    // Wait for rate limiter
-    rate_limiter.until_ready().await;
+/// Files handed to a worker together: a single file, or a whole album when grouping by album
+type WorkItem = Vec<PathBuf>;
 
-    // Fetch lyrics with smart normalization and fuzzy matching
-    use crate::api::SearchResult;
-    match client.get_lyrics_smart(&track).await {
-        Ok(SearchResult::Found(lyrics)) => {
-            // Exact match found
-            if let Some(synced) = lyrics.synced_lyrics {
-                if let Err(e) = write_lrc_file(path, &synced) {
-                    tx.send(WorkerMessage::Error {
-                        path: path.display().to_string(),
-                        error: e.to_string(),
-                    })?;
-                    *shared_state.failed.lock().await += 1;
-                    shared_state
-                        .session
-                        .lock()
-                        .await
-                        .add_log(filename, StatusType::Error);
-                } else {
-                    tx.send(WorkerMessage::LyricsFound {
-                        path: path.display().to_string(),
-                    })?;
-                    *shared_state.downloaded.lock().await += 1;
-                    shared_state
-                        .session
-                        .lock()
-                        .await
-                        .add_log(filename, StatusType::Downloaded);
-
-                    // If force_retry is enabled and this was in cache, remove it
-                    if shared_state.force_retry {
-                        if let Err(e) = shared_state.cache.lock().await.remove(&sig_hash) {
-                            tracing::warn!(
-                                "Failed to remove {} from negative cache: {}",
-                                path.display(),
-                                e
-                            );
-                        } else {
-                            tracing::info!(
-                                "Force retry success: removed {} from negative cache",
-                                path.display()
-                            );
-                        }
+/// A file's modification time in seconds since the epoch
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(secs).ok()
+}
+
+/// The album (directory) a file belongs to
+fn album_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+/// Split files into work items, one per album when grouping by album
+/// Albums keep the position of their first file.
+fn work_items(files: Vec<PathBuf>, group_albums: bool) -> Vec<WorkItem> {
+    if !group_albums {
+        return files.into_iter().map(|path| vec![path]).collect();
+    }
+    let mut items: Vec<WorkItem> = Vec::new();
+    let mut albums: HashMap<PathBuf, usize> = HashMap::new();
+    for path in files {
+        let index = *albums
+            .entry(album_dir(&path).to_path_buf())
+            .or_insert_with(|| {
+                items.push(Vec::new());
+                items.len() - 1
+            });
+        items[index].push(path);
+    }
+    items
+}
+
+/// Sort items by path, and each album's files
+fn sort_items(items: &mut [WorkItem]) {
+    for item in items.iter_mut() {
+        item.sort();
+    }
+    items.sort();
+}
+
+/// What a worker takes from the queue
+#[derive(Debug, PartialEq)]
+enum Work {
+    Item(WorkItem),
+    /// A failed file queued again from the UI
+    Retry(PathBuf),
+}
+
+/// Order in which queued files are processed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueOrder {
+    /// The order the scan (or the resumed session) lists them in
+    #[default]
+    Discovery,
+    /// Most recently modified first, among the files found so far
+    Newest,
+    /// Sorted by path, once the scan has found every file
+    Path,
+}
+
+/// A queued item with its place in the queue
+/// Albums moved to the front come first, then the queue order's key, then arrival order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Queued {
+    pinned: bool,
+    key: i64,
+    arrival: std::cmp::Reverse<u64>,
+    item: WorkItem,
+}
+
+/// Work shared by the scan, the workers and the control handler
+/// Items arrive over an MPMC channel, so the scan never waits on workers; workers move them
+/// into a priority queue as they take work, and sleep on `added` while the scan finds more.
+struct WorkQueue {
+    tx: crossbeam_channel::Sender<WorkItem>,
+    rx: crossbeam_channel::Receiver<WorkItem>,
+    /// Items that have arrived, by priority
+    ordered: std::sync::Mutex<BinaryHeap<Queued>>,
+    /// Items in `ordered`, readable without its lock
+    in_order: AtomicUsize,
+    order: QueueOrder,
+    arrivals: AtomicU64,
+    /// Albums (directories) the user moved to the front
+    pinned: DashSet<PathBuf>,
+    /// Failed files the user asked to retry, taken before anything else
+    retry_tx: crossbeam_channel::Sender<PathBuf>,
+    retry_rx: crossbeam_channel::Receiver<PathBuf>,
+    added: Notify,
+    scanning: AtomicBool,
+    group_albums: bool,
+    /// Files of the item each worker took that it hasn't started yet
+    held: DashMap<usize, VecDeque<PathBuf>>,
+    /// Files queued, retried or held but not started yet
+    queued: AtomicUsize,
+}
+
+impl WorkQueue {
+    fn new(files: Vec<PathBuf>, group_albums: bool, order: QueueOrder, scanning: bool) -> Self {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let (retry_tx, retry_rx) = crossbeam_channel::unbounded();
+        let queue = Self {
+            tx,
+            rx,
+            ordered: std::sync::Mutex::new(BinaryHeap::new()),
+            in_order: AtomicUsize::new(0),
+            order,
+            arrivals: AtomicU64::new(0),
+            pinned: DashSet::new(),
+            retry_tx,
+            retry_rx,
+            added: Notify::new(),
+            scanning: AtomicBool::new(scanning),
+            group_albums,
+            held: DashMap::new(),
+            queued: AtomicUsize::new(0),
+        };
+        let mut items = work_items(files, group_albums);
+        if order == QueueOrder::Path {
+            sort_items(&mut items);
+        }
+        for item in items {
+            queue.push(item);
+        }
+        queue
+    }
+
+    /// Queue an item and wake a waiting worker
+    fn push(&self, item: WorkItem) {
+        let files = item.len();
+        if files > 0 && self.tx.send(item).is_ok() {
+            self.queued.fetch_add(files, Ordering::Relaxed);
+            self.added.notify_one();
+        }
+    }
+
+    /// Queue a failed file to be processed again ahead of the rest
+    fn push_retry(&self, path: PathBuf) {
+        if self.retry_tx.send(path).is_ok() {
+            self.queued.fetch_add(1, Ordering::Relaxed);
+            self.added.notify_one();
+        }
+    }
+
+    /// Queue a file found by the walk
+    /// When grouping by album, files are collected in `album` until the walk moves on to
+    /// another directory; it reads each directory's files together.
+    fn push_found(&self, album: &mut WorkItem, path: PathBuf) {
+        if !self.group_albums {
+            return self.push(vec![path]);
+        }
+        if album
+            .last()
+            .is_some_and(|last| album_dir(last) != album_dir(&path))
+        {
+            self.push(std::mem::take(album));
+        }
+        album.push(path);
+    }
+
+    /// Move an album's queued files, and any found later, ahead of everything else
+    fn prioritize(&self, dir: PathBuf) {
+        self.pinned.insert(dir);
+        let arrived = self.arrived();
+        let mut ordered = self.ordered.lock().unwrap();
+        let mut items = std::mem::take(&mut *ordered).into_vec();
+        items.extend(arrived);
+        for queued in &mut items {
+            queued.pinned = self.is_pinned(&queued.item);
+        }
+        *ordered = BinaryHeap::from(items);
+        self.in_order.store(ordered.len(), Ordering::Release);
+    }
+
+    fn is_pinned(&self, item: &WorkItem) -> bool {
+        item.first()
+            .is_some_and(|path| self.pinned.contains(album_dir(path)))
+    }
+
+    /// Items waiting in the channel, ready to be ordered
+    /// Modification times are read here, outside the priority queue's lock.
+    fn arrived(&self) -> Vec<Queued> {
+        self.rx
+            .try_iter()
+            .map(|item| Queued {
+                pinned: self.is_pinned(&item),
+                key: match self.order {
+                    QueueOrder::Discovery | QueueOrder::Path => 0,
+                    QueueOrder::Newest => item
+                        .iter()
+                        .filter_map(|path| modified_secs(path))
+                        .max()
+                        .unwrap_or(0),
+                },
+                arrival: std::cmp::Reverse(self.arrivals.fetch_add(1, Ordering::Relaxed)),
+                item,
+            })
+            .collect()
+    }
+
+    /// Take the item that should be processed next, if any has arrived
+    fn take(&self) -> Option<WorkItem> {
+        // The channel already holds items in discovery order, so the priority queue is only
+        // needed once an album is pinned, or for what was moved into it before
+        if self.order == QueueOrder::Discovery
+            && self.pinned.is_empty()
+            && self.in_order.load(Ordering::Acquire) == 0
+        {
+            return self.rx.try_recv().ok();
+        }
+        let arrived = self.arrived();
+        let mut ordered = self.ordered.lock().unwrap();
+        ordered.extend(arrived);
+        let item = ordered.pop().map(|queued| queued.item);
+        self.in_order.store(ordered.len(), Ordering::Release);
+        item
+    }
+
+    /// Mark the walk as done, waking idle workers so they can stop
+    fn finish_scan(&self) {
+        // Everything has arrived, so it can be sorted before the first item is taken
+        if self.order == QueueOrder::Path {
+            let mut items: Vec<WorkItem> = self.rx.try_iter().collect();
+            let mut ordered = self.ordered.lock().unwrap();
+            items.extend(std::mem::take(&mut *ordered).into_iter().map(|q| q.item));
+            self.in_order.store(0, Ordering::Release);
+            sort_items(&mut items);
+            drop(ordered);
+            // Already counted when first queued
+            for item in items {
+                let _ = self.tx.send(item);
+            }
+        }
+        self.scanning.store(false, Ordering::SeqCst);
+        self.added.notify_waiters();
+    }
+
+    fn is_scanning(&self) -> bool {
+        self.scanning.load(Ordering::SeqCst)
+    }
+
+    /// Wait for the next item
+    /// Returns None once the scan is done and nothing is left, or on cancellation.
+    async fn next(&self, cancel: &CancellationToken) -> Option<Work> {
+        loop {
+            // Register and check the scan first: once it has finished, the channel holds
+            // everything it found
+            let added = self.added.notified();
+            let scan_running = self.is_scanning();
+            if let Ok(path) = self.retry_rx.try_recv() {
+                return Some(Work::Retry(path));
+            }
+            // Sorted runs start once the scan has found every file
+            let ready = !(scan_running && self.order == QueueOrder::Path);
+            match ready.then(|| self.take()).flatten() {
+                Some(item) => return Some(Work::Item(item)),
+                None if scan_running => cancel.run_until_cancelled(added).await?,
+                None => return None,
+            }
+        }
+    }
+
+    /// Keep a worker's files until it starts each one
+    fn hold(&self, worker_id: usize, files: WorkItem) {
+        // Files are counted until taken from here
+        if let Some(abandoned) = self.held.insert(worker_id, files.into()) {
+            self.queued.fetch_sub(abandoned.len(), Ordering::Relaxed);
+        }
+    }
+
+    fn take_held(&self, worker_id: usize) -> Option<PathBuf> {
+        let path = self.held.get_mut(&worker_id)?.pop_front()?;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        Some(path)
+    }
+
+    /// How many files haven't been started yet
+    fn len(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Every file not started yet, in the order it would be processed, for saving the session
+    fn pending(&self) -> Vec<PathBuf> {
+        let mut pending: Vec<PathBuf> = self
+            .held
+            .iter()
+            .flat_map(|files| files.value().iter().cloned().collect::<Vec<_>>())
+            .collect();
+        let retries: Vec<PathBuf> = self.retry_rx.try_iter().collect();
+        for path in retries {
+            pending.push(path.clone());
+            let _ = self.retry_tx.send(path);
+        }
+
+        // Copy the queue and sort the copy, so workers aren't kept waiting on the lock
+        let arrived = self.arrived();
+        let mut ordered = self.ordered.lock().unwrap();
+        ordered.extend(arrived);
+        self.in_order.store(ordered.len(), Ordering::Release);
+        let snapshot = ordered.clone();
+        drop(ordered);
+        let queued = snapshot.into_sorted_vec();
+        pending.reserve(queued.iter().map(|queued| queued.item.len()).sum());
+        pending.extend(queued.into_iter().rev().flat_map(|queued| queued.item));
+        pending
+    }
+}
+
+/// Wrap up an album once all its files have been processed
+/// Errors (timeouts, rate limiting) tend to hit a run of consecutive lookups, so tracks that
+/// failed with one are retried together after a pause before the album's outcome is reported.
+async fn finish_album(
+    dir: &Path,
+    tracks: Vec<PathBuf>,
+    client: &LrcLibClient,
+    shared_state: &WorkerPoolState,
+    rate_limiter: &SharedRateLimiter,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+    worker: usize,
+) -> Result<()> {
+    let mut errored = Vec::new();
+    for path in &tracks {
+        if shared_state
+            .indexed(path)
+            .await
+            .is_some_and(|entry| entry.status == StatusType::Error)
+        {
+            errored.push(path);
+        }
+    }
+
+    if !errored.is_empty() {
+        tracing::info!(
+            "Retrying {} failed tracks of album {}",
+            errored.len(),
+            dir.display()
+        );
+        // The retried tracks are counted again once processed
+        let _ = shared_state
+            .failed
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |failed| {
+                Some(failed.saturating_sub(errored.len()))
+            });
+        for path in &errored {
+            shared_state.errors.remove(*path);
+        }
+        tx.send(WorkerMessage::AlbumRetry {
+            dir: dir.to_path_buf(),
+            tracks: errored.len(),
+        })?;
+
+        shared_state.set_stage(worker, None, WorkerStage::AlbumRetry);
+        tokio::time::sleep(tokio::time::Duration::from_secs(ALBUM_RETRY_DELAY_SECS)).await;
+        for path in errored {
+            let started = Instant::now();
+            if let Err(e) = process_file(path, client, shared_state, rate_limiter, tx, worker).await
+            {
+                tracing::error!("Error retrying {}: {}", path.display(), e);
+            }
+            shared_state.file_done(path, started);
+        }
+    }
+
+    let mut with_lyrics = 0;
+    for path in &tracks {
+        if shared_state
+            .indexed(path)
+            .await
+            .is_some_and(|entry| entry.status.has_lyrics())
+        {
+            with_lyrics += 1;
+        }
+    }
+    tracing::info!(
+        "Album {} complete: {}/{} tracks with lyrics",
+        dir.display(),
+        with_lyrics,
+        tracks.len()
+    );
+    tx.send(WorkerMessage::AlbumComplete {
+        dir: dir.to_path_buf(),
+        with_lyrics,
+        total: tracks.len(),
+    })?;
+    Ok(())
+}
+
+/// Inputs for a fresh directory walk
+struct FreshScan {
+    root: PathBuf,
+    /// Library index entries by path
+    journal: HashMap<String, IndexEntry>,
+    dir_journal: DirJournal,
+    ignore_embedded: bool,
+}
+
+/// What a finished directory walk found besides the queued files
+#[derive(Debug, Default)]
+struct ScanSummary {
+    /// Every directory visited
+    dirs: Vec<PathBuf>,
+}
+
+/// Walk the library and queue files for the workers as they are discovered
+async fn feed_scan(
+    scan: FreshScan,
+    shared_state: &WorkerPoolState,
+    work_queue: &WorkQueue,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+) -> Result<ScanSummary> {
+    let FreshScan {
+        root,
+        journal,
+        dir_journal,
+        ignore_embedded,
+    } = scan;
+
+    // Walk on a blocking thread, streaming files back as they are found
+    let (found_tx, mut found_rx) = mpsc::unbounded_channel();
+    let walk = tokio::task::spawn_blocking(move || {
+        incremental::walk_incremental(&root, &dir_journal, |found| {
+            let _ = found_tx.send(found);
+        })
+    });
+
+    let mut summary = ScanSummary::default();
+    let mut progress = ProgressThrottle::new();
+    let mut queued = 0;
+    let mut existing = 0;
+    let mut unchanged = 0;
+    // Files of the directory being read, when grouping by album
+    let mut album = Vec::new();
+
+    while let Some(found) = found_rx.recv().await {
+        // Files in unchanged directories that had lyrics last time still do: sidecars live
+        // alongside them, so adding or removing one would have changed the directory
+        let (path, dir_unchanged) = match found {
+            Discovered::Changed(path) => (path, false),
+            Discovered::Unchanged(path) => (path, true),
+        };
+        let previous = journal.get(&path_encoding::encode(&path));
+
+        // Skip files that already have lyrics: unchanged since the last scan, or with .lrc sidecars
+        if previous.is_some_and(|previous| {
+            still_has_lyrics(previous, &path, dir_unchanged, ignore_embedded)
+        }) {
+            unchanged += 1;
+            // Counted as found, so a session saved mid-scan includes them
+            shared_state.existing.fetch_add(1, Ordering::Relaxed);
+            existing += 1;
+            tx.send(WorkerMessage::AlreadyHasLrc {
+                path: path.to_path_buf(),
+            })?;
+        } else if !dir_unchanged && scanner::has_lrc_sidecar(&path) {
+            shared_state
+                .cache
+                .record(IndexEntry::new(&path, None, StatusType::Existing)?)
+                .await?;
+            shared_state.existing.fetch_add(1, Ordering::Relaxed);
+            existing += 1;
+            tx.send(WorkerMessage::AlreadyHasLrc {
+                path: path.to_path_buf(),
+            })?;
+        } else {
+            work_queue.push_found(&mut album, path);
+            queued += 1;
+        }
+
+        if let Some(files_found) = progress.tick() {
+            tx.send(WorkerMessage::ScanProgress { files_found })?;
+        }
+    }
+    work_queue.push(album);
+    summary.dirs = walk.await?;
+
+    if unchanged > 0 {
+        tracing::info!("Skipped {} files unchanged since the last scan", unchanged);
+    }
+    tx.send(WorkerMessage::ScanStarted {
+        total_files: existing + queued,
+    })?;
+
+    Ok(summary)
+}
+
+/// Process a single file
+async fn process_file(
+    path: &Path,
+    client: &LrcLibClient,
+    shared_state: &WorkerPoolState,
+    rate_limiter: &SharedRateLimiter,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+    worker: usize,
+) -> Result<()> {
+    shared_state.set_stage(worker, Some(path), WorkerStage::Reading);
+    let filename = path.file_name().map_or_else(
+        || "unknown".to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    );
+
+    let previous = shared_state
+        .timed_async(path, Stage::Cache, shared_state.indexed(path))
+        .await;
+    let retry_requested = shared_state.retry_requested.remove(path).is_some();
+
+    // Unchanged files that are still negative cached don't need their tags read again
+    if let Some(signature) = previous
+        .as_ref()
+        .filter(|_| !retry_requested)
+        .and_then(|previous| shared_state.journaled_signature(previous, path))
+    {
+        if shared_state.skip_cached(path, signature)? {
+            let signature = signature.to_string();
+            return shared_state.cache_hit(path, filename, &signature, tx).await;
+        }
+    }
+
+    // Single-file albums are split into their tracks by an accompanying cue sheet
+    if let Some(sheet) = shared_state.cue_sheet(path) {
+        shared_state.set_stage(worker, Some(path), WorkerStage::Searching);
+        return process_cue_image(
+            path,
+            filename,
+            sheet,
+            client,
+            shared_state,
+            rate_limiter,
+            tx,
+        )
+        .await;
+    }
+
+    // Extract metadata (untagged files are identified by fingerprint or guessed from their path)
+    let mut track = match shared_state.read_track(path).await {
+        Ok(t) => t,
+        Err(e) => {
+            tx.send(WorkerMessage::NoMetadata {
+                path: path.to_path_buf(),
+                error: e.to_string(),
+            })?;
+
+            shared_state.untagged.fetch_add(1, Ordering::Relaxed);
+            shared_state
+                .log(path, filename, StatusType::NoMetadata, None)
+                .await;
+            return Ok(());
+        }
+    };
+
+    // Fields entered in the manual search replace the file's
+    if let Some((_, tags)) = shared_state.manual.remove(path) {
+        tags.apply(&mut track);
+    }
+
+    report_sanitized(path, &track.sanitize(), tx)?;
+
+    if let Some(reason) = shared_state.filter.exclusion(&track) {
+        return shared_state.exclude(path, filename, reason, tx).await;
+    }
+
+    // Podcasts and audiobooks have no lyrics to find
+    if shared_state.detect_spoken {
+        if let Some(reason) = spoken::detect(&track) {
+            return shared_state.skip_spoken(path, filename, reason, tx).await;
+        }
+    }
+
+    // Skip tracks that already ship with lyrics in their tags
+    if track.has_embedded_lyrics && !shared_state.ignore_embedded {
+        tracing::debug!("Embedded lyrics found, skipping: {}", path.display());
+        tx.send(WorkerMessage::EmbeddedLyrics {
+            path: path.to_path_buf(),
+        })?;
+        shared_state.embedded.fetch_add(1, Ordering::Relaxed);
+        shared_state
+            .log(path, filename, StatusType::EmbeddedExists, None)
+            .await;
+        return Ok(());
+    }
+
+    if track.source == MetadataSource::Path {
+        tracing::warn!(
+            "No usable tags in {}, searching for {} - {} guessed from the path. Manual verification recommended.",
+            path.display(),
+            track.artist,
+            track.title
+        );
+    }
+
+    tx.send(WorkerMessage::TrackProcessing {
+        track: track.clone(),
+    })?;
+
+    // Generate signature
+    let sig_hash = TrackSignature::from_track(&track).generate_hash();
+
+    // Retagged files get a fresh lookup even if their signature is negative cached
+    let bypass_cache = shared_state.force_retry
+        || retry_requested
+        || shared_state.retagged(previous, path, &sig_hash).await;
+
+    // Check negative cache (bypass if force_retry is enabled or the file changed)
+    shared_state.set_stage(worker, Some(path), WorkerStage::CheckingCache);
+    if !bypass_cache {
+        if shared_state.skip_cached(path, &sig_hash)? {
+            return shared_state.cache_hit(path, filename, &sig_hash, tx).await;
+        }
+    } else {
+        // Bypass mode - check if in cache and log bypass
+        if shared_state.cache.is_cached(&sig_hash)? {
+            tracing::info!("Bypassing negative cache for {}", path.display());
+        }
+    }
+
+    // Files with the same signature (duplicate rips, FLAC+MP3 copies) share one lookup per run;
+    // the slot stays locked until the first of them has an outcome
+    if retry_requested {
+        shared_state.lookups.remove(&sig_hash);
+    }
+    let slot = shared_state
+        .lookups
+        .entry(sig_hash.clone())
+        .or_default()
+        .clone();
+    let mut outcome = slot.lock().await;
+    match outcome.clone() {
+        Some(SharedLookup::Written(source)) => {
+            drop(outcome);
+            return shared_state
+                .copy_lyrics(&source, path, filename, &sig_hash, tx)
+                .await;
+        }
+        Some(SharedLookup::NotFound) => {
+            drop(outcome);
+            return shared_state.cache_hit(path, filename, &sig_hash, tx).await;
+        }
+        None => {}
+    }
+
+    // The same recording under different tags (a retagged copy, another rip's metadata)
+    let content_key = shared_state
+        .dedupe_audio
+        .then(|| audio_hash::content_key(path))
+        .flatten();
+    if let Some(source) = content_key
+        .as_ref()
+        .and_then(|key| shared_state.content_written.get(key))
+        .map(|source| source.clone())
+    {
+        tracing::info!(
+            "{} has the same audio as {}",
+            path.display(),
+            source.display()
+        );
+        *outcome = Some(SharedLookup::Written(source.clone()));
+        drop(outcome);
+        return shared_state
+            .copy_lyrics(&source, path, filename, &sig_hash, tx)
+            .await;
+    }
+
+    // Fetch lyrics with smart normalization and fuzzy matching
+    use crate::api::SearchResult;
+    let mut result =
+        search_lyrics(&track, client, shared_state, rate_limiter, tx, Some(worker)).await;
+    if shared_state.pick {
+        if let Ok(found) = result {
+            result = pick_lyrics(
+                &track,
+                found,
+                client,
+                shared_state,
+                rate_limiter,
+                tx,
+                worker,
+            )
+            .await;
+        }
+    }
+    match result {
+        Ok(SearchResult::Found(lyrics)) => {
+            // Exact match found
+            let reason = negative_reason(&lyrics);
+            if let Some(synced) = lyrics.into_lyrics(shared_state.allow_plain) {
+                shared_state.set_stage(worker, Some(path), WorkerStage::Writing);
+                let written = shared_state.timed(path, Stage::Write, || {
+                    lrc::write_sidecar(path, &synced, &shared_state.write_options)
+                });
+                if let Err(e) = written {
+                    shared_state
+                        .fail(
+                            path,
+                            filename,
+                            ErrorKind::Io,
+                            e.to_string(),
+                            Some(&sig_hash),
+                            tx,
+                        )
+                        .await?;
+                } else {
+                    tx.send(WorkerMessage::LyricsFound {
+                        path: path.to_path_buf(),
+                    })?;
+                    shared_state.downloaded.fetch_add(1, Ordering::Relaxed);
+                    shared_state
+                        .log(path, filename, StatusType::Downloaded, Some(&sig_hash))
+                        .await;
+                    *outcome = Some(SharedLookup::Written(path.to_path_buf()));
+                    if let Some(key) = &content_key {
+                        shared_state
+                            .content_written
+                            .insert(key.clone(), path.to_path_buf());
                     }
+                    shared_state.clear_negative(path, &sig_hash).await;
                 }
             } else {
-                // No synced lyrics, add to negative cache
-                shared_state.cache.lock().await.add(&sig_hash)?;
+                // Nothing usable to write, add to negative cache
+                shared_state
+                    .cache
+                    .add(
+                        FailedLookup::for_track(&track, &sig_hash, reason)
+                            .with_response(api::PROVIDER, 200),
+                    )
+                    .await?;
                 tx.send(WorkerMessage::LyricsNotFound {
-                    path: path.display().to_string(),
+                    path: path.to_path_buf(),
                 })?;
-                *shared_state.failed.lock().await += 1;
+                shared_state.failed.fetch_add(1, Ordering::Relaxed);
                 shared_state
-                    .session
-                    .lock()
-                    .await
-                    .add_log(filename, StatusType::NotFound);
+                    .log(path, filename, StatusType::NotFound, Some(&sig_hash))
+                    .await;
+                *outcome = Some(SharedLookup::NotFound);
             }
         }
         Ok(SearchResult::PotentialMatch { lyrics, similarity }) => {
@@ -477,92 +2076,805 @@ async fn process_file(
                 lyrics.track_name
             );
 
-            if let Some(synced) = lyrics.synced_lyrics {
-                if let Err(e) = write_lrc_file(path, &synced) {
-                    tx.send(WorkerMessage::Error {
-                        path: path.display().to_string(),
-                        error: e.to_string(),
-                    })?;
-                    *shared_state.failed.lock().await += 1;
+            let reason = negative_reason(&lyrics);
+            if let Some(synced) = lyrics.into_lyrics(shared_state.allow_plain) {
+                shared_state.set_stage(worker, Some(path), WorkerStage::Writing);
+                let written = shared_state.timed(path, Stage::Write, || {
+                    lrc::write_sidecar(path, &synced, &shared_state.write_options)
+                });
+                if let Err(e) = written {
                     shared_state
-                        .session
-                        .lock()
-                        .await
-                        .add_log(filename, StatusType::Error);
+                        .fail(
+                            path,
+                            filename,
+                            ErrorKind::Io,
+                            e.to_string(),
+                            Some(&sig_hash),
+                            tx,
+                        )
+                        .await?;
                 } else {
                     tx.send(WorkerMessage::LyricsFound {
-                        path: path.display().to_string(),
+                        path: path.to_path_buf(),
                     })?;
-                    *shared_state.downloaded.lock().await += 1;
+                    shared_state.downloaded.fetch_add(1, Ordering::Relaxed);
                     shared_state
-                        .session
-                        .lock()
-                        .await
-                        .add_log(filename, StatusType::Downloaded);
-
-                    // If force_retry is enabled and this was in cache, remove it
-                    if shared_state.force_retry {
-                        if let Err(e) = shared_state.cache.lock().await.remove(&sig_hash) {
-                            tracing::warn!(
-                                "Failed to remove {} from negative cache: {}",
-                                path.display(),
-                                e
-                            );
-                        } else {
-                            tracing::info!(
-                                "Force retry success: removed {} from negative cache",
-                                path.display()
-                            );
-                        }
+                        .log(path, filename, StatusType::Downloaded, Some(&sig_hash))
+                        .await;
+                    *outcome = Some(SharedLookup::Written(path.to_path_buf()));
+                    if let Some(key) = &content_key {
+                        shared_state
+                            .content_written
+                            .insert(key.clone(), path.to_path_buf());
                     }
+                    shared_state.clear_negative(path, &sig_hash).await;
                 }
             } else {
-                // No synced lyrics, add to negative cache
-                shared_state.cache.lock().await.add(&sig_hash)?;
+                // Nothing usable to write, add to negative cache
+                shared_state
+                    .cache
+                    .add(
+                        FailedLookup::for_track(&track, &sig_hash, reason)
+                            .with_response(api::PROVIDER, 200),
+                    )
+                    .await?;
                 tx.send(WorkerMessage::LyricsNotFound {
-                    path: path.display().to_string(),
+                    path: path.to_path_buf(),
                 })?;
-                *shared_state.failed.lock().await += 1;
+                shared_state.failed.fetch_add(1, Ordering::Relaxed);
                 shared_state
-                    .session
-                    .lock()
-                    .await
-                    .add_log(filename, StatusType::NotFound);
+                    .log(path, filename, StatusType::NotFound, Some(&sig_hash))
+                    .await;
+                *outcome = Some(SharedLookup::NotFound);
             }
         }
-        Ok(SearchResult::NotFound) => {
+        Ok(SearchResult::NotFound { status }) => {
             // All search attempts failed - add to negative cache
-            shared_state.cache.lock().await.add(&sig_hash)?;
+            shared_state
+                .cache
+                .add(
+                    FailedLookup::for_track(&track, &sig_hash, NegativeReason::NotFound)
+                        .with_response(api::PROVIDER, status),
+                )
+                .await?;
             tx.send(WorkerMessage::LyricsNotFound {
-                path: path.display().to_string(),
+                path: path.to_path_buf(),
             })?;
-            *shared_state.failed.lock().await += 1;
+            shared_state.failed.fetch_add(1, Ordering::Relaxed);
             shared_state
-                .session
-                .lock()
-                .await
-                .add_log(filename, StatusType::NotFound);
+                .log(path, filename, StatusType::NotFound, Some(&sig_hash))
+                .await;
+            *outcome = Some(SharedLookup::NotFound);
         }
         Err(e) => {
-            tx.send(WorkerMessage::Error {
-                path: path.display().to_string(),
-                error: e.to_string(),
-            })?;
-            *shared_state.failed.lock().await += 1;
             shared_state
-                .session
-                .lock()
-                .await
-                .add_log(filename, StatusType::Error);
+                .fail(
+                    path,
+                    filename,
+                    ErrorKind::of(&e),
+                    e.to_string(),
+                    Some(&sig_hash),
+                    tx,
+                )
+                .await?;
         }
     }
 
     Ok(())
 }
 
-fn write_lrc_file(audio_path: &Path, lyrics: &str) -> Result<()> {
-    let mut lrc_path = audio_path.to_path_buf();
-    lrc_path.set_extension("lrc");
-    std::fs::write(lrc_path, lyrics)?;
+/// Look for other uploads of a track the exact lookup had no confident match for
+/// A single plausible search result is used as is. Between several, the UI is asked which
+/// to write, and the best one is taken if no answer comes in time.
+async fn pick_lyrics(
+    track: &Track,
+    found: api::SearchResult,
+    client: &LrcLibClient,
+    shared_state: &WorkerPoolState,
+    rate_limiter: &SharedRateLimiter,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+    worker: usize,
+) -> Result<api::SearchResult> {
+    if matches!(found, api::SearchResult::Found(_)) {
+        return Ok(found);
+    }
+
+    shared_state.set_stage(worker, Some(&track.path), WorkerStage::RateLimited);
+    shared_state.breaker.until_closed().await;
+    shared_state
+        .timed_async(&track.path, Stage::RateLimit, rate_limiter.until_ready())
+        .await;
+    shared_state.set_stage(worker, Some(&track.path), WorkerStage::Searching);
+    let search = client.search_candidates(track);
+    let mut candidates = match shared_state
+        .timed_async(&track.path, Stage::Api, search)
+        .await
+    {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            tracing::warn!(
+                "Search for other uploads of {} failed: {:#}",
+                track.path.display(),
+                e
+            );
+            return Ok(found);
+        }
+    };
+
+    let choice = match candidates.len() {
+        0 => return Ok(found),
+        1 => Some(0),
+        _ => {
+            let (reply, answer) = oneshot::channel();
+            shared_state.picks.insert(track.path.clone(), reply);
+            tx.send(WorkerMessage::PickLyrics {
+                path: track.path.clone(),
+                candidates: candidates
+                    .iter()
+                    .map(|(_, candidate)| candidate.clone())
+                    .collect(),
+            })?;
+            shared_state.set_stage(worker, Some(&track.path), WorkerStage::Picking);
+            let timeout = std::time::Duration::from_secs(PICK_TIMEOUT_SECS);
+            match tokio::time::timeout(timeout, answer).await {
+                Ok(Ok(choice)) => choice,
+                _ => {
+                    shared_state.picks.remove(&track.path);
+                    tracing::info!(
+                        "Nothing picked for {}, using the best of {} search results",
+                        track.path.display(),
+                        candidates.len()
+                    );
+                    Some(0)
+                }
+            }
+        }
+    };
+    shared_state.set_stage(worker, Some(&track.path), WorkerStage::Searching);
+
+    match choice.filter(|choice| *choice < candidates.len()) {
+        Some(choice) => {
+            let (lyrics, candidate) = candidates.swap_remove(choice);
+            tracing::info!(
+                "Using search result {} - {} for {} (similarity: {:.2})",
+                candidate.artist,
+                candidate.title,
+                track.path.display(),
+                candidate.similarity()
+            );
+            Ok(api::SearchResult::Found(lyrics))
+        }
+        None => {
+            tracing::info!("Search results for {} skipped", track.path.display());
+            Ok(api::SearchResult::NotFound {
+                status: reqwest::StatusCode::OK.as_u16(),
+            })
+        }
+    }
+}
+
+/// Search for a track's lyrics once the rate limit and the circuit breaker allow it
+/// Connection failures while the breaker is open are retried after its backoff rather than
+/// reported, so a dead connection doesn't turn every remaining file into an error.
+async fn search_lyrics(
+    track: &Track,
+    client: &LrcLibClient,
+    shared_state: &WorkerPoolState,
+    rate_limiter: &SharedRateLimiter,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+    worker: Option<usize>,
+) -> Result<api::SearchResult> {
+    let stage = |stage| {
+        if let Some(worker) = worker {
+            shared_state.set_stage(worker, Some(&track.path), stage);
+        }
+    };
+    shared_state.fetched.insert(track.path.clone());
+    let mut attempt = 1;
+    let mut attempts = Vec::new();
+    loop {
+        stage(WorkerStage::RateLimited);
+        shared_state.breaker.until_closed().await;
+        shared_state
+            .timed_async(&track.path, Stage::RateLimit, rate_limiter.until_ready())
+            .await;
+
+        stage(WorkerStage::Searching);
+        let lookup = client.get_lyrics_traced(track, &mut attempts);
+        let result = shared_state
+            .timed_async(&track.path, Stage::Api, lookup)
+            .await;
+        tx.send(WorkerMessage::SearchAttempts {
+            path: track.path.clone(),
+            attempts: attempts.clone(),
+        })?;
+        let error = match result {
+            Err(e) if api::is_connection_failure(&e) => match shared_state.breaker.record_failure()
+            {
+                Failure::Isolated => e,
+                Failure::Tripped(retry_in) => {
+                    tracing::warn!(
+                        "{} lookups failed in a row ({}), pausing lookups for {}s",
+                        BREAKER_THRESHOLD,
+                        e,
+                        retry_in.as_secs()
+                    );
+                    tx.send(WorkerMessage::ConnectionLost {
+                        error: e.to_string(),
+                        retry_in,
+                    })?;
+                    continue;
+                }
+                Failure::Open => continue,
+            },
+            Err(e) if retry::classify(&e).is_some() => e,
+            result => {
+                if shared_state.breaker.record_success() {
+                    tracing::info!("Connection to LRCLIB restored, resuming lookups");
+                    tx.send(WorkerMessage::ConnectionRestored)?;
+                }
+                return result;
+            }
+        };
+
+        // Attempts held back by the open breaker above don't count towards the policy
+        let Some(backoff) = shared_state.retry.retry_after(&error, attempt) else {
+            return Err(error);
+        };
+        tracing::warn!(
+            "Lookup for {} failed ({}), attempt {} of {}, retrying in {}ms",
+            track.path.display(),
+            error,
+            attempt,
+            shared_state.retry.max_attempts,
+            backoff.as_millis()
+        );
+        stage(WorkerStage::Backoff);
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+/// Warn that invisible characters were stripped from a track's tags before searching
+/// They are a silent cause of failed lookups, so the tags are worth fixing at the source.
+fn report_sanitized(
+    path: &Path,
+    fields: &[&str],
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+) -> Result<()> {
+    if fields.is_empty() {
+        return Ok(());
+    }
+    let message = format!("invisible characters removed from {}", fields.join(", "));
+    tracing::warn!("{}: {}", path.display(), message);
+    tx.send(WorkerMessage::Warning {
+        path: path.to_path_buf(),
+        message,
+    })?;
     Ok(())
 }
+
+/// Process a single-file album, looking up each track listed in its cue sheet
+/// The image counts as one file: downloaded if any track's lyrics were written.
+async fn process_cue_image(
+    path: &Path,
+    filename: String,
+    sheet: CueSheet,
+    client: &LrcLibClient,
+    shared_state: &WorkerPoolState,
+    rate_limiter: &SharedRateLimiter,
+    tx: &mpsc::UnboundedSender<WorkerMessage>,
+) -> Result<()> {
+    use crate::api::SearchResult;
+
+    let image_secs = match metadata::duration_secs(path) {
+        Ok(secs) => secs,
+        Err(e) => {
+            shared_state
+                .fail(path, filename, ErrorKind::of(&e), e.to_string(), None, tx)
+                .await?;
+            return Ok(());
+        }
+    };
+    tracing::info!(
+        "Cue sheet splits {} into {} tracks",
+        path.display(),
+        sheet.tracks.len()
+    );
+
+    let mut found = Vec::new();
+    let (mut existing, mut cached, mut not_found, mut errors) = (0, 0, 0, 0);
+    let mut error_kind = ErrorKind::Other;
+    let mut exclusion = None;
+
+    for (cue_track, mut track) in sheet.to_tracks(path, image_secs) {
+        report_sanitized(path, &track.sanitize(), tx)?;
+        if let Some(reason) = shared_state.filter.exclusion(&track) {
+            tracing::debug!("Track {} excluded: {}", cue_track.number, reason);
+            exclusion = Some(reason);
+            continue;
+        }
+
+        if shared_state.cue_layout == CueLayout::Tracks
+            && cue::track_lrc_path(path, &cue_track).exists()
+        {
+            existing += 1;
+            continue;
+        }
+
+        let sig_hash = TrackSignature::from_track(&track).generate_hash();
+        if !shared_state.force_retry && shared_state.skip_cached(path, &sig_hash)? {
+            tracing::debug!("Track {} is negative cached", cue_track.number);
+            cached += 1;
+            continue;
+        }
+
+        tx.send(WorkerMessage::TrackProcessing {
+            track: track.clone(),
+        })?;
+        let (lyrics, status) = match search_lyrics(
+            &track,
+            client,
+            shared_state,
+            rate_limiter,
+            tx,
+            None,
+        )
+        .await
+        {
+            Ok(SearchResult::Found(lyrics)) => (lyrics, 200),
+            Ok(SearchResult::PotentialMatch { lyrics, similarity }) => {
+                tracing::warn!(
+                    "Potential match for {} - {} (similarity: {:.2}). API returned: {} - {}. Manual verification recommended.",
+                    track.artist,
+                    track.title,
+                    similarity,
+                    lyrics.artist_name,
+                    lyrics.track_name
+                );
+                (lyrics, 200)
+            }
+            Ok(SearchResult::NotFound { status }) => {
+                shared_state
+                    .cache
+                    .add(
+                        FailedLookup::for_track(&track, &sig_hash, NegativeReason::NotFound)
+                            .with_response(api::PROVIDER, status),
+                    )
+                    .await?;
+                not_found += 1;
+                continue;
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Lookup failed for track {} of {}: {}",
+                    cue_track.number,
+                    path.display(),
+                    e
+                );
+                errors += 1;
+                error_kind = ErrorKind::of(&e);
+                continue;
+            }
+        };
+
+        let reason = negative_reason(&lyrics);
+        match lyrics.into_lyrics(shared_state.allow_plain) {
+            Some(text) => {
+                shared_state.clear_negative(path, &sig_hash).await;
+                found.push((cue_track, text));
+            }
+            None => {
+                shared_state
+                    .cache
+                    .add(
+                        FailedLookup::for_track(&track, &sig_hash, reason)
+                            .with_response(api::PROVIDER, status),
+                    )
+                    .await?;
+                not_found += 1;
+            }
+        }
+    }
+
+    let written = shared_state.timed(path, Stage::Write, || match shared_state.cue_layout {
+        CueLayout::Tracks => found.iter().try_for_each(|(cue_track, text)| {
+            lrc::write_lrc(
+                path,
+                &cue::track_lrc_path(path, cue_track),
+                text,
+                &shared_state.write_options,
+            )
+        }),
+        CueLayout::Combined if found.is_empty() => Ok(()),
+        CueLayout::Combined => {
+            let combined = found
+                .iter()
+                .map(|(cue_track, text)| lrc::offset_lyrics(text, cue_track.start_ms))
+                .collect::<Vec<_>>()
+                .join("\n");
+            lrc::write_sidecar(path, &combined, &shared_state.write_options).map(|_| ())
+        }
+    });
+
+    tracing::info!(
+        "{}: {} tracks found, {} existing, {} cached, {} not found, {} errors",
+        path.display(),
+        found.len(),
+        existing,
+        cached,
+        not_found,
+        errors
+    );
+
+    if let Err(e) = written {
+        shared_state
+            .fail(path, filename, ErrorKind::Io, e.to_string(), None, tx)
+            .await?;
+    } else if !found.is_empty() {
+        tx.send(WorkerMessage::LyricsFound {
+            path: path.to_path_buf(),
+        })?;
+        shared_state.downloaded.fetch_add(1, Ordering::Relaxed);
+        shared_state
+            .log(path, filename, StatusType::Downloaded, None)
+            .await;
+    } else if errors > 0 {
+        shared_state
+            .fail(
+                path,
+                filename,
+                error_kind,
+                format!("{} tracks failed to look up", errors),
+                None,
+                tx,
+            )
+            .await?;
+    } else if not_found > 0 {
+        tx.send(WorkerMessage::LyricsNotFound {
+            path: path.to_path_buf(),
+        })?;
+        shared_state.failed.fetch_add(1, Ordering::Relaxed);
+        shared_state
+            .log(path, filename, StatusType::NotFound, None)
+            .await;
+    } else if cached > 0 {
+        tx.send(WorkerMessage::CacheHit {
+            path: path.to_path_buf(),
+        })?;
+        shared_state.cached.fetch_add(1, Ordering::Relaxed);
+        shared_state
+            .log(path, filename, StatusType::Cached, None)
+            .await;
+    } else if let (0, Some(reason)) = (existing, exclusion) {
+        shared_state.exclude(path, filename, reason, tx).await?;
+    } else {
+        // Every track already has its .lrc
+        tx.send(WorkerMessage::AlreadyHasLrc {
+            path: path.to_path_buf(),
+        })?;
+        shared_state
+            .log(path, filename, StatusType::Existing, None)
+            .await;
+    }
+
+    Ok(())
+}
+
+/// Whether a file indexed with lyrics can be skipped if it hasn't changed
+fn keeps_lyrics(previous: &IndexEntry, ignore_embedded: bool) -> bool {
+    previous.status.has_lyrics()
+        && !(previous.status == StatusType::EmbeddedExists && ignore_embedded)
+}
+
+/// Whether a file indexed with lyrics still has them, so the scan can skip it
+/// Sidecars in a changed directory are checked again, since one may have been deleted to
+/// have its lyrics downloaded again.
+fn still_has_lyrics(
+    previous: &IndexEntry,
+    path: &Path,
+    dir_unchanged: bool,
+    ignore_embedded: bool,
+) -> bool {
+    if !keeps_lyrics(previous, ignore_embedded) {
+        return false;
+    }
+    if dir_unchanged {
+        return true;
+    }
+    let needs_sidecar = matches!(
+        previous.status,
+        StatusType::Downloaded | StatusType::Existing
+    );
+    previous.is_current(path) && (!needs_sidecar || scanner::has_lrc_sidecar(path))
+}
+
+/// Write the playlist of files without readable tags, or remove it when there are none
+fn write_untagged_list(files: &[PathBuf]) -> Result<usize> {
+    let list_path = paths::get_untagged_list_path()?;
+    if files.is_empty() {
+        if list_path.exists() {
+            std::fs::remove_file(&list_path)?;
+        }
+    } else {
+        playlist::write(&list_path, files)?;
+    }
+    Ok(files.len())
+}
+
+/// Why a matched track left nothing to write
+fn negative_reason(lyrics: &LyricsResponse) -> NegativeReason {
+    if lyrics.instrumental {
+        NegativeReason::Instrumental
+    } else if lyrics.plain_lyrics.is_some() {
+        NegativeReason::PlainOnly
+    } else {
+        NegativeReason::NotFound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pool state over a fresh cache in `dir`, with default options
+    fn test_state(dir: &Path) -> (WorkerPoolState, mpsc::UnboundedReceiver<WorkerMessage>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let cache = NegativeCache::open(&dir.join("cache.db")).unwrap();
+        let state = WorkerPoolState {
+            session: Mutex::new(PersistentSession::new(dir.to_path_buf(), Vec::new(), false)),
+            session_write: Mutex::new(()),
+            session_log: crossbeam_channel::unbounded(),
+            cache: CacheHandle::spawn(cache).unwrap(),
+            downloaded: AtomicUsize::new(0),
+            existing: AtomicUsize::new(0),
+            cached: AtomicUsize::new(0),
+            failed: AtomicUsize::new(0),
+            embedded: AtomicUsize::new(0),
+            untagged: AtomicUsize::new(0),
+            excluded: AtomicUsize::new(0),
+            spoken: AtomicUsize::new(0),
+            force_retry: false,
+            ignore_embedded: false,
+            allow_plain: false,
+            full_scan: false,
+            write_options: WriteOptions::default(),
+            cue_layout: CueLayout::default(),
+            cue_sheets: DashMap::new(),
+            filter: TrackFilter::default(),
+            detect_spoken: false,
+            acoustid: None,
+            lookups: DashMap::new(),
+            dedupe_audio: false,
+            content_written: DashMap::new(),
+            activity: DashMap::new(),
+            tx,
+            retry_requested: DashSet::new(),
+            manual: DashMap::new(),
+            pick: false,
+            picks: DashMap::new(),
+            breaker: CircuitBreaker::new(BREAKER_THRESHOLD),
+            fetched: DashSet::new(),
+            throughput: Throughput::new(1),
+            timings: None,
+            retry: RetryPolicy::default(),
+            errors: DashMap::new(),
+        };
+        (state, rx)
+    }
+
+    /// Walk `root` as a fresh scan against the given index entries, returning the queued files
+    async fn scan(
+        state: &WorkerPoolState,
+        root: &Path,
+        journal: impl IntoIterator<Item = IndexEntry>,
+    ) -> Vec<PathBuf> {
+        let scan = FreshScan {
+            root: root.to_path_buf(),
+            journal: journal
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+            dir_journal: DirJournal::default(),
+            ignore_embedded: false,
+        };
+        let queue = WorkQueue::new(Vec::new(), false, QueueOrder::Discovery, true);
+        feed_scan(scan, state, &queue, &state.tx).await.unwrap();
+        queue.pending()
+    }
+
+    #[tokio::test]
+    async fn test_plain_only_entry_cleared_after_retry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state, _rx) = test_state(dir.path());
+        let path = dir.path().join("song.flac");
+        state
+            .cache
+            .add(FailedLookup::new("sig", NegativeReason::PlainOnly))
+            .await
+            .unwrap();
+        assert!(state.skip_cached(&path, "sig").unwrap());
+
+        // Looked up again with --allow-plain, then dropped once the lyrics are saved
+        state.allow_plain = true;
+        assert!(!state.skip_cached(&path, "sig").unwrap());
+        state.clear_negative(&path, "sig").await;
+        assert!(!state.cache.contains("sig"));
+        state.allow_plain = false;
+        assert!(!state.skip_cached(&path, "sig").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_deleted_sidecar_is_fetched_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let music = dir.path().join("music");
+        std::fs::create_dir(&music).unwrap();
+        let track = music.join("song.flac");
+        std::fs::write(&track, b"audio").unwrap();
+        std::fs::write(music.join("song.lrc"), b"[00:01.00]la").unwrap();
+        let (state, _rx) = test_state(dir.path());
+
+        let indexed = IndexEntry::new(&track, None, StatusType::Downloaded).unwrap();
+        assert!(scan(&state, &music, [indexed.clone()]).await.is_empty());
+
+        // Deleting the sidecar has the unchanged file looked up again
+        std::fs::remove_file(music.join("song.lrc")).unwrap();
+        assert_eq!(scan(&state, &music, [indexed]).await, [track]);
+    }
+
+    #[tokio::test]
+    async fn test_session_counts_files_found_with_lyrics() {
+        let dir = tempfile::tempdir().unwrap();
+        let music = dir.path().join("music");
+        std::fs::create_dir(&music).unwrap();
+        for name in ["a", "b"] {
+            std::fs::write(music.join(format!("{}.flac", name)), b"audio").unwrap();
+        }
+        std::fs::write(music.join("a.lrc"), b"[00:01.00]la").unwrap();
+        let (state, _rx) = test_state(dir.path());
+        state.downloaded.store(3, Ordering::Relaxed);
+
+        // A session saved once the scan has found them, before the workers finish
+        scan(&state, &music, []).await;
+        state.flush_session().await;
+        let session = state.session.lock().await;
+        assert_eq!(session.existing_count, 1);
+        assert_eq!(session.downloaded_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_pause_gate_wakes_on_resume_or_cancel() {
+        let gate = Arc::new(PauseGate::new(true));
+        let cancel = CancellationToken::new();
+
+        let waiter = tokio::spawn({
+            let (gate, cancel) = (gate.clone(), cancel.clone());
+            async move { gate.wait(&cancel).await }
+        });
+        tokio::task::yield_now().await;
+        gate.resume();
+        assert!(waiter.await.unwrap());
+
+        gate.pause();
+        let waiter = tokio::spawn({
+            let (gate, cancel) = (gate.clone(), cancel.clone());
+            async move { gate.wait(&cancel).await }
+        });
+        cancel.cancel();
+        assert!(!waiter.await.unwrap());
+    }
+
+    #[test]
+    fn test_work_items_group_albums() {
+        let files = || -> Vec<PathBuf> {
+            ["/a/1.flac", "/b/1.flac", "/a/2.flac"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect()
+        };
+        assert_eq!(work_items(files(), false).len(), 3);
+        assert_eq!(
+            work_items(files(), true),
+            [
+                vec![PathBuf::from("/a/1.flac"), PathBuf::from("/a/2.flac")],
+                vec![PathBuf::from("/b/1.flac")],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_work_queue_priorities() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |album: &str, name: &str, age_secs: u64| {
+            let path = dir.path().join(album).join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            let file = std::fs::File::create(&path).unwrap();
+            let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(age_secs);
+            file.set_modified(modified).unwrap();
+            path
+        };
+        let old = file("a", "old.flac", 3600);
+        let new = file("a", "new.flac", 60);
+        let other = file("b", "other.flac", 7200);
+        let files = vec![old.clone(), new.clone(), other.clone()];
+
+        let fifo = WorkQueue::new(files.clone(), false, QueueOrder::Discovery, false);
+        assert_eq!(fifo.take(), Some(vec![old.clone()]));
+        assert_eq!(fifo.in_order.load(Ordering::Relaxed), 0);
+        fifo.prioritize(dir.path().join("b"));
+        assert_eq!(fifo.take(), Some(vec![other.clone()]));
+        assert_eq!(fifo.take(), Some(vec![new.clone()]));
+        assert_eq!(fifo.take(), None);
+
+        let newest = WorkQueue::new(files, false, QueueOrder::Newest, false);
+        assert_eq!(newest.take(), Some(vec![new]));
+        newest.prioritize(dir.path().join("b"));
+        assert_eq!(newest.pending(), [other.clone(), old.clone()]);
+        assert_eq!(newest.take(), Some(vec![other]));
+        assert_eq!(newest.take(), Some(vec![old]));
+        assert_eq!(newest.take(), None);
+    }
+
+    #[tokio::test]
+    async fn test_path_order_waits_for_the_scan() {
+        let cancel = CancellationToken::new();
+        let queue = WorkQueue::new(Vec::new(), true, QueueOrder::Path, true);
+        let mut album = Vec::new();
+        for path in ["/b/2.flac", "/b/1.flac", "/a/1.flac"] {
+            queue.push_found(&mut album, PathBuf::from(path));
+        }
+        queue.push(album);
+
+        // Nothing is handed out while files may still arrive ahead of the ones found so far
+        let early =
+            tokio::time::timeout(std::time::Duration::from_millis(50), queue.next(&cancel)).await;
+        assert!(early.is_err());
+
+        queue.finish_scan();
+        assert_eq!(queue.len(), 3);
+        let a = vec![PathBuf::from("/a/1.flac")];
+        assert_eq!(queue.next(&cancel).await, Some(Work::Item(a)));
+        let b = vec![PathBuf::from("/b/1.flac"), PathBuf::from("/b/2.flac")];
+        assert_eq!(queue.next(&cancel).await, Some(Work::Item(b)));
+        assert_eq!(queue.next(&cancel).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_work_queue_pending_keeps_held_files() {
+        let cancel = CancellationToken::new();
+        let queue = WorkQueue::new(Vec::new(), true, QueueOrder::Discovery, true);
+        let mut album = Vec::new();
+        for path in ["/a/1.flac", "/a/2.flac", "/b/1.flac"] {
+            queue.push_found(&mut album, PathBuf::from(path));
+        }
+        queue.push(album);
+        queue.finish_scan();
+
+        let Some(Work::Item(first)) = queue.next(&cancel).await else {
+            panic!("expected the first album");
+        };
+        assert_eq!(queue.len(), 3);
+        queue.hold(0, first);
+        assert_eq!(queue.take_held(0), Some(PathBuf::from("/a/1.flac")));
+        assert_eq!(queue.len(), 2);
+
+        // A saved session keeps the album's rest and the queued album, which stays queued
+        assert_eq!(
+            queue.pending(),
+            [PathBuf::from("/a/2.flac"), PathBuf::from("/b/1.flac")]
+        );
+        assert_eq!(
+            queue.next(&cancel).await,
+            Some(Work::Item(vec![PathBuf::from("/b/1.flac")]))
+        );
+        assert_eq!(queue.next(&cancel).await, None);
+
+        // Retries go ahead of queued work
+        queue.push(vec![PathBuf::from("/c/1.flac")]);
+        queue.push_retry(PathBuf::from("/a/1.flac"));
+        assert_eq!(
+            queue.next(&cancel).await,
+            Some(Work::Retry(PathBuf::from("/a/1.flac")))
+        );
+    }
+}